@@ -1,3 +1,4 @@
+use cap_editor::LogLevel;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use specta::Type;
@@ -11,6 +12,10 @@ pub struct GeneralSettingsStore {
     pub open_editor_after_recording: bool,
     #[serde(default)]
     pub hide_dock_icon: bool,
+    /// Writes the editor's logs at this level to the app's log directory when set.
+    /// Takes effect the next time the app starts.
+    #[serde(default)]
+    pub editor_log_level: Option<LogLevel>,
 }
 
 impl GeneralSettingsStore {
@@ -17,17 +17,22 @@ mod windows;
 
 use audio::AppSounds;
 use auth::AuthStore;
-use cap_editor::{AudioData, EditorState, ProjectRecordings};
-use cap_editor::{EditorInstance, FRAMES_WS_PATH};
+use cap_editor::{
+    fade_gain, track_gain, AudioData, AudioLevel, AudioReplaceMode, EditorState,
+    EditorStateSnapshot, LogFile, LogGuard, ProjectRecordings, SilentRange, MICROPHONE_TRACK,
+};
+use cap_editor::{EditorInstance, EditorInstanceOptions};
+use cap_ffmpeg::{ApplyFFmpegArgs, ExportEncoder};
 use cap_media::{
     feeds::{CameraFeed, CameraFrameSender},
     platform::Bounds,
     sources::{AudioInputSource, ScreenCaptureTarget},
 };
 use cap_project::{
-    ProjectConfiguration, RecordingMeta, SharingMeta, TimelineConfiguration, TimelineSegment,
+    ColorGrade, ExportConfig, ExportPreset, ProjectConfiguration, RecordingMeta, Reframe,
+    SharingMeta, SilenceDetection, StreamLengthPolicy, TimelinePosition,
 };
-use cap_rendering::ProjectUniforms;
+use cap_rendering::{GpuReport, ProjectUniforms, VisualizerAudio};
 use cap_utils::create_named_pipe;
 // use display::{list_capture_windows, Bounds, CaptureTarget, FPS};
 use general_settings::GeneralSettingsStore;
@@ -35,7 +40,7 @@ use image::{ImageBuffer, Rgba};
 use mp4::Mp4Reader;
 use num_traits::ToBytes;
 use png::{ColorType, Encoder};
-use recording::{list_cameras, list_capture_windows, InProgressRecording, FPS};
+use recording::{list_cameras, list_capture_windows, InProgressRecording};
 use scap::capturer::Capturer;
 use scap::frame::Frame;
 use serde::{Deserialize, Serialize};
@@ -43,10 +48,14 @@ use serde_json::json;
 use specta::Type;
 use std::fs::File;
 use std::io::BufWriter;
-use std::io::{BufReader, Write};
+use std::io::{BufReader, Read, Write};
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::{
-    collections::HashMap, marker::PhantomData, path::PathBuf, process::Command, sync::Arc,
+    collections::HashMap,
+    marker::PhantomData,
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::Arc,
     time::Duration,
 };
 use tauri::{AppHandle, Manager, Runtime, State, WindowEvent};
@@ -414,25 +423,10 @@ async fn stop_recording(app: AppHandle, state: MutableState<'_, App>) -> Result<
     .emit(&app)
     .ok();
 
-    let config = {
-        let mut segments = vec![];
-        let mut passed_duration = 0.0;
-
-        for i in (0..current_recording.segments.len()).step_by(2) {
-            let start = passed_duration;
-            passed_duration += current_recording.segments[i + 1] - current_recording.segments[i];
-            segments.push(TimelineSegment {
-                start,
-                end: passed_duration,
-                timescale: 1.0,
-            });
-        }
-
-        ProjectConfiguration {
-            timeline: Some(TimelineConfiguration { segments }),
-            ..Default::default()
-        }
-    };
+    // the meta was saved when the recording stopped
+    let config = RecordingMeta::load_for_project(&current_recording.recording_dir)
+        .map(|meta| ProjectConfiguration::default_for(&meta))
+        .unwrap_or_default();
 
     std::fs::write(
         current_recording.recording_dir.join("project-config.json"),
@@ -597,7 +591,7 @@ async fn get_rendered_video(
     video_id: String,
     project: ProjectConfiguration,
 ) -> Result<PathBuf, String> {
-    let editor_instance = upsert_editor_instance(&app, video_id.clone()).await;
+    let editor_instance = upsert_editor_instance(&app, video_id.clone()).await?;
 
     get_rendered_video_impl(editor_instance, project).await
 }
@@ -609,7 +603,17 @@ async fn get_rendered_video_impl(
     let output_path = editor_instance.project_path.join("output/result.mp4");
 
     if !output_path.exists() {
-        render_to_file_impl(&editor_instance, project, output_path.clone(), |_| {}).await?;
+        render_to_file_impl(
+            &editor_instance,
+            project,
+            output_path.clone(),
+            None,
+            ExportConfig::default(),
+            true,
+            |_| {},
+            |_| {},
+        )
+        .await?;
     }
 
     Ok(output_path)
@@ -725,31 +729,382 @@ struct AudioRender {
     pipe_tx: tokio::sync::mpsc::Sender<Vec<f64>>,
 }
 
+/// Samples of `audio` that play during output frame `frame_number`, with the project's
+/// fades and track mutes applied. Samples past the end of the audio and during intro and
+/// outro cards are silent, and `None` is returned once the frame starts after the audio has
+/// ended or outside the timeline.
+fn audio_frame_samples(
+    audio: &AudioData,
+    project: &ProjectConfiguration,
+    frame_number: u32,
+    fps: u32,
+) -> Option<Vec<f64>> {
+    let samples_per_frame = audio.sample_rate as f64 / fps as f64;
+    let recording_duration = audio.duration();
+
+    let frame_time =
+        match project.timeline_position(frame_number as f64 / fps as f64, recording_duration)? {
+            TimelinePosition::Recording(time) => time,
+            TimelinePosition::Intro | TimelinePosition::Outro => {
+                return Some(vec![0.0; samples_per_frame as usize])
+            }
+        };
+
+    // held frames are silent
+    if project.timeline().is_some_and(|t| t.is_held(frame_time)) {
+        return Some(vec![0.0; samples_per_frame as usize]);
+    }
+
+    let start = match project.timeline() {
+        Some(timeline) => timeline.get_recording_time(frame_time)? * audio.sample_rate as f64,
+        None => frame_time * audio.sample_rate as f64,
+    } as usize;
+
+    if start >= audio.buffer.len() {
+        return None;
+    }
+
+    let end = (start + samples_per_frame as usize).min(audio.buffer.len());
+    let mut samples_iter = audio.buffer[start..end].iter().copied();
+
+    let mic_gain = track_gain(project, MICROPHONE_TRACK);
+
+    let mut frame_samples = Vec::with_capacity(samples_per_frame as usize);
+    for i in 0..samples_per_frame as usize {
+        let time = frame_time + i as f64 / audio.sample_rate as f64;
+        let gain = fade_gain(project, time, recording_duration) * mic_gain;
+        frame_samples.push(samples_iter.next().unwrap_or(0.0) * gain);
+    }
+
+    Some(frame_samples)
+}
+
+/// Audio to mux with output frame `frame_number` under `policy`. Once the audio has ended
+/// it's padded with silence, or `None` is returned to truncate the export at this frame.
+fn policy_frame_samples(
+    audio: &AudioData,
+    project: &ProjectConfiguration,
+    frame_number: u32,
+    fps: u32,
+    policy: StreamLengthPolicy,
+) -> Option<Vec<f64>> {
+    match audio_frame_samples(audio, project, frame_number, fps) {
+        Some(samples) => Some(samples),
+        None if policy == StreamLengthPolicy::TruncateToShortest => None,
+        None => Some(vec![0.0; (audio.sample_rate as f64 / fps as f64) as usize]),
+    }
+}
+
+/// Where an export's encoded output goes.
+enum ExportOutput {
+    /// Encoded in parts that are checkpointed, and joined once they're all done.
+    /// With `resume`, parts left by an interrupted export with the same settings are kept.
+    File { path: PathBuf, resume: bool },
+    /// Chunks of the exported file sent as soon as FFmpeg writes them.
+    Stream(tokio::sync::mpsc::Sender<Vec<u8>>),
+}
+
+// bytes read from FFmpeg's stdout at a time when streaming an export
+const EXPORT_STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+// length of each checkpointed part of a file export, every one starting with a keyframe
+const EXPORT_CHECKPOINT_SECS: u32 = 10;
+
+/// Parts of a file export encoded so far, kept in the project so an interrupted
+/// export can continue from the last finished part instead of starting over.
+struct ExportCheckpoint {
+    dir: PathBuf,
+    completed_parts: u32,
+}
+
+impl ExportCheckpoint {
+    /// Opens the checkpoint for an export with `settings`. Parts from an export with
+    /// other settings are discarded, as are all parts unless `resume` is set.
+    fn open(recording_dir: &std::path::Path, settings: &str, resume: bool) -> Result<Self, String> {
+        let dir = recording_dir.join("output/export-checkpoint");
+        let settings_path = dir.join("settings.json");
+
+        let matches =
+            resume && std::fs::read_to_string(&settings_path).is_ok_and(|saved| saved == settings);
+
+        if !matches {
+            if dir.exists() {
+                std::fs::remove_dir_all(&dir)
+                    .map_err(|e| format!("Failed to clear export checkpoint: {e}"))?;
+            }
+            std::fs::create_dir_all(&dir)
+                .map_err(|e| format!("Failed to create export checkpoint: {e}"))?;
+            std::fs::write(&settings_path, settings)
+                .map_err(|e| format!("Failed to write export checkpoint: {e}"))?;
+        }
+
+        let completed_parts = Self::count_completed_parts(&dir);
+        if completed_parts > 0 {
+            tracing::info!("Resuming export after {completed_parts} checkpointed parts");
+        }
+
+        Ok(Self {
+            dir,
+            completed_parts,
+        })
+    }
+
+    /// FFmpeg's segment muxer only lists a part once it's been closed, so parts that
+    /// were being written when the export stopped aren't counted.
+    fn count_completed_parts(dir: &std::path::Path) -> u32 {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return 0;
+        };
+
+        entries
+            .flatten()
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                name.starts_with("parts-") && name.ends_with(".csv")
+            })
+            .filter_map(|entry| std::fs::read_to_string(entry.path()).ok())
+            .map(|list| list.lines().filter(|line| !line.is_empty()).count() as u32)
+            .sum()
+    }
+
+    fn part_path(&self, index: u32) -> PathBuf {
+        self.dir.join(format!("part-{index:05}.mkv"))
+    }
+
+    /// Sets FFmpeg up to write the export in parts of `frames_per_part` frames,
+    /// numbered on from the parts already completed.
+    fn apply_ffmpeg_args(&self, command: &mut Command, frames_per_part: u32, fps: u32) {
+        command
+            // every part has to start with a keyframe to be decodable on its own
+            .args([
+                "-force_key_frames",
+                &format!("expr:gte(n,n_forced*{frames_per_part})"),
+            ])
+            .args(["-f", "segment", "-segment_format", "matroska"])
+            .args([
+                "-segment_time",
+                &(frames_per_part as f64 / fps as f64).to_string(),
+            ])
+            .args(["-segment_start_number", &self.completed_parts.to_string()])
+            .args(["-reset_timestamps", "1"])
+            // a list per run, as FFmpeg overwrites the list when it starts
+            .args(["-segment_list_type", "csv", "-segment_list"])
+            .arg(self.dir.join(format!("parts-{}.csv", self.completed_parts)))
+            .arg("-y")
+            .arg(self.dir.join("part-%05d.mkv"));
+    }
+
+    /// Joins the completed parts into a `container` file at `output_path`
+    /// and removes the checkpoint.
+    fn finish(self, output_path: &std::path::Path, container: &str) -> Result<(), String> {
+        let parts = Self::count_completed_parts(&self.dir);
+        if parts == 0 {
+            return Err("No frames were exported".to_string());
+        }
+
+        let list = (0..parts)
+            .map(|index| {
+                let path = self
+                    .part_path(index)
+                    .to_string_lossy()
+                    .replace('\'', "'\\''");
+                format!("file '{path}'\n")
+            })
+            .collect::<String>();
+        let list_path = self.dir.join("concat.txt");
+        std::fs::write(&list_path, list)
+            .map_err(|e| format!("Failed to write export part list: {e}"))?;
+
+        let status = cap_ffmpeg::FFmpeg::new()
+            .command
+            .args(["-f", "concat", "-safe", "0", "-i"])
+            .arg(&list_path)
+            .args(["-c", "copy", "-f", container, "-y"])
+            .arg(output_path)
+            .status()
+            .map_err(|e| format!("Failed to run ffmpeg: {e}"))?;
+
+        if !status.success() {
+            return Err(format!("Failed to join export parts: {status}"));
+        }
+
+        std::fs::remove_dir_all(&self.dir).ok();
+
+        Ok(())
+    }
+}
+
 async fn render_to_file_impl(
     editor_instance: &Arc<EditorInstance>,
     project: ProjectConfiguration,
     output_path: PathBuf,
+    export_fps: Option<u32>,
+    export_config: ExportConfig,
+    resume: bool,
+    on_encoder: impl FnOnce(&ExportEncoder) + Send,
     on_progress: impl Fn(u32) + Send + 'static,
 ) -> Result<PathBuf, String> {
+    let (container, _) = export_config.container();
+    let output_folder = output_path.parent().unwrap();
+    std::fs::create_dir_all(output_folder)
+        .map_err(|e| format!("Failed to create output directory: {:?}", e))?;
+
+    export_impl(
+        editor_instance,
+        project,
+        ExportOutput::File {
+            path: output_path.clone(),
+            resume,
+        },
+        export_fps,
+        export_config,
+        on_encoder,
+        on_progress,
+    )
+    .await?;
+
+    let recording_dir = &editor_instance.project_path;
+
+    tracing::info!("Copying file to {}", recording_dir.display());
+    let result_path = recording_dir.join("output/result.mp4");
+    // Function to check if the file is a valid MP4
+    fn is_valid_mp4(path: &std::path::Path) -> bool {
+        if let Ok(file) = std::fs::File::open(path) {
+            let file_size = match file.metadata() {
+                Ok(metadata) => metadata.len(),
+                Err(_) => return false,
+            };
+            let reader = std::io::BufReader::new(file);
+            Mp4Reader::read_header(reader, file_size).is_ok()
+        } else {
+            false
+        }
+    }
+
+    // result.mp4 is what gets shared and uploaded, so other containers aren't copied to it
+    if output_path != result_path && container == "mp4" {
+        tracing::info!("Waiting for valid MP4 file at {}", output_path.display());
+        // Wait for the file to become a valid MP4
+        let mut attempts = 0;
+        while attempts < 10 {
+            // Wait for up to 10 seconds
+            if is_valid_mp4(&output_path) {
+                tracing::info!("Valid MP4 file detected after {attempts} seconds");
+                match std::fs::copy(&output_path, &result_path) {
+                    Ok(bytes) => tracing::info!(
+                        "Successfully copied {bytes} bytes to {}",
+                        result_path.display()
+                    ),
+                    Err(e) => tracing::error!("Failed to copy file: {e}"),
+                }
+                break;
+            }
+            tracing::debug!("Attempt {}: File not yet valid, waiting", attempts + 1);
+            std::thread::sleep(std::time::Duration::from_secs(1));
+            attempts += 1;
+        }
+
+        if attempts == 10 {
+            tracing::error!("Timeout: Failed to detect a valid MP4 file after 10 seconds");
+        }
+    }
+
+    Ok(output_path)
+}
+
+/// Exports `project` like `render_to_file_impl`, but sends the encoded file to `chunk_tx`
+/// in order as it's produced instead of writing it to disk. The export finishes once
+/// every chunk has been sent. Fails up front for containers that can't be streamed,
+/// see `ExportConfig::stream_muxer_args`.
+pub async fn render_to_stream(
+    editor_instance: &Arc<EditorInstance>,
+    project: ProjectConfiguration,
+    export_fps: Option<u32>,
+    export_config: ExportConfig,
+    chunk_tx: tokio::sync::mpsc::Sender<Vec<u8>>,
+    on_progress: impl Fn(u32) + Send + 'static,
+) -> Result<(), String> {
+    export_impl(
+        editor_instance,
+        project,
+        ExportOutput::Stream(chunk_tx),
+        export_fps,
+        export_config,
+        |_| {},
+        on_progress,
+    )
+    .await
+}
+
+async fn export_impl(
+    editor_instance: &Arc<EditorInstance>,
+    project: ProjectConfiguration,
+    output: ExportOutput,
+    export_fps: Option<u32>,
+    export_config: ExportConfig,
+    on_encoder: impl FnOnce(&ExportEncoder) + Send,
+    on_progress: impl Fn(u32) + Send + 'static,
+) -> Result<(), String> {
+    let encoder = tokio::task::spawn_blocking({
+        let export_config = export_config.clone();
+        move || cap_ffmpeg::select_export_encoder(&export_config)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    cap_ffmpeg::validate_export_encoder(&encoder)?;
+    tracing::info!("Exporting with the {} encoder", encoder.name());
+    on_encoder(&encoder);
+
+    let project = editor_instance.with_silence_skipped(project);
     let recording_dir = &editor_instance.project_path;
-    let audio = editor_instance.audio.clone();
+    let source_fps = editor_instance.recordings.display.fps as f64;
+    let fps = export_fps.unwrap_or(source_fps.round() as u32);
+    // processed up front so the audio visualizer is drawn from the audio that's exported
+    let audio_data = editor_instance
+        .audio
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|audio| audio.processed(&project.audio));
+    let visualizer_audio = audio_data.as_ref().map(|audio| VisualizerAudio {
+        samples: audio.buffer.clone(),
+        sample_rate: audio.sample_rate,
+    });
     let decoders = editor_instance.decoders.clone();
-    let options = editor_instance.render_constants.options.clone();
+    let options = editor_instance.render_constants.current_options();
 
     let (tx_image_data, mut rx_image_data) = tokio::sync::mpsc::unbounded_channel::<Vec<u8>>();
-
-    let output_folder = output_path.parent().unwrap();
-    std::fs::create_dir_all(output_folder)
-        .map_err(|e| format!("Failed to create output directory: {:?}", e))?;
+    // whether every frame was rendered, sent once the frames stop
+    let (rendered_tx, rendered_rx) = tokio::sync::oneshot::channel::<Result<(), String>>();
 
     let output_size = ProjectUniforms::get_output_size(&options, &project);
 
+    let frames_per_part = fps * EXPORT_CHECKPOINT_SECS;
+    let checkpoint = match &output {
+        ExportOutput::File { path, resume } => {
+            let settings = serde_json::to_string(&(&project, &export_config, fps, path))
+                .map_err(|e| e.to_string())?;
+            Some(ExportCheckpoint::open(recording_dir, &settings, *resume)?)
+        }
+        ExportOutput::Stream(_) => None,
+    };
+    let start_frame = checkpoint
+        .as_ref()
+        .map_or(0, |checkpoint| checkpoint.completed_parts * frames_per_part);
+    let (container, audio_encoder) = export_config.container();
+    let stream_muxer_args = match &output {
+        ExportOutput::File { .. } => None,
+        ExportOutput::Stream(_) => Some(export_config.stream_muxer_args().ok_or_else(|| {
+            format!("{container} exports need to seek in their output, export to a file instead")
+        })?),
+    };
+
     let ffmpeg_handle = tokio::spawn({
         let project = project.clone();
-        let output_path = output_path.clone();
         let recording_dir = recording_dir.clone();
         async move {
-            println!("Starting FFmpeg output process...");
+            tracing::debug!("Starting FFmpeg output process");
             let mut ffmpeg = cap_ffmpeg::FFmpeg::new();
 
             let audio_dir = tempfile::tempdir().unwrap();
@@ -762,7 +1117,7 @@ async fn render_to_file_impl(
                 ffmpeg.add_input(cap_ffmpeg::FFmpegRawVideoInput {
                     width: output_size.0,
                     height: output_size.1,
-                    fps: 30,
+                    fps,
                     pix_fmt: "rgba",
                     input: pipe_path.clone().into_os_string(),
                 });
@@ -771,18 +1126,20 @@ async fn render_to_file_impl(
 
                 tokio::spawn(async move {
                     let mut file = std::fs::File::create(&pipe_path).unwrap();
-                    println!("video pipe opened");
+                    tracing::debug!("Video pipe opened");
 
                     while let Some(bytes) = rx.recv().await {
-                        file.write_all(&bytes).unwrap();
+                        if file.write_all(&bytes).is_err() {
+                            break;
+                        }
                     }
 
-                    println!("done writing to video pipe");
+                    tracing::debug!("Done writing to video pipe");
                 });
 
                 tx
             };
-            let audio = if let Some(audio_data) = audio.lock().unwrap().as_ref() {
+            let audio = if let Some(audio_data) = audio_data {
                 let pipe_path = audio_dir.path().join("audio.pipe");
                 create_named_pipe(&pipe_path).unwrap();
 
@@ -797,40 +1154,80 @@ async fn render_to_file_impl(
 
                 tokio::spawn(async move {
                     let mut file = std::fs::File::create(&pipe_path).unwrap();
-                    println!("audio pipe opened");
+                    tracing::debug!("Audio pipe opened");
 
                     while let Some(bytes) = rx.recv().await {
                         let bytes = bytes
                             .iter()
                             .flat_map(|f| f.to_le_bytes())
                             .collect::<Vec<_>>();
-                        file.write_all(&bytes).unwrap();
+                        if file.write_all(&bytes).is_err() {
+                            break;
+                        }
                     }
 
-                    println!("done writing to audio pipe");
+                    tracing::debug!("Done writing to audio pipe");
                 });
 
                 Some(AudioRender {
-                    data: audio_data.clone(),
+                    data: audio_data,
                     pipe_tx: tx,
                 })
             } else {
                 None
             };
 
-            ffmpeg
-                .command
-                .args(["-f", "mp4"])
-                .args(["-codec:v", "libx264", "-codec:a", "aac"])
-                .args(["-preset", "ultrafast"])
-                .args(["-pix_fmt", "yuv420p", "-tune", "zerolatency"])
-                .arg("-y")
-                .arg(&output_path);
+            ffmpeg.command.args(["-f", container]);
+            encoder.apply_ffmpeg_args(&mut ffmpeg.command);
+            ffmpeg.command.args(["-codec:a", audio_encoder]);
+
+            // file exports always have a checkpoint, and streams always have muxer args
+            match &checkpoint {
+                Some(checkpoint) => {
+                    checkpoint.apply_ffmpeg_args(&mut ffmpeg.command, frames_per_part, fps);
+                }
+                None => {
+                    ffmpeg
+                        .command
+                        .args(stream_muxer_args.unwrap_or_default())
+                        .arg("pipe:1")
+                        .stdout(Stdio::piped());
+                }
+            }
 
             let mut ffmpeg_process = ffmpeg.start();
 
-            let mut frame_count = 0;
+            let output_path = match &output {
+                ExportOutput::File { path, .. } => Some(path.clone()),
+                ExportOutput::Stream(_) => None,
+            };
+
+            let stream_task = match output {
+                ExportOutput::File { .. } => None,
+                ExportOutput::Stream(chunk_tx) => {
+                    let stdout = ffmpeg_process.take_stdout().unwrap();
+
+                    // once the receiver is dropped FFmpeg's stdout is closed, failing the export
+                    Some(tokio::task::spawn_blocking(move || {
+                        let read =
+                            cap_ffmpeg::read_chunks(stdout, EXPORT_STREAM_CHUNK_SIZE, |chunk| {
+                                let sent = chunk_tx.blocking_send(chunk).is_ok();
+                                if !sent {
+                                    tracing::info!("Export stream receiver dropped");
+                                }
+                                sent
+                            });
+
+                        if let Err(e) = read {
+                            tracing::error!("Failed to read FFmpeg output: {e}");
+                        }
+                    }))
+                }
+            };
+
+            let mut frame_count = start_frame;
             let mut first_frame = None;
+            let mut last_frame = None::<Vec<u8>>;
 
             loop {
                 match rx_image_data.recv().await {
@@ -842,30 +1239,22 @@ async fn render_to_file_impl(
                         }
 
                         if let Some(audio) = &audio {
-                            let samples_per_frame = audio.data.sample_rate as f64 / FPS as f64;
-
-                            let start_samples = match project.timeline() {
-                                Some(timeline) => timeline
-                                    .get_recording_time(frame_count as f64 / FPS as f64)
-                                    .map(|recording_time| {
-                                        recording_time * audio.data.sample_rate as f64
-                                    }),
-                                None => Some(frame_count as f64 * samples_per_frame),
+                            let Some(samples) = policy_frame_samples(
+                                &audio.data,
+                                &project,
+                                frame_count,
+                                fps,
+                                export_config.length_policy,
+                            ) else {
+                                tracing::info!("Audio ended before video, truncating export");
+                                break;
                             };
 
-                            if let Some(start) = start_samples {
-                                let end = start + samples_per_frame;
-
-                                let samples = &audio.data.buffer[start as usize..end as usize];
-                                let mut samples_iter = samples.iter().copied();
-
-                                let mut frame_samples = Vec::new();
-                                for _ in 0..samples_per_frame as usize {
-                                    frame_samples.push(samples_iter.next().unwrap_or(0.0));
-                                }
+                            audio.pipe_tx.send(samples).await.unwrap();
+                        }
 
-                                audio.pipe_tx.send(frame_samples).await.unwrap();
-                            }
+                        if export_config.length_policy == StreamLengthPolicy::PadToLongest {
+                            last_frame = Some(frame.clone());
                         }
 
                         video_tx.send(frame).await.unwrap();
@@ -873,13 +1262,66 @@ async fn render_to_file_impl(
                         frame_count += 1;
                     }
                     None => {
-                        println!("All frames sent to FFmpeg");
+                        // FFmpeg would close the last checkpointed part short of a failed
+                        // render, so it's killed instead, keeping the checkpoint to resume from
+                        let rendered = rendered_rx
+                            .await
+                            .unwrap_or_else(|_| Err("Rendering stopped unexpectedly".to_string()));
+                        if let Err(e) = rendered {
+                            ffmpeg_process.kill();
+                            ffmpeg_process.wait().ok();
+                            return Err(e);
+                        }
+
+                        tracing::debug!("All frames sent to FFmpeg");
+
+                        // hold the last frame for as long as there's audio left
+                        if let (Some(audio), Some(frame)) = (&audio, &last_frame) {
+                            while let Some(samples) =
+                                audio_frame_samples(&audio.data, &project, frame_count, fps)
+                            {
+                                on_progress(frame_count);
+                                audio.pipe_tx.send(samples).await.unwrap();
+                                video_tx.send(frame.clone()).await.unwrap();
+                                frame_count += 1;
+                            }
+                        }
+
                         break;
                     }
                 }
             }
 
-            ffmpeg_process.stop();
+            // frames rendered after the export was truncated aren't needed
+            drop(rx_image_data);
+
+            // let FFmpeg read to the end of its inputs instead of stopping it, so it encodes
+            // every frame sent and finishes the last checkpointed part or the stream's last
+            // fragment
+            drop(video_tx);
+            drop(audio);
+
+            let result = tokio::task::spawn_blocking(move || {
+                let status = ffmpeg_process
+                    .finish()
+                    .map_err(|e| format!("Failed to wait for FFmpeg: {e}"))?;
+                if !status.success() {
+                    return Err(format!("FFmpeg failed to export: {status}"));
+                }
+
+                match checkpoint.zip(output_path) {
+                    Some((checkpoint, output_path)) => checkpoint.finish(&output_path, container),
+                    None => Ok(()),
+                }
+            })
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|result| result);
+
+            // the stream is complete once FFmpeg exits and closes its stdout
+            if let Some(stream_task) = stream_task {
+                stream_task.await.ok();
+            }
 
             // Save the first frame as a screenshot and thumbnail
             if let Some(frame_data) = first_frame {
@@ -898,13 +1340,13 @@ async fn render_to_file_impl(
 
                 let screenshots_dir = recording_dir.join("screenshots");
                 std::fs::create_dir_all(&screenshots_dir).unwrap_or_else(|e| {
-                    eprintln!("Failed to create screenshots directory: {:?}", e);
+                    tracing::error!("Failed to create screenshots directory: {e}");
                 });
 
                 // Save full-size screenshot
                 let screenshot_path = screenshots_dir.join("display.jpg");
                 rgb_img.save(&screenshot_path).unwrap_or_else(|e| {
-                    eprintln!("Failed to save screenshot: {:?}", e);
+                    tracing::error!("Failed to save screenshot: {e}");
                 });
 
                 // Create and save thumbnail
@@ -916,63 +1358,32 @@ async fn render_to_file_impl(
                 );
                 let thumbnail_path = screenshots_dir.join("thumbnail.png");
                 thumbnail.save(&thumbnail_path).unwrap_or_else(|e| {
-                    eprintln!("Failed to save thumbnail: {:?}", e);
+                    tracing::error!("Failed to save thumbnail: {e}");
                 });
-            } else {
-                eprintln!("No frames were processed, cannot save screenshot or thumbnail");
+            } else if start_frame == 0 {
+                tracing::warn!("No frames were processed, cannot save screenshot or thumbnail");
             }
-        }
-    });
 
-    println!("Rendering video to channel");
-
-    cap_rendering::render_video_to_channel(options, project, tx_image_data, decoders).await?;
-
-    ffmpeg_handle.await.ok();
-
-    println!("Copying file to {:?}", recording_dir);
-    let result_path = recording_dir.join("output/result.mp4");
-    // Function to check if the file is a valid MP4
-    fn is_valid_mp4(path: &std::path::Path) -> bool {
-        if let Ok(file) = std::fs::File::open(path) {
-            let file_size = match file.metadata() {
-                Ok(metadata) => metadata.len(),
-                Err(_) => return false,
-            };
-            let reader = std::io::BufReader::new(file);
-            Mp4Reader::read_header(reader, file_size).is_ok()
-        } else {
-            false
+            result
         }
-    }
+    });
 
-    if output_path != result_path {
-        println!("Waiting for valid MP4 file at {:?}", output_path);
-        // Wait for the file to become a valid MP4
-        let mut attempts = 0;
-        while attempts < 10 {
-            // Wait for up to 60 seconds
-            if is_valid_mp4(&output_path) {
-                println!("Valid MP4 file detected after {} seconds", attempts);
-                match std::fs::copy(&output_path, &result_path) {
-                    Ok(bytes) => {
-                        println!("Successfully copied {} bytes to {:?}", bytes, result_path)
-                    }
-                    Err(e) => eprintln!("Failed to copy file: {:?}", e),
-                }
-                break;
-            }
-            println!("Attempt {}: File not yet valid, waiting...", attempts + 1);
-            std::thread::sleep(std::time::Duration::from_secs(1));
-            attempts += 1;
-        }
+    tracing::debug!("Rendering video to channel");
 
-        if attempts == 10 {
-            eprintln!("Timeout: Failed to detect a valid MP4 file after 60 seconds");
-        }
-    }
+    let rendered = cap_rendering::render_video_to_channel(
+        options,
+        project,
+        tx_image_data,
+        decoders,
+        source_fps,
+        fps,
+        start_frame,
+        visualizer_audio,
+    )
+    .await;
+    rendered_tx.send(rendered).ok();
 
-    Ok(output_path)
+    ffmpeg_handle.await.map_err(|e| e.to_string())?
 }
 
 #[derive(Deserialize, specta::Type, tauri_specta::Event, Debug, Clone)]
@@ -983,29 +1394,41 @@ struct RenderFrameEvent {
 #[derive(Serialize, specta::Type, tauri_specta::Event, Debug, Clone)]
 struct EditorStateChanged {
     playhead_position: u32,
+    audio_level: Option<AudioLevel>,
+    idle_disposed: bool,
+    task_error: Option<String>,
+    render_warning: Option<String>,
 }
 
 impl EditorStateChanged {
     fn new(s: &EditorState) -> Self {
         Self {
             playhead_position: s.playhead_position,
+            audio_level: s.audio_level,
+            idle_disposed: s.idle_disposed,
+            task_error: s.task_error.clone(),
+            render_warning: s.render_warning.clone(),
         }
     }
 }
 
 #[tauri::command]
 #[specta::specta]
-async fn start_playback(app: AppHandle, video_id: String) {
-    upsert_editor_instance(&app, video_id)
-        .await
-        .start_playback()
+async fn start_playback(app: AppHandle, video_id: String) -> Result<(), String> {
+    let editor_instance = upsert_editor_instance(&app, video_id).await?;
+
+    editor_instance
+        .start_playback(None)
         .await
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 #[specta::specta]
 async fn stop_playback(app: AppHandle, video_id: String) {
-    let editor_instance = upsert_editor_instance(&app, video_id).await;
+    let Ok(editor_instance) = upsert_editor_instance(&app, video_id).await else {
+        return;
+    };
 
     let mut state = editor_instance.state.lock().await;
 
@@ -1014,6 +1437,62 @@ async fn stop_playback(app: AppHandle, video_id: String) {
     }
 }
 
+#[tauri::command]
+#[specta::specta]
+async fn scrub_audio(app: AppHandle, video_id: String, frame_number: u32) {
+    let Ok(editor_instance) = upsert_editor_instance(&app, video_id).await else {
+        return;
+    };
+
+    editor_instance.scrub_audio(frame_number).await;
+}
+
+/// Repairs seeking in a recording whose video files have a missing or corrupt index.
+/// Sends the fraction of the recording scanned so far over `progress_channel`.
+#[tauri::command]
+#[specta::specta]
+async fn rebuild_frame_index(
+    app: AppHandle,
+    video_id: String,
+    progress_channel: tauri::ipc::Channel<f64>,
+) -> Result<(), String> {
+    let editor_instance = upsert_editor_instance(&app, video_id).await?;
+
+    editor_instance
+        .rebuild_index(move |progress| {
+            progress_channel.send(progress).ok();
+        })
+        .await
+}
+
+/// Frame numbers of the recording's keyframes, for snapping to positions that seek quickly.
+#[tauri::command]
+#[specta::specta]
+async fn get_keyframe_positions(app: AppHandle, video_id: String) -> Result<Vec<u32>, String> {
+    let editor_instance = upsert_editor_instance(&app, video_id).await?;
+
+    Ok(editor_instance.keyframe_positions())
+}
+
+/// The editor's current playhead and playback state, without waiting for a state change.
+#[tauri::command]
+#[specta::specta]
+async fn get_editor_state(app: AppHandle, video_id: String) -> Result<EditorStateSnapshot, String> {
+    let editor_instance = upsert_editor_instance(&app, video_id).await?;
+
+    Ok(editor_instance.state_snapshot().await)
+}
+
+/// First and last recording frames that aren't black, proposed as trim points for
+/// recordings that start or end on black frames.
+#[tauri::command]
+#[specta::specta]
+async fn detect_content_bounds(app: AppHandle, video_id: String) -> Result<(u32, u32), String> {
+    let editor_instance = upsert_editor_instance(&app, video_id).await?;
+
+    Ok(editor_instance.detect_content_bounds().await)
+}
+
 #[derive(Serialize, Type, Debug)]
 #[serde(rename_all = "camelCase")]
 struct SerializedEditorInstance {
@@ -1022,6 +1501,8 @@ struct SerializedEditorInstance {
     saved_project_config: ProjectConfiguration,
     recordings: ProjectRecordings,
     path: PathBuf,
+    /// Keyframe positions of the screen recording, in recording frames
+    keyframes: Vec<u32>,
 }
 
 #[tauri::command]
@@ -1030,17 +1511,15 @@ async fn create_editor_instance(
     app: AppHandle,
     video_id: String,
 ) -> Result<SerializedEditorInstance, String> {
-    let editor_instance = upsert_editor_instance(&app, video_id).await;
+    let editor_instance = upsert_editor_instance(&app, video_id).await?;
 
     Ok(SerializedEditorInstance {
-        frames_socket_url: format!("ws://localhost:{}{FRAMES_WS_PATH}", editor_instance.ws_port),
+        frames_socket_url: editor_instance.frames_socket_url(),
         recording_duration: editor_instance.recordings.duration(),
-        saved_project_config: {
-            let project_config = editor_instance.project_config.1.borrow();
-            project_config.clone()
-        },
-        recordings: editor_instance.recordings,
+        saved_project_config: editor_instance.get_project(),
+        recordings: editor_instance.recordings.clone(),
         path: editor_instance.project_path.clone(),
+        keyframes: editor_instance.keyframe_positions(),
     })
 }
 
@@ -1052,7 +1531,7 @@ async fn copy_rendered_video_to_clipboard(
     project: ProjectConfiguration,
 ) -> Result<(), String> {
     println!("copying");
-    let editor_instance = upsert_editor_instance(&app, video_id.clone()).await;
+    let editor_instance = upsert_editor_instance(&app, video_id.clone()).await?;
 
     let output_path = match get_rendered_video_impl(editor_instance, project).await {
         Ok(path) => {
@@ -1312,6 +1791,7 @@ enum RenderProgress {
     Starting { total_frames: u32 },
     EstimatedTotalFrames { total_frames: u32 },
     FrameRendered { current_frame: u32 },
+    EncoderSelected { encoder: String, hardware: bool },
 }
 
 #[tauri::command]
@@ -1321,43 +1801,152 @@ async fn render_to_file(
     output_path: PathBuf,
     video_id: String,
     project: ProjectConfiguration,
+    export_fps: Option<u32>,
+    export_config: Option<ExportConfig>,
     progress_channel: tauri::ipc::Channel<RenderProgress>,
-) {
+) -> Result<(), String> {
+    export_to_file(
+        app,
+        output_path,
+        video_id,
+        project,
+        export_fps,
+        export_config,
+        progress_channel,
+        false,
+    )
+    .await
+}
+
+/// Exports the project to `output_path` with the settings of the export preset called
+/// `preset_name`, one saved in the project or a built in one.
+#[tauri::command]
+#[specta::specta]
+async fn export_with_preset(
+    app: AppHandle,
+    output_path: PathBuf,
+    video_id: String,
+    preset_name: String,
+    progress_channel: tauri::ipc::Channel<RenderProgress>,
+) -> Result<(), String> {
+    let editor_instance = upsert_editor_instance(&app, video_id.clone()).await?;
+    let preset = editor_instance.export_preset(&preset_name)?;
+    let project = preset.apply(editor_instance.get_project());
+
+    export_to_file(
+        app,
+        output_path,
+        video_id,
+        project,
+        preset.fps,
+        Some(preset.config),
+        progress_channel,
+        false,
+    )
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_export_presets(app: AppHandle, video_id: String) -> Result<Vec<ExportPreset>, String> {
+    let editor_instance = upsert_editor_instance(&app, video_id).await?;
+
+    Ok(editor_instance.export_presets())
+}
+
+/// Continues an export to `output_path` that was interrupted, from its last checkpoint.
+/// Starts over if there's no checkpoint or it was made with different settings.
+#[tauri::command]
+#[specta::specta]
+async fn resume_export(
+    app: AppHandle,
+    output_path: PathBuf,
+    video_id: String,
+    project: ProjectConfiguration,
+    export_fps: Option<u32>,
+    export_config: Option<ExportConfig>,
+    progress_channel: tauri::ipc::Channel<RenderProgress>,
+) -> Result<(), String> {
+    export_to_file(
+        app,
+        output_path,
+        video_id,
+        project,
+        export_fps,
+        export_config,
+        progress_channel,
+        true,
+    )
+    .await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn export_to_file(
+    app: AppHandle,
+    output_path: PathBuf,
+    video_id: String,
+    project: ProjectConfiguration,
+    export_fps: Option<u32>,
+    export_config: Option<ExportConfig>,
+    progress_channel: tauri::ipc::Channel<RenderProgress>,
+    resume: bool,
+) -> Result<(), String> {
     let (duration, _size) =
-        get_video_metadata(app.clone(), video_id.clone(), Some(VideoType::Screen))
-            .await
-            .unwrap();
+        get_video_metadata(app.clone(), video_id.clone(), Some(VideoType::Screen)).await?;
 
-    // 30 FPS (calculated for output video)
-    let total_frames = (duration * 30.0).round() as u32;
+    let editor_instance = upsert_editor_instance(&app, video_id.clone()).await?;
 
-    let editor_instance = upsert_editor_instance(&app, video_id.clone()).await;
+    // calculated for output video, which defaults to the source frame rate
+    let fps = export_fps.unwrap_or(editor_instance.recordings.display.fps.round() as u32);
+    let total_frames = ((duration + project.intro_duration() + project.outro_duration())
+        * fps as f64)
+        .round() as u32;
+
+    // resumed exports don't start at frame 0
+    let total_sent = std::sync::Once::new();
 
     render_to_file_impl(
         &editor_instance,
         project,
         output_path,
+        Some(fps),
+        export_config.unwrap_or_default(),
+        resume,
+        {
+            let progress_channel = progress_channel.clone();
+            move |encoder| {
+                progress_channel
+                    .send(RenderProgress::EncoderSelected {
+                        encoder: encoder.name().to_string(),
+                        hardware: encoder.hardware.is_some(),
+                    })
+                    .ok();
+            }
+        },
         move |current_frame| {
-            if current_frame == 0 {
+            total_sent.call_once(|| {
                 progress_channel
                     .send(RenderProgress::EstimatedTotalFrames { total_frames })
                     .ok();
-            }
+            });
             progress_channel
                 .send(RenderProgress::FrameRendered { current_frame })
                 .ok();
         },
     )
-    .await
-    .ok();
+    .await?;
 
     ShowCapturesPanel.emit(&app).ok();
+
+    Ok(())
 }
 
 #[tauri::command]
 #[specta::specta]
 async fn set_playhead_position(app: AppHandle, video_id: String, frame_number: u32) {
-    let editor_instance = upsert_editor_instance(&app, video_id).await;
+    let Ok(editor_instance) = upsert_editor_instance(&app, video_id).await else {
+        return;
+    };
 
     editor_instance
         .modify_and_emit_state(|state| {
@@ -1368,16 +1957,100 @@ async fn set_playhead_position(app: AppHandle, video_id: String, frame_number: u
 
 #[tauri::command]
 #[specta::specta]
-async fn set_project_config(app: AppHandle, video_id: String, config: ProjectConfiguration) {
-    let editor_instance = upsert_editor_instance(&app, video_id).await;
+async fn seek_next_marker(app: AppHandle, video_id: String) -> Result<Option<u32>, String> {
+    let editor_instance = upsert_editor_instance(&app, video_id).await?;
+
+    Ok(editor_instance.seek_next_marker().await)
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn seek_prev_marker(app: AppHandle, video_id: String) -> Result<Option<u32>, String> {
+    let editor_instance = upsert_editor_instance(&app, video_id).await?;
+
+    Ok(editor_instance.seek_prev_marker().await)
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_external_audio(
+    app: AppHandle,
+    video_id: String,
+    path: PathBuf,
+    mode: AudioReplaceMode,
+) -> Result<(), String> {
+    let editor_instance = upsert_editor_instance(&app, video_id).await?;
+
+    editor_instance.set_external_audio(path, mode).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn set_project_config(
+    app: AppHandle,
+    video_id: String,
+    config: ProjectConfiguration,
+) -> Result<(), String> {
+    let editor_instance = upsert_editor_instance(&app, video_id).await?;
+
+    let describe = |errors: Vec<cap_project::ConfigError>| {
+        errors
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ")
+    };
+
+    // an invalid config is rejected before its LUT replaces the one in use
+    config.validate().map_err(describe)?;
+
+    if let Some(ColorGrade::Lut { path }) = &config.color_grade {
+        editor_instance.render_constants.load_lut(path)?;
+    }
+
+    let json = serde_json::to_string_pretty(&json!(config)).unwrap();
+
+    editor_instance.set_project(config).map_err(describe)?;
 
     std::fs::write(
         editor_instance.project_path.join("project-config.json"),
-        serde_json::to_string_pretty(&json!(config)).unwrap(),
+        json,
     )
     .unwrap();
 
-    editor_instance.project_config.0.send(config).ok();
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_silent_ranges(
+    app: AppHandle,
+    video_id: String,
+    detection: SilenceDetection,
+) -> Result<Vec<SilentRange>, String> {
+    let editor_instance = upsert_editor_instance(&app, video_id).await?;
+
+    Ok(editor_instance.silent_ranges(&detection))
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn generate_reframe(app: AppHandle, video_id: String) -> Result<Reframe, String> {
+    let editor_instance = upsert_editor_instance(&app, video_id).await?;
+
+    editor_instance.generate_reframe().await
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+fn get_gpu_report() -> GpuReport {
+    EditorInstance::gpu_report()
+}
+
+#[tauri::command(async)]
+#[specta::specta]
+fn get_dependency_status() -> cap_ffmpeg::DependencyStatus {
+    cap_ffmpeg::dependency_status()
 }
 
 #[tauri::command(async)]
@@ -1478,7 +2151,7 @@ async fn upload_rendered_video(
         }
     }
 
-    let editor_instance = upsert_editor_instance(&app, video_id.clone()).await;
+    let editor_instance = upsert_editor_instance(&app, video_id.clone()).await?;
 
     let mut meta = editor_instance.meta();
 
@@ -1786,12 +2459,15 @@ async fn take_screenshot(app: AppHandle, state: MutableState<'_, App>) -> Result
             use cap_project::*;
             RecordingMeta {
                 project_path: recording_dir.clone(),
+                version: RECORDING_META_VERSION,
                 sharing: None,
                 pretty_name: screenshot_name,
                 display: Display {
                     path: screenshot_path.clone(),
+                    extra_segments: vec![],
                 },
                 camera: None,
+                extra_cameras: vec![],
                 audio: None,
                 segments: vec![],
             }
@@ -2088,6 +2764,9 @@ pub async fn run() {
             focus_captures_panel,
             get_current_recording,
             render_to_file,
+            resume_export,
+            export_with_preset,
+            get_export_presets,
             get_rendered_video,
             copy_file_to_path,
             copy_rendered_video_to_clipboard,
@@ -2097,9 +2776,21 @@ pub async fn run() {
             create_editor_instance,
             start_playback,
             stop_playback,
+            scrub_audio,
+            rebuild_frame_index,
+            get_keyframe_positions,
+            detect_content_bounds,
+            get_editor_state,
             set_playhead_position,
+            seek_next_marker,
+            seek_prev_marker,
+            set_external_audio,
             open_in_finder,
             set_project_config,
+            get_silent_ranges,
+            generate_reframe,
+            get_gpu_report,
+            get_dependency_status,
             open_editor,
             open_main_window,
             permissions::open_permission_settings,
@@ -2173,6 +2864,7 @@ pub async fn run() {
             specta_builder.mount_events(app);
             hotkeys::init(app.handle());
             general_settings::init(app.handle());
+            init_editor_logging(app.handle());
 
             let app_handle = app.handle().clone();
 
@@ -2357,6 +3049,34 @@ pub async fn run() {
 
 type EditorInstancesState = Arc<Mutex<HashMap<String, Arc<EditorInstance>>>>;
 
+/// Editors left without rendering, playing or seeking for this long free their decoders
+/// and GPU resources.
+const EDITOR_IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+type EditorLogGuardState = Mutex<Option<LogGuard>>;
+
+/// Prints the editor's logs, and writes them to the app's log directory if enabled in
+/// the general settings.
+fn init_editor_logging(app: &AppHandle) {
+    let log_file = GeneralSettingsStore::get(app)
+        .ok()
+        .flatten()
+        .and_then(|settings| settings.editor_log_level)
+        .and_then(|level| {
+            Some(LogFile {
+                directory: app.path().app_log_dir().ok()?,
+                level,
+            })
+        });
+
+    match cap_editor::init_logging(log_file, env!("CARGO_CRATE_NAME")) {
+        Ok(guard) => {
+            app.manage(EditorLogGuardState::new(guard));
+        }
+        Err(e) => eprintln!("{e}"),
+    }
+}
+
 pub async fn remove_editor_instance(
     app: &AppHandle<impl Runtime>,
     video_id: String,
@@ -2382,7 +3102,10 @@ pub async fn remove_editor_instance(
     result
 }
 
-pub async fn upsert_editor_instance(app: &AppHandle, video_id: String) -> Arc<EditorInstance> {
+pub async fn upsert_editor_instance(
+    app: &AppHandle,
+    video_id: String,
+) -> Result<Arc<EditorInstance>, String> {
     let map = match app.try_state::<EditorInstancesState>() {
         Some(s) => (*s).clone(),
         None => {
@@ -2396,23 +3119,46 @@ pub async fn upsert_editor_instance(app: &AppHandle, video_id: String) -> Arc<Ed
 
     use std::collections::hash_map::Entry;
     match map.entry(video_id.clone()) {
-        Entry::Occupied(o) => o.get().clone(),
+        Entry::Occupied(o) => Ok(o.get().clone()),
         Entry::Vacant(v) => {
-            let instance = create_editor_instance_impl(app, video_id).await;
+            let instance = create_editor_instance_impl(app, video_id).await?;
             v.insert(instance.clone());
-            instance
+            Ok(instance)
         }
     }
 }
 
-async fn create_editor_instance_impl(app: &AppHandle, video_id: String) -> Arc<EditorInstance> {
-    let instance = EditorInstance::new(recordings_path(app), video_id, {
-        let app = app.clone();
-        move |state| {
-            EditorStateChanged::new(state).emit(&app).ok();
-        }
-    })
-    .await;
+async fn create_editor_instance_impl(
+    app: &AppHandle,
+    video_id: String,
+) -> Result<Arc<EditorInstance>, String> {
+    let instance = EditorInstance::new_with_options(
+        recordings_path(app),
+        video_id.clone(),
+        {
+            let app = app.clone();
+            move |state| {
+                EditorStateChanged::new(state).emit(&app).ok();
+            }
+        },
+        EditorInstanceOptions {
+            idle_timeout: Some(EDITOR_IDLE_TIMEOUT),
+            // the disposed instance is dropped so the next command opens a working one
+            on_idle_dispose: Some(Box::new({
+                let app = app.clone();
+                move || {
+                    tauri::async_runtime::spawn(async move {
+                        if let Some(map) = app.try_state::<EditorInstancesState>() {
+                            map.lock().await.remove(&video_id);
+                        }
+                    });
+                }
+            })),
+            ..Default::default()
+        },
+    )
+    .await
+    .map_err(|e| e.to_string())?;
 
     RenderFrameEvent::listen_any(app, {
         let instance = instance.clone();
@@ -2421,7 +3167,12 @@ async fn create_editor_instance_impl(app: &AppHandle, video_id: String) -> Arc<E
         }
     });
 
-    instance
+    tokio::spawn({
+        let instance = instance.clone();
+        async move { instance.warm_up().await }
+    });
+
+    Ok(instance)
 }
 
 // use EditorInstance.project_path instead of this
@@ -2440,3 +3191,198 @@ fn screenshots_path(app: &AppHandle) -> PathBuf {
 fn screenshot_path(app: &AppHandle, screenshot_id: &str) -> PathBuf {
     screenshots_path(app).join(format!("{}.cap", screenshot_id))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cap_project::{SpeedSegment, TimelineConfiguration, TimelineSegment};
+
+    /// `secs` seconds of full scale audio at 3kHz, 100 samples per frame at 30fps.
+    fn audio(secs: usize) -> AudioData {
+        AudioData {
+            buffer: Arc::new(vec![1.0; secs * 3000]),
+            sample_rate: 3000,
+        }
+    }
+
+    /// Frames and samples an export of `video_frames` frames ends up with, sent the way
+    /// `export_impl` sends them.
+    fn export_lengths(
+        audio: &AudioData,
+        video_frames: u32,
+        policy: StreamLengthPolicy,
+    ) -> (u32, Vec<f64>) {
+        let project = ProjectConfiguration::default();
+        let mut samples = Vec::new();
+        let mut frame_count = 0;
+
+        while frame_count < video_frames {
+            let Some(frame_samples) =
+                policy_frame_samples(audio, &project, frame_count, 30, policy)
+            else {
+                break;
+            };
+            samples.extend(frame_samples);
+            frame_count += 1;
+        }
+
+        if policy == StreamLengthPolicy::PadToLongest {
+            while let Some(frame_samples) = audio_frame_samples(audio, &project, frame_count, 30) {
+                samples.extend(frame_samples);
+                frame_count += 1;
+            }
+        }
+
+        (frame_count, samples)
+    }
+
+    #[test]
+    fn short_audio_is_padded_with_silence_to_the_video_length() {
+        let (frames, samples) = export_lengths(&audio(1), 60, StreamLengthPolicy::PadToLongest);
+
+        assert_eq!(frames, 60);
+        assert_eq!(samples.len(), 6000);
+        assert!(samples[..2900].iter().all(|&s| s == 1.0));
+        assert!(samples[3000..].iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn short_audio_truncates_the_export_to_its_length() {
+        let (frames, samples) =
+            export_lengths(&audio(1), 60, StreamLengthPolicy::TruncateToShortest);
+
+        assert_eq!(frames, 30);
+        assert_eq!(samples.len(), 3000);
+    }
+
+    #[test]
+    fn short_video_holds_its_last_frame_until_the_audio_ends() {
+        let (frames, samples) = export_lengths(&audio(3), 60, StreamLengthPolicy::PadToLongest);
+
+        assert_eq!(frames, 90);
+        assert_eq!(samples.len(), 9000);
+    }
+
+    #[test]
+    fn short_video_truncates_the_export_to_its_length() {
+        let (frames, _) = export_lengths(&audio(3), 60, StreamLengthPolicy::TruncateToShortest);
+
+        assert_eq!(frames, 60);
+    }
+
+    /// Lists `parts` more parts as finished, as FFmpeg's segment muxer does once it closes them.
+    fn complete_parts(checkpoint: &ExportCheckpoint, parts: u32) {
+        let list = (0..parts)
+            .map(|i| format!("part-{:05}.mkv,0,10\n", checkpoint.completed_parts + i))
+            .collect::<String>();
+        std::fs::write(
+            checkpoint
+                .dir
+                .join(format!("parts-{}.csv", checkpoint.completed_parts)),
+            list,
+        )
+        .unwrap();
+    }
+
+    fn segment_start_number(checkpoint: &ExportCheckpoint) -> String {
+        let mut command = Command::new("ffmpeg");
+        checkpoint.apply_ffmpeg_args(&mut command, 300, 30);
+        let args = command.get_args().collect::<Vec<_>>();
+        let i = args
+            .iter()
+            .position(|arg| *arg == "-segment_start_number")
+            .unwrap();
+        args[i + 1].to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn interrupted_exports_resume_after_their_completed_parts() {
+        let recording = tempfile::tempdir().unwrap();
+
+        let checkpoint = ExportCheckpoint::open(recording.path(), "settings", true).unwrap();
+        assert_eq!(checkpoint.completed_parts, 0);
+        complete_parts(&checkpoint, 2);
+        // the export is interrupted while FFmpeg writes a third part, which isn't listed
+        std::fs::write(checkpoint.part_path(2), b"partial").unwrap();
+        drop(checkpoint);
+
+        let resumed = ExportCheckpoint::open(recording.path(), "settings", true).unwrap();
+        assert_eq!(resumed.completed_parts, 2);
+        assert_eq!(segment_start_number(&resumed), "2");
+
+        complete_parts(&resumed, 1);
+        drop(resumed);
+
+        let resumed = ExportCheckpoint::open(recording.path(), "settings", true).unwrap();
+        assert_eq!(resumed.completed_parts, 3);
+        assert_eq!(segment_start_number(&resumed), "3");
+    }
+
+    #[test]
+    fn exports_start_over_with_other_settings_or_without_resume() {
+        let recording = tempfile::tempdir().unwrap();
+
+        let checkpoint = ExportCheckpoint::open(recording.path(), "settings", true).unwrap();
+        complete_parts(&checkpoint, 2);
+        drop(checkpoint);
+
+        let other = ExportCheckpoint::open(recording.path(), "other settings", true).unwrap();
+        assert_eq!(other.completed_parts, 0);
+        complete_parts(&other, 2);
+        drop(other);
+
+        let fresh = ExportCheckpoint::open(recording.path(), "other settings", false).unwrap();
+        assert_eq!(fresh.completed_parts, 0);
+        assert!(!fresh.dir.join("parts-0.csv").exists());
+    }
+
+    #[test]
+    fn finishing_without_completed_parts_fails_and_keeps_the_checkpoint() {
+        let recording = tempfile::tempdir().unwrap();
+        let checkpoint = ExportCheckpoint::open(recording.path(), "settings", true).unwrap();
+        let dir = checkpoint.dir.clone();
+
+        let output = recording.path().join("output.mp4");
+        assert!(checkpoint.finish(&output, "mp4").is_err());
+        assert!(dir.join("settings.json").exists());
+    }
+
+    #[test]
+    fn sped_up_exports_have_as_much_audio_as_video() {
+        let audio = audio(4);
+        let mut project = ProjectConfiguration::default();
+        project.timeline = Some(TimelineConfiguration {
+            segments: vec![TimelineSegment {
+                timescale: 1.0,
+                start: 0.0,
+                end: 4.0,
+            }],
+            speed_segments: vec![SpeedSegment {
+                range: (1.0, 3.0),
+                factor: 2.0,
+            }],
+            holds: Vec::new(),
+        });
+
+        // rendering stops at the first frame past the end of the timeline
+        let frames = (0..)
+            .take_while(|&frame| {
+                project
+                    .timeline_position(frame as f64 / 30.0, audio.duration())
+                    .is_some()
+            })
+            .count() as u32;
+        let samples = (0..frames)
+            .map(|frame| {
+                audio_frame_samples(&audio, &project, frame, 30)
+                    .unwrap()
+                    .len()
+            })
+            .sum::<usize>();
+
+        // a second at normal speed either side of two seconds played in one
+        assert_eq!(frames, 90);
+        assert_eq!(samples, 9000);
+        assert!(audio_frame_samples(&audio, &project, frames, 30).is_none());
+    }
+}
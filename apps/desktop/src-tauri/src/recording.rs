@@ -67,6 +67,7 @@ impl InProgressRecording {
         use cap_project::*;
         let meta = RecordingMeta {
             project_path: self.recording_dir.clone(),
+            version: RECORDING_META_VERSION,
             sharing: None,
             pretty_name: format!(
                 "Cap {}",
@@ -78,10 +79,12 @@ impl InProgressRecording {
                     .strip_prefix(&self.recording_dir)
                     .unwrap()
                     .to_owned(),
+                extra_segments: vec![],
             },
             camera: self.camera_output_path.as_ref().map(|path| CameraMeta {
                 path: path.strip_prefix(&self.recording_dir).unwrap().to_owned(),
             }),
+            extra_cameras: vec![],
             audio: self.audio_output_path.as_ref().map(|path| AudioMeta {
                 path: path.strip_prefix(&self.recording_dir).unwrap().to_owned(),
             }),
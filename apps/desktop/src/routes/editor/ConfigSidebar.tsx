@@ -349,6 +349,14 @@ export function ConfigSidebar() {
               step={0.1}
             />
           </Field>
+          <Field name="Shadow" icon={<IconCapShadow />}>
+            <Slider
+              value={[project.background.shadow ?? 0]}
+              onChange={(v) => setProject("background", "shadow", v[0])}
+              minValue={0}
+              maxValue={100}
+            />
+          </Field>
           <ComingSoonTooltip>
             <Field name="Inset" icon={<IconCapInset />}>
               <Slider
@@ -438,17 +446,14 @@ export function ConfigSidebar() {
               step={0.1}
             />
           </Field>
-          <ComingSoonTooltip>
-            <Field name="Shadow" icon={<IconCapShadow />}>
-              <Slider
-                disabled
-                value={[project.camera.shadow]}
-                onChange={(v) => setProject("camera", "shadow", v[0])}
-                minValue={0}
-                maxValue={100}
-              />
-            </Field>
-          </ComingSoonTooltip>
+          <Field name="Shadow" icon={<IconCapShadow />}>
+            <Slider
+              value={[project.camera.shadow]}
+              onChange={(v) => setProject("camera", "shadow", v[0])}
+              minValue={0}
+              maxValue={100}
+            />
+          </Field>
         </KTabs.Content>
         <KTabs.Content value="transcript" class="flex flex-col gap-6">
           <Field name="Transcript" icon={<IconCapMessageBubble />}>
@@ -1,5 +1,4 @@
 import { Button } from "@cap/ui-solid";
-import { trackDeep } from "@solid-primitives/deep";
 import { throttle } from "@solid-primitives/scheduled";
 import { useSearchParams } from "@solidjs/router";
 import {
@@ -38,6 +37,7 @@ import { Header } from "./Header";
 import { Player } from "./Player";
 import { ConfigSidebar } from "./ConfigSidebar";
 import { Timeline } from "./Timeline";
+import { showPlaybackStartError } from "./utils";
 
 export function Editor() {
   const [params] = useSearchParams<{ id: string }>();
@@ -70,7 +70,6 @@ export function Editor() {
 
 function Inner() {
   const {
-    project,
     videoId,
     playbackTime,
     setPlaybackTime,
@@ -105,24 +104,18 @@ function Inner() {
     })
   );
 
-  createEffect(
-    on(
-      () => {
-        trackDeep(project);
-      },
-      () => {
-        renderFrame(playbackTime());
-      }
-    )
-  );
-
   const togglePlayback = async () => {
     try {
       if (playing()) {
         await commands.stopPlayback(videoId);
         setPlaying(false);
       } else {
-        await commands.startPlayback(videoId);
+        const result = await commands.startPlayback(videoId);
+        if (result.status === "error") {
+          setPlaying(false);
+          await showPlaybackStartError(result.error);
+          return;
+        }
         setPlaying(true);
       }
     } catch (error) {
@@ -91,8 +91,14 @@ function ExportButton() {
             };
 
             return commands
-              .renderToFile(p, videoId, project, progress)
-              .then(() => {
+              .renderToFile(p, videoId, project, null, null, progress)
+              .then((result) => {
+                if (result.status === "error") {
+                  console.error("Export failed: ", result.error);
+                  setState(reconcile({ open: false, type: "idle" }));
+                  return;
+                }
+
                 setState({ ...state, type: "finished", path: p });
               });
           });
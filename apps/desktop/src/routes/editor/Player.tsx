@@ -19,7 +19,7 @@ import {
   dropdownContainerClasses,
   topLeftAnimateClasses,
 } from "./ui";
-import { formatTime } from "./utils";
+import { formatTime, showPlaybackStartError } from "./utils";
 import { flags } from "~/flags";
 
 export function Player() {
@@ -181,7 +181,10 @@ export function Player() {
             <button
               type="button"
               onClick={() =>
-                commands.startPlayback(videoId).then(() => setPlaying(true))
+                commands.startPlayback(videoId).then((result) => {
+                  if (result.status === "ok") setPlaying(true);
+                  else showPlaybackStartError(result.error);
+                })
               }
             >
               <IconCapPlayCircle class="size-[1.5rem]" />
@@ -61,9 +61,13 @@ export function Timeline() {
       }}
       onMouseMove={(e) => {
         const { left, width } = timelineBounds;
-        setPreviewTime(
-          editorInstance.recordingDuration * ((e.clientX - left!) / width!)
-        );
+        const time =
+          editorInstance.recordingDuration * ((e.clientX - left!) / width!);
+        setPreviewTime(time);
+
+        // give an audible reference while dragging with the primary button held
+        if (e.buttons & 1)
+          commands.scrubAudio(videoId, Math.max(Math.round(time * 30), 0));
       }}
       onMouseLeave={() => {
         setPreviewTime(undefined);
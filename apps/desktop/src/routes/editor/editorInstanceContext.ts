@@ -25,6 +25,11 @@ export const [EditorInstanceContextProvider, useEditorInstanceContext] =
         setLatestFrame
       );
 
+      // frames are streamed as raw RGBA once the server receives the handshake
+      ws.addEventListener("open", () => {
+        ws.send(JSON.stringify({ format: "rgba" }));
+      });
+
       createEffect(() => {
         if (isConnected()) {
           events.renderFrameEvent.emit({
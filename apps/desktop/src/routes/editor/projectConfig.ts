@@ -16,6 +16,7 @@ export const DEFAULT_PROJECT_CONFIG: ProjectConfiguration = {
     blur: 0,
     padding: 0,
     rounding: 0,
+    shadow: 0,
     inset: 0,
     crop: null,
   },
@@ -1,6 +1,13 @@
+import { message } from "@tauri-apps/plugin-dialog";
+
 export function formatTime(secs: number) {
   const minutes = Math.floor(secs / 60);
   const seconds = Math.round(secs % 60);
 
   return `${minutes}:${seconds.toString().padStart(2, "0")}`;
 }
+
+/** Tells the user why playback couldn't start, e.g. a missing audio device. */
+export async function showPlaybackStartError(error: string) {
+  await message(error, { title: "Couldn't start playback", kind: "error" });
+}
@@ -108,8 +108,45 @@ async getCurrentRecording() : Promise<Result<JsonValue<InProgressRecording | nul
     else return { status: "error", error: e  as any };
 }
 },
-async renderToFile(outputPath: string, videoId: string, project: ProjectConfiguration, progressChannel: TAURI_CHANNEL<RenderProgress>) : Promise<void> {
-    await TAURI_INVOKE("render_to_file", { outputPath, videoId, project, progressChannel });
+async renderToFile(outputPath: string, videoId: string, project: ProjectConfiguration, exportFps: number | null, exportConfig: ExportConfig | null, progressChannel: TAURI_CHANNEL<RenderProgress>) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("render_to_file", { outputPath, videoId, project, exportFps, exportConfig, progressChannel }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Exports the project to `output_path` with the settings of the export preset called
+ * `preset_name`, one saved in the project or a built in one.
+ */
+async exportWithPreset(outputPath: string, videoId: string, presetName: string, progressChannel: TAURI_CHANNEL<RenderProgress>) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("export_with_preset", { outputPath, videoId, presetName, progressChannel }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async getExportPresets(videoId: string) : Promise<Result<ExportPreset[], string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_export_presets", { videoId }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Continues an export to `output_path` that was interrupted, from its last checkpoint.
+ * Starts over if there's no checkpoint or it was made with different settings.
+ */
+async resumeExport(outputPath: string, videoId: string, project: ProjectConfiguration, exportFps: number | null, exportConfig: ExportConfig | null, progressChannel: TAURI_CHANNEL<RenderProgress>) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("resume_export", { outputPath, videoId, project, exportFps, exportConfig, progressChannel }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
 },
 async getRenderedVideo(videoId: string, project: ProjectConfiguration) : Promise<Result<string, string>> {
     try {
@@ -167,20 +204,122 @@ async createEditorInstance(videoId: string) : Promise<Result<SerializedEditorIns
     else return { status: "error", error: e  as any };
 }
 },
-async startPlayback(videoId: string) : Promise<void> {
-    await TAURI_INVOKE("start_playback", { videoId });
+async startPlayback(videoId: string) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("start_playback", { videoId }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
 },
 async stopPlayback(videoId: string) : Promise<void> {
     await TAURI_INVOKE("stop_playback", { videoId });
 },
+async scrubAudio(videoId: string, frameNumber: number) : Promise<void> {
+    await TAURI_INVOKE("scrub_audio", { videoId, frameNumber });
+},
+/**
+ * Repairs seeking in a recording whose video files have a missing or corrupt index.
+ * Sends the fraction of the recording scanned so far over `progress_channel`.
+ */
+async rebuildFrameIndex(videoId: string, progressChannel: TAURI_CHANNEL<number>) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("rebuild_frame_index", { videoId, progressChannel }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async getKeyframePositions(videoId: string) : Promise<Result<number[], string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_keyframe_positions", { videoId }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * First and last recording frames that aren't black, proposed as trim points for
+ * recordings that start or end on black frames.
+ */
+async detectContentBounds(videoId: string) : Promise<Result<[number, number], string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("detect_content_bounds", { videoId }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * The editor's current playhead and playback state, without waiting for a state change.
+ */
+async getEditorState(videoId: string) : Promise<Result<EditorStateSnapshot, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_editor_state", { videoId }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 async setPlayheadPosition(videoId: string, frameNumber: number) : Promise<void> {
     await TAURI_INVOKE("set_playhead_position", { videoId, frameNumber });
 },
+async seekNextMarker(videoId: string) : Promise<Result<number | null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("seek_next_marker", { videoId }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async seekPrevMarker(videoId: string) : Promise<Result<number | null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("seek_prev_marker", { videoId }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async setExternalAudio(videoId: string, path: string, mode: AudioReplaceMode) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("set_external_audio", { videoId, path, mode }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
 async openInFinder(path: string) : Promise<void> {
     await TAURI_INVOKE("open_in_finder", { path });
 },
-async setProjectConfig(videoId: string, config: ProjectConfiguration) : Promise<void> {
-    await TAURI_INVOKE("set_project_config", { videoId, config });
+async setProjectConfig(videoId: string, config: ProjectConfiguration) : Promise<Result<null, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("set_project_config", { videoId, config }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async getSilentRanges(videoId: string, detection: SilenceDetection) : Promise<Result<SilentRange[], string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_silent_ranges", { videoId, detection }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async generateReframe(videoId: string) : Promise<Result<Reframe, string>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("generate_reframe", { videoId }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async getGpuReport() : Promise<GpuReport> {
+    return await TAURI_INVOKE("get_gpu_report");
+},
+async getDependencyStatus() : Promise<DependencyStatus> {
+    return await TAURI_INVOKE("get_dependency_status");
 },
 async openEditor(id: string) : Promise<void> {
     await TAURI_INVOKE("open_editor", { id });
@@ -353,63 +492,103 @@ showCapturesPanel: "show-captures-panel"
 
 /** user-defined types **/
 
+export type AdapterReport = { name: string; backend: string; deviceType: string; driver: string; maxTextureSize: number; missingFeatures: string[]; unmetLimits: string[]; supported: boolean }
 export type AspectRatio = "wide" | "vertical" | "square" | "classic" | "tall"
 export type Audio = { duration: number; sample_rate: number; channels: number }
-export type AudioConfiguration = { mute: boolean; improve: boolean }
+export type AudioConfiguration = { mute: boolean; improve: boolean; fadeInMs?: number; fadeOutMs?: number; skipSilence?: SilenceDetection | null; mutedTracks?: string[]; noiseGate?: NoiseGate | null; normalizeLoudness?: LoudnessNormalization | null; ducking?: Ducking | null }
+export type AudioLevel = { peak: number; rms: number }
 export type AudioMeta = { path: string }
+export type AudioReplaceMode = "Replace" | "Mix"
+export type AudioVisualizer = { style?: AudioVisualizerStyle; position: CameraPosition; width: number; height: number; color: [number, number, number]; bars: number; exportOnly?: boolean }
+export type AudioVisualizerStyle = "bars" | "waveform"
 export type AuthStore = { token: string; expires: number; plan: Plan | null }
-export type BackgroundConfiguration = { source: BackgroundSource; blur: number; padding: number; rounding: number; inset: number; crop: Crop | null }
-export type BackgroundSource = { type: "wallpaper"; id: number } | { type: "image"; path: string | null } | { type: "color"; value: [number, number, number] } | { type: "gradient"; from: [number, number, number]; to: [number, number, number]; angle?: number }
+export type BackgroundConfiguration = { source: BackgroundSource; blur: number; padding: number; rounding: number; shadow?: number; inset: number; crop: Crop | null }
+export type BackgroundSource = { type: "wallpaper"; id: number } | { type: "image"; path: string | null } | { type: "color"; value: [number, number, number] } | { type: "gradient"; from: [number, number, number]; to: [number, number, number]; angle?: number } | { type: "transparent" }
+export type BitDepth = "eight" | "ten"
 export type Bounds = { x: number; y: number; width: number; height: number }
-export type CameraConfiguration = { hide: boolean; mirror: boolean; position: CameraPosition; rounding: number; shadow: number; size: number }
+export type CameraBorder = { width: number; color: [number, number, number] }
+export type CameraConfiguration = { hide: boolean; mirror: boolean; position: CameraPosition; rounding: number; shadow: number; size: number; opacity?: number; shape?: CameraShape | null; border?: CameraBorder | null }
 export type CameraMeta = { path: string }
 export type CameraPosition = { x: CameraXPosition; y: CameraYPosition }
+export type CameraShape = { type: "rectangle" } | { type: "roundedRect"; radius: number } | { type: "circle" }
 export type CameraXPosition = "left" | "center" | "right"
 export type CameraYPosition = "top" | "bottom"
+export type Caption = { startFrame: number; endFrame: number; text: string }
+export type Card = { image: string; durationFrames: number }
 export type CaptureWindow = { id: number; name: string; bounds: Bounds }
+export type ColorGrade = { type: "adjustments"; brightness: number; contrast: number; saturation: number } | { type: "lut"; path: string }
+export type ColorSpace = "srgb" | "rec709" | "rec2020"
 export type Crop = { position: XY<number>; size: XY<number> }
 export type CurrentRecordingChanged = JsonValue<InProgressRecording | null>
-export type CursorConfiguration = { hideWhenIdle: boolean; size: number; type: CursorType }
+export type CursorConfiguration = { hideWhenIdle: boolean; size: number; type: CursorType; smoothing?: number }
 export type CursorType = "pointer" | "circle"
-export type Display = { path: string }
-export type EditorStateChanged = { playhead_position: number }
-export type GeneralSettingsStore = { upload_individual_files: boolean; open_editor_after_recording: boolean; hide_dock_icon?: boolean }
+export type DependencyStatus = { ffmpegVersion: string | null; ffprobeVersion: string | null; audioDecode: boolean; export: boolean; codecs: VideoCodec[] }
+export type Display = { path: string; extra_segments?: string[] }
+export type Ducking = { thresholdDb: number; amountDb: number; attackMs: number; releaseMs: number }
+export type EditorStateChanged = { playhead_position: number; audio_level: AudioLevel | null; idle_disposed: boolean; task_error: string | null; render_warning: string | null }
+export type EditorStateSnapshot = { playheadPosition: number; isPlaying: boolean; audioEnabled: boolean; audioLevel: AudioLevel | null; idleDisposed: boolean }
+export type EncoderPreset = "fast" | "balanced" | "quality"
+export type ExportConfig = { codec?: VideoCodec; rateControl?: RateControl; preset?: EncoderPreset; lengthPolicy?: StreamLengthPolicy; colorSpace?: ColorSpace; bitDepth?: BitDepth; hardwareAcceleration?: HardwareAcceleration; alpha?: boolean; maxSize?: [number, number] | null; extraFfmpegArgs?: string[] }
+export type ExportPreset = { name: string; fps?: number | null; aspectRatio?: AspectRatio | null; config?: ExportConfig }
+export type GeneralSettingsStore = { upload_individual_files: boolean; open_editor_after_recording: boolean; hide_dock_icon?: boolean; editor_log_level?: LogLevel | null }
+export type GpuReport = { adapters: AdapterReport[] }
+export type HardwareAcceleration = "off" | "auto"
+export type Hold = { frame: number; durationFrames: number }
 export type Hotkey = { code: string; meta: boolean; ctrl: boolean; alt: boolean; shift: boolean }
 export type HotkeyAction = "startRecording" | "stopRecording" | "restartRecording" | "takeScreenshot"
 export type HotkeysConfiguration = { show: boolean }
 export type HotkeysStore = { hotkeys: { [key in HotkeyAction]: Hotkey } }
 export type InProgressRecording = { recordingDir: string; displaySource: ScreenCaptureTarget; segments: number[] }
 export type JsonValue<T> = [T]
+export type LogLevel = "Error" | "Warn" | "Info" | "Debug" | "Trace"
+export type LoudnessNormalization = { targetLufs: number }
+export type Marker = { time: number; label?: string | null }
+export type MotionBlur = { strength: number }
 export type NewRecordingAdded = { path: string }
 export type NewScreenshotAdded = { path: string }
+export type NoiseGate = { thresholdDb: number }
 export type OSPermission = "screenRecording" | "camera" | "microphone" | "accessibility"
 export type OSPermissionStatus = "notNeeded" | "empty" | "granted" | "denied"
 export type OSPermissionsCheck = { screenRecording: OSPermissionStatus; microphone: OSPermissionStatus; camera: OSPermissionStatus; accessibility: OSPermissionStatus }
 export type Plan = { upgraded: boolean; last_checked: number }
-export type ProjectConfiguration = { aspectRatio: AspectRatio | null; background: BackgroundConfiguration; camera: CameraConfiguration; audio: AudioConfiguration; cursor: CursorConfiguration; hotkeys: HotkeysConfiguration; timeline?: TimelineConfiguration | null }
-export type ProjectRecordings = { display: Video; camera: Video | null; audio: Audio | null }
-export type RecordingMeta = { pretty_name: string; sharing?: SharingMeta | null; display: Display; camera?: CameraMeta | null; audio?: AudioMeta | null; segments?: RecordingSegment[] }
+export type ProjectConfiguration = { aspectRatio: AspectRatio | null; background: BackgroundConfiguration; camera: CameraConfiguration; extraCameras?: CameraConfiguration[]; audio: AudioConfiguration; cursor: CursorConfiguration; hotkeys: HotkeysConfiguration; timeline?: TimelineConfiguration | null; colorGrade?: ColorGrade | null; captions?: Caption[]; timeBadge?: TimeBadge | null; intro?: Card | null; outro?: Card | null; reframe?: Reframe | null; markers?: Marker[]; splitScreen?: SplitScreen | null; spotlight?: Spotlight | null; motionBlur?: MotionBlur | null; audioVisualizer?: AudioVisualizer | null; exportPresets?: ExportPreset[] }
+export type ProjectRecordings = { display: Video; camera: Video | null; extra_cameras: Video[]; audio: Audio | null }
+export type RateControl = { type: "crf"; value: number } | { type: "bitrate"; kbps: number }
+export type RecordingMeta = { version?: number; pretty_name: string; sharing?: SharingMeta | null; display: Display; camera?: CameraMeta | null; extra_cameras?: CameraMeta[]; audio?: AudioMeta | null; segments?: RecordingSegment[] }
 export type RecordingMetaChanged = { id: string }
 export type RecordingOptions = { captureTarget: ScreenCaptureTarget; cameraLabel: string | null; audioInputName: string | null }
 export type RecordingOptionsChanged = null
 export type RecordingSegment = { start: number; end: number }
 export type RecordingStarted = null
 export type RecordingStopped = { path: string }
+export type Reframe = { keyframes: ReframeKeyframe[] }
+export type ReframeKeyframe = { time: number; x: number }
 export type RenderFrameEvent = { frame_number: number }
-export type RenderProgress = { type: "Starting"; total_frames: number } | { type: "EstimatedTotalFrames"; total_frames: number } | { type: "FrameRendered"; current_frame: number }
+export type RenderProgress = { type: "Starting"; total_frames: number } | { type: "EstimatedTotalFrames"; total_frames: number } | { type: "FrameRendered"; current_frame: number } | { type: "EncoderSelected"; encoder: string; hardware: boolean }
 export type RequestNewScreenshot = null
 export type RequestOpenSettings = { page: string }
 export type RequestRestartRecording = null
 export type RequestStartRecording = null
 export type RequestStopRecording = null
 export type ScreenCaptureTarget = ({ variant: "window" } & CaptureWindow) | { variant: "screen" }
-export type SerializedEditorInstance = { framesSocketUrl: string; recordingDuration: number; savedProjectConfig: ProjectConfiguration; recordings: ProjectRecordings; path: string }
+export type SerializedEditorInstance = { framesSocketUrl: string; recordingDuration: number; savedProjectConfig: ProjectConfiguration; recordings: ProjectRecordings; path: string; keyframes: number[] }
 export type SharingMeta = { id: string; link: string }
 export type ShowCapturesPanel = null
-export type TimelineConfiguration = { segments: TimelineSegment[] }
+export type SilenceDetection = { thresholdDb: number; minDurationMs: number }
+export type SilentRange = { start: number; end: number }
+export type SpeedSegment = { range: [number, number]; factor: number }
+export type SplitScreen = { divider: number }
+export type Spotlight = { center: XY<number>; radius: number; darkness: number; keyframes?: SpotlightKeyframe[] }
+export type SpotlightKeyframe = { time: number; center: XY<number>; radius: number }
+export type StreamLengthPolicy = "padToLongest" | "truncateToShortest"
+export type TimeBadge = { position: CameraPosition; format?: TimeBadgeFormat; style?: TimeBadgeStyle; exportOnly?: boolean }
+export type TimeBadgeFormat = "elapsed" | "elapsedWithFrames" | "elapsedOfTotal"
+export type TimeBadgeStyle = { size: number; color: [number, number, number]; backgroundOpacity: number }
+export type TimelineConfiguration = { segments: TimelineSegment[]; speedSegments?: SpeedSegment[]; holds?: Hold[] }
 export type TimelineSegment = { timescale: number; start: number; end: number }
 export type UploadResult = { Success: string } | "NotAuthenticated" | "PlanCheckFailed" | "UpgradeRequired"
 export type Video = { duration: number; width: number; height: number; fps: number }
+export type VideoCodec = "h264" | "h265" | "vp9" | "av1"
 export type VideoType = "screen" | "output"
 export type XY<T> = { x: T; y: T }
 
@@ -1,4 +1,18 @@
-use std::sync::Arc;
+use cap_ffmpeg::FFmpeg;
+use cap_project::{
+    AudioConfiguration, Ducking, LoudnessNormalization, NoiseGate, ProjectConfiguration,
+    SilenceDetection,
+};
+use cap_rendering::VisualizerAudio;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::{
+    ops::Range,
+    path::Path,
+    sync::{Arc, Mutex, Weak},
+};
+
+use crate::project_recordings::Audio;
 
 #[derive(Clone)]
 pub struct AudioData {
@@ -6,3 +20,1232 @@ pub struct AudioData {
     pub sample_rate: u32,
     // pub channels: u18
 }
+
+impl From<&AudioData> for VisualizerAudio {
+    fn from(audio: &AudioData) -> Self {
+        Self {
+            samples: audio.buffer.clone(),
+            sample_rate: audio.sample_rate,
+        }
+    }
+}
+
+impl AudioData {
+    /// Decodes the audio file to f64 samples using the stream parameters ffmpeg
+    /// actually reports, retrying if they disagree with `recording` or if the
+    /// first decode fails.
+    pub fn decode(path: &Path, recording: &Audio) -> Result<Self, String> {
+        let requested = StreamParams {
+            sample_rate: recording.sample_rate,
+            channels: Some(recording.channels),
+        };
+
+        let (buffer, sample_rate) = match run_decode(path, Some(requested)) {
+            Ok((buffer, Some(actual)))
+                if actual.sample_rate != requested.sample_rate
+                    || actual
+                        .channels
+                        .is_some_and(|c| Some(c) != requested.channels) =>
+            {
+                tracing::warn!(
+                    "Audio metadata for {} doesn't match the stream ({requested:?} vs {actual:?}), decoding with the stream's parameters",
+                    path.display(),
+                );
+                drop(buffer);
+
+                let actual = StreamParams {
+                    channels: actual.channels.or(requested.channels),
+                    ..actual
+                };
+
+                let (buffer, _) = run_decode(path, Some(actual))?;
+                (buffer, actual.sample_rate)
+            }
+            Ok((buffer, _)) => (buffer, requested.sample_rate),
+            Err(e) => {
+                tracing::warn!("{e}, retrying with auto-detected format");
+
+                let (buffer, actual) = run_decode(path, None)?;
+                let actual = actual.ok_or(format!(
+                    "Could not determine the sample rate of {}",
+                    path.display()
+                ))?;
+
+                (buffer, actual.sample_rate)
+            }
+        };
+
+        tracing::debug!("audio buffer length: {}", buffer.len());
+
+        Ok(AudioData {
+            buffer: Arc::new(buffer),
+            sample_rate,
+        })
+    }
+
+    /// Decodes an audio file of any format, resampled to `sample_rate` with `quality` and
+    /// mixed to `channels` channels so it can stand in for or be mixed with a recording's audio.
+    pub fn decode_resampled(
+        path: &Path,
+        sample_rate: u32,
+        channels: u16,
+        quality: ResampleQuality,
+    ) -> Result<Self, String> {
+        let (buffer, source) = run_decode_channels(path, channels)?;
+        let source = source.ok_or(format!(
+            "Could not determine the sample rate of {}",
+            path.display()
+        ))?;
+
+        Ok(AudioData {
+            buffer: Arc::new(resample(
+                &buffer,
+                channels as usize,
+                source.sample_rate,
+                sample_rate,
+                quality,
+            )),
+            sample_rate,
+        })
+    }
+
+    /// Sums this audio with `other`, which must have the same sample rate, clamping the
+    /// result to avoid wrapping. The shorter of the two is padded with silence.
+    pub fn mixed_with(&self, other: &Self) -> Self {
+        let (longer, shorter) = if self.buffer.len() >= other.buffer.len() {
+            (&self.buffer, &other.buffer)
+        } else {
+            (&other.buffer, &self.buffer)
+        };
+
+        let mut buffer = longer.to_vec();
+        for (sample, other) in buffer.iter_mut().zip(shorter.iter()) {
+            *sample = (*sample + other).clamp(-1.0, 1.0);
+        }
+
+        Self {
+            buffer: Arc::new(buffer),
+            sample_rate: self.sample_rate,
+        }
+    }
+
+    /// Lowers this audio by the ducking amount wherever `voice`, which must have the same
+    /// sample rate, is louder than the ducking threshold, so speech stays clear over it.
+    /// The gain eases towards its target over the attack and release times so it doesn't pump.
+    pub fn ducked_under(&self, voice: &Self, ducking: &Ducking) -> Self {
+        let window = ((self.sample_rate as f64 * SILENCE_WINDOW_SECS) as usize).max(1);
+        let threshold = 10f64.powf(ducking.threshold_db as f64 / 20.0);
+        let ducked_gain = 10f64.powf(-ducking.amount_db.abs() as f64 / 20.0);
+        // per sample step towards the target gain, covering most of the way in `ms`
+        let smoothing = |ms: u32| {
+            let samples = ms as f64 / 1000.0 * self.sample_rate as f64;
+            if samples < 1.0 {
+                1.0
+            } else {
+                1.0 - (-1.0 / samples).exp()
+            }
+        };
+        let (attack, release) = (smoothing(ducking.attack_ms), smoothing(ducking.release_ms));
+
+        let mut buffer = self.buffer.to_vec();
+        let mut gain = 1.0;
+
+        for (i, chunk) in buffer.chunks_mut(window).enumerate() {
+            let start = i * window;
+            let voice_chunk = voice
+                .buffer
+                .get(start..(start + window).min(voice.buffer.len()))
+                .unwrap_or(&[]);
+            let speaking = !voice_chunk.is_empty()
+                && (voice_chunk.iter().map(|s| s * s).sum::<f64>() / voice_chunk.len() as f64)
+                    .sqrt()
+                    >= threshold;
+
+            let target = if speaking { ducked_gain } else { 1.0 };
+            let step = if target < gain { attack } else { release };
+
+            for sample in chunk {
+                gain += (target - gain) * step;
+                *sample *= gain;
+            }
+        }
+
+        Self {
+            buffer: Arc::new(buffer),
+            sample_rate: self.sample_rate,
+        }
+    }
+
+    /// Appends silence so the audio lasts at least `duration` seconds.
+    /// Returns the padded audio and how many seconds of silence were added.
+    pub fn padded_to(self, duration: f64) -> (Self, f64) {
+        let expected_len = (duration * self.sample_rate as f64).ceil() as usize;
+        if self.buffer.len() >= expected_len {
+            return (self, 0.0);
+        }
+
+        let padding = (expected_len - self.buffer.len()) as f64 / self.sample_rate as f64;
+        let mut buffer = Arc::unwrap_or_clone(self.buffer);
+        buffer.resize(expected_len, 0.0);
+
+        (
+            Self {
+                buffer: Arc::new(buffer),
+                sample_rate: self.sample_rate,
+            },
+            padding,
+        )
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.buffer.len() as f64 / self.sample_rate as f64
+    }
+
+    /// Bytes used by the decoded samples.
+    pub fn size_bytes(&self) -> usize {
+        self.buffer.len() * std::mem::size_of::<f64>()
+    }
+
+    /// Downsamples the audio by averaging neighbouring samples until it uses at most
+    /// `max_bytes`, without going below `MIN_SAMPLE_RATE`. The new sample rate always
+    /// divides the original so the duration is unchanged.
+    pub fn fit_to(self, max_bytes: usize) -> Self {
+        let size = self.size_bytes();
+        if size <= max_bytes {
+            return self;
+        }
+
+        let needed = size.div_ceil(max_bytes.max(1)) as u32;
+        let max_factor = (self.sample_rate / MIN_SAMPLE_RATE).max(1);
+        let Some(factor) = (needed..=max_factor)
+            .find(|f| self.sample_rate % f == 0)
+            .or_else(|| (1..=max_factor).rev().find(|f| self.sample_rate % f == 0))
+            .filter(|&f| f > 1)
+        else {
+            return self;
+        };
+
+        tracing::info!(
+            "Downsampling audio from {}Hz to {}Hz to fit memory budget",
+            self.sample_rate,
+            self.sample_rate / factor
+        );
+
+        let buffer = self
+            .buffer
+            .chunks(factor as usize)
+            .map(|chunk| chunk.iter().sum::<f64>() / chunk.len() as f64)
+            .collect();
+
+        Self {
+            buffer: Arc::new(buffer),
+            sample_rate: self.sample_rate / factor,
+        }
+    }
+
+    /// Applies the configuration's noise gate and loudness normalization, in that order.
+    /// Returns the audio unchanged if neither is enabled.
+    pub fn processed(&self, config: &AudioConfiguration) -> Self {
+        if config.noise_gate.is_none() && config.normalize_loudness.is_none() {
+            return self.clone();
+        }
+
+        let mut buffer = self.buffer.to_vec();
+
+        if let Some(gate) = &config.noise_gate {
+            self.gate(&mut buffer, gate.threshold_db as f64);
+        }
+
+        if let Some(normalization) = &config.normalize_loudness {
+            normalize(
+                &mut buffer,
+                self.sample_rate,
+                normalization.target_lufs as f64,
+            );
+        }
+
+        Self {
+            buffer: Arc::new(buffer),
+            sample_rate: self.sample_rate,
+        }
+    }
+
+    /// Silences windows quieter than `threshold_db`, ramping the gain across each
+    /// window so the gate opening and closing doesn't click.
+    fn gate(&self, buffer: &mut [f64], threshold_db: f64) {
+        let window = ((self.sample_rate as f64 * SILENCE_WINDOW_SECS) as usize).max(1);
+        let threshold = 10f64.powf(threshold_db / 20.0);
+        let mut previous_gain = 1.0;
+
+        for chunk in buffer.chunks_mut(window) {
+            let rms = (chunk.iter().map(|s| s * s).sum::<f64>() / chunk.len() as f64).sqrt();
+            let gain = if rms < threshold { 0.0 } else { 1.0 };
+
+            let len = chunk.len() as f64;
+            for (i, sample) in chunk.iter_mut().enumerate() {
+                *sample *= previous_gain + (gain - previous_gain) * (i + 1) as f64 / len;
+            }
+
+            previous_gain = gain;
+        }
+    }
+
+    /// Finds stretches of the recording, in recording time, whose loudness stays below
+    /// the detection threshold for at least its minimum duration.
+    pub fn detect_silence(&self, detection: &SilenceDetection) -> Vec<SilentRange> {
+        let window = ((self.sample_rate as f64 * SILENCE_WINDOW_SECS) as usize).max(1);
+        let threshold = 10f64.powf(detection.threshold_db as f64 / 20.0);
+        let min_duration = detection.min_duration_ms as f64 / 1000.0;
+
+        let mut ranges = vec![];
+        let mut silence_start = None;
+
+        let mut push_range = |start: f64, end: f64| {
+            if end - start >= min_duration {
+                ranges.push(SilentRange { start, end });
+            }
+        };
+
+        for (i, chunk) in self.buffer.chunks(window).enumerate() {
+            let rms = (chunk.iter().map(|s| s * s).sum::<f64>() / chunk.len() as f64).sqrt();
+            let time = (i * window) as f64 / self.sample_rate as f64;
+
+            match (rms < threshold, silence_start) {
+                (true, None) => silence_start = Some(time),
+                (false, Some(start)) => {
+                    push_range(start, time);
+                    silence_start = None;
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(start) = silence_start {
+            push_range(start, self.duration());
+        }
+
+        ranges
+    }
+
+    /// Sample range of a `window` seconds long slice centered on `time`,
+    /// clamped to the buffer.
+    pub fn window_range(&self, time: f64, window: f64) -> Range<usize> {
+        let len = self.buffer.len();
+        let half = (window * self.sample_rate as f64 / 2.0) as usize;
+        let center = ((time.max(0.0) * self.sample_rate as f64) as usize).min(len);
+
+        center.saturating_sub(half)..(center + half).min(len)
+    }
+
+    /// The slice of audio played while scrubbing to `time`, with its edges faded
+    /// so bursts don't click.
+    pub fn scrub_burst(&self, time: f64) -> Vec<f64> {
+        let samples = &self.buffer[self.window_range(time, SCRUB_WINDOW_SECS)];
+        let fade_len = ((SCRUB_FADE_SECS * self.sample_rate as f64) as usize).max(1);
+
+        samples
+            .iter()
+            .enumerate()
+            .map(|(i, sample)| {
+                let edge_distance = i.min(samples.len() - 1 - i);
+                sample * (edge_distance as f64 / fade_len as f64).min(1.0)
+            })
+            .collect()
+    }
+}
+
+/// The last audio processed by `ProcessedAudioCache::get`, so playback doesn't gate
+/// and normalize the whole recording again every time it starts.
+#[derive(Default)]
+pub struct ProcessedAudioCache(Mutex<Option<ProcessedAudio>>);
+
+struct ProcessedAudio {
+    // weak so the cache doesn't keep audio that's since been replaced alive
+    source: Weak<Vec<f64>>,
+    noise_gate: Option<NoiseGate>,
+    normalize_loudness: Option<LoudnessNormalization>,
+    audio: AudioData,
+}
+
+impl ProcessedAudioCache {
+    /// `audio` processed with `config` as `AudioData::processed` does, reusing the cached
+    /// result when the audio, noise gate and loudness normalization match the last call's.
+    pub fn get(&self, audio: &AudioData, config: &AudioConfiguration) -> AudioData {
+        let mut cached = self.0.lock().unwrap();
+
+        if let Some(cached) = cached.as_ref().filter(|cached| {
+            cached.source.as_ptr() == Arc::as_ptr(&audio.buffer)
+                && cached.noise_gate == config.noise_gate
+                && cached.normalize_loudness == config.normalize_loudness
+        }) {
+            return cached.audio.clone();
+        }
+
+        let processed = audio.processed(config);
+        *cached = Some(ProcessedAudio {
+            source: Arc::downgrade(&audio.buffer),
+            noise_gate: config.noise_gate.clone(),
+            normalize_loudness: config.normalize_loudness.clone(),
+            audio: processed.clone(),
+        });
+
+        processed
+    }
+
+    /// Drops the cached audio, eg. once the config it was processed with changes.
+    pub fn clear(&self) {
+        *self.0.lock().unwrap() = None;
+    }
+}
+
+// audio is never downsampled below this to fit a memory budget
+const MIN_SAMPLE_RATE: u32 = 8000;
+
+// loudness is measured over windows of this length when detecting silence and gating noise
+const SILENCE_WINDOW_SECS: f64 = 0.01;
+
+// integrated loudness is measured over overlapping blocks as specified by ITU-R BS.1770
+const LOUDNESS_BLOCK_SECS: f64 = 0.4;
+const LOUDNESS_BLOCK_OVERLAP: f64 = 0.75;
+const LOUDNESS_ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const LOUDNESS_RELATIVE_GATE_LU: f64 = -10.0;
+
+// length of audio played around the playhead while scrubbing, and of the fade at each end
+const SCRUB_WINDOW_SECS: f64 = 0.05;
+const SCRUB_FADE_SECS: f64 = 0.005;
+
+// input samples either side of an output sample the sinc filter reaches, at full bandwidth
+const SINC_HALF_WIDTH: f64 = 16.0;
+
+/// How audio is interpolated when it's played or converted at a different sample rate.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum ResampleQuality {
+    /// Interpolates between neighbouring samples. Cheap enough for realtime, but high
+    /// frequencies fold back into audible aliasing, most of all when downsampling.
+    #[default]
+    Linear,
+    /// Band-limited interpolation with a windowed sinc filter, which keeps aliasing
+    /// inaudible at dozens of times the cost of `Linear` per sample.
+    Sinc,
+}
+
+impl ResampleQuality {
+    /// Value of `samples` at the fractional index `position`, when reading them `step`
+    /// samples apart. A `step` above 1 downsamples. Samples outside `samples` are silent.
+    pub fn sample_at(&self, samples: &[f64], position: f64, step: f64) -> f64 {
+        let sample = |index: isize| {
+            usize::try_from(index)
+                .ok()
+                .and_then(|index| samples.get(index))
+                .copied()
+                .unwrap_or(0.0)
+        };
+        let index = position.floor() as isize;
+
+        match self {
+            Self::Linear => {
+                let frac = position - index as f64;
+                sample(index) * (1.0 - frac) + sample(index + 1) * frac
+            }
+            Self::Sinc => {
+                // when downsampling, the cutoff is lowered to what the new rate can hold
+                let cutoff = (1.0 / step).min(1.0);
+                let half_width = (SINC_HALF_WIDTH / cutoff).ceil();
+
+                let (mut sum, mut weights) = (0.0, 0.0);
+                for tap in index - half_width as isize + 1..=index + half_width as isize {
+                    let distance = position - tap as f64;
+                    let weight = sinc(distance * cutoff) * blackman(distance / half_width);
+                    sum += sample(tap) * weight;
+                    weights += weight;
+                }
+
+                if weights == 0.0 {
+                    0.0
+                } else {
+                    sum / weights
+                }
+            }
+        }
+    }
+}
+
+/// Resampling quality for each use of the editor's audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct AudioResampling {
+    /// Used while playing and scrubbing, which resample to the output device's rate.
+    pub preview: ResampleQuality,
+    /// Used for audio converted ahead of time, like external audio, which is also exported.
+    pub export: ResampleQuality,
+}
+
+impl Default for AudioResampling {
+    fn default() -> Self {
+        Self {
+            preview: ResampleQuality::Linear,
+            export: ResampleQuality::Sinc,
+        }
+    }
+}
+
+/// Converts interleaved `samples` with `channels` channels from `from_rate` to `to_rate`.
+fn resample(
+    samples: &[f64],
+    channels: usize,
+    from_rate: u32,
+    to_rate: u32,
+    quality: ResampleQuality,
+) -> Vec<f64> {
+    if from_rate == to_rate || channels == 0 {
+        return samples.to_vec();
+    }
+
+    let planes = (0..channels)
+        .map(|channel| {
+            samples
+                .iter()
+                .skip(channel)
+                .step_by(channels)
+                .copied()
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let step = from_rate as f64 / to_rate as f64;
+    let frames = (planes[0].len() as f64 / step).round() as usize;
+
+    (0..frames)
+        .flat_map(|frame| {
+            let position = frame as f64 * step;
+            planes
+                .iter()
+                .map(move |plane| quality.sample_at(plane, position, step))
+        })
+        .collect()
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        let x = x * std::f64::consts::PI;
+        x.sin() / x
+    }
+}
+
+/// Blackman window over -1..=1, tapering the sinc filter so cutting it off doesn't ring.
+fn blackman(t: f64) -> f64 {
+    if t.abs() >= 1.0 {
+        return 0.0;
+    }
+
+    let x = std::f64::consts::PI * t;
+    0.42 + 0.5 * x.cos() + 0.08 * (2.0 * x).cos()
+}
+
+/// How audio from an external file is combined with the recording's own audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum AudioReplaceMode {
+    /// Plays only the external audio, like a dub.
+    Replace,
+    /// Plays the external audio over the recording's, like background music.
+    Mix,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Type)]
+pub struct SilentRange {
+    pub start: f64,
+    pub end: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct StreamParams {
+    sample_rate: u32,
+    channels: Option<u16>,
+}
+
+/// Runs ffmpeg to decode `path` to raw f64le samples, resampling to `params` if provided.
+/// Also returns the input stream's parameters as reported by ffmpeg.
+fn run_decode(
+    path: &Path,
+    params: Option<StreamParams>,
+) -> Result<(Vec<f64>, Option<StreamParams>), String> {
+    // TODO: Use ffmpeg crate instead of command line
+    let mut ffmpeg = FFmpeg::new();
+    ffmpeg
+        .command
+        .arg("-i")
+        .arg(path)
+        .args(["-f", "f64le", "-acodec", "pcm_f64le"]);
+
+    if let Some(params) = params {
+        ffmpeg
+            .command
+            .args(["-ar", &params.sample_rate.to_string()]);
+
+        if let Some(channels) = params.channels {
+            ffmpeg.command.args(["-ac", &channels.to_string()]);
+        }
+    }
+
+    read_decode_output(path, ffmpeg)
+}
+
+/// Runs the ffmpeg decode of `path` set up in `ffmpeg`, writing samples to stdout.
+fn read_decode_output(
+    path: &Path,
+    mut ffmpeg: FFmpeg,
+) -> Result<(Vec<f64>, Option<StreamParams>), String> {
+    let output = ffmpeg
+        .command
+        .arg("-")
+        .output()
+        .map_err(|e| format!("Failed to run ffmpeg: {e}"))?;
+
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(format!(
+            "ffmpeg failed to decode audio from {}",
+            path.display()
+        ));
+    }
+
+    let buffer = output
+        .stdout
+        .chunks_exact(8)
+        .map(|c| f64::from_le_bytes([c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]]))
+        .collect::<Vec<_>>();
+
+    Ok((
+        buffer,
+        parse_input_stream(&String::from_utf8_lossy(&output.stderr)),
+    ))
+}
+
+/// Runs ffmpeg to decode `path` to raw f64le samples at the stream's own sample rate,
+/// mixed to `channels` channels, returning the input stream's parameters alongside them.
+fn run_decode_channels(
+    path: &Path,
+    channels: u16,
+) -> Result<(Vec<f64>, Option<StreamParams>), String> {
+    let mut ffmpeg = FFmpeg::new();
+    ffmpeg
+        .command
+        .arg("-i")
+        .arg(path)
+        .args(["-f", "f64le", "-acodec", "pcm_f64le"])
+        .args(["-ac", &channels.to_string()]);
+
+    read_decode_output(path, ffmpeg)
+}
+
+/// Parses the first audio stream line of ffmpeg's log output, which describes the input,
+/// eg. `Stream #0:0: Audio: aac (LC), 48000 Hz, stereo, fltp, 128 kb/s`.
+fn parse_input_stream(log: &str) -> Option<StreamParams> {
+    let line = log.lines().find(|line| line.contains("Audio:"))?;
+    let mut parts = line.split(',').map(str::trim);
+
+    let sample_rate = parts
+        .find_map(|part| part.strip_suffix(" Hz"))?
+        .parse()
+        .ok()?;
+
+    let channels = parts.next().and_then(|layout| match layout {
+        "mono" => Some(1),
+        "stereo" => Some(2),
+        layout => layout.strip_suffix(" channels")?.parse().ok(),
+    });
+
+    Some(StreamParams {
+        sample_rate,
+        channels,
+    })
+}
+
+/// Id of the track holding the recorded microphone input, currently the only audio track.
+pub const MICROPHONE_TRACK: &str = "microphone";
+
+/// Ids of the audio tracks a recording can contain. System audio isn't recorded,
+/// so the microphone is the only one.
+pub const AUDIO_TRACKS: [&str; 1] = [MICROPHONE_TRACK];
+
+/// Gain applied to `track_id` when mixing the project's audio, silencing it if the
+/// project mutes it.
+pub fn track_gain(project: &ProjectConfiguration, track_id: &str) -> f64 {
+    if project.audio.is_track_muted(track_id) {
+        0.0
+    } else {
+        1.0
+    }
+}
+
+/// Scales `buffer` so its integrated loudness reaches `target_lufs`, without raising
+/// its peak above full scale. Audio too quiet to measure is left alone.
+fn normalize(buffer: &mut [f64], sample_rate: u32, target_lufs: f64) {
+    let Some(loudness) = integrated_loudness(buffer, sample_rate) else {
+        return;
+    };
+
+    let peak = buffer.iter().fold(0.0f64, |peak, s| peak.max(s.abs()));
+    let gain = 10f64
+        .powf((target_lufs - loudness) / 20.0)
+        .min(1.0 / peak.max(f64::EPSILON));
+
+    tracing::info!("Normalizing audio from {loudness:.1} LUFS with gain {gain:.2}");
+
+    for sample in buffer {
+        *sample *= gain;
+    }
+}
+
+/// Integrated loudness of mono audio in LUFS, measured as in ITU-R BS.1770 with
+/// K-weighting and gating. `None` if every block is below the absolute gate.
+fn integrated_loudness(buffer: &[f64], sample_rate: u32) -> Option<f64> {
+    let weighted = k_weighted(buffer, sample_rate);
+
+    let block = ((sample_rate as f64 * LOUDNESS_BLOCK_SECS) as usize).max(1);
+    let step = ((block as f64 * (1.0 - LOUDNESS_BLOCK_OVERLAP)) as usize).max(1);
+
+    let mut powers = (0..weighted.len().saturating_sub(block) + 1)
+        .step_by(step)
+        .map(|start| {
+            let block = &weighted[start..(start + block).min(weighted.len())];
+            block.iter().map(|s| s * s).sum::<f64>() / block.len().max(1) as f64
+        })
+        .collect::<Vec<_>>();
+
+    let loudness = |power: f64| -0.691 + 10.0 * power.log10();
+    let mean = |powers: &[f64]| powers.iter().sum::<f64>() / powers.len() as f64;
+
+    powers.retain(|&power| loudness(power) > LOUDNESS_ABSOLUTE_GATE_LUFS);
+    if powers.is_empty() {
+        return None;
+    }
+
+    let relative_gate = loudness(mean(&powers)) + LOUDNESS_RELATIVE_GATE_LU;
+    powers.retain(|&power| loudness(power) > relative_gate);
+
+    Some(loudness(mean(&powers)))
+}
+
+/// Applies the BS.1770 K-weighting filter, a high shelf followed by a high pass,
+/// with coefficients derived for `sample_rate`.
+fn k_weighted(buffer: &[f64], sample_rate: u32) -> Vec<f64> {
+    let fs = sample_rate as f64;
+
+    let shelf = {
+        let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+        let k = (std::f64::consts::PI * f0 / fs).tan();
+        let vh = 10f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+            2.0 * (k * k - 1.0) / a0,
+            (1.0 - k / q + k * k) / a0,
+        ]
+    };
+
+    let high_pass = {
+        let (f0, q) = (38.13547087602444, 0.5003270373238773);
+        let k = (std::f64::consts::PI * f0 / fs).tan();
+        let a0 = 1.0 + k / q + k * k;
+        [
+            1.0,
+            -2.0,
+            1.0,
+            2.0 * (k * k - 1.0) / a0,
+            (1.0 - k / q + k * k) / a0,
+        ]
+    };
+
+    biquad(&biquad(buffer, shelf), high_pass)
+}
+
+fn biquad(input: &[f64], [b0, b1, b2, a1, a2]: [f64; 5]) -> Vec<f64> {
+    let (mut x1, mut x2, mut y1, mut y2) = (0.0, 0.0, 0.0, 0.0);
+
+    input
+        .iter()
+        .map(|&x| {
+            let y = b0 * x + b1 * x1 + b2 * x2 - a1 * y1 - a2 * y2;
+            (x2, x1, y2, y1) = (x1, x, y1, y);
+            y
+        })
+        .collect()
+}
+
+/// Gain for the sample at `time` (in timeline time) after applying the project's
+/// fade in/out ramps at the boundaries of the clip it belongs to.
+/// Without a timeline the whole recording is treated as a single clip.
+pub fn fade_gain(project: &ProjectConfiguration, time: f64, recording_duration: f64) -> f64 {
+    let audio = &project.audio;
+
+    if audio.fade_in_ms == 0 && audio.fade_out_ms == 0 {
+        return 1.0;
+    }
+
+    let Some((start, end)) = (match project.timeline() {
+        Some(timeline) => timeline.get_segment_bounds(time),
+        None => Some((0.0, recording_duration)),
+    }) else {
+        return 1.0;
+    };
+
+    let clip_duration = end - start;
+    let fade_in = (audio.fade_in_ms as f64 / 1000.0).min(clip_duration);
+    let fade_out = (audio.fade_out_ms as f64 / 1000.0).min(clip_duration);
+
+    let mut gain = 1.0;
+
+    if fade_in > 0.0 {
+        gain *= ((time - start) / fade_in).clamp(0.0, 1.0);
+    }
+
+    if fade_out > 0.0 {
+        gain *= ((end - time) / fade_out).clamp(0.0, 1.0);
+    }
+
+    gain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cap_project::{TimelineConfiguration, TimelineSegment};
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    fn audio(buffer: Vec<f64>, sample_rate: u32) -> AudioData {
+        AudioData {
+            buffer: Arc::new(buffer),
+            sample_rate,
+        }
+    }
+
+    /// `secs` seconds of each `(amplitude, secs)` stretch in turn, at 1kHz.
+    fn stretches(stretches: &[(f64, f64)]) -> AudioData {
+        let buffer = stretches
+            .iter()
+            .flat_map(|&(amplitude, secs)| {
+                // alternating signs, so the stretch is a square wave of `amplitude`
+                (0..(secs * 1000.0).round() as usize)
+                    .map(move |i| amplitude * (1 - (i % 2) as i32 * 2) as f64)
+            })
+            .collect();
+
+        audio(buffer, 1000)
+    }
+
+    fn faded(fade_in_ms: u32, fade_out_ms: u32) -> ProjectConfiguration {
+        let mut project = ProjectConfiguration::default();
+        project.audio.fade_in_ms = fade_in_ms;
+        project.audio.fade_out_ms = fade_out_ms;
+        project
+    }
+
+    #[test]
+    fn fade_gain_is_unity_without_fades() {
+        let project = faded(0, 0);
+
+        assert_close(fade_gain(&project, 0.0, 10.0), 1.0);
+        assert_close(fade_gain(&project, 10.0, 10.0), 1.0);
+    }
+
+    #[test]
+    fn fade_gain_ramps_at_the_ends_of_the_recording() {
+        let project = faded(1000, 2000);
+
+        assert_close(fade_gain(&project, 0.0, 10.0), 0.0);
+        assert_close(fade_gain(&project, 0.5, 10.0), 0.5);
+        assert_close(fade_gain(&project, 5.0, 10.0), 1.0);
+        assert_close(fade_gain(&project, 9.0, 10.0), 0.5);
+        assert_close(fade_gain(&project, 10.0, 10.0), 0.0);
+    }
+
+    #[test]
+    fn fade_gain_ramps_at_each_clip_boundary() {
+        let mut project = faded(1000, 1000);
+        project.timeline = Some(TimelineConfiguration {
+            segments: vec![
+                TimelineSegment {
+                    timescale: 1.0,
+                    start: 0.0,
+                    end: 4.0,
+                },
+                TimelineSegment {
+                    timescale: 1.0,
+                    start: 6.0,
+                    end: 10.0,
+                },
+            ],
+            ..Default::default()
+        });
+
+        // the second clip starts 4 seconds into the timeline
+        assert_close(fade_gain(&project, 3.5, 10.0), 0.5);
+        assert_close(fade_gain(&project, 4.0, 10.0), 0.0);
+        assert_close(fade_gain(&project, 4.5, 10.0), 0.5);
+        assert_close(fade_gain(&project, 6.0, 10.0), 1.0);
+    }
+
+    #[test]
+    fn fade_gain_shortens_fades_longer_than_the_clip() {
+        let project = faded(4000, 0);
+
+        // the fade in is limited to the clip's 2 seconds
+        assert_close(fade_gain(&project, 1.0, 2.0), 0.5);
+    }
+
+    #[test]
+    fn parses_input_stream_sample_rate_and_channels() {
+        let log = "\
+Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'audio.mp4':
+  Stream #0:0[0x1](und): Audio: aac (LC) (mp4a / 0x6134706D), 44100 Hz, stereo, fltp, 128 kb/s
+Output #0, f64le, to 'pipe:':
+  Stream #0:0(und): Audio: pcm_f64le, 48000 Hz, mono, dbl, 3072 kb/s";
+
+        let params = parse_input_stream(log).unwrap();
+
+        assert_eq!(params.sample_rate, 44100);
+        assert_eq!(params.channels, Some(2));
+    }
+
+    #[test]
+    fn parses_mono_and_counted_channel_layouts() {
+        let mono = parse_input_stream("Stream #0:0: Audio: mp3, 22050 Hz, mono, fltp").unwrap();
+        let counted =
+            parse_input_stream("Stream #0:0: Audio: pcm_s16le, 8000 Hz, 3 channels, s16").unwrap();
+
+        assert_eq!((mono.sample_rate, mono.channels), (22050, Some(1)));
+        assert_eq!((counted.sample_rate, counted.channels), (8000, Some(3)));
+    }
+
+    #[test]
+    fn leaves_unknown_channel_layouts_unset() {
+        let params = parse_input_stream("Stream #0:0: Audio: ac3, 48000 Hz, 5.1(side), fltp");
+
+        assert_eq!(params.unwrap().channels, None);
+    }
+
+    #[test]
+    fn finds_no_input_stream_without_audio() {
+        assert!(parse_input_stream("Stream #0:0: Video: h264, yuv420p, 1920x1080").is_none());
+        assert!(parse_input_stream("Stream #0:0: Audio: aac (LC), stereo, fltp").is_none());
+    }
+
+    fn silence(threshold_db: f32, min_duration_ms: u32) -> SilenceDetection {
+        SilenceDetection {
+            threshold_db,
+            min_duration_ms,
+        }
+    }
+
+    #[test]
+    fn detects_silence_between_sounds() {
+        let audio = stretches(&[(0.5, 1.0), (0.0, 1.0), (0.5, 1.0)]);
+        let ranges = audio.detect_silence(&silence(-40.0, 500));
+
+        assert_eq!(ranges.len(), 1);
+        assert_close(ranges[0].start, 1.0);
+        assert_close(ranges[0].end, 2.0);
+    }
+
+    #[test]
+    fn ignores_silence_shorter_than_the_minimum() {
+        let audio = stretches(&[(0.5, 1.0), (0.0, 0.2), (0.5, 1.0)]);
+
+        assert!(audio.detect_silence(&silence(-40.0, 500)).is_empty());
+    }
+
+    #[test]
+    fn treats_audio_under_the_threshold_as_silence() {
+        // -40 dBFS is an amplitude of 0.01
+        let audio = stretches(&[(0.5, 1.0), (0.005, 1.0), (0.02, 1.0)]);
+        let ranges = audio.detect_silence(&silence(-40.0, 500));
+
+        assert_eq!(ranges.len(), 1);
+        assert_close(ranges[0].start, 1.0);
+        assert_close(ranges[0].end, 2.0);
+    }
+
+    #[test]
+    fn trailing_silence_runs_to_the_end() {
+        let audio = stretches(&[(0.0, 0.6), (0.5, 1.0), (0.0, 1.5)]);
+        let ranges = audio.detect_silence(&silence(-40.0, 500));
+
+        assert_eq!(ranges.len(), 2);
+        assert_close(ranges[0].start, 0.0);
+        assert_close(ranges[0].end, 0.6);
+        assert_close(ranges[1].start, 1.6);
+        assert_close(ranges[1].end, 3.1);
+    }
+
+    #[test]
+    fn scrub_burst_is_centered_on_the_time() {
+        let ramp = audio((0..2000).map(|i| i as f64).collect(), 1000);
+        let burst = ramp.scrub_burst(1.0);
+
+        // 50ms of audio, from 25ms before the time to 25ms after
+        assert_eq!(burst.len(), 50);
+        assert_close(burst[25], 1000.0);
+    }
+
+    #[test]
+    fn scrub_burst_fades_its_edges() {
+        let burst = audio(vec![1.0; 2000], 1000).scrub_burst(1.0);
+
+        assert_close(burst[0], 0.0);
+        assert_close(burst[2], 0.4);
+        assert_close(burst[5], 1.0);
+        assert_close(burst[44], 1.0);
+        assert_close(burst[47], 0.4);
+        assert_close(burst[49], 0.0);
+    }
+
+    #[test]
+    fn scrub_burst_is_clamped_to_the_audio() {
+        let audio = audio(vec![1.0; 2000], 1000);
+
+        assert_eq!(audio.scrub_burst(0.0).len(), 25);
+        assert_eq!(audio.scrub_burst(-1.0).len(), 25);
+        assert_eq!(audio.scrub_burst(10.0).len(), 25);
+    }
+
+    #[test]
+    fn track_gain_silences_muted_tracks() {
+        let mut project = ProjectConfiguration::default();
+        assert_close(track_gain(&project, MICROPHONE_TRACK), 1.0);
+
+        project.audio.muted_tracks = vec![MICROPHONE_TRACK.to_string()];
+        assert_close(track_gain(&project, MICROPHONE_TRACK), 0.0);
+    }
+
+    #[test]
+    fn track_gain_ignores_mutes_of_other_tracks() {
+        let mut project = ProjectConfiguration::default();
+        project.audio.muted_tracks = vec!["system".to_string()];
+
+        assert_close(track_gain(&project, MICROPHONE_TRACK), 1.0);
+    }
+
+    fn sine(amplitude: f64, secs: f64) -> Vec<f64> {
+        let sample_rate = 48_000.0;
+        (0..(secs * sample_rate) as usize)
+            .map(|i| {
+                amplitude * (2.0 * std::f64::consts::PI * 997.0 * i as f64 / sample_rate).sin()
+            })
+            .collect()
+    }
+
+    fn peak(buffer: &[f64]) -> f64 {
+        buffer.iter().fold(0.0, |peak, s| peak.max(s.abs()))
+    }
+
+    #[test]
+    fn processing_without_gate_or_normalization_keeps_the_audio() {
+        let original = stretches(&[(0.5, 1.0)]);
+        let processed = original.processed(&AudioConfiguration::default());
+
+        assert!(Arc::ptr_eq(&original.buffer, &processed.buffer));
+    }
+
+    #[test]
+    fn gate_silences_audio_under_the_threshold() {
+        let original = stretches(&[(0.001, 0.5), (0.5, 0.5)]);
+        let mut config = AudioConfiguration::default();
+        config.noise_gate = Some(NoiseGate {
+            threshold_db: -40.0,
+        });
+
+        let gated = original.processed(&config);
+
+        // the gate ramps over a 10ms window as it closes and opens
+        assert!(gated.buffer[10..500].iter().all(|s| *s == 0.0));
+        assert_close(gated.buffer[509].abs(), 0.5);
+        assert_eq!(gated.buffer[510..], original.buffer[510..]);
+    }
+
+    #[test]
+    fn normalizes_to_the_target_loudness() {
+        let mut buffer = sine(0.05, 3.0);
+        normalize(&mut buffer, 48_000, -16.0);
+
+        let loudness = integrated_loudness(&buffer, 48_000).unwrap();
+        assert!((loudness - -16.0).abs() < 0.05, "{loudness} LUFS");
+    }
+
+    #[test]
+    fn normalization_doesnt_clip() {
+        let mut buffer = sine(0.5, 3.0);
+        normalize(&mut buffer, 48_000, 0.0);
+
+        assert!((peak(&buffer) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn normalization_leaves_silence_alone() {
+        let mut buffer = vec![0.0; 48_000];
+        normalize(&mut buffer, 48_000, -16.0);
+
+        assert!(buffer.iter().all(|s| *s == 0.0));
+        assert_eq!(integrated_loudness(&buffer, 48_000), None);
+    }
+
+    #[test]
+    fn padded_to_appends_silence() {
+        let (padded, padding) = audio(vec![0.5; 10], 10).padded_to(1.5);
+
+        assert_close(padding, 0.5);
+        assert_eq!(padded.buffer.len(), 15);
+        assert!(padded.buffer[..10].iter().all(|s| *s == 0.5));
+        assert!(padded.buffer[10..].iter().all(|s| *s == 0.0));
+    }
+
+    #[test]
+    fn padded_to_keeps_audio_that_is_long_enough() {
+        let original = audio(vec![0.5; 10], 10);
+        let (padded, padding) = original.clone().padded_to(0.5);
+
+        assert_close(padding, 0.0);
+        assert!(Arc::ptr_eq(&original.buffer, &padded.buffer));
+    }
+
+    #[test]
+    fn padded_to_rounds_partial_samples_up() {
+        let (padded, padding) = audio(vec![0.5; 10], 10).padded_to(1.01);
+
+        assert_eq!(padded.buffer.len(), 11);
+        assert_close(padding, 0.1);
+    }
+
+    fn ducking(attack_ms: u32, release_ms: u32) -> Ducking {
+        Ducking {
+            threshold_db: -30.0,
+            amount_db: 20.0,
+            attack_ms,
+            release_ms,
+        }
+    }
+
+    #[test]
+    fn ducked_under_lowers_audio_while_the_voice_speaks() {
+        let music = stretches(&[(0.5, 1.0)]);
+        let voice = stretches(&[(0.0, 0.5), (0.5, 0.5)]);
+
+        let ducked = music.ducked_under(&voice, &ducking(0, 0));
+
+        assert_eq!(ducked.buffer[..500], music.buffer[..500]);
+        assert!(ducked.buffer[500..]
+            .iter()
+            .all(|s| (s.abs() - 0.05).abs() < 1e-9));
+    }
+
+    #[test]
+    fn ducked_under_eases_into_the_ducked_gain() {
+        let music = stretches(&[(0.5, 1.0)]);
+        let voice = stretches(&[(0.0, 0.5), (0.5, 0.5)]);
+
+        let ducked = music.ducked_under(&voice, &ducking(100, 0));
+
+        // 100ms is 100 samples at 1kHz, so the gain is still falling well after speech starts
+        let levels = ducked.buffer[500..]
+            .iter()
+            .map(|s| s.abs())
+            .collect::<Vec<_>>();
+        assert!(levels.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert!(levels[10] > 0.4);
+        assert!(levels[499] > 0.05 && levels[499] < 0.06);
+    }
+
+    #[test]
+    fn ducked_under_restores_the_gain_after_the_voice_ends() {
+        let music = stretches(&[(0.5, 1.0)]);
+        let voice = stretches(&[(0.5, 0.5)]);
+
+        let ducked = music.ducked_under(&voice, &ducking(0, 0));
+
+        assert!(ducked.buffer[..500]
+            .iter()
+            .all(|s| (s.abs() - 0.05).abs() < 1e-9));
+        assert_eq!(ducked.buffer[500..], music.buffer[500..]);
+    }
+
+    /// A tenth of a second of a `frequency` tone at half amplitude, at 48kHz.
+    fn tone(frequency: f64) -> Vec<f64> {
+        (0..4800)
+            .map(|i| 0.5 * (2.0 * std::f64::consts::PI * frequency * i as f64 / 48_000.0).sin())
+            .collect()
+    }
+
+    #[test]
+    fn resample_keeps_audio_at_the_same_rate() {
+        let samples = vec![0.1, 0.2, 0.3];
+
+        assert_eq!(
+            resample(&samples, 1, 48_000, 48_000, ResampleQuality::Sinc),
+            samples
+        );
+    }
+
+    #[test]
+    fn linear_resampling_interpolates_between_samples() {
+        let upsampled = resample(
+            &[0.0, 1.0, 0.0, -1.0],
+            1,
+            1000,
+            2000,
+            ResampleQuality::Linear,
+        );
+
+        assert_eq!(upsampled, vec![0.0, 0.5, 1.0, 0.5, 0.0, -0.5, -1.0, -0.5]);
+    }
+
+    #[test]
+    fn resampling_keeps_channels_interleaved() {
+        let upsampled = resample(
+            &[0.0, 1.0, 1.0, 1.0],
+            2,
+            1000,
+            2000,
+            ResampleQuality::Linear,
+        );
+
+        assert_eq!(upsampled, vec![0.0, 1.0, 0.5, 1.0, 1.0, 1.0, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn sinc_resampling_passes_through_original_samples() {
+        let samples = tone(997.0);
+        let upsampled = resample(&samples, 1, 48_000, 96_000, ResampleQuality::Sinc);
+
+        for (i, sample) in samples.iter().enumerate() {
+            assert!((upsampled[i * 2] - sample).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn sinc_downsampling_keeps_frequencies_the_new_rate_can_hold() {
+        let downsampled = resample(&tone(1000.0), 1, 48_000, 16_000, ResampleQuality::Sinc);
+
+        // away from the edges, where the tone starts and stops abruptly
+        assert!((peak(&downsampled[200..1400]) - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn sinc_downsampling_filters_out_what_linear_aliases() {
+        // 12kHz is above the 8kHz a 16kHz rate can hold, and would fold back to 4kHz
+        let samples = tone(12_000.0);
+        let linear = resample(&samples, 1, 48_000, 16_000, ResampleQuality::Linear);
+        let sinc = resample(&samples, 1, 48_000, 16_000, ResampleQuality::Sinc);
+
+        assert!(peak(&linear[200..1400]) > 0.49);
+        assert!(peak(&sinc[200..1400]) < 0.01);
+    }
+}
@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+
+const LINE_WIDTH: u32 = 2;
+const LINE_COLOR: [u8; 4] = [255, 255, 255, 255];
+
+/// A before/after comparison drawn over preview frames only. Left of the wipe line shows the
+/// frame without its color grade and spotlight, right of it the frame as exported.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompareWipe {
+    /// Where the wipe line is, as a fraction of the frame's width from its left edge.
+    pub position: f32,
+}
+
+impl CompareWipe {
+    /// The first column right of the wipe line, in pixels.
+    pub fn split_x(&self, width: u32) -> u32 {
+        (width as f32 * self.position.clamp(0.0, 1.0)).round() as u32
+    }
+
+    /// Copies the columns left of the wipe line from `original` into `frame` and draws the line.
+    /// Both are tightly packed RGBA frames of the same size.
+    pub fn composite(&self, frame: &mut [u8], original: &[u8], width: u32, height: u32) {
+        let split_x = self.split_x(width);
+        let row_bytes = width as usize * 4;
+
+        for row in 0..height as usize {
+            let start = row * row_bytes;
+            let end = start + split_x as usize * 4;
+            let (Some(dst), Some(src)) = (frame.get_mut(start..end), original.get(start..end))
+            else {
+                return;
+            };
+
+            dst.copy_from_slice(src);
+
+            let line_start = split_x.saturating_sub(LINE_WIDTH / 2);
+            let line_end = (line_start + LINE_WIDTH).min(width);
+            for col in line_start..line_end {
+                let i = start + col as usize * 4;
+                frame[i..i + 4].copy_from_slice(&LINE_COLOR);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(width: u32, height: u32, value: u8) -> Vec<u8> {
+        vec![value; (width * height * 4) as usize]
+    }
+
+    fn column(frame: &[u8], width: u32, row: u32, col: u32) -> &[u8] {
+        let i = ((row * width + col) * 4) as usize;
+        &frame[i..i + 4]
+    }
+
+    #[test]
+    fn composite_shows_original_left_of_the_line() {
+        let (width, height) = (10, 2);
+        let mut graded = frame(width, height, 7);
+        let original = frame(width, height, 3);
+
+        CompareWipe { position: 0.5 }.composite(&mut graded, &original, width, height);
+
+        for row in 0..height {
+            for col in 0..4 {
+                assert_eq!(column(&graded, width, row, col), [3; 4]);
+            }
+            for col in 4..6 {
+                assert_eq!(column(&graded, width, row, col), LINE_COLOR);
+            }
+            for col in 6..width {
+                assert_eq!(column(&graded, width, row, col), [7; 4]);
+            }
+        }
+    }
+
+    #[test]
+    fn composite_at_the_edges_keeps_one_side() {
+        let (width, height) = (8, 1);
+        let original = frame(width, height, 3);
+
+        let mut graded = frame(width, height, 7);
+        CompareWipe { position: 0.0 }.composite(&mut graded, &original, width, height);
+        assert_eq!(column(&graded, width, 0, 0), LINE_COLOR);
+        assert_eq!(column(&graded, width, 0, 1), LINE_COLOR);
+        assert!(graded[8..].iter().all(|&b| b == 7));
+
+        let mut graded = frame(width, height, 7);
+        CompareWipe { position: 1.5 }.composite(&mut graded, &original, width, height);
+        assert!(graded[..28].iter().all(|&b| b == 3));
+        assert_eq!(column(&graded, width, 0, 7), LINE_COLOR);
+    }
+
+    #[test]
+    fn composite_leaves_mismatched_frames_alone() {
+        let (width, height) = (4, 2);
+        let mut graded = frame(width, height, 7);
+        let original = frame(width, 1, 3);
+
+        CompareWipe { position: 1.0 }.composite(&mut graded, &original, width, height);
+
+        // the first row fits both frames, the second doesn't and is left as it was
+        assert!(graded[..12].iter().all(|&b| b == 3));
+        assert!(graded[16..].iter().all(|&b| b == 7));
+    }
+}
@@ -0,0 +1,69 @@
+use cap_rendering::SeekMode;
+
+use crate::editor_instance::{EditorInstance, FPS};
+
+/// Frames with no sampled channel brighter than this count as black, so near-black frames
+/// with some noise or compression artifacts do too.
+const BLACK_THRESHOLD: u8 = 16;
+/// Only every this many pixels of a frame are checked.
+const PIXEL_STRIDE: usize = 7;
+/// Frames skipped at a time while looking for content, before narrowing down frame by frame.
+const SEARCH_STEP: u32 = FPS / 2;
+
+impl EditorInstance {
+    /// First and last recording frames that aren't black, for proposing trims that cut black
+    /// frames from the start and end of recordings that begin before the screen content shows.
+    /// Gives the whole recording when it's all black.
+    pub async fn detect_content_bounds(&self) -> (u32, u32) {
+        let last_frame = ((self.recordings.duration() * FPS as f64) as u32).saturating_sub(1);
+
+        let Some(start) = self
+            .find_content_frame((0..=last_frame).step_by(SEARCH_STEP as usize))
+            .await
+        else {
+            return (0, last_frame);
+        };
+        // the black frames before it end somewhere in the step before
+        let start = self
+            .find_content_frame(start.saturating_sub(SEARCH_STEP - 1)..=start)
+            .await
+            .unwrap_or(start);
+
+        let end = self
+            .find_content_frame((start..=last_frame).rev().step_by(SEARCH_STEP as usize))
+            .await
+            .unwrap_or(start);
+        let end = self
+            .find_content_frame((end..=(end + SEARCH_STEP - 1).min(last_frame)).rev())
+            .await
+            .unwrap_or(end);
+
+        (start, end)
+    }
+
+    /// The first of `frames` that isn't black. Frames that fail to decode are skipped.
+    async fn find_content_frame(&self, frames: impl Iterator<Item = u32>) -> Option<u32> {
+        for frame_number in frames {
+            let Some(frame) = self
+                .decoders
+                .get_screen_frame(frame_number, SeekMode::Exact)
+                .await
+            else {
+                continue;
+            };
+
+            if !is_black(&frame) {
+                return Some(frame_number);
+            }
+        }
+
+        None
+    }
+}
+
+/// Whether an RGBA frame is black, going by a sample of its pixels.
+fn is_black(rgba: &[u8]) -> bool {
+    rgba.chunks_exact(4)
+        .step_by(PIXEL_STRIDE)
+        .all(|pixel| pixel[..3].iter().all(|&channel| channel <= BLACK_THRESHOLD))
+}
@@ -1,13 +1,25 @@
-use std::{sync::Arc, time::Instant};
+use std::{
+    sync::{Arc, Mutex as StdMutex},
+    time::{Duration, Instant},
+};
 
 use cap_project::{BackgroundSource, ProjectConfiguration};
-use cap_rendering::{decoder::DecodedFrame, produce_frame, ProjectUniforms, RenderVideoConstants};
+use cap_rendering::{
+    decoder::DecodedFrame, produce_frame, Background, FrameError, Layer, ProjectUniforms,
+    RenderVideoConstants,
+};
 use tokio::{
-    sync::{mpsc, oneshot},
+    sync::{mpsc, oneshot, watch},
     task::JoinHandle,
 };
 
-use crate::editor_instance::SocketMessage;
+use crate::{
+    compare::CompareWipe,
+    editor_instance::{FrameSender, RenderedFrame},
+    overlay::OverlayConfig,
+    panic_guard::catch_panic,
+    preview_stats::PreviewCounters,
+};
 
 struct EditorState {
     config: ProjectConfiguration,
@@ -100,94 +112,358 @@ impl EditorHandle {
 pub enum RendererMessage {
     RenderFrame {
         screen_frame: DecodedFrame,
-        camera_frame: Option<DecodedFrame>,
+        camera_frames: Vec<Option<DecodedFrame>>,
         background: BackgroundSource,
         uniforms: ProjectUniforms,
-        finished: oneshot::Sender<()>,
+        // receives the error if rendering failed or panicked
+        finished: oneshot::Sender<Result<(), String>>,
     },
     Stop {
         finished: oneshot::Sender<()>,
     },
+    HealthCheck {
+        healthy: oneshot::Sender<bool>,
+    },
 }
 
+/// How long a single frame may take to render before the renderer is considered stuck.
+const RENDER_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long the renderer has to respond to a health check.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(1);
+/// Output scales the renderer falls back to, after dropping effects, each time the GPU
+/// runs out of memory.
+const DEGRADED_SCALES: [f32; 3] = [0.75, 0.5, 0.25];
+
 pub struct Renderer {
     rx: mpsc::Receiver<RendererMessage>,
-    frame_tx: mpsc::UnboundedSender<SocketMessage>,
-    render_constants: Arc<RenderVideoConstants>,
+    outputs: FrameOutputs,
+    gpu: RendererGpu,
 }
 
 pub struct RendererHandle {
-    tx: mpsc::Sender<RendererMessage>,
+    tx: StdMutex<mpsc::Sender<RendererMessage>>,
+    gpu: RendererGpu,
+    outputs: FrameOutputs,
+}
+
+/// The GPU state shared by every actor a handle spawns, which outlives any one of them
+/// so a restarted actor keeps rendering the way the last one left off.
+#[derive(Clone)]
+struct RendererGpu {
+    // replaced when the device is lost
+    constants: Arc<StdMutex<Arc<RenderVideoConstants>>>,
+    // how many steps rendering has been scaled back, see `degrade`
+    degradation: Arc<StdMutex<usize>>,
+    warnings: Arc<watch::Sender<Option<String>>>,
+}
+
+impl RendererGpu {
+    fn constants(&self) -> Arc<RenderVideoConstants> {
+        self.constants.lock().unwrap().clone()
+    }
+
+    fn warn(&self, warning: String) {
+        tracing::warn!("{warning}");
+        self.warnings.send_replace(Some(warning));
+    }
+
+    /// Renders a frame at the current degradation, stepping down each time the GPU runs out
+    /// of memory and replacing the render constants if the device is lost.
+    /// Returns the frame along with the uniforms it was rendered with.
+    async fn produce_frame(
+        &self,
+        screen_frame: &DecodedFrame,
+        camera_frames: &[Option<DecodedFrame>],
+        background: Background,
+        uniforms: &ProjectUniforms,
+    ) -> Result<(Vec<u8>, ProjectUniforms), String> {
+        let mut recovered = false;
+
+        loop {
+            let step = *self.degradation.lock().unwrap();
+            let degraded = degrade(uniforms.clone(), step);
+            let constants = self.constants();
+
+            match produce_frame(
+                &constants,
+                screen_frame,
+                camera_frames,
+                background,
+                &degraded,
+            )
+            .await
+            {
+                Ok(frame) => return Ok((frame, degraded)),
+                Err(FrameError::OutOfMemory) if step < DEGRADED_SCALES.len() + 1 => {
+                    let mut degradation = self.degradation.lock().unwrap();
+                    // another frame may already have stepped down
+                    if *degradation == step {
+                        *degradation += 1;
+                        self.warn(format!(
+                            "The GPU ran out of memory, so the preview is now {}",
+                            describe_degradation(*degradation)
+                        ));
+                    }
+                }
+                Err(FrameError::DeviceLost) if !recovered => {
+                    recovered = true;
+                    self.recover(&constants).await?;
+                }
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+    }
+
+    /// Replaces `lost` with new render constants, unless another frame already did.
+    async fn recover(&self, lost: &Arc<RenderVideoConstants>) -> Result<(), String> {
+        if !Arc::ptr_eq(&self.constants(), lost) {
+            return Ok(());
+        }
+
+        let constants = RenderVideoConstants::new(lost.current_options())
+            .await
+            .map_err(|e| format!("Failed to recover the lost GPU device: {e}"))?;
+
+        let mut current = self.constants.lock().unwrap();
+        if Arc::ptr_eq(&current, lost) {
+            *current = Arc::new(constants);
+            drop(current);
+            self.warn("The GPU device was lost and has been recovered".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// Scales `uniforms` back `step` steps from full quality, first by dropping effects and
+/// then by rendering smaller, to use less GPU memory.
+fn degrade(uniforms: ProjectUniforms, step: usize) -> ProjectUniforms {
+    match step {
+        0 => uniforms,
+        1 => uniforms.without_effects(),
+        step => uniforms
+            .without_effects()
+            .scaled(DEGRADED_SCALES[(step - 2).min(DEGRADED_SCALES.len() - 1)]),
+    }
+}
+
+fn describe_degradation(step: usize) -> String {
+    match step {
+        0 => "rendered in full".to_string(),
+        1 => "rendered without effects".to_string(),
+        step => format!(
+            "rendered without effects at {}% size",
+            DEGRADED_SCALES[(step - 2).min(DEGRADED_SCALES.len() - 1)] * 100.0
+        ),
+    }
+}
+
+/// Where rendered frames go, shared by every actor a handle spawns.
+#[derive(Clone)]
+struct FrameOutputs {
+    frame_tx: FrameSender,
+    frame_sink: Option<mpsc::UnboundedSender<RenderedFrame>>,
+    overlay: Arc<StdMutex<Option<Arc<OverlayConfig>>>>,
+    compare: Arc<StdMutex<Option<CompareWipe>>>,
+    solo_layer: Arc<StdMutex<Option<Layer>>>,
+    // holds the latest frame for the fixed cadence output while it's running
+    cadence: Arc<StdMutex<Option<watch::Sender<Option<RenderedFrame>>>>>,
+    stats: Arc<PreviewCounters>,
 }
 
 impl Renderer {
+    /// Frames are sent to the frames socket via `frame_tx`, and also to `frame_sink` if provided.
+    /// They're rendered at the size the socket's client asked for, see `FrameSender::render_scale`.
+    /// The sink always receives full frames, without the preview overlay, compare wipe
+    /// or solo layer.
+    /// Rendered and dropped frames are counted in `stats`.
+    /// If the GPU runs out of memory, frames are rendered without effects and then smaller
+    /// rather than failing, and a lost device is replaced, each with a warning on `warnings`.
     pub fn spawn(
         render_constants: Arc<RenderVideoConstants>,
-        frame_tx: mpsc::UnboundedSender<SocketMessage>,
+        frame_tx: FrameSender,
+        frame_sink: Option<mpsc::UnboundedSender<RenderedFrame>>,
+        stats: Arc<PreviewCounters>,
     ) -> RendererHandle {
-        let (tx, rx) = mpsc::channel(4);
-
-        let this = Self {
-            rx,
+        let outputs = FrameOutputs {
             frame_tx,
-            render_constants,
+            frame_sink,
+            overlay: Arc::new(StdMutex::new(None)),
+            compare: Arc::new(StdMutex::new(None)),
+            solo_layer: Arc::new(StdMutex::new(None)),
+            cadence: Arc::new(StdMutex::new(None)),
+            stats,
+        };
+        let gpu = RendererGpu {
+            constants: Arc::new(StdMutex::new(render_constants)),
+            degradation: Arc::new(StdMutex::new(0)),
+            warnings: Arc::new(watch::channel(None).0),
         };
+        let tx = Self::spawn_actor(gpu.clone(), outputs.clone());
+
+        RendererHandle {
+            tx: StdMutex::new(tx),
+            gpu,
+            outputs,
+        }
+    }
+
+    fn spawn_actor(gpu: RendererGpu, outputs: FrameOutputs) -> mpsc::Sender<RendererMessage> {
+        let (tx, rx) = mpsc::channel(4);
+
+        let this = Self { rx, outputs, gpu };
 
         tokio::spawn(this.run());
 
-        RendererHandle { tx }
+        tx
     }
 
     async fn run(mut self) {
-        let mut frame_task: Option<JoinHandle<()>> = None;
+        let mut frame_task: Option<(JoinHandle<()>, Instant)> = None;
 
         loop {
             while let Some(msg) = self.rx.recv().await {
                 match msg {
                     RendererMessage::RenderFrame {
                         screen_frame,
-                        camera_frame,
+                        camera_frames,
                         background,
                         uniforms,
                         finished,
                     } => {
-                        if let Some(task) = frame_task.as_ref() {
+                        if let Some((task, _)) = frame_task.as_ref() {
                             if task.is_finished() {
                                 frame_task = None
                             } else {
+                                self.outputs.stats.frame_dropped();
                                 continue;
                             }
                         }
 
-                        let render_constants = self.render_constants.clone();
-                        let frame_tx = self.frame_tx.clone();
-
-                        frame_task = Some(tokio::spawn(async move {
-                            let time = Instant::now();
-                            let frame = produce_frame(
-                                &render_constants,
-                                &screen_frame,
-                                &camera_frame,
-                                cap_rendering::Background::from(background),
-                                &uniforms,
-                            )
+                        let gpu = self.gpu.clone();
+                        let frame_tx = self.outputs.frame_tx.clone();
+                        let frame_sink = self.outputs.frame_sink.clone();
+                        let overlay = self.outputs.overlay.lock().unwrap().clone();
+                        let compare = *self.outputs.compare.lock().unwrap();
+                        let solo_layer = *self.outputs.solo_layer.lock().unwrap();
+                        let cadence = self.outputs.cadence.lock().unwrap().clone();
+                        let stats = self.outputs.stats.clone();
+
+                        // previews are rendered at the size the socket client shows them
+                        let scale =
+                            frame_tx.render_scale(uniforms.output_size.0, uniforms.output_size.1);
+                        let uniforms = if scale == 1.0 {
+                            uniforms
+                        } else {
+                            uniforms.scaled(scale)
+                        };
+
+                        let task = tokio::spawn(async move {
+                            let rendered = catch_panic(async move {
+                                let (mut frame, uniforms) = gpu
+                                    .produce_frame(
+                                        &screen_frame,
+                                        &camera_frames,
+                                        Background::from(background.clone()),
+                                        &uniforms,
+                                    )
+                                    .await?;
+                                stats.frame_rendered();
+                                // the extra renders below are skipped rather than
+                                // degraded further, since they must match `frame`
+                                let render_constants = gpu.constants();
+
+                                if let Some(frame_sink) = frame_sink {
+                                    frame_sink
+                                        .send(RenderedFrame {
+                                            data: frame.clone(),
+                                            width: uniforms.output_size.0,
+                                            height: uniforms.output_size.1,
+                                        })
+                                        .ok();
+                                }
+
+                                if let Some(cadence) = cadence {
+                                    cadence.send_replace(Some(RenderedFrame {
+                                        data: frame.clone(),
+                                        width: uniforms.output_size.0,
+                                        height: uniforms.output_size.1,
+                                    }));
+                                }
+
+                                if let Some(layer) = solo_layer {
+                                    let background = match layer {
+                                        Layer::Background => Background::from(background),
+                                        _ => Background::Color([0.0, 0.0, 0.0, 1.0]),
+                                    };
+
+                                    match produce_frame(
+                                        &render_constants,
+                                        &screen_frame,
+                                        &camera_frames,
+                                        background,
+                                        &uniforms.clone().only_layer(layer),
+                                    )
+                                    .await
+                                    {
+                                        Ok(solo) => frame = solo,
+                                        Err(e) => {
+                                            tracing::warn!("Failed to render solo layer: {e}")
+                                        }
+                                    }
+                                } else if let Some(compare) = compare {
+                                    match produce_frame(
+                                        &render_constants,
+                                        &screen_frame,
+                                        &camera_frames,
+                                        Background::from(background),
+                                        &uniforms.clone().without_effects(),
+                                    )
+                                    .await
+                                    {
+                                        Ok(original) => compare.composite(
+                                            &mut frame,
+                                            &original,
+                                            uniforms.output_size.0,
+                                            uniforms.output_size.1,
+                                        ),
+                                        Err(e) => {
+                                            tracing::warn!("Failed to render compare wipe: {e}")
+                                        }
+                                    }
+                                }
+
+                                if let Some(overlay) = overlay {
+                                    overlay.draw(
+                                        &mut frame,
+                                        uniforms.output_size.0,
+                                        uniforms.output_size.1,
+                                    );
+                                }
+
+                                frame_tx.send(
+                                    frame,
+                                    uniforms.output_size.0,
+                                    uniforms.output_size.1,
+                                );
+
+                                Ok::<_, String>(())
+                            })
                             .await
-                            .unwrap();
-                            // println!("produced frame in {:?}", time.elapsed());
-
-                            frame_tx
-                                .send(SocketMessage::Frame {
-                                    data: frame,
-                                    width: uniforms.output_size.0,
-                                    height: uniforms.output_size.1,
-                                })
-                                .ok();
-                            finished.send(()).ok();
-                        }));
+                            .and_then(|rendered| rendered);
+
+                            if let Err(e) = &rendered {
+                                tracing::error!("Rendering a frame failed: {e}");
+                            }
+
+                            finished.send(rendered).ok();
+                        });
+
+                        frame_task = Some((task, Instant::now()));
                     }
                     RendererMessage::Stop { finished } => {
                         // Cancel any ongoing frame task
-                        if let Some(task) = frame_task.take() {
+                        if let Some((task, _)) = frame_task.take() {
                             task.abort();
                         }
                         // Acknowledge the stop
@@ -195,6 +471,13 @@ impl Renderer {
                         // Exit the run loop
                         return;
                     }
+                    RendererMessage::HealthCheck { healthy } => {
+                        let stuck = frame_task.as_ref().is_some_and(|(task, started)| {
+                            !task.is_finished() && started.elapsed() > RENDER_TIMEOUT
+                        });
+
+                        healthy.send(!stuck).ok();
+                    }
                 }
             }
         }
@@ -202,36 +485,124 @@ impl Renderer {
 }
 
 impl RendererHandle {
+    fn sender(&self) -> mpsc::Sender<RendererMessage> {
+        self.tx.lock().unwrap().clone()
+    }
+
     async fn send(&self, msg: RendererMessage) {
-        self.tx.send(msg).await.unwrap();
+        if self.sender().send(msg).await.is_err() {
+            tracing::warn!("Renderer is not running, dropping message");
+        }
     }
 
+    /// Renders a frame and sends it on, returning the error if rendering failed or panicked.
+    /// Frames dropped because the renderer is busy, or that time out, aren't errors.
     pub async fn render_frame(
         &self,
         screen_frame: DecodedFrame,
-        camera_frame: Option<DecodedFrame>,
+        camera_frames: Vec<Option<DecodedFrame>>,
         background: BackgroundSource,
         uniforms: ProjectUniforms,
-    ) {
+    ) -> Result<(), String> {
         let (finished_tx, finished_rx) = oneshot::channel();
 
         self.send(RendererMessage::RenderFrame {
             screen_frame,
-            camera_frame,
+            camera_frames,
             background,
             uniforms,
             finished: finished_tx,
         })
         .await;
 
-        finished_rx.await.ok();
+        match tokio::time::timeout(RENDER_TIMEOUT, finished_rx).await {
+            Ok(Ok(rendered)) => rendered,
+            Ok(Err(_)) => Ok(()),
+            Err(_) => {
+                tracing::info!("Timed out waiting for frame to render");
+                Ok(())
+            }
+        }
+    }
+
+    /// Sets the guides drawn over frames sent to the frames socket.
+    pub fn set_overlay(&self, overlay: OverlayConfig) {
+        *self.outputs.overlay.lock().unwrap() = (!overlay.is_empty()).then(|| Arc::new(overlay));
+    }
+
+    /// Sets the before/after wipe drawn over frames sent to the frames socket, or clears it.
+    pub fn set_compare(&self, compare: Option<CompareWipe>) {
+        *self.outputs.compare.lock().unwrap() = compare;
+    }
+
+    /// Shows only `layer` in frames sent to the frames socket, or every layer with `None`.
+    /// Takes the place of the compare wipe while set.
+    pub fn set_solo_layer(&self, layer: Option<Layer>) {
+        *self.outputs.solo_layer.lock().unwrap() = layer;
+    }
+
+    /// Receives a warning each time rendering is scaled back or the GPU device is recovered.
+    pub fn warnings(&self) -> watch::Receiver<Option<String>> {
+        self.gpu.warnings.subscribe()
+    }
+
+    /// Keeps the latest rendered frame, without the overlay or compare wipe, in `latest`
+    /// for a fixed cadence output, or stops with `None`.
+    pub fn set_cadence_output(&self, latest: Option<watch::Sender<Option<RenderedFrame>>>) {
+        *self.outputs.cadence.lock().unwrap() = latest;
+    }
+
+    /// Returns false if the renderer has exited, isn't processing messages,
+    /// or has been stuck on a frame for longer than `RENDER_TIMEOUT`.
+    pub async fn is_healthy(&self) -> bool {
+        let (tx, rx) = oneshot::channel();
+
+        let sent = self
+            .sender()
+            .send_timeout(
+                RendererMessage::HealthCheck { healthy: tx },
+                HEALTH_CHECK_TIMEOUT,
+            )
+            .await;
+        if sent.is_err() {
+            return false;
+        }
+
+        matches!(
+            tokio::time::timeout(HEALTH_CHECK_TIMEOUT, rx).await,
+            Ok(Ok(true))
+        )
+    }
+
+    /// Replaces the renderer actor with a fresh one using the same render constants
+    /// and frame socket. The old actor's in-flight and queued renders are cancelled.
+    pub async fn restart(&self) {
+        let new_tx = Renderer::spawn_actor(self.gpu.clone(), self.outputs.clone());
+        let old_tx = std::mem::replace(&mut *self.tx.lock().unwrap(), new_tx);
+
+        // Dropping the old sender also ends the actor once its queue drains,
+        // but stop it explicitly so an in-flight frame is aborted.
+        let (tx, rx) = oneshot::channel();
+        if old_tx
+            .send_timeout(RendererMessage::Stop { finished: tx }, HEALTH_CHECK_TIMEOUT)
+            .await
+            .is_ok()
+        {
+            tokio::time::timeout(HEALTH_CHECK_TIMEOUT, rx).await.ok();
+        }
+
+        tracing::info!("Renderer restarted");
     }
 
     pub async fn stop(&self) {
         // Send a stop message to the renderer
         let (tx, rx) = oneshot::channel();
-        if let Err(_) = self.tx.send(RendererMessage::Stop { finished: tx }).await {
-            println!("Failed to send stop message to renderer");
+        if let Err(_) = self
+            .sender()
+            .send(RendererMessage::Stop { finished: tx })
+            .await
+        {
+            tracing::warn!("Failed to send stop message to renderer");
         }
         // Wait for the renderer to acknowledge the stop
         let _ = rx.await;
@@ -1,23 +1,86 @@
-use crate::audio::AudioData;
+use crate::audio::{
+    AudioData, AudioReplaceMode, AudioResampling, ProcessedAudioCache, SilentRange, AUDIO_TRACKS,
+};
+use crate::compare::CompareWipe;
 use crate::editor;
-use crate::playback::{self, PlaybackHandle};
-use crate::project_recordings::ProjectRecordings;
-use cap_ffmpeg::FFmpeg;
-use cap_project::{ProjectConfiguration, RecordingMeta};
-use cap_rendering::decoder::AsyncVideoDecoder;
-use cap_rendering::{ProjectUniforms, RecordingDecoders, RenderOptions, RenderVideoConstants};
+use crate::frame_format::{FrameControl, FrameHandshake, INVALID_HANDSHAKE_CLOSE_CODE};
+use crate::overlay::OverlayConfig;
+use crate::panic_guard::catch_panic;
+use crate::playback::{self, AudioLevel, PlaybackHandle, PlaybackStartError};
+use crate::preview_stats::{PreviewCounters, PreviewStats, SEND_LAG_THRESHOLD};
+use crate::project_recordings::{ProjectRecordings, Video};
+use crate::sync_check::{self, SyncMeasurement};
+use cap_project::{
+    AudioConfiguration, ConfigError, ExportPreset, Marker, ProjectConfiguration, RecordingMeta,
+    SilenceDetection, TimelineConfiguration, TimelinePosition, TimelineSegment,
+};
+use cap_rendering::decoder::{AsyncVideoDecoder, DecoderConfig, FrameIndex};
+use cap_rendering::{
+    produce_frame, Antialiasing, Background, CardFrames, DecodedFrame, DecoderError, GpuReport,
+    Layer, ProjectUniforms, RecordingDecoders, RenderOptions, RenderVideoConstants, SeekMode,
+    VisualizerAudio,
+};
+use serde::Serialize;
+use specta::Type;
 use std::ops::Deref;
 use std::sync::Mutex as StdMutex;
+use std::time::Duration;
 use std::{path::PathBuf, sync::Arc};
-use tokio::sync::{mpsc, watch, Mutex};
-
-const FPS: u32 = 30;
+use tokio::sync::{mpsc, watch, Mutex, OnceCell};
+
+pub(crate) const FPS: u32 = 30;
+
+// project updates closer together than this are coalesced into one re-render,
+// though a render always happens within the max delay while updates keep coming
+const PROJECT_RENDER_DEBOUNCE: Duration = Duration::from_millis(50);
+const PROJECT_RENDER_MAX_DELAY: Duration = Duration::from_millis(150);
+
+// smallest brightness and amplitude changes that count as a sync test flash and beep
+const SYNC_FLASH_MIN_STEP: f64 = 0.25;
+const SYNC_BEEP_MIN_STEP: f64 = 0.05;
+
+// preview requests closer together than this are treated as scrubbing
+const SCRUB_SETTLE_TIME: Duration = Duration::from_millis(150);
+
+const PREVIEW_STATS_LOG_INTERVAL: Duration = Duration::from_secs(30);
+
+// external audio is decoded at this rate when the recording has no audio to match
+const EXTERNAL_AUDIO_SAMPLE_RATE: u32 = 48_000;
+
+// written to the project by `rebuild_index`, one frame index per screen recording segment
+const FRAME_INDEX_FILE: &str = "frame-index.json";
+
+#[derive(Debug, thiserror::Error)]
+pub enum EditorInstanceError {
+    #[error("Project {0} not found")]
+    ProjectNotFound(PathBuf),
+    #[error("Failed to load recording meta: {0}")]
+    Meta(String),
+    #[error(transparent)]
+    Decoder(#[from] DecoderError),
+    #[error("Failed to initialize renderer: {0}")]
+    Renderer(String),
+    #[error("Project {0} has no recorded frames, the recording may have stopped unexpectedly")]
+    EmptyRecording(PathBuf),
+}
 
 pub struct EditorInstance {
     pub project_path: PathBuf,
     pub id: String,
     pub audio: Arc<StdMutex<Option<AudioData>>>,
+    /// Seconds of silence appended to the recording's audio because it decoded shorter
+    /// than the screen recording, usually after an interrupted recording or decode.
+    pub audio_shortfall: Option<f64>,
+    // the recording's own audio, kept so external audio can be swapped or mixed in again
+    recorded_audio: Option<Arc<AudioData>>,
+    // external audio mixed over the recording's, kept so it can be ducked again
+    mixed_audio: StdMutex<Option<Arc<AudioData>>>,
+    // tells the audio remixer the ducking changed, so the external audio is mixed in again
+    remix_tx: watch::Sender<()>,
+    processed_audio: Arc<ProcessedAudioCache>,
     pub ws_port: u16,
+    /// Shared secret that clients of the frames WebSocket must provide.
+    pub ws_token: String,
     pub decoders: RecordingDecoders,
     pub recordings: ProjectRecordings,
     pub renderer: Arc<editor::RendererHandle>,
@@ -25,11 +88,74 @@ pub struct EditorInstance {
     pub state: Arc<Mutex<EditorState>>,
     on_state_change: Box<dyn Fn(&EditorState) + Send + Sync + 'static>,
     pub preview_tx: watch::Sender<Option<PreviewFrameInstruction>>,
-    pub project_config: (
+    project_config: (
         watch::Sender<ProjectConfiguration>,
         watch::Receiver<ProjectConfiguration>,
     ),
     ws_shutdown: Arc<StdMutex<Option<mpsc::Sender<()>>>>,
+    memory_budget: Option<usize>,
+    audio_resampling: AudioResampling,
+    warmed_up: OnceCell<()>,
+    scrub_cancel: StdMutex<Option<watch::Sender<bool>>>,
+    audio_enabled: watch::Sender<bool>,
+    decoders_readiness: watch::Sender<DecodersReadiness>,
+    activity: watch::Sender<()>,
+    idle_task: StdMutex<Option<tokio::task::JoinHandle<()>>>,
+    cadence_task: StdMutex<Option<tokio::task::JoinHandle<()>>>,
+    preview_stats: Arc<PreviewCounters>,
+}
+
+/// Optional settings for an `EditorInstance`, see `EditorInstance::new_with_options`.
+pub struct EditorInstanceOptions {
+    /// How often the frames socket is pinged while no frames are being sent,
+    /// so idle connections aren't dropped by proxies or browsers.
+    pub ws_keepalive_interval: Duration,
+    /// Receives every rendered preview and playback frame, alongside the frames socket.
+    /// Useful for feeding frames into a pipeline of your own without a WebSocket.
+    /// Frames are rendered at the size the connected socket client asked for, if any.
+    pub frame_sink: Option<mpsc::UnboundedSender<RenderedFrame>>,
+    /// Maximum bytes to spend on decoded frames and audio together. Audio may use up to
+    /// half and is downsampled if it needs more, decoded frame caches get the rest.
+    /// Every decoder always keeps at least one frame, so tiny budgets can be exceeded.
+    pub memory_budget: Option<usize>,
+    /// How often the last frame is sent again while no new frames are being rendered, for
+    /// clients that may miss frames. New connections are always sent the latest frame
+    /// straight away, so this is off with `None` by default.
+    pub idle_frame_resend_interval: Option<Duration>,
+    /// Records how long each render pass takes on the GPU, read back with
+    /// `RenderVideoConstants::gpu_profile`. Only for performance tuning, as it slows rendering.
+    pub profile_gpu: bool,
+    /// Another `.cap` project whose screen recording is shown beside this one's when the
+    /// project configuration enables split screen. It's played in lockstep with this one.
+    pub split_screen_project: Option<PathBuf>,
+    /// Disposes the instance once nothing has been rendered, played or seeked for this
+    /// long, then emits a state change with `idle_disposed` set so the UI can offer to
+    /// reopen it. Any activity restarts the timer. `None` keeps the instance alive.
+    pub idle_timeout: Option<Duration>,
+    /// Called once the instance has disposed itself after `idle_timeout`, so whatever holds
+    /// on to it can let it go and open a fresh instance the next time it's needed.
+    pub on_idle_dispose: Option<Box<dyn FnOnce() + Send + 'static>>,
+    /// How smoothly the edges of the screen and cameras are drawn in previews and exports.
+    pub antialiasing: Antialiasing,
+    /// How the audio is resampled when played and when converted ahead of time.
+    pub audio_resampling: AudioResampling,
+}
+
+impl Default for EditorInstanceOptions {
+    fn default() -> Self {
+        Self {
+            ws_keepalive_interval: Duration::from_secs(15),
+            frame_sink: None,
+            memory_budget: None,
+            idle_frame_resend_interval: None,
+            profile_gpu: false,
+            split_screen_project: None,
+            idle_timeout: None,
+            on_idle_dispose: None,
+            antialiasing: Antialiasing::default(),
+            audio_resampling: AudioResampling::default(),
+        }
+    }
 }
 
 impl EditorInstance {
@@ -37,7 +163,22 @@ impl EditorInstance {
         projects_path: PathBuf,
         video_id: String,
         on_state_change: impl Fn(&EditorState) + Send + Sync + 'static,
-    ) -> Arc<Self> {
+    ) -> Result<Arc<Self>, EditorInstanceError> {
+        Self::new_with_options(
+            projects_path,
+            video_id,
+            on_state_change,
+            EditorInstanceOptions::default(),
+        )
+        .await
+    }
+
+    pub async fn new_with_options(
+        projects_path: PathBuf,
+        video_id: String,
+        on_state_change: impl Fn(&EditorState) + Send + Sync + 'static,
+        options: EditorInstanceOptions,
+    ) -> Result<Arc<Self>, EditorInstanceError> {
         let project_path = projects_path.join(format!(
             "{}{}",
             video_id,
@@ -49,139 +190,284 @@ impl EditorInstance {
         ));
 
         if !project_path.exists() {
-            println!("Video path {} not found!", project_path.display());
-            // return Err(format!("Video path {} not found!", path.display()));
-            panic!("Video path {} not found!", project_path.display());
+            tracing::warn!("Video path {} not found!", project_path.display());
+            return Err(EditorInstanceError::ProjectNotFound(project_path));
+        }
+
+        let meta = cap_project::RecordingMeta::load_for_project(&project_path)
+            .map_err(EditorInstanceError::Meta)?;
+
+        // decoders validate their files, so spawn them before probing the recordings
+        let decoder_config = DecoderConfig::default();
+        let screen_decoder = AsyncVideoDecoder::spawn_segments(
+            meta.display.paths().map(|p| project_path.join(p)).collect(),
+            decoder_config,
+        )
+        .await?;
+
+        let saved_index = std::fs::read_to_string(project_path.join(FRAME_INDEX_FILE))
+            .ok()
+            .and_then(|s| serde_json::from_str::<Vec<FrameIndex>>(&s).ok())
+            .filter(|index| index.len() == screen_decoder.segment_count());
+        if let Some(index) = saved_index {
+            screen_decoder.set_frame_index(index);
+        }
+
+        let mut camera_decoders = vec![];
+        for camera in meta.cameras() {
+            camera_decoders.push(
+                AsyncVideoDecoder::spawn_segments(
+                    vec![project_path.join(&camera.path)],
+                    decoder_config,
+                )
+                .await?,
+            );
         }
 
-        let meta = cap_project::RecordingMeta::load_for_project(&project_path).unwrap();
+        let secondary_screen = match &options.split_screen_project {
+            Some(path) => {
+                let meta =
+                    RecordingMeta::load_for_project(path).map_err(EditorInstanceError::Meta)?;
+                let decoder = AsyncVideoDecoder::spawn_segments(
+                    meta.display.paths().map(|p| path.join(p)).collect(),
+                    decoder_config,
+                )
+                .await?;
+
+                Some((decoder, Video::new(&path.join(&meta.display.path))))
+            }
+            None => None,
+        };
 
         let recordings = ProjectRecordings::new(&meta);
 
+        // playback, export and the audio waveform all divide by the frame count
+        if recordings.duration() * (FPS as f64) < 1.0 {
+            return Err(EditorInstanceError::EmptyRecording(project_path));
+        }
+
         let render_options = RenderOptions {
             screen_size: (recordings.display.width, recordings.display.height),
-            camera_size: recordings.camera.as_ref().map(|c| (c.width, c.height)),
+            camera_sizes: recordings.cameras().map(|c| (c.width, c.height)).collect(),
+            profile_gpu: options.profile_gpu,
+            secondary_screen_size: secondary_screen
+                .as_ref()
+                .map(|(_, video)| (video.width, video.height)),
+            antialiasing: options.antialiasing,
         };
 
-        let screen_decoder =
-            AsyncVideoDecoder::spawn(project_path.join(&meta.display.path).clone());
-        let camera_decoder = meta
-            .camera
-            .as_ref()
-            .map(|camera| AsyncVideoDecoder::spawn(project_path.join(&camera.path).clone()));
-
         let audio = meta
             .audio
             .as_ref()
             .zip(recordings.audio)
-            .map(|(meta, recording)| {
-                let audio_path = project_path.join(&meta.path);
-
-                // TODO: Use ffmpeg crate instead of command line
-                let stdout = FFmpeg::new()
-                    .command
-                    .arg("-i")
-                    .arg(audio_path)
-                    .args(["-f", "f64le", "-acodec", "pcm_f64le"])
-                    .args(["-ar", &recording.sample_rate.to_string()])
-                    .args(["-ac", &recording.channels.to_string(), "-"])
-                    .output()
-                    .unwrap()
-                    .stdout;
-
-                let buffer = stdout
-                    .chunks_exact(8)
-                    .map(|c| f64::from_le_bytes([c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]]))
-                    .collect::<Vec<_>>();
-
-                println!("audio buffer length: {}", buffer.len());
-
-                AudioData {
-                    buffer: Arc::new(buffer),
-                    sample_rate: recording.sample_rate,
-                }
+            .and_then(|(meta, recording)| {
+                AudioData::decode(&project_path.join(&meta.path), &recording)
+                    .inspect_err(|e| tracing::warn!("Failed to decode audio: {e}"))
+                    .ok()
             });
 
-        let (frame_tx, frame_rx) = tokio::sync::mpsc::unbounded_channel();
+        // a short decode would otherwise cut the audio off early without any sign why
+        let (audio, audio_shortfall) = match audio {
+            Some(audio) if recordings.display.duration - audio.duration() > 1.0 / FPS as f64 => {
+                let (audio, padding) = audio.padded_to(recordings.display.duration);
+                tracing::warn!(
+                    "Audio decoded {padding:.2}s shorter than the video, padding with silence"
+                );
+                (Some(audio), Some(padding))
+            }
+            audio => (audio, None),
+        };
+
+        let decoders = RecordingDecoders::new(screen_decoder, camera_decoders);
+        let decoders = match secondary_screen {
+            Some((decoder, _)) => decoders.with_secondary_screen(decoder),
+            None => decoders,
+        };
 
-        let (ws_port, ws_shutdown) = create_frames_ws(frame_rx).await;
+        let audio = match options.memory_budget {
+            Some(budget) => audio.map(|audio| audio.fit_to(budget / 2)),
+            None => audio,
+        };
 
-        let render_constants = Arc::new(RenderVideoConstants::new(render_options).await.unwrap());
+        if let Some(budget) = options.memory_budget {
+            let audio_bytes = audio.as_ref().map(AudioData::size_bytes).unwrap_or(0);
+            // every frame number caches one screen frame and one frame per camera,
+            // plus one for the secondary screen in split screen
+            let bytes_per_frame = std::iter::once(render_options.screen_size)
+                .chain(render_options.camera_sizes.iter().copied())
+                .chain(render_options.secondary_screen_size)
+                .map(|(width, height)| width as usize * height as usize * 4)
+                .sum::<usize>();
+
+            decoders.set_cache_size(budget.saturating_sub(audio_bytes) / bytes_per_frame.max(1));
+        }
 
-        let renderer = Arc::new(editor::Renderer::spawn(render_constants.clone(), frame_tx));
+        let (frame_tx, frame_rx) = FrameSender::channel();
+
+        let preview_stats = Arc::new(PreviewCounters::default());
+        spawn_preview_stats_log(Arc::downgrade(&preview_stats));
+
+        let ws_token = uuid::Uuid::new_v4().simple().to_string();
+        let (ws_port, ws_shutdown) = create_frames_ws(
+            frame_rx,
+            frame_tx.client(),
+            ws_token.clone(),
+            options.ws_keepalive_interval,
+            options.idle_frame_resend_interval,
+            preview_stats.clone(),
+        )
+        .await;
+
+        let render_constants = Arc::new(
+            RenderVideoConstants::new(render_options)
+                .await
+                .map_err(EditorInstanceError::Renderer)?,
+        );
+
+        let renderer = Arc::new(editor::Renderer::spawn(
+            render_constants.clone(),
+            frame_tx,
+            options.frame_sink,
+            preview_stats.clone(),
+        ));
 
         let (preview_tx, preview_rx) = watch::channel(None);
 
         let project_config = std::fs::read_to_string(project_path.join("project-config.json"))
             .ok()
             .and_then(|s| serde_json::from_str(&s).ok())
-            .unwrap_or_default();
+            .unwrap_or_else(|| ProjectConfiguration::default_for(&meta));
+
+        let decoders_readiness = watch::channel(DecodersReadiness {
+            screen: DecoderReadiness::Preparing,
+            cameras: vec![DecoderReadiness::Preparing; decoders.cameras().len()],
+        })
+        .0;
 
         let this = Arc::new(Self {
             id: video_id,
             project_path,
-            decoders: RecordingDecoders::new(screen_decoder, camera_decoder),
+            decoders,
             recordings,
             ws_port,
+            ws_token,
             renderer,
             render_constants,
+            recorded_audio: audio.clone().map(Arc::new),
+            mixed_audio: StdMutex::new(None),
+            remix_tx: watch::channel(()).0,
+            processed_audio: Arc::new(ProcessedAudioCache::default()),
             audio: Arc::new(StdMutex::new(audio)),
+            audio_shortfall,
             state: Arc::new(Mutex::new(EditorState {
                 playhead_position: 0,
                 playback_task: None,
                 preview_task: None,
+                project_render_task: None,
+                audio_remix_task: None,
+                audio_enabled: true,
+                audio_level: None,
+                idle_disposed: false,
+                task_error: None,
+                render_warning: None,
             })),
             on_state_change: Box::new(on_state_change),
             preview_tx,
             project_config: watch::channel(project_config),
             ws_shutdown: Arc::new(StdMutex::new(Some(ws_shutdown))),
+            memory_budget: options.memory_budget,
+            audio_resampling: options.audio_resampling,
+            warmed_up: OnceCell::new(),
+            scrub_cancel: StdMutex::new(None),
+            audio_enabled: watch::channel(true).0,
+            decoders_readiness,
+            activity: watch::channel(()).0,
+            idle_task: StdMutex::new(None),
+            cadence_task: StdMutex::new(None),
+            preview_stats,
         });
 
-        this.state.lock().await.preview_task =
-            Some(this.clone().spawn_preview_renderer(preview_rx));
+        {
+            let mut state = this.state.lock().await;
+            state.preview_task = Some(this.clone().spawn_preview_renderer(preview_rx));
+            state.project_render_task = Some(this.clone().spawn_project_renderer());
+            state.audio_remix_task = Some(this.clone().spawn_audio_remixer());
+        }
+
+        this.clone().spawn_decoder_readiness();
+        this.spawn_render_warnings();
+
+        if let Some(idle_timeout) = options.idle_timeout {
+            let task = this.spawn_idle_timeout(idle_timeout, options.on_idle_dispose);
+            *this.idle_task.lock().unwrap() = Some(task);
+        }
 
-        this
+        Ok(this)
     }
 
     pub fn meta(&self) -> RecordingMeta {
         RecordingMeta::load_for_project(&self.project_path).unwrap()
     }
 
+    /// URL the frontend should connect to for preview frames, including the access token.
+    pub fn frames_socket_url(&self) -> String {
+        format!(
+            "ws://localhost:{}{FRAMES_WS_PATH}?token={}",
+            self.ws_port, self.ws_token
+        )
+    }
+
     pub async fn dispose(&self) {
-        println!("Disposing EditorInstance");
+        tracing::info!("Disposing EditorInstance");
 
         let mut state = self.state.lock().await;
 
         // Stop playback
         if let Some(handle) = state.playback_task.take() {
-            println!("Stopping playback");
+            tracing::info!("Stopping playback");
             handle.stop();
         }
 
         // Stop preview
         if let Some(task) = state.preview_task.take() {
-            println!("Stopping preview");
+            tracing::info!("Stopping preview");
             task.abort();
             task.await.ok(); // Await the task to ensure it's fully stopped
         }
 
+        if let Some(task) = state.project_render_task.take() {
+            task.abort();
+            task.await.ok();
+        }
+
+        if let Some(task) = state.audio_remix_task.take() {
+            task.abort();
+            task.await.ok();
+        }
+
+        if let Some(task) = self.idle_task.lock().unwrap().take() {
+            task.abort();
+        }
+
+        self.stop_fixed_cadence();
+
         // Stop WebSocket server
         if let Some(ws_shutdown) = self.ws_shutdown.lock().unwrap().take() {
-            println!("Shutting down WebSocket server");
+            tracing::info!("Shutting down WebSocket server");
             let _ = ws_shutdown.send(());
         }
 
         // Stop renderer
-        println!("Stopping renderer");
+        tracing::info!("Stopping renderer");
         self.renderer.stop().await;
 
         // Stop decoders
-        println!("Stopping decoders");
+        tracing::info!("Stopping decoders");
         self.decoders.stop().await;
 
         // Clear audio data
         if self.audio.lock().unwrap().is_some() {
-            println!("Clearing audio data");
+            tracing::info!("Clearing audio data");
             *self.audio.lock().unwrap() = None; // Explicitly drop the audio data
         }
 
@@ -190,7 +476,461 @@ impl EditorInstance {
 
         drop(state);
 
-        println!("EditorInstance disposed");
+        tracing::info!("EditorInstance disposed");
+    }
+
+    /// Counts of rendered and dropped preview frames and of slow or failed frames socket
+    /// sends since the instance was created, to tell slow rendering from a slow connection.
+    pub fn preview_stats(&self) -> PreviewStats {
+        self.preview_stats.snapshot()
+    }
+
+    /// Describes the GPUs available for rendering and whether they meet the renderer's
+    /// requirements, without opening a project.
+    pub fn gpu_report() -> GpuReport {
+        cap_rendering::gpu_report()
+    }
+
+    /// Memory currently used by decoded frame caches and audio.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        MemoryUsage {
+            budget: self.memory_budget,
+            frame_cache: self.decoders.cache_bytes(),
+            audio: self
+                .audio
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(AudioData::size_bytes)
+                .unwrap_or(0),
+        }
+    }
+
+    /// Frees the decoded frames cached for previews and playback, including those decoded
+    /// ahead of the playhead, returning the bytes freed. Decoders and the renderer are
+    /// kept, so the editor carries on working and refills the caches as frames are needed.
+    /// Meant for when the system is low on memory.
+    pub async fn clear_caches(&self) -> usize {
+        let freed = self.decoders.clear_caches().await;
+        tracing::info!("Cleared {freed} bytes of cached frames");
+        freed
+    }
+
+    /// Replaces the project configuration used by previews and playback.
+    /// The frame at the playhead is re-rendered once updates stop arriving, so rapid
+    /// changes like slider drags coalesce into a single render of the latest config.
+    /// Configs that fail `ProjectConfiguration::validate` are rejected with its errors.
+    /// Mixed in external audio is ducked again in the background once ducking changes stop.
+    pub fn set_project(&self, config: ProjectConfiguration) -> Result<(), Vec<ConfigError>> {
+        config.validate()?;
+
+        let (ducking_changed, processing_changed) = {
+            let current = &self.project_config.1.borrow().audio;
+            (
+                current.ducking != config.audio.ducking,
+                current.noise_gate != config.audio.noise_gate
+                    || current.normalize_loudness != config.audio.normalize_loudness,
+            )
+        };
+        if processing_changed {
+            self.processed_audio.clear();
+        }
+        self.project_config.0.send_replace(config);
+
+        if ducking_changed {
+            self.remix_tx.send_replace(());
+        }
+
+        Ok(())
+    }
+
+    /// Mutes or unmutes one of the recording's audio tracks in the project configuration,
+    /// affecting playback immediately and any export of the updated config. The microphone,
+    /// `MICROPHONE_TRACK`, is the only track, so muting it silences the recorded audio.
+    pub fn set_track_muted(&self, track_id: &str, muted: bool) -> Result<(), String> {
+        if !AUDIO_TRACKS.contains(&track_id) || self.audio.lock().unwrap().is_none() {
+            return Err(format!("Recording has no audio track '{track_id}'"));
+        }
+
+        self.project_config.0.send_modify(|project| {
+            let muted_tracks = &mut project.audio.muted_tracks;
+            muted_tracks.retain(|id| id != track_id);
+            if muted {
+                muted_tracks.push(track_id.to_string());
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Replaces the recording's audio with an external file's, or mixes the two, for
+    /// playback started afterwards and exports. The file is resampled to match the recording,
+    /// with the export resampling quality since it's also what gets exported.
+    /// Each call starts over from the recording's own audio, so external audio doesn't stack.
+    /// Mixed audio is ducked under the recording's when the project's audio config enables it.
+    /// Audio longer or shorter than the video is handled by the export's length policy.
+    pub async fn set_external_audio(
+        &self,
+        path: PathBuf,
+        mode: AudioReplaceMode,
+    ) -> Result<(), String> {
+        let sample_rate = self
+            .recorded_audio
+            .as_ref()
+            .map_or(EXTERNAL_AUDIO_SAMPLE_RATE, |audio| audio.sample_rate);
+        let channels = self.recordings.audio.map_or(1, |audio| audio.channels);
+        let quality = self.audio_resampling.export;
+
+        let external = tokio::task::spawn_blocking(move || {
+            AudioData::decode_resampled(&path, sample_rate, channels, quality)
+        })
+        .await
+        .map_err(|e| e.to_string())??;
+
+        match (mode, &self.recorded_audio) {
+            (AudioReplaceMode::Mix, Some(_)) => {
+                *self.mixed_audio.lock().unwrap() = Some(Arc::new(external));
+                self.remix_audio(&self.get_project().audio).await;
+            }
+            _ => {
+                *self.mixed_audio.lock().unwrap() = None;
+                *self.audio.lock().unwrap() = Some(self.fit_audio(external));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Mixes the external audio set with `AudioReplaceMode::Mix` over the recording's own,
+    /// ducking it under the recording's if `config` enables it, then swaps the mix in.
+    /// Mixing runs on the blocking thread pool, without holding any locks. Does nothing
+    /// without external audio, or if it's replaced while mixing.
+    async fn remix_audio(&self, config: &AudioConfiguration) {
+        let Some(recorded) = self.recorded_audio.clone() else {
+            return;
+        };
+        let Some(external) = self.mixed_audio.lock().unwrap().clone() else {
+            return;
+        };
+
+        let ducking = config.ducking.clone();
+        let memory_budget = self.memory_budget;
+        let mix = {
+            let external = external.clone();
+            tokio::task::spawn_blocking(move || {
+                let mixed = match &ducking {
+                    Some(ducking) => {
+                        recorded.mixed_with(&external.ducked_under(&recorded, ducking))
+                    }
+                    None => recorded.mixed_with(&external),
+                };
+                fit_audio(mixed, memory_budget)
+            })
+        };
+
+        let mixed = match mix.await {
+            Ok(mixed) => mixed,
+            Err(e) => {
+                tracing::error!("Mixing in external audio failed: {e}");
+                return;
+            }
+        };
+
+        let current = self.mixed_audio.lock().unwrap();
+        if current
+            .as_ref()
+            .is_some_and(|current| Arc::ptr_eq(current, &external))
+        {
+            *self.audio.lock().unwrap() = Some(mixed);
+        }
+    }
+
+    /// Mixes the external audio in again after ducking changes, once they settle so a
+    /// slider drag is mixed once rather than on every update.
+    fn spawn_audio_remixer(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let mut remix_rx = self.remix_tx.subscribe();
+
+        tokio::spawn(async move {
+            while remix_rx.changed().await.is_ok() {
+                if !settle(&mut remix_rx).await {
+                    return;
+                }
+
+                self.remix_audio(&self.get_project().audio).await;
+            }
+        })
+    }
+
+    fn fit_audio(&self, audio: AudioData) -> AudioData {
+        fit_audio(audio, self.memory_budget)
+    }
+
+    /// Swaps the recording of the camera at `index`, where 0 is the primary camera, for the
+    /// video at `path`, eg. after it was re-recorded. `None` removes the camera so it isn't
+    /// drawn. Playback, exports and the frame at the playhead, which is re-rendered, all
+    /// use the new recording. Its frames are laid out at its own size.
+    pub async fn replace_camera(&self, index: usize, path: Option<PathBuf>) -> Result<(), String> {
+        if index >= self.decoders.cameras().len() {
+            return Err(format!("Recording has no camera {index}"));
+        }
+
+        let readiness = match path {
+            Some(path) => {
+                let video = Video::try_new(&path)?;
+                let decoder =
+                    AsyncVideoDecoder::spawn_segments(vec![path.clone()], DecoderConfig::default())
+                        .await
+                        .map_err(|e| e.to_string())?;
+                let readiness =
+                    DecoderReadiness::from_first_frame(decoder.get_frame(0, SeekMode::Exact).await);
+
+                self.render_constants
+                    .set_camera_size(index, (video.width, video.height));
+                self.decoders.replace_camera(index, Some(decoder));
+
+                readiness
+            }
+            None => {
+                self.decoders.replace_camera(index, None);
+                DecoderReadiness::Removed
+            }
+        };
+
+        self.decoders_readiness
+            .send_modify(|state| state.cameras[index] = readiness);
+
+        let frame_number = self.state.lock().await.playhead_position;
+        self.preview_tx.send(Some(frame_number)).ok();
+
+        Ok(())
+    }
+
+    /// Presets the project can be exported with, its own followed by the built in ones.
+    pub fn export_presets(&self) -> Vec<ExportPreset> {
+        let mut presets = self.get_project().export_presets;
+        presets.extend(ExportPreset::built_in());
+        presets
+    }
+
+    /// The export preset called `name`, checked to be valid. Exports themselves are run by
+    /// the app, which applies the preset to the project with `ExportPreset::apply`.
+    pub fn export_preset(&self, name: &str) -> Result<ExportPreset, String> {
+        let preset = ExportPreset::find(&self.get_project(), name)
+            .ok_or_else(|| format!("There's no export preset called {name}"))?;
+        preset.validate()?;
+
+        Ok(preset)
+    }
+
+    /// Frame numbers of the screen recording's keyframes, in ascending order, which are the
+    /// cheapest frames to seek to. Read from the decoder's index, so stays up to date after
+    /// `rebuild_index`.
+    pub fn keyframe_positions(&self) -> Vec<u32> {
+        self.decoders.keyframes()
+    }
+
+    /// Scans the screen recording's files for where their frames and keyframes are and
+    /// seeks using that from now on, saving it in the project for the next time it's opened.
+    /// Repairs seeking in recordings whose files have a missing or corrupt index.
+    /// `on_progress` is called with the fraction of the recording scanned so far.
+    pub async fn rebuild_index(
+        &self,
+        on_progress: impl Fn(f64) + Send + 'static,
+    ) -> Result<(), String> {
+        let meta = RecordingMeta::load_for_project(&self.project_path)?;
+        let paths = meta
+            .display
+            .paths()
+            .map(|p| self.project_path.join(p))
+            .collect::<Vec<_>>();
+
+        let index = tokio::task::spawn_blocking(move || {
+            let segments = paths.len() as f64;
+            paths
+                .iter()
+                .enumerate()
+                .map(|(i, path)| {
+                    FrameIndex::scan(path, |progress| {
+                        on_progress((i as f64 + progress) / segments)
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .await
+        .map_err(|e| format!("Frame index scan failed: {e}"))?
+        .map_err(|e| e.to_string())?;
+
+        let json = serde_json::to_string(&index).map_err(|e| e.to_string())?;
+        std::fs::write(self.project_path.join(FRAME_INDEX_FILE), json)
+            .map_err(|e| format!("Failed to save frame index: {e}"))?;
+
+        self.decoders.screen().set_frame_index(index);
+        // cached frames may have been decoded after seeking to the wrong place
+        self.decoders.clear_caches().await;
+
+        let frame_number = self.state.lock().await.playhead_position;
+        self.preview_tx.send(Some(frame_number)).ok();
+
+        Ok(())
+    }
+
+    pub fn get_project(&self) -> ProjectConfiguration {
+        self.project_config.1.borrow().clone()
+    }
+
+    /// Silent stretches of the recording's audio, in recording time.
+    pub fn silent_ranges(&self, detection: &SilenceDetection) -> Vec<SilentRange> {
+        self.audio
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|audio| audio.detect_silence(detection))
+            .unwrap_or_default()
+    }
+
+    /// Applies the project's silence skipping, if enabled, by cutting the silent
+    /// ranges out of its timeline.
+    pub fn with_silence_skipped(&self, mut project: ProjectConfiguration) -> ProjectConfiguration {
+        let Some(detection) = project.audio.skip_silence.clone() else {
+            return project;
+        };
+
+        let ranges = self
+            .silent_ranges(&detection)
+            .iter()
+            .map(|range| (range.start, range.end))
+            .collect::<Vec<_>>();
+
+        if ranges.is_empty() {
+            return project;
+        }
+
+        let timeline = project
+            .timeline
+            .take()
+            .unwrap_or_else(|| TimelineConfiguration {
+                segments: vec![TimelineSegment {
+                    timescale: 1.0,
+                    start: 0.0,
+                    end: self.recordings.duration(),
+                }],
+                speed_segments: vec![],
+                holds: vec![],
+            });
+
+        project.timeline = Some(timeline.without_recording_ranges(&ranges));
+        project
+    }
+
+    /// Sets the composition guides drawn over preview frames.
+    /// Only frames sent to the frames socket are affected, not snapshots or exports.
+    pub fn set_overlay(&self, overlay: OverlayConfig) {
+        self.renderer.set_overlay(overlay);
+    }
+
+    /// Shows the current frame with and without its color grade and spotlight either side of a
+    /// wipe line, or goes back to the normal preview with `None`. Like the overlay, this only
+    /// affects frames sent to the frames socket. Comparing renders every frame twice.
+    pub fn set_compare(&self, compare: Option<CompareWipe>) {
+        self.renderer.set_compare(compare);
+    }
+
+    /// Previews a single layer on its own, over black unless it's the background, to see
+    /// how each contributes to the composite. `None` goes back to the full composite.
+    /// Only frames sent to the frames socket are affected, not snapshots or exports, and
+    /// while a layer is soloed every frame is rendered twice.
+    pub async fn set_solo_layer(&self, layer: Option<Layer>) {
+        self.renderer.set_solo_layer(layer);
+
+        let frame_number = self.state.lock().await.playhead_position;
+        self.preview_tx.send(Some(frame_number)).ok();
+    }
+
+    /// Sends the latest rendered frame to `tx` exactly `fps` times a second, repeating it
+    /// while nothing new is rendered, eg. when paused. Unlike the frames socket, which only
+    /// gets frames as they're rendered, this suits a live streaming encoder. Frames don't
+    /// include the preview overlay. The frame at the playhead is rendered to start with.
+    /// Replaces any cadence already running, and stops once `tx` is closed.
+    pub async fn start_fixed_cadence(&self, fps: u32, tx: mpsc::UnboundedSender<RenderedFrame>) {
+        self.stop_fixed_cadence();
+
+        let (latest_tx, latest_rx) = watch::channel(None);
+        self.renderer.set_cadence_output(Some(latest_tx));
+
+        let task = tokio::spawn(async move {
+            // missed ticks are caught up on, so the number of frames always matches the time
+            let mut interval =
+                tokio::time::interval(Duration::from_secs_f64(1.0 / fps.max(1) as f64));
+
+            loop {
+                interval.tick().await;
+
+                let Some(frame) = latest_rx.borrow().clone() else {
+                    continue;
+                };
+
+                if tx.send(frame).is_err() {
+                    break;
+                }
+            }
+        });
+        *self.cadence_task.lock().unwrap() = Some(task);
+
+        let frame_number = self.state.lock().await.playhead_position;
+        self.preview_tx.send(Some(frame_number)).ok();
+    }
+
+    /// Stops the output started by `start_fixed_cadence`, if it's running.
+    pub fn stop_fixed_cadence(&self) {
+        if let Some(task) = self.cadence_task.lock().unwrap().take() {
+            task.abort();
+        }
+        self.renderer.set_cadence_output(None);
+    }
+
+    /// Rebuilds the renderer if it has stopped responding, cancelling any pending renders.
+    /// Returns true if a restart was needed.
+    pub async fn ensure_renderer_healthy(&self) -> bool {
+        if self.renderer.is_healthy().await {
+            return false;
+        }
+
+        tracing::warn!("Renderer is unresponsive, restarting");
+        self.restart_renderer().await;
+
+        true
+    }
+
+    /// Replaces the renderer with a fresh one connected to the same frames socket.
+    /// The handle is shared, so playback picks up the new renderer too.
+    pub async fn restart_renderer(&self) {
+        self.renderer.restart().await;
+    }
+
+    /// Master switch for audio output. While disabled, playback keeps advancing frames
+    /// but plays silence and scrubbing makes no sound, regardless of track mutes.
+    /// Takes effect immediately, including during playback.
+    pub async fn set_audio_enabled(&self, enabled: bool) {
+        self.audio_enabled.send_replace(enabled);
+        self.modify_and_emit_state(|state| state.audio_enabled = enabled)
+            .await;
+    }
+
+    /// A copy of the editor's current state, for code that needs it without waiting for
+    /// the next state change.
+    pub async fn state_snapshot(&self) -> EditorStateSnapshot {
+        let state = self.state.lock().await;
+
+        EditorStateSnapshot {
+            playhead_position: state.playhead_position,
+            is_playing: state
+                .playback_task
+                .as_ref()
+                .is_some_and(PlaybackHandle::is_playing),
+            audio_enabled: state.audio_enabled,
+            audio_level: state.audio_level,
+            idle_disposed: state.idle_disposed,
+        }
     }
 
     pub async fn modify_and_emit_state(&self, modify: impl Fn(&mut EditorState)) {
@@ -199,53 +939,139 @@ impl EditorInstance {
         (self.on_state_change)(&state);
     }
 
-    pub async fn start_playback(self: Arc<Self>) {
+    /// Logs a panic caught in a background task and emits a state change with it as the
+    /// `task_error`, so the UI can tell the user instead of the task failing silently.
+    async fn report_task_panic(&self, task: &str, message: String) {
+        tracing::error!("{task} panicked: {message}");
+
+        let mut state = self.state.lock().await;
+        state.task_error = Some(format!("{task} failed: {message}"));
+        (self.on_state_change)(&state);
+        state.task_error = None;
+    }
+
+    /// Moves the playhead to the first marker after it, returning the marker's frame.
+    /// Returns `None` and leaves the playhead alone if there are no markers after it.
+    pub async fn seek_next_marker(&self) -> Option<u32> {
+        let frames = marker_frames(&self.get_project().markers);
+        let mut state = self.state.lock().await;
+
+        let frame = next_marker(&frames, state.playhead_position)?;
+        state.playhead_position = frame;
+        self.touch();
+        (self.on_state_change)(&state);
+        self.preview_tx.send(Some(frame)).ok();
+
+        Some(frame)
+    }
+
+    /// Moves the playhead to the last marker before it, returning the marker's frame.
+    /// Returns `None` and leaves the playhead alone if there are no markers before it.
+    pub async fn seek_prev_marker(&self) -> Option<u32> {
+        let frames = marker_frames(&self.get_project().markers);
+        let mut state = self.state.lock().await;
+
+        let frame = prev_marker(&frames, state.playhead_position)?;
+        state.playhead_position = frame;
+        self.touch();
+        (self.on_state_change)(&state);
+        self.preview_tx.send(Some(frame)).ok();
+
+        Some(frame)
+    }
+
+    /// Starts playback from the playhead using the instance's project configuration,
+    /// or `project` if provided. An override is fixed for the lifetime of the playback.
+    pub async fn start_playback(
+        self: Arc<Self>,
+        project: Option<ProjectConfiguration>,
+    ) -> Result<(), PlaybackStartError> {
         let (mut handle, prev) = {
             let Ok(mut state) = self.state.try_lock() else {
-                return;
+                return Err(PlaybackStartError::Busy);
             };
 
             let start_frame_number = state.playhead_position;
 
+            // skipping silence rewrites the timeline, so playback gets a fixed copy of the project
+            let project = project.or_else(|| {
+                let project = self.get_project();
+                project.audio.skip_silence.is_some().then_some(project)
+            });
+
             let playback_handle = playback::Playback {
                 audio: Arc::clone(&self.audio),
+                processed_audio: self.processed_audio.clone(),
                 renderer: self.renderer.clone(),
                 render_constants: self.render_constants.clone(),
                 decoders: self.decoders.clone(),
-                recordings: self.recordings,
+                recordings: self.recordings.clone(),
+                audio_enabled: self.audio_enabled.subscribe(),
+                resample_quality: self.audio_resampling.preview,
                 start_frame_number,
-                project: self.project_config.0.subscribe(),
+                project: match project {
+                    Some(project) => watch::channel(self.with_silence_skipped(project)).1,
+                    None => self.project_config.0.subscribe(),
+                },
             }
             .start()
-            .await;
+            .await?;
 
             let prev = state.playback_task.replace(playback_handle.clone());
+            self.touch();
 
             (playback_handle, prev)
         };
 
+        let cancelled = handle.cancelled();
         tokio::spawn(async move {
-            loop {
-                let event = *handle.receive_event().await;
-
-                match event {
-                    playback::PlaybackEvent::Start => {}
-                    playback::PlaybackEvent::Frame(frame_number) => {
-                        self.modify_and_emit_state(|state| {
-                            state.playhead_position = frame_number;
-                        })
-                        .await;
-                    }
-                    playback::PlaybackEvent::Stop => {
-                        return;
+            tokio::pin!(cancelled);
+
+            let events = catch_panic(async {
+                loop {
+                    // a replaced or disposed playback must not move the playhead any more
+                    let event = tokio::select! {
+                        biased;
+                        _ = &mut cancelled => return,
+                        event = handle.receive_event() => event,
+                    };
+
+                    match event {
+                        playback::PlaybackEvent::Start => {}
+                        playback::PlaybackEvent::Frame(frame_number) => {
+                            self.touch();
+                            self.modify_and_emit_state(|state| {
+                                state.playhead_position = frame_number;
+                            })
+                            .await;
+                        }
+                        playback::PlaybackEvent::AudioLevel(level) => {
+                            self.modify_and_emit_state(|state| {
+                                state.audio_level = Some(level);
+                            })
+                            .await;
+                        }
+                        playback::PlaybackEvent::Stop => {
+                            self.modify_and_emit_state(|state| state.audio_level = None)
+                                .await;
+                            return;
+                        }
                     }
                 }
+            })
+            .await;
+
+            if let Err(e) = events {
+                handle.stop();
+                self.report_task_panic("Playback", e).await;
             }
         });
 
         if let Some(prev) = prev {
             prev.stop();
         }
+
+        Ok(())
     }
 
     fn spawn_preview_renderer(
@@ -253,98 +1079,837 @@ impl EditorInstance {
         mut preview_rx: watch::Receiver<Option<u32>>,
     ) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
+            let mut last_request = None::<std::time::Instant>;
+            // frame to render exactly once scrubbing stops
+            let mut settle_frame = None::<u32>;
+
             loop {
-                preview_rx.changed().await.unwrap();
-                let Some(frame_number) = preview_rx.borrow().deref().clone() else {
-                    continue;
+                let changed = match settle_frame {
+                    Some(frame_number) => {
+                        match tokio::time::timeout(SCRUB_SETTLE_TIME, preview_rx.changed()).await {
+                            Ok(changed) => changed,
+                            Err(_) => {
+                                settle_frame = None;
+                                self.render_frame_with_seek(frame_number, None, SeekMode::Exact)
+                                    .await;
+                                continue;
+                            }
+                        }
+                    }
+                    None => preview_rx.changed().await,
                 };
+                changed.unwrap();
 
-                let project = self.project_config.1.borrow().clone();
-
-                let Some(time) = project
-                    .timeline
-                    .as_ref()
-                    .map(|timeline| timeline.get_recording_time(frame_number as f64 / FPS as f64))
-                    .unwrap_or(Some(frame_number as f64 / FPS as f64))
-                else {
+                let Some(frame_number) = preview_rx.borrow().deref().clone() else {
                     continue;
                 };
+                self.touch();
 
-                let Some((screen_frame, camera_frame)) =
-                    self.decoders.get_frames((time * FPS as f64) as u32).await
-                else {
-                    continue;
-                };
+                // requests arriving in quick succession mean the playhead is being dragged,
+                // so show the nearest keyframe now and the exact frame once it settles
+                let scrubbing = last_request.is_some_and(|t| t.elapsed() < SCRUB_SETTLE_TIME);
+                last_request = Some(std::time::Instant::now());
 
-                self.renderer
-                    .render_frame(
-                        screen_frame,
-                        camera_frame,
-                        project.background.source.clone(),
-                        ProjectUniforms::new(&self.render_constants, &project),
-                    )
-                    .await;
+                if scrubbing {
+                    self.render_frame_with_seek(frame_number, None, SeekMode::Keyframe)
+                        .await;
+                    settle_frame = Some(frame_number);
+                } else {
+                    self.try_render_frame(frame_number, None).await;
+                    settle_frame = None;
+                }
             }
         })
     }
-}
-
-async fn create_frames_ws(
-    frame_rx: mpsc::UnboundedReceiver<SocketMessage>,
-) -> (u16, mpsc::Sender<()>) {
-    use axum::{
-        extract::{
-            ws::{Message, WebSocket, WebSocketUpgrade},
-            State,
-        },
-        response::IntoResponse,
-        routing::get,
-    };
-    use tokio::sync::{mpsc::UnboundedReceiver, Mutex};
-
-    type RouterState = Arc<Mutex<UnboundedReceiver<SocketMessage>>>;
 
-    async fn ws_handler(
-        ws: WebSocketUpgrade,
-        State(state): State<RouterState>,
-    ) -> impl IntoResponse {
-        // let rx = rx.lock().await.take().unwrap();
-        ws.on_upgrade(move |socket| handle_socket(socket, state))
+    /// Restarts the idle timeout, if there is one.
+    fn touch(&self) {
+        self.activity.send_replace(());
     }
 
-    async fn handle_socket(mut socket: WebSocket, state: RouterState) {
-        let mut rx = state.lock().await;
-        println!("socket connection established");
-        let now = std::time::Instant::now();
+    /// Waits for the instance to go `idle_timeout` without activity, then disposes it and
+    /// calls `on_dispose`. Only holds a weak reference so an idle timeout doesn't keep the
+    /// instance alive.
+    fn spawn_idle_timeout(
+        self: &Arc<Self>,
+        idle_timeout: Duration,
+        on_dispose: Option<Box<dyn FnOnce() + Send + 'static>>,
+    ) -> tokio::task::JoinHandle<()> {
+        let mut activity_rx = self.activity.subscribe();
+        let this = Arc::downgrade(self);
 
-        loop {
-            tokio::select! {
-                _ = socket.recv() => {
-                    break;
+        tokio::spawn(async move {
+            loop {
+                match tokio::time::timeout(idle_timeout, activity_rx.changed()).await {
+                    Ok(Ok(())) => continue,
+                    Ok(Err(_)) => return,
+                    Err(_) => break,
+                }
+            }
+
+            let Some(this) = this.upgrade() else {
+                return;
+            };
+
+            tracing::info!("EditorInstance idle for {idle_timeout:?}, disposing");
+
+            // dispose aborts the idle task, which would cancel it partway through
+            this.idle_task.lock().unwrap().take();
+            this.dispose().await;
+            this.modify_and_emit_state(|state| state.idle_disposed = true)
+                .await;
+
+            if let Some(on_dispose) = on_dispose {
+                on_dispose();
+            }
+        })
+    }
+
+    fn spawn_project_renderer(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let mut project_rx = self.project_config.1.clone();
+
+        tokio::spawn(async move {
+            while project_rx.changed().await.is_ok() {
+                if !settle(&mut project_rx).await {
+                    return;
+                }
+
+                let frame_number = self.state.lock().await.playhead_position;
+                self.preview_tx.send(Some(frame_number)).ok();
+            }
+        })
+    }
+
+    /// Whether each decoder can serve frames yet. Decoders are opened by `new`, but
+    /// their first frame is still being decoded for a moment after.
+    pub fn decoders_readiness(&self) -> DecodersReadiness {
+        self.decoders_readiness.borrow().clone()
+    }
+
+    /// Waits until every decoder has either decoded its first frame or failed to,
+    /// so the UI can show a preparing state instead of an incomplete preview.
+    pub async fn wait_decoders_ready(&self) -> DecodersReadiness {
+        self.decoders_readiness
+            .subscribe()
+            .wait_for(DecodersReadiness::is_settled)
+            .await
+            .map(|readiness| readiness.clone())
+            .unwrap_or_else(|_| self.decoders_readiness())
+    }
+
+    /// Emits a state change with each of the renderer's warnings as the `render_warning`,
+    /// eg. when it scales the preview back after the GPU runs out of memory.
+    fn spawn_render_warnings(self: &Arc<Self>) {
+        let mut warnings = self.renderer.warnings();
+        let this = Arc::downgrade(self);
+
+        tokio::spawn(async move {
+            while warnings.changed().await.is_ok() {
+                let Some(this) = this.upgrade() else {
+                    break;
+                };
+                let warning = warnings.borrow_and_update().clone();
+
+                let mut state = this.state.lock().await;
+                state.render_warning = warning;
+                (this.on_state_change)(&state);
+                state.render_warning = None;
+            }
+        });
+    }
+
+    /// Decodes the first frame of every decoder in the background, recording each as
+    /// ready once it can be served.
+    fn spawn_decoder_readiness(self: Arc<Self>) {
+        let screen = self.decoders.screen().clone();
+        let this = self.clone();
+        tokio::spawn(async move {
+            let readiness =
+                DecoderReadiness::from_first_frame(screen.get_frame(0, SeekMode::Exact).await);
+            this.decoders_readiness
+                .send_modify(|state| state.screen = readiness);
+        });
+
+        for (i, camera) in self.decoders.cameras().into_iter().enumerate() {
+            let Some(camera) = camera else {
+                continue;
+            };
+
+            let this = self.clone();
+            tokio::spawn(async move {
+                let readiness =
+                    DecoderReadiness::from_first_frame(camera.get_frame(0, SeekMode::Exact).await);
+                this.decoders_readiness
+                    .send_modify(|state| state.cameras[i] = readiness);
+            });
+        }
+    }
+
+    /// Renders a throwaway first frame so shader compilation, GPU allocations and
+    /// decoder startup happen before the first real render instead of during playback.
+    /// The frame isn't sent anywhere. Only the first call does any work.
+    pub async fn warm_up(&self) {
+        self.warmed_up
+            .get_or_init(|| async {
+                let project = self.get_project();
+
+                let Some((screen_frame, camera_frames)) = self.decoders.get_frames(0).await else {
+                    tracing::warn!("Failed to decode first frame for warm up");
+                    return;
+                };
+
+                if let Err(e) = produce_frame(
+                    &self.render_constants,
+                    &screen_frame,
+                    &camera_frames,
+                    Background::from(project.background.source.clone()),
+                    &ProjectUniforms::new(&self.render_constants, &project),
+                )
+                .await
+                {
+                    tracing::warn!("Failed to render warm up frame: {e}");
                 }
-                msg = rx.recv() => {
-                    let Some(chunk) = msg else {
+            })
+            .await;
+    }
+
+    /// Plays a short burst of audio centered on `frame_number` as an audible reference
+    /// while the playhead is dragged. Starting a new burst cancels the previous one.
+    pub async fn scrub_audio(&self, frame_number: u32) {
+        if !*self.audio_enabled.borrow() {
+            return;
+        }
+
+        let Some(audio) = self.audio.lock().unwrap().clone() else {
+            return;
+        };
+
+        let project = self.get_project();
+        let Some(burst) =
+            scrub_burst_at_frame(&audio, &project, frame_number, self.recordings.duration())
+        else {
+            return;
+        };
+
+        let (cancel_tx, cancel_rx) = watch::channel(false);
+        if let Some(previous) = self.scrub_cancel.lock().unwrap().replace(cancel_tx) {
+            previous.send(true).ok();
+        }
+
+        if let Err(e) = playback::play_burst(
+            burst,
+            audio.sample_rate,
+            self.audio_resampling.preview,
+            cancel_rx,
+        )
+        .await
+        {
+            tracing::warn!("Failed to play scrub audio: {e}");
+        }
+    }
+
+    /// Clamps a recording frame number to the frames the screen decoder has,
+    /// so requests past the end show the last frame instead of nothing.
+    pub fn clamp_frame(&self, frame_number: u32) -> u32 {
+        clamp_to_frames(frame_number, self.decoders.total_frames())
+    }
+
+    /// Source recording time, in seconds, of the frame shown for timeline frame `frame_number`.
+    /// Accounts for trims, cuts and timescales, and matches the recording frame that's
+    /// actually rendered. Intro card frames map to where the recording starts, and outro
+    /// card frames and frames past the end of the timeline to where it ends.
+    pub fn frame_timestamp(&self, frame_number: u32) -> f64 {
+        recording_timestamp(
+            &self.get_project(),
+            frame_number,
+            self.recordings.duration(),
+            self.decoders.total_frames(),
+        )
+    }
+
+    /// Debug check for a recording of a sync test pattern, a flash on screen coinciding
+    /// with a beep. Finds both on the timeline the way playback and export map it to the
+    /// recording and reports how far apart they land.
+    pub async fn measure_av_sync(&self) -> Result<SyncMeasurement, String> {
+        // cards can't contain the test pattern, so only the recording is checked
+        let project = ProjectConfiguration {
+            intro: None,
+            outro: None,
+            ..self.get_project()
+        };
+        let audio = self
+            .audio
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or("Recording has no audio")?;
+
+        let duration = project
+            .timeline()
+            .map(|timeline| timeline.duration())
+            .unwrap_or_else(|| self.recordings.duration());
+        let total_frames = (duration * FPS as f64).ceil() as u32;
+
+        let mut brightness = Vec::with_capacity(total_frames as usize);
+        for frame_number in 0..total_frames {
+            let Some(recording_frame) =
+                get_recording_frame(&project, frame_number, self.recordings.duration())
+            else {
+                break;
+            };
+
+            let (screen_frame, _) = self
+                .decoders
+                .get_frames(self.clamp_frame(recording_frame))
+                .await
+                .ok_or(format!("Failed to decode frame {recording_frame}"))?;
+
+            brightness.push(sync_check::mean_luma(&screen_frame));
+        }
+
+        let flash_frame = sync_check::find_onset(&brightness, SYNC_FLASH_MIN_STEP)
+            .ok_or("No flash found in the video")? as u32;
+
+        // samples are mapped through the timeline one by one, as playback and export do
+        let sample_step = 1.0 / audio.sample_rate as f64;
+        let amplitudes = (0..(duration * audio.sample_rate as f64) as usize)
+            .map(|i| {
+                let time = i as f64 * sample_step;
+                let recording_time = match project.timeline() {
+                    Some(timeline) if timeline.is_held(time) => None,
+                    Some(timeline) => timeline.get_recording_time(time),
+                    None => Some(time),
+                };
+
+                recording_time
+                    .map(|t| (t * audio.sample_rate as f64) as usize)
+                    .and_then(|index| audio.buffer.get(index))
+                    .map_or(0.0, |sample| sample.abs())
+            })
+            .collect::<Vec<_>>();
+
+        let beep_sample = sync_check::find_onset(&amplitudes, SYNC_BEEP_MIN_STEP)
+            .ok_or("No beep found in the audio")?;
+
+        Ok(SyncMeasurement::new(
+            flash_frame,
+            flash_frame as f64 / FPS as f64,
+            beep_sample as f64 * sample_step,
+        ))
+    }
+
+    /// Renders a single frame to the frames socket using the instance's project
+    /// configuration, or `project` if provided. Frames past the end of the recording
+    /// render its last frame.
+    /// Returns false if the frame is outside the timeline or couldn't be decoded.
+    /// A panic while rendering is reported through the editor state's `task_error`.
+    pub async fn try_render_frame(
+        &self,
+        frame_number: u32,
+        project: Option<ProjectConfiguration>,
+    ) -> bool {
+        self.render_frame_with_seek(frame_number, project, SeekMode::Exact)
+            .await
+    }
+
+    async fn render_frame_with_seek(
+        &self,
+        frame_number: u32,
+        project: Option<ProjectConfiguration>,
+        seek_mode: SeekMode,
+    ) -> bool {
+        let rendered = catch_panic(self.render_frame_unguarded(frame_number, project, seek_mode))
+            .await
+            .and_then(|rendered| rendered);
+
+        match rendered {
+            Ok(rendered) => rendered,
+            Err(e) => {
+                self.report_task_panic("Rendering the preview", e).await;
+                false
+            }
+        }
+    }
+
+    /// Returns the panic message if the renderer panicked.
+    async fn render_frame_unguarded(
+        &self,
+        frame_number: u32,
+        project: Option<ProjectConfiguration>,
+        seek_mode: SeekMode,
+    ) -> Result<bool, String> {
+        let project = project.unwrap_or_else(|| self.get_project());
+
+        // card images are only loaded when one is shown
+        let cards = match project
+            .timeline_position(frame_number as f64 / FPS as f64, self.recordings.duration())
+        {
+            Some(TimelinePosition::Intro | TimelinePosition::Outro) => {
+                CardFrames::load(&project, self.render_constants.options.screen_size)
+            }
+            _ => CardFrames::default(),
+        };
+
+        let Some((screen_frame, camera_frames)) = self
+            .get_timeline_frames(&project, frame_number, &cards, seek_mode)
+            .await
+        else {
+            return Ok(false);
+        };
+
+        let uniforms = ProjectUniforms::new(&self.render_constants, &project)
+            .for_preview()
+            .at_time(frame_number as f64 / FPS as f64, FPS);
+
+        self.renderer
+            .render_frame(
+                screen_frame,
+                camera_frames,
+                project.background.source.clone(),
+                with_visualizer_audio(
+                    uniforms,
+                    &project,
+                    &self.audio,
+                    frame_number,
+                    self.recordings.duration(),
+                ),
+            )
+            .await?;
+
+        Ok(true)
+    }
+
+    /// Screen and camera frames shown at timeline frame `frame_number`, taking card frames
+    /// from `cards`. Frames past the end of the recording use its last frame.
+    pub(crate) async fn get_timeline_frames(
+        &self,
+        project: &ProjectConfiguration,
+        frame_number: u32,
+        cards: &CardFrames,
+        seek_mode: SeekMode,
+    ) -> Option<(DecodedFrame, Vec<Option<DecodedFrame>>)> {
+        let recording_duration = self.recordings.duration();
+
+        let position =
+            project.timeline_position(frame_number as f64 / FPS as f64, recording_duration)?;
+        if let Some(card) = cards.get(position) {
+            return Some((card, vec![None; self.decoders.cameras().len()]));
+        }
+
+        let recording_frame = get_recording_frame(project, frame_number, recording_duration)?;
+
+        self.decoders
+            .get_frames_with_seek(self.clamp_frame(recording_frame), seek_mode)
+            .await
+    }
+}
+
+/// Maps a frame number on the project's timeline to the recording's frame number.
+/// Returns `None` for intro and outro card frames and past the end of the timeline.
+/// `recording_duration` is used for projects without a timeline.
+pub(crate) fn get_recording_frame(
+    project: &ProjectConfiguration,
+    frame_number: u32,
+    recording_duration: f64,
+) -> Option<u32> {
+    let time =
+        match project.timeline_position(frame_number as f64 / FPS as f64, recording_duration)? {
+            TimelinePosition::Recording(time) => time,
+            TimelinePosition::Intro | TimelinePosition::Outro => return None,
+        };
+
+    let time = project
+        .timeline
+        .as_ref()
+        .map(|timeline| timeline.get_recording_time(time))
+        .unwrap_or(Some(time))?;
+
+    Some((time * FPS as f64) as u32)
+}
+
+/// Sets `uniforms` up to draw the project's audio visualizer from `audio` at timeline frame
+/// `frame_number`. The visualizer isn't drawn without audio.
+pub(crate) fn with_visualizer_audio(
+    uniforms: ProjectUniforms,
+    project: &ProjectConfiguration,
+    audio: &StdMutex<Option<AudioData>>,
+    frame_number: u32,
+    recording_duration: f64,
+) -> ProjectUniforms {
+    if project.audio_visualizer.is_none() {
+        return uniforms;
+    }
+
+    match audio.lock().unwrap().as_ref() {
+        Some(audio) => uniforms.with_audio(
+            &VisualizerAudio::from(audio),
+            project.audio_time(frame_number as f64 / FPS as f64, recording_duration),
+        ),
+        None => uniforms,
+    }
+}
+
+/// `EditorInstance::frame_timestamp` for a recording of `total_frames` frames.
+fn recording_timestamp(
+    project: &ProjectConfiguration,
+    frame_number: u32,
+    recording_duration: f64,
+    total_frames: u32,
+) -> f64 {
+    let recording_frame = get_recording_frame(project, frame_number, recording_duration)
+        .or_else(|| {
+            let timeline = project.timeline()?;
+            let time = match project
+                .timeline_position(frame_number as f64 / FPS as f64, recording_duration)
+            {
+                Some(TimelinePosition::Intro) => timeline.segments.first()?.start,
+                _ => timeline.segments.last()?.end,
+            };
+            Some((time * FPS as f64) as u32)
+        })
+        .unwrap_or(u32::MAX);
+
+    clamp_to_frames(recording_frame, total_frames) as f64 / FPS as f64
+}
+
+/// Clamps `frame_number` to the last of `total_frames` frames.
+fn clamp_to_frames(frame_number: u32, total_frames: u32) -> u32 {
+    frame_number.min(total_frames.saturating_sub(1))
+}
+
+/// The burst `scrub_audio` plays for `frame_number` on the timeline, or `None` where
+/// there's no recording audio to hear, on the cards, in holds and past the end.
+fn scrub_burst_at_frame(
+    audio: &AudioData,
+    project: &ProjectConfiguration,
+    frame_number: u32,
+    recording_duration: f64,
+) -> Option<Vec<f64>> {
+    let recording_time =
+        project.audio_time(frame_number as f64 / FPS as f64, recording_duration)?;
+
+    Some(audio.scrub_burst(recording_time))
+}
+
+/// Frame numbers of `markers`, in ascending order without repeats.
+fn marker_frames(markers: &[Marker]) -> Vec<u32> {
+    let mut frames = markers
+        .iter()
+        .filter(|marker| marker.time.is_finite() && marker.time >= 0.0)
+        .map(|marker| (marker.time * FPS as f64).round() as u32)
+        .collect::<Vec<_>>();
+    frames.sort_unstable();
+    frames.dedup();
+    frames
+}
+
+/// The first of the ascending marker `frames` after `playhead`, without wrapping around.
+fn next_marker(frames: &[u32], playhead: u32) -> Option<u32> {
+    frames.iter().copied().find(|&frame| frame > playhead)
+}
+
+/// The last of the ascending marker `frames` before `playhead`, without wrapping around.
+fn prev_marker(frames: &[u32], playhead: u32) -> Option<u32> {
+    frames.iter().rev().copied().find(|&frame| frame < playhead)
+}
+
+/// Compares tokens in time that doesn't depend on where they first differ, so a token
+/// can't be worked out a byte at a time from how long rejections take.
+fn tokens_match(provided: &str, expected: &str) -> bool {
+    provided.len() == expected.len()
+        && provided
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn create_frames_ws(
+    frame_rx: watch::Receiver<Option<SocketMessage>>,
+    client: FrameClient,
+    token: String,
+    keepalive_interval: Duration,
+    idle_resend_interval: Option<Duration>,
+    stats: Arc<PreviewCounters>,
+) -> (u16, mpsc::Sender<()>) {
+    use axum::{
+        extract::{
+            ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade},
+            Query, State,
+        },
+        http::{header, HeaderMap, StatusCode},
+        response::{IntoResponse, Response},
+        routing::get,
+    };
+    use std::collections::HashMap;
+    use tokio::sync::Mutex;
+
+    #[derive(Clone)]
+    struct RouterState {
+        rx: Arc<Mutex<watch::Receiver<Option<SocketMessage>>>>,
+        token: Arc<str>,
+        keepalive_interval: Duration,
+        idle_resend_interval: Option<Duration>,
+        client: FrameClient,
+        stats: Arc<PreviewCounters>,
+    }
+
+    async fn ws_handler(
+        ws: WebSocketUpgrade,
+        Query(query): Query<HashMap<String, String>>,
+        headers: HeaderMap,
+        State(state): State<RouterState>,
+    ) -> Response {
+        // browsers can't set headers on WebSocket connections, so accept the token as a query param too
+        let provided = query.get("token").map(String::as_str).or_else(|| {
+            headers
+                .get(header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "))
+        });
+
+        if !provided.is_some_and(|provided| tokens_match(provided, &state.token)) {
+            tracing::info!("rejecting frames socket connection without a valid token");
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+
+        // let rx = rx.lock().await.take().unwrap();
+        ws.on_upgrade(move |socket| handle_socket(socket, state))
+    }
+
+    /// Encodes a frame for the connection and sends it, returning false if the socket is gone.
+    /// A frame the client doesn't take within `FRAME_SEND_TIMEOUT` is dropped, so a client
+    /// that stops reading can't hold up the socket. Failed, slow and timed out sends are
+    /// counted in `stats`. `last_size` is the size of the previous frame sent, used to notify
+    /// clients that asked for resize events. Encoding runs on the blocking thread pool, as
+    /// resizing and compressing a frame would otherwise stall the runtime's worker.
+    async fn send_frame(
+        socket: &mut WebSocket,
+        handshake: &FrameHandshake,
+        stats: &PreviewCounters,
+        last_size: &mut Option<(u32, u32)>,
+        data: Arc<Vec<u8>>,
+        width: u32,
+        height: u32,
+    ) -> bool {
+        let encoder = *handshake;
+        let encoded = tokio::task::spawn_blocking(move || {
+            encoder.encode(Arc::unwrap_or_clone(data), width, height)
+        })
+        .await;
+
+        let (mut data, width, height) = match encoded {
+            Ok(Ok(encoded)) => encoded,
+            Ok(Err(e)) => {
+                tracing::warn!("{e}");
+                return true;
+            }
+            Err(e) => {
+                tracing::warn!("Encoding a frame panicked: {e}");
+                return true;
+            }
+        };
+
+        data.extend_from_slice(&height.to_le_bytes());
+        data.extend_from_slice(&width.to_le_bytes());
+
+        let started = std::time::Instant::now();
+        let send = async {
+            if handshake.resize_events && *last_size != Some((width, height)) {
+                let resize = FrameControl::Resize { width, height };
+                socket.send(Message::Text(resize.to_json())).await?;
+            }
+            *last_size = Some((width, height));
+
+            for message in handshake.chunk(data) {
+                socket.send(Message::Binary(message)).await?;
+            }
+
+            Ok::<_, axum::Error>(())
+        };
+
+        match tokio::time::timeout(FRAME_SEND_TIMEOUT, send).await {
+            Ok(Ok(())) => {}
+            Ok(Err(_)) => {
+                stats.send_failed();
+                return false;
+            }
+            // clients start over on the next frame's first chunk, so a partly sent frame is
+            // discarded, and messages already queued are flushed with the next send
+            Err(_) => {
+                tracing::debug!("frames socket client isn't reading, dropping frame");
+                stats.send_timed_out();
+                return true;
+            }
+        }
+
+        if started.elapsed() > SEND_LAG_THRESHOLD {
+            stats.send_lagged();
+        }
+
+        true
+    }
+
+    async fn handle_socket(mut socket: WebSocket, state: RouterState) {
+        let RouterState {
+            rx: state,
+            keepalive_interval,
+            idle_resend_interval,
+            client,
+            stats,
+            ..
+        } = state;
+
+        // clients pick the frame encoding with a handshake before any frames are sent
+        let handshake = match tokio::time::timeout(HANDSHAKE_TIMEOUT, socket.recv()).await {
+            Ok(Some(Ok(Message::Text(text)))) => FrameHandshake::parse(&text),
+            Ok(Some(Ok(_))) => Err("expected a text handshake message".to_string()),
+            Ok(_) => return,
+            Err(_) => Err("timed out waiting for handshake".to_string()),
+        };
+
+        let handshake = match handshake {
+            Ok(handshake) => handshake,
+            Err(reason) => {
+                tracing::info!("closing frames socket: {reason}");
+                socket
+                    .send(Message::Close(Some(CloseFrame {
+                        code: INVALID_HANDSHAKE_CLOSE_CODE,
+                        reason: reason.into(),
+                    })))
+                    .await
+                    .ok();
+                return;
+            }
+        };
+
+        let mut rx = state.lock().await;
+        tracing::info!("socket connection established with {handshake:?}");
+        *client.lock().unwrap() = Some(handshake);
+        let now = std::time::Instant::now();
+
+        // the first tick of an interval completes immediately, so delay it
+        let mut keepalive = tokio::time::interval_at(
+            tokio::time::Instant::now() + keepalive_interval,
+            keepalive_interval,
+        );
+
+        let mut last_size = None;
+
+        let mut idle_resend = idle_resend_interval.map(|interval| {
+            tokio::time::interval_at(tokio::time::Instant::now() + interval, interval)
+        });
+
+        // the latest frame is sent straight away, even if it was rendered before the client
+        // connected, so clients connecting while nothing is being rendered show it
+        let frame = rx.borrow_and_update().clone();
+        if let Some(SocketMessage::Frame {
+            data,
+            width,
+            height,
+        }) = frame
+        {
+            if !send_frame(
+                &mut socket,
+                &handshake,
+                &stats,
+                &mut last_size,
+                data,
+                width,
+                height,
+            )
+            .await
+            {
+                return;
+            }
+        }
+
+        loop {
+            tokio::select! {
+                msg = socket.recv() => {
+                    match msg {
+                        // axum answers pings itself, and pongs are replies to our keepalives
+                        Some(Ok(Message::Ping(_) | Message::Pong(_))) => continue,
+                        _ => break,
+                    }
+                }
+                _ = keepalive.tick() => {
+                    let ping = socket.send(Message::Ping(vec![]));
+                    // a client that isn't reading will be sent the next frame or ping anyway
+                    if let Ok(Err(_)) = tokio::time::timeout(FRAME_SEND_TIMEOUT, ping).await {
+                        break;
+                    }
+                }
+                _ = async {
+                    match idle_resend.as_mut() {
+                        Some(idle_resend) => idle_resend.tick().await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    let frame = rx.borrow().clone();
+                    let Some(SocketMessage::Frame { data, width, height }) = frame else {
                         continue;
                     };
 
-                    match chunk {
-                        SocketMessage::Frame { width, height, mut data } => {
-                                data.extend_from_slice(&height.to_le_bytes());
-                              data.extend_from_slice(&width.to_le_bytes());
+                    let sent = send_frame(
+                        &mut socket, &handshake, &stats, &mut last_size, data, width, height,
+                    )
+                    .await;
+                    if !sent {
+                        break;
+                    }
+                }
+                changed = rx.changed() => {
+                    if changed.is_err() {
+                        break;
+                    }
+
+                    // frames rendered while the previous one was being sent are replaced by
+                    // newer ones rather than queued, so a slow client only ever holds one up
+                    let Some(frame) = rx.borrow_and_update().clone() else {
+                        continue;
+                    };
 
-                            socket.send(Message::Binary(data)).await.unwrap();
+                    match frame {
+                        SocketMessage::Frame { width, height, data } => {
+                            let sent = send_frame(
+                                &mut socket,
+                                &handshake,
+                                &stats,
+                                &mut last_size,
+                                data,
+                                width,
+                                height,
+                            )
+                            .await;
+                            if !sent {
+                                break;
+                            }
+                            // only ping or resend while no frames are flowing
+                            keepalive.reset();
+                            if let Some(idle_resend) = idle_resend.as_mut() {
+                                idle_resend.reset();
+                            }
                         }
                     }
                 }
             }
         }
+        *client.lock().unwrap() = None;
         let elapsed = now.elapsed();
-        println!("Websocket closing after {elapsed:.2?}");
+        tracing::info!("Websocket closing after {elapsed:.2?}");
     }
 
     let router = axum::Router::new()
         .route(FRAMES_WS_PATH, get(ws_handler))
-        .with_state(Arc::new(Mutex::new(frame_rx)));
+        .with_state(RouterState {
+            rx: Arc::new(Mutex::new(frame_rx)),
+            token: token.into(),
+            keepalive_interval,
+            idle_resend_interval,
+            client,
+            stats,
+        });
 
     let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
     let port = listener.local_addr().unwrap().port();
@@ -356,7 +1921,7 @@ async fn create_frames_ws(
         tokio::select! {
             _ = server => {},
             _ = shutdown_rx.recv() => {
-                println!("WebSocket server shutting down");
+                tracing::info!("WebSocket server shutting down");
             }
         }
     });
@@ -364,20 +1929,443 @@ async fn create_frames_ws(
     (port, shutdown_tx)
 }
 
+/// Logs the preview stats every `PREVIEW_STATS_LOG_INTERVAL` while they're changing,
+/// until the instance they belong to is dropped.
+fn spawn_preview_stats_log(stats: std::sync::Weak<PreviewCounters>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PREVIEW_STATS_LOG_INTERVAL);
+        let mut last_logged = PreviewStats::default();
+
+        loop {
+            interval.tick().await;
+
+            let Some(stats) = stats.upgrade().map(|stats| stats.snapshot()) else {
+                return;
+            };
+
+            if stats != last_logged {
+                tracing::info!("Preview stats: {stats:?}");
+                last_logged = stats;
+            }
+        }
+    });
+}
+
 type PreviewFrameInstruction = u32;
 
 pub struct EditorState {
     pub playhead_position: u32,
     pub playback_task: Option<PlaybackHandle>,
     pub preview_task: Option<tokio::task::JoinHandle<()>>,
+    pub project_render_task: Option<tokio::task::JoinHandle<()>>,
+    pub audio_remix_task: Option<tokio::task::JoinHandle<()>>,
+    pub audio_enabled: bool,
+    /// Level of the audio being played, while playing.
+    pub audio_level: Option<AudioLevel>,
+    /// Only set in the state change emitted when a background task, like rendering the
+    /// preview or following playback, panics. The editor carries on after reporting it.
+    pub task_error: Option<String>,
+    /// Only set in the state change emitted when the preview renderer scales back its
+    /// quality, or recovers a lost GPU device, instead of failing.
+    pub render_warning: Option<String>,
+    /// Set once the instance has disposed itself after its idle timeout.
+    pub idle_disposed: bool,
+}
+
+/// The parts of `EditorState` that can be copied out, see `EditorInstance::state_snapshot`.
+#[derive(Debug, Clone, Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct EditorStateSnapshot {
+    pub playhead_position: u32,
+    pub is_playing: bool,
+    pub audio_enabled: bool,
+    /// Level of the audio being played, while playing.
+    pub audio_level: Option<AudioLevel>,
+    pub idle_disposed: bool,
+}
+
+/// Waits for further updates to `rx` to settle, but not so long that dragging shows
+/// nothing, marking them seen. Returns false if the sender is dropped meanwhile.
+async fn settle<T>(rx: &mut watch::Receiver<T>) -> bool {
+    let deadline = tokio::time::Instant::now() + PROJECT_RENDER_MAX_DELAY;
+    loop {
+        let quiet_at = (tokio::time::Instant::now() + PROJECT_RENDER_DEBOUNCE).min(deadline);
+        match tokio::time::timeout_at(quiet_at, rx.changed()).await {
+            Ok(Ok(())) => continue,
+            Ok(Err(_)) => return false,
+            Err(_) => break,
+        }
+    }
+    rx.borrow_and_update();
+    true
+}
+
+fn fit_audio(audio: AudioData, memory_budget: Option<usize>) -> AudioData {
+    match memory_budget {
+        Some(budget) => audio.fit_to(budget / 2),
+        None => audio,
+    }
 }
 
 pub const FRAMES_WS_PATH: &str = "/frames-ws";
 
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Longest the frames socket waits for a client to take a frame before dropping it.
+const FRAME_SEND_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecoderReadiness {
+    /// Still decoding the first frame.
+    Preparing,
+    Ready,
+    /// The first frame couldn't be decoded, so the decoder won't serve frames.
+    Failed,
+    /// The camera was removed with `EditorInstance::replace_camera`, so there's nothing to serve.
+    Removed,
+}
+
+impl DecoderReadiness {
+    fn from_first_frame(frame: Option<DecodedFrame>) -> Self {
+        match frame {
+            Some(_) => Self::Ready,
+            None => Self::Failed,
+        }
+    }
+}
+
+/// Readiness of an `EditorInstance`'s screen decoder and of each camera decoder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodersReadiness {
+    pub screen: DecoderReadiness,
+    pub cameras: Vec<DecoderReadiness>,
+}
+
+impl DecodersReadiness {
+    /// Whether every decoder is ready to serve frames. Removed cameras don't count.
+    pub fn is_ready(&self) -> bool {
+        self.all(|readiness| {
+            matches!(
+                readiness,
+                DecoderReadiness::Ready | DecoderReadiness::Removed
+            )
+        })
+    }
+
+    /// Whether no decoder is still preparing.
+    pub fn is_settled(&self) -> bool {
+        self.all(|readiness| readiness != DecoderReadiness::Preparing)
+    }
+
+    fn all(&self, f: impl Fn(DecoderReadiness) -> bool) -> bool {
+        std::iter::once(&self.screen)
+            .chain(&self.cameras)
+            .all(|&readiness| f(readiness))
+    }
+}
+
+/// Bytes tracked against an `EditorInstance`'s memory budget.
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryUsage {
+    pub budget: Option<usize>,
+    pub frame_cache: usize,
+    pub audio: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.frame_cache + self.audio
+    }
+}
+
+/// A tightly packed RGBA frame as produced by the renderer.
+#[derive(Clone)]
+pub struct RenderedFrame {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Frames are shared between the renderer and the socket sending them rather than copied.
+#[derive(Clone)]
 pub enum SocketMessage {
     Frame {
-        data: Vec<u8>,
+        data: Arc<Vec<u8>>,
         width: u32,
         height: u32,
     },
 }
+
+/// The handshake of the client connected to the frames socket, if any.
+type FrameClient = Arc<StdMutex<Option<FrameHandshake>>>;
+
+/// Sends rendered frames to the frames socket. Only the latest frame is kept, replacing any
+/// the socket hasn't sent yet, so a client that falls behind skips frames instead of them
+/// piling up, and clients that connect while nothing is being rendered get the current one.
+#[derive(Clone)]
+pub(crate) struct FrameSender {
+    tx: Arc<watch::Sender<Option<SocketMessage>>>,
+    client: FrameClient,
+}
+
+impl FrameSender {
+    pub(crate) fn channel() -> (Self, watch::Receiver<Option<SocketMessage>>) {
+        let (tx, rx) = watch::channel(None);
+        let client = Arc::new(StdMutex::new(None));
+
+        (
+            Self {
+                tx: Arc::new(tx),
+                client,
+            },
+            rx,
+        )
+    }
+
+    /// How much to scale a `width` by `height` output for the connected client,
+    /// see `FrameHandshake::render_scale`. Without a client frames are rendered as is.
+    pub(crate) fn render_scale(&self, width: u32, height: u32) -> f32 {
+        self.client
+            .lock()
+            .unwrap()
+            .map_or(1.0, |client| client.render_scale(width, height))
+    }
+
+    pub(crate) fn send(&self, data: Vec<u8>, width: u32, height: u32) {
+        let frame = SocketMessage::Frame {
+            data: Arc::new(data),
+            width,
+            height,
+        };
+        self.tx.send_replace(Some(frame));
+    }
+
+    fn client(&self) -> FrameClient {
+        self.client.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cap_project::Card;
+    use futures::SinkExt;
+    use tokio_tungstenite::tungstenite::Message;
+
+    fn markers(times: &[f64]) -> Vec<Marker> {
+        times
+            .iter()
+            .map(|&time| Marker { time, label: None })
+            .collect()
+    }
+
+    #[test]
+    fn frames_past_the_end_are_clamped_to_the_last_frame() {
+        assert_eq!(clamp_to_frames(300, 300), 299);
+        assert_eq!(clamp_to_frames(u32::MAX, 300), 299);
+    }
+
+    #[test]
+    fn frames_in_range_are_left_alone() {
+        assert_eq!(clamp_to_frames(0, 300), 0);
+        assert_eq!(clamp_to_frames(299, 300), 299);
+    }
+
+    #[test]
+    fn frames_of_an_empty_video_are_clamped_to_the_first() {
+        assert_eq!(clamp_to_frames(10, 0), 0);
+    }
+
+    /// A project playing `segments` of the recording, as `(start, end, timescale)`.
+    fn trimmed(segments: &[(f64, f64, f64)]) -> ProjectConfiguration {
+        let mut project = ProjectConfiguration::default();
+        project.timeline = Some(TimelineConfiguration {
+            segments: segments
+                .iter()
+                .map(|&(start, end, timescale)| TimelineSegment {
+                    timescale,
+                    start,
+                    end,
+                })
+                .collect(),
+            speed_segments: vec![],
+            holds: vec![],
+        });
+        project
+    }
+
+    #[test]
+    fn recording_frames_follow_trims_and_timescales() {
+        let project = trimmed(&[(2.0, 4.0, 1.0), (6.0, 10.0, 2.0)]);
+
+        assert_eq!(get_recording_frame(&project, 0, 10.0), Some(60));
+        assert_eq!(get_recording_frame(&project, 45, 10.0), Some(105));
+        // one second into the second segment, played at double speed
+        assert_eq!(get_recording_frame(&project, 90, 10.0), Some(240));
+        assert_eq!(get_recording_frame(&project, 120, 10.0), None);
+    }
+
+    #[test]
+    fn recording_frames_skip_the_cards() {
+        let mut project = ProjectConfiguration::default();
+        project.intro = Some(Card {
+            image: "intro.png".to_string(),
+            duration_frames: 30,
+        });
+
+        assert_eq!(get_recording_frame(&project, 15, 10.0), None);
+        assert_eq!(get_recording_frame(&project, 45, 10.0), Some(15));
+    }
+
+    #[test]
+    fn timestamp_of_a_trimmed_frame_is_the_trim_offset_plus_elapsed_time() {
+        let project = trimmed(&[(2.0, 6.0, 1.0)]);
+
+        assert_eq!(recording_timestamp(&project, 45, 10.0, 300), 3.5);
+    }
+
+    #[test]
+    fn timestamp_without_a_timeline_is_the_elapsed_time() {
+        let project = ProjectConfiguration::default();
+
+        assert_eq!(recording_timestamp(&project, 45, 10.0, 300), 1.5);
+    }
+
+    #[test]
+    fn timestamps_off_the_recording_map_to_its_ends() {
+        let mut project = trimmed(&[(2.0, 6.0, 1.0)]);
+        project.intro = Some(Card {
+            image: "intro.png".to_string(),
+            duration_frames: 30,
+        });
+
+        assert_eq!(recording_timestamp(&project, 10, 10.0, 300), 2.0);
+        assert_eq!(recording_timestamp(&project, 1000, 10.0, 300), 6.0);
+        // the end of the trim is past the decoded frames
+        assert_eq!(recording_timestamp(&project, 1000, 10.0, 150), 149.0 / 30.0);
+    }
+
+    fn ramp_audio() -> AudioData {
+        AudioData {
+            buffer: Arc::new((0..4000).map(|i| i as f64).collect()),
+            sample_rate: 1000,
+        }
+    }
+
+    #[test]
+    fn scrub_burst_is_centered_on_the_frame() {
+        let project = ProjectConfiguration::default();
+
+        // frame 45 is 1.5s in, and the burst covers 25ms either side of it
+        let burst = scrub_burst_at_frame(&ramp_audio(), &project, 45, 4.0).unwrap();
+
+        assert_eq!(burst.len(), 50);
+        assert_eq!(burst[25], 1500.0);
+    }
+
+    #[test]
+    fn scrub_burst_follows_the_timeline_into_the_recording() {
+        let mut project = ProjectConfiguration::default();
+        project.timeline = Some(TimelineConfiguration {
+            segments: vec![TimelineSegment {
+                timescale: 1.0,
+                start: 2.0,
+                end: 4.0,
+            }],
+            speed_segments: vec![],
+            holds: vec![],
+        });
+
+        // the first two seconds are trimmed off, so frame 30 plays the recording at 3s
+        let burst = scrub_burst_at_frame(&ramp_audio(), &project, 30, 4.0).unwrap();
+        assert_eq!(burst[25], 3000.0);
+
+        assert!(scrub_burst_at_frame(&ramp_audio(), &project, 90, 4.0).is_none());
+    }
+
+    #[test]
+    fn marker_frames_are_sorted_without_repeats() {
+        let markers = markers(&[2.0, 1.0, -1.0, f64::NAN, 1.01, 0.5]);
+
+        assert_eq!(marker_frames(&markers), vec![15, 30, 60]);
+    }
+
+    #[test]
+    fn seeking_next_visits_markers_in_order_and_stops_at_the_last() {
+        let frames = marker_frames(&markers(&[3.0, 1.0, 2.0]));
+
+        let mut playhead = 0;
+        let mut visited = Vec::new();
+        while let Some(frame) = next_marker(&frames, playhead) {
+            visited.push(frame);
+            playhead = frame;
+        }
+
+        assert_eq!(visited, vec![30, 60, 90]);
+        assert_eq!(next_marker(&frames, 90), None);
+    }
+
+    #[test]
+    fn seeking_prev_visits_markers_in_reverse_and_stops_at_the_first() {
+        let frames = marker_frames(&markers(&[3.0, 1.0, 2.0]));
+
+        let mut playhead = 100;
+        let mut visited = Vec::new();
+        while let Some(frame) = prev_marker(&frames, playhead) {
+            visited.push(frame);
+            playhead = frame;
+        }
+
+        assert_eq!(visited, vec![90, 60, 30]);
+        assert_eq!(prev_marker(&frames, 30), None);
+    }
+
+    #[test]
+    fn seeking_from_between_markers_skips_the_one_at_the_playhead() {
+        let frames = vec![30, 60, 90];
+
+        assert_eq!(next_marker(&frames, 60), Some(90));
+        assert_eq!(prev_marker(&frames, 60), Some(30));
+        assert_eq!(next_marker(&frames, 45), Some(60));
+        assert_eq!(prev_marker(&frames, 45), Some(30));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn frames_socket_drops_frames_for_a_client_that_stops_reading() {
+        let (frame_tx, frame_rx) = FrameSender::channel();
+        let stats = Arc::new(PreviewCounters::default());
+        let (port, _shutdown) = create_frames_ws(
+            frame_rx,
+            frame_tx.client(),
+            "token".to_string(),
+            Duration::from_secs(60),
+            None,
+            stats.clone(),
+        )
+        .await;
+
+        let url = format!("ws://127.0.0.1:{port}{FRAMES_WS_PATH}?token=token");
+        let (mut client, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        client.send(Message::Text("{}".to_string())).await.unwrap();
+
+        // the client never reads again, so once the socket's buffers fill every send stalls
+        let (width, height) = (2048, 1024);
+        let started = std::time::Instant::now();
+        let mut sent = Vec::new();
+        while started.elapsed() < Duration::from_secs(3) {
+            frame_tx.send(vec![0; width * height * 4], width as u32, height as u32);
+            if let Some(SocketMessage::Frame { data, .. }) = frame_tx.tx.borrow().as_ref() {
+                sent.push(Arc::downgrade(data));
+            }
+            tokio::time::sleep(Duration::from_millis(33)).await;
+        }
+
+        // the socket kept going past the first stalled send rather than hanging on it
+        let stats = stats.snapshot();
+        assert!(stats.sends_timed_out >= 2, "{stats:?}");
+        // and at most the latest frame and the one being encoded are still held
+        let held = sent.iter().filter(|frame| frame.strong_count() > 0).count();
+        assert!(held <= 2, "{held} of {} frames are still held", sent.len());
+
+        drop(client);
+    }
+}
@@ -0,0 +1,350 @@
+use std::io::Cursor;
+
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, ImageBuffer, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// Close code sent when a client's handshake is missing or malformed.
+pub const INVALID_HANDSHAKE_CLOSE_CODE: u16 = 4400;
+
+const DEFAULT_JPEG_QUALITY: u8 = 80;
+
+// higher device pixel ratios are clamped to this, as no display needs more
+const MAX_DEVICE_PIXEL_RATIO: f32 = 4.0;
+
+/// Bytes at the start of every message when frames are chunked: the chunk's index,
+/// then the number of chunks in the frame, both little-endian u32s.
+pub const CHUNK_HEADER_SIZE: usize = 8;
+
+/// Sent by frames socket clients as their first message to choose how frames are encoded,
+/// eg. `{ "format": "jpeg", "maxWidth": 960 }`.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrameHandshake {
+    #[serde(default)]
+    pub format: FrameFormat,
+    /// Frames larger than this are scaled down, preserving their aspect ratio.
+    /// In CSS pixels when `device_pixel_ratio` is set.
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    /// Previews are rendered at their size in CSS pixels, the output size scaled down to
+    /// fit `max_width` and `max_height`, times this, so HiDPI displays get frames at their
+    /// physical resolution. Clients should size frames by the width and height sent with
+    /// each one divided by this. Clamped to at most 4.
+    pub device_pixel_ratio: Option<f32>,
+    /// JPEG quality from 1 to 100.
+    pub quality: Option<u8>,
+    /// Largest binary message the client accepts, in bytes. When set, every frame is sent
+    /// as one or more messages of at most this size, each starting with a chunk header
+    /// (see `CHUNK_HEADER_SIZE`), and the client joins their remaining bytes back together.
+    pub max_message_size: Option<usize>,
+    /// When set, a `FrameControl::Resize` text message is sent before any frame whose size
+    /// differs from the previous one, including the first, so clients can resize their canvas.
+    #[serde(default)]
+    pub resize_events: bool,
+}
+
+/// Text messages sent over the frames socket alongside the binary frames,
+/// eg. `{ "type": "resize", "width": 1920, "height": 1080 }`.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum FrameControl {
+    /// The frames that follow are this size, in the same pixels as their trailing dimensions.
+    Resize { width: u32, height: u32 },
+}
+
+impl FrameControl {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FrameFormat {
+    /// Tightly packed 8-bit RGBA pixels.
+    #[default]
+    Rgba,
+    /// Tightly packed 8-bit BGRA pixels, for renderers that expect that order.
+    Bgra,
+    /// Planar YUV 4:2:0 (I420) using full range BT.601: the Y plane, then the U and V planes
+    /// at half the width and height, rounded up. Alpha is dropped.
+    Yuv420,
+    Jpeg,
+}
+
+impl FrameHandshake {
+    pub fn parse(message: &str) -> Result<Self, String> {
+        let handshake: Self =
+            serde_json::from_str(message).map_err(|e| format!("invalid handshake: {e}"))?;
+
+        if handshake.max_width == Some(0) || handshake.max_height == Some(0) {
+            return Err("invalid handshake: maximum size must be non-zero".to_string());
+        }
+
+        if handshake
+            .device_pixel_ratio
+            .is_some_and(|ratio| !(ratio.is_finite() && ratio > 0.0))
+        {
+            return Err("invalid handshake: device pixel ratio must be positive".to_string());
+        }
+
+        if handshake.quality.is_some_and(|q| !(1..=100).contains(&q)) {
+            return Err("invalid handshake: quality must be between 1 and 100".to_string());
+        }
+
+        if handshake
+            .max_message_size
+            .is_some_and(|size| size <= CHUNK_HEADER_SIZE)
+        {
+            return Err(format!(
+                "invalid handshake: maximum message size must be larger than {CHUNK_HEADER_SIZE}"
+            ));
+        }
+
+        Ok(handshake)
+    }
+
+    /// How much to scale a `width` by `height` output when rendering previews for this
+    /// client: down to fit the maximum size, then up by the device pixel ratio.
+    pub fn render_scale(&self, width: u32, height: u32) -> f32 {
+        let fit = f64::min(
+            self.max_width.map_or(1.0, |max| max as f64 / width as f64),
+            self.max_height
+                .map_or(1.0, |max| max as f64 / height as f64),
+        );
+
+        (fit.min(1.0) * self.pixel_ratio()) as f32
+    }
+
+    /// Encodes a tightly packed RGBA frame in the negotiated format.
+    /// Returns the encoded bytes and the size of the encoded image.
+    /// Frames rendered at `render_scale` are kept at their size, while larger frames,
+    /// such as ones rendered before the client connected, are scaled down to match.
+    pub fn encode(
+        &self,
+        data: Vec<u8>,
+        width: u32,
+        height: u32,
+    ) -> Result<(Vec<u8>, u32, u32), String> {
+        let pixel_ratio = self.pixel_ratio();
+        let scale = f64::min(
+            self.max_width
+                .map_or(1.0, |max| max as f64 * pixel_ratio / width as f64),
+            self.max_height
+                .map_or(1.0, |max| max as f64 * pixel_ratio / height as f64),
+        );
+        // rendered sizes are rounded, so frames rendered at `render_scale` can be a pixel over
+        let scaled_size = (
+            ((width as f64 * scale).round() as u32).max(1),
+            ((height as f64 * scale).round() as u32).max(1),
+        );
+        let downscale = scale < 1.0 && scaled_size != (width, height);
+
+        if !downscale && matches!(self.format, FrameFormat::Rgba) {
+            return Ok((data, width, height));
+        }
+
+        let mut image = ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, data)
+            .ok_or("frame data doesn't match its size")?;
+
+        if downscale {
+            let (scaled_width, scaled_height) = scaled_size;
+            image =
+                image::imageops::resize(&image, scaled_width, scaled_height, FilterType::Triangle);
+        }
+
+        let (width, height) = image.dimensions();
+
+        match self.format {
+            FrameFormat::Rgba => Ok((image.into_raw(), width, height)),
+            FrameFormat::Bgra => {
+                let mut data = image.into_raw();
+                for pixel in data.chunks_exact_mut(4) {
+                    pixel.swap(0, 2);
+                }
+                Ok((data, width, height))
+            }
+            FrameFormat::Yuv420 => Ok((rgba_to_i420(&image), width, height)),
+            FrameFormat::Jpeg => {
+                let rgb = image::DynamicImage::ImageRgba8(image).into_rgb8();
+                let mut jpeg = Cursor::new(vec![]);
+
+                JpegEncoder::new_with_quality(
+                    &mut jpeg,
+                    self.quality.unwrap_or(DEFAULT_JPEG_QUALITY),
+                )
+                .encode_image(&rgb)
+                .map_err(|e| format!("failed to encode jpeg: {e}"))?;
+
+                Ok((jpeg.into_inner(), width, height))
+            }
+        }
+    }
+
+    fn pixel_ratio(&self) -> f64 {
+        self.device_pixel_ratio
+            .map_or(1.0, |ratio| ratio.min(MAX_DEVICE_PIXEL_RATIO) as f64)
+    }
+
+    /// Splits an encoded frame into the messages it's sent as.
+    /// Without a maximum message size the frame is sent as is.
+    pub fn chunk(&self, frame: Vec<u8>) -> Vec<Vec<u8>> {
+        let Some(max_message_size) = self.max_message_size else {
+            return vec![frame];
+        };
+
+        let chunk_size = max_message_size - CHUNK_HEADER_SIZE;
+        let count = frame.len().div_ceil(chunk_size).max(1) as u32;
+
+        (0..count)
+            .map(|index| {
+                let start = index as usize * chunk_size;
+                let end = (start + chunk_size).min(frame.len());
+
+                let mut message = Vec::with_capacity(CHUNK_HEADER_SIZE + end - start);
+                message.extend_from_slice(&index.to_le_bytes());
+                message.extend_from_slice(&count.to_le_bytes());
+                message.extend_from_slice(&frame[start..end]);
+                message
+            })
+            .collect()
+    }
+}
+
+fn rgba_to_i420(image: &RgbaImage) -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+    let luma_size = (width * height) as usize;
+    let chroma_size = (chroma_width * chroma_height) as usize;
+
+    let mut data = vec![0; luma_size + chroma_size * 2];
+    let (y_plane, chroma) = data.split_at_mut(luma_size);
+    let (u_plane, v_plane) = chroma.split_at_mut(chroma_size);
+
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let [r, g, b, _] = pixel.0.map(|c| c as f32);
+        y_plane[(y * width + x) as usize] = (0.299 * r + 0.587 * g + 0.114 * b).round() as u8;
+    }
+
+    // each chroma sample averages the (up to) 2x2 block of pixels it covers
+    for cy in 0..chroma_height {
+        for cx in 0..chroma_width {
+            let (mut r, mut g, mut b, mut count) = (0.0, 0.0, 0.0, 0.0);
+            for y in cy * 2..(cy * 2 + 2).min(height) {
+                for x in cx * 2..(cx * 2 + 2).min(width) {
+                    let [pr, pg, pb, _] = image.get_pixel(x, y).0;
+                    r += pr as f32;
+                    g += pg as f32;
+                    b += pb as f32;
+                    count += 1.0;
+                }
+            }
+            let (r, g, b) = (r / count, g / count, b / count);
+
+            let index = (cy * chroma_width + cx) as usize;
+            u_plane[index] = (128.0 - 0.168736 * r - 0.331264 * g + 0.5 * b)
+                .round()
+                .clamp(0.0, 255.0) as u8;
+            v_plane[index] = (128.0 + 0.5 * r - 0.418688 * g - 0.081312 * b)
+                .round()
+                .clamp(0.0, 255.0) as u8;
+        }
+    }
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handshake(json: &str) -> FrameHandshake {
+        FrameHandshake::parse(json).unwrap()
+    }
+
+    #[test]
+    fn chunk_sends_frames_whole_without_a_maximum() {
+        let frame = vec![7; 100];
+
+        assert_eq!(handshake("{}").chunk(frame.clone()), vec![frame]);
+    }
+
+    #[test]
+    fn chunk_splits_frames_with_headers() {
+        let frame = (0..10).collect::<Vec<u8>>();
+        let chunks = handshake(r#"{ "maxMessageSize": 12 }"#).chunk(frame);
+
+        assert_eq!(
+            chunks,
+            vec![
+                vec![0, 0, 0, 0, 3, 0, 0, 0, 0, 1, 2, 3],
+                vec![1, 0, 0, 0, 3, 0, 0, 0, 4, 5, 6, 7],
+                vec![2, 0, 0, 0, 3, 0, 0, 0, 8, 9],
+            ]
+        );
+    }
+
+    #[test]
+    fn chunk_rejoins_to_the_frame() {
+        let frame = (0..=255).cycle().take(1000).collect::<Vec<u8>>();
+        let chunks = handshake(r#"{ "maxMessageSize": 64 }"#).chunk(frame.clone());
+
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 64));
+        let rejoined = chunks
+            .iter()
+            .flat_map(|chunk| chunk[CHUNK_HEADER_SIZE..].iter().copied())
+            .collect::<Vec<_>>();
+        assert_eq!(rejoined, frame);
+    }
+
+    #[test]
+    fn chunk_sends_an_empty_frame_as_one_header() {
+        let chunks = handshake(r#"{ "maxMessageSize": 64 }"#).chunk(vec![]);
+
+        assert_eq!(chunks, vec![vec![0, 0, 0, 0, 1, 0, 0, 0]]);
+    }
+
+    #[test]
+    fn rejects_messages_too_small_for_a_chunk_header() {
+        assert!(FrameHandshake::parse(r#"{ "maxMessageSize": 8 }"#).is_err());
+        assert!(FrameHandshake::parse(r#"{ "maxMessageSize": 9 }"#).is_ok());
+    }
+
+    #[test]
+    fn encode_swaps_red_and_blue_for_bgra() {
+        let frame = vec![1, 2, 3, 4, 5, 6, 7, 8];
+
+        let (data, width, height) = handshake(r#"{ "format": "bgra" }"#)
+            .encode(frame, 2, 1)
+            .unwrap();
+
+        assert_eq!(data, vec![3, 2, 1, 4, 7, 6, 5, 8]);
+        assert_eq!((width, height), (2, 1));
+    }
+
+    #[test]
+    fn encode_swaps_bgra_after_scaling_down() {
+        let frame = [10, 20, 30, 255].repeat(4 * 4);
+
+        let (data, width, height) = handshake(r#"{ "format": "bgra", "maxWidth": 2 }"#)
+            .encode(frame, 4, 4)
+            .unwrap();
+
+        assert_eq!((width, height), (2, 2));
+        assert_eq!(data, [30, 20, 10, 255].repeat(2 * 2));
+    }
+
+    #[test]
+    fn encode_yuv420_rounds_chroma_planes_up() {
+        let frame = [255; 4].repeat(3 * 3);
+
+        let (data, width, height) = handshake(r#"{ "format": "yuv420" }"#)
+            .encode(frame, 3, 3)
+            .unwrap();
+
+        assert_eq!((width, height), (3, 3));
+        assert_eq!(data.len(), 9 + 4 + 4);
+        assert!(data[..9].iter().all(|&y| y == 255));
+        assert!(data[9..].iter().all(|&c| c == 128));
+    }
+}
@@ -0,0 +1,85 @@
+use std::ops::Range;
+
+use cap_project::ProjectConfiguration;
+use cap_rendering::{produce_frame, Background, CardFrames, ProjectUniforms, SeekMode};
+use futures::Stream;
+
+use crate::editor_instance::{with_visualizer_audio, EditorInstance, RenderedFrame, FPS};
+
+#[derive(Debug, thiserror::Error)]
+pub enum RenderError {
+    #[error("Frame {0} is outside the timeline or couldn't be decoded")]
+    Decode(u32),
+    #[error("Failed to render frame {frame}: {reason}")]
+    Render { frame: u32, reason: String },
+}
+
+impl EditorInstance {
+    /// Renders each frame of `range` (timeline frame numbers) with `config` as the stream is
+    /// polled, so rendering never runs ahead of the consumer. Frames are rendered at the
+    /// output size without preview overlays, like exports. The stream ends after an error.
+    pub fn frames(
+        &self,
+        range: Range<u32>,
+        config: ProjectConfiguration,
+    ) -> impl Stream<Item = Result<RenderedFrame, RenderError>> + '_ {
+        let uniforms = ProjectUniforms::new(&self.render_constants, &config);
+        let cards = CardFrames::load(&config, self.render_constants.options.screen_size);
+
+        futures::stream::unfold(
+            (range, config, uniforms, cards),
+            move |(mut range, config, uniforms, cards)| async move {
+                let frame_number = range.next()?;
+
+                let rendered = self
+                    .render_stream_frame(frame_number, &config, &uniforms, &cards)
+                    .await;
+                if rendered.is_err() {
+                    range = range.end..range.end;
+                }
+
+                Some((rendered, (range, config, uniforms, cards)))
+            },
+        )
+    }
+
+    async fn render_stream_frame(
+        &self,
+        frame_number: u32,
+        config: &ProjectConfiguration,
+        uniforms: &ProjectUniforms,
+        cards: &CardFrames,
+    ) -> Result<RenderedFrame, RenderError> {
+        let (screen_frame, camera_frames) = self
+            .get_timeline_frames(config, frame_number, cards, SeekMode::Exact)
+            .await
+            .ok_or(RenderError::Decode(frame_number))?;
+
+        let data = produce_frame(
+            &self.render_constants,
+            &screen_frame,
+            &camera_frames,
+            Background::from(config.background.source.clone()),
+            &with_visualizer_audio(
+                uniforms
+                    .clone()
+                    .at_time(frame_number as f64 / FPS as f64, FPS),
+                config,
+                &self.audio,
+                frame_number,
+                self.recordings.duration(),
+            ),
+        )
+        .await
+        .map_err(|e| RenderError::Render {
+            frame: frame_number,
+            reason: e.to_string(),
+        })?;
+
+        Ok(RenderedFrame {
+            data,
+            width: uniforms.output_size.0,
+            height: uniforms.output_size.1,
+        })
+    }
+}
@@ -0,0 +1,109 @@
+use std::{ops::Range, path::PathBuf};
+
+use cap_ffmpeg::{FFmpeg, FFmpegRawVideoInput};
+use cap_rendering::{produce_frame, Background, CardFrames, ProjectUniforms, SeekMode};
+
+use crate::editor_instance::{with_visualizer_audio, EditorInstance, FPS};
+
+const MAX_GIF_WIDTH: u32 = 1280;
+const MAX_GIF_FRAMES: u32 = 300;
+
+impl EditorInstance {
+    /// Renders `range` (timeline frame numbers) to an animated GIF at `fps`, scaled to
+    /// at most `max_width` pixels wide. The frame rate is capped at the editor's frame
+    /// rate, and both the width and number of frames are capped to keep files shareable.
+    pub async fn export_gif(
+        &self,
+        range: Range<u32>,
+        fps: u32,
+        max_width: u32,
+        out: PathBuf,
+    ) -> Result<(), String> {
+        if range.is_empty() {
+            return Err("Cannot export an empty range to GIF".to_string());
+        }
+
+        let project = self.get_project();
+        let uniforms = ProjectUniforms::new(&self.render_constants, &project);
+        let background = Background::from(project.background.source.clone());
+        let cards = CardFrames::load(&project, self.render_constants.options.screen_size);
+        let (width, height) = uniforms.output_size;
+
+        let fps = fps.clamp(1, FPS);
+        let gif_width = max_width.clamp(2, MAX_GIF_WIDTH.min(width)) & !1;
+
+        let frame_count =
+            ((range.len() as u64 * fps as u64).div_ceil(FPS as u64) as u32).min(MAX_GIF_FRAMES);
+
+        if let Some(parent) = out.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create output directory: {e}"))?;
+        }
+
+        let mut ffmpeg = FFmpeg::new();
+
+        ffmpeg.add_input(FFmpegRawVideoInput {
+            width,
+            height,
+            fps,
+            pix_fmt: "rgba",
+            input: "-".into(),
+        });
+
+        ffmpeg
+            .command
+            .args([
+                "-vf",
+                &format!(
+                    "scale={gif_width}:-2:flags=lanczos,split[a][b];[a]palettegen[p];[b][p]paletteuse"
+                ),
+            ])
+            .args(["-loop", "0", "-f", "gif"])
+            .arg("-y")
+            .arg(&out);
+
+        let mut process = ffmpeg.start();
+
+        for i in 0..frame_count {
+            let frame_number = range.start + i * FPS / fps;
+
+            let Some((screen_frame, camera_frames)) = self
+                .get_timeline_frames(&project, frame_number, &cards, SeekMode::Exact)
+                .await
+            else {
+                break;
+            };
+
+            let frame = produce_frame(
+                &self.render_constants,
+                &screen_frame,
+                &camera_frames,
+                background,
+                &with_visualizer_audio(
+                    uniforms
+                        .clone()
+                        .at_time(frame_number as f64 / FPS as f64, FPS),
+                    &project,
+                    &self.audio,
+                    frame_number,
+                    self.recordings.duration(),
+                ),
+            )
+            .await?;
+
+            process
+                .write(&frame)
+                .map_err(|e| format!("Failed to write GIF frame: {e}"))?;
+        }
+
+        let status = process
+            .finish()
+            .map_err(|e| format!("Failed to finish GIF export: {e}"))?;
+
+        if !status.success() {
+            return Err(format!("FFmpeg exited with {status} while exporting GIF"));
+        }
+
+        Ok(())
+    }
+}
@@ -1,9 +1,40 @@
 mod audio;
+mod compare;
+mod content_bounds;
 mod editor;
 mod editor_instance;
+mod frame_format;
+mod frame_stream;
+mod gif;
+mod logging;
+mod overlay;
+mod panic_guard;
 mod playback;
+mod preview_stats;
 mod project_recordings;
+mod reframe;
+mod subtitles;
+mod sync_check;
+mod thumbnail;
 
-pub use audio::AudioData;
-pub use editor_instance::{EditorInstance, EditorState, FRAMES_WS_PATH};
+pub use audio::{
+    fade_gain, track_gain, AudioData, AudioReplaceMode, AudioResampling, ResampleQuality,
+    SilentRange, AUDIO_TRACKS, MICROPHONE_TRACK,
+};
+pub use compare::CompareWipe;
+pub use editor_instance::{
+    DecoderReadiness, DecodersReadiness, EditorInstance, EditorInstanceError,
+    EditorInstanceOptions, EditorState, EditorStateSnapshot, MemoryUsage, RenderedFrame,
+    FRAMES_WS_PATH,
+};
+pub use frame_format::{
+    FrameControl, FrameFormat, FrameHandshake, CHUNK_HEADER_SIZE, INVALID_HANDSHAKE_CLOSE_CODE,
+};
+pub use frame_stream::RenderError;
+pub use logging::{init_logging, LogFile, LogGuard, LogLevel};
+pub use overlay::{OverlayConfig, SafeZone};
+pub use playback::{AudioLevel, PlaybackStartError};
+pub use preview_stats::PreviewStats;
 pub use project_recordings::ProjectRecordings;
+pub use subtitles::{format_subtitles, SubtitleFormat};
+pub use sync_check::SyncMeasurement;
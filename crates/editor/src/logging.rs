@@ -0,0 +1,96 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{filter::Targets, fmt, prelude::*};
+
+/// Log files kept before the oldest is deleted. A new file is started each day.
+const MAX_LOG_FILES: usize = 7;
+const LOG_FILE_PREFIX: &str = "editor";
+/// Crates whose events are logged along with the editor's, as it renders and exports with them.
+const LOG_TARGETS: [&str; 3] = ["cap_editor", "cap_rendering", "cap_project"];
+
+/// Keeps file logging running while held.
+pub type LogGuard = tracing_appender::non_blocking::WorkerGuard;
+
+/// The least severe events written to the log file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for tracing::Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => Self::ERROR,
+            LogLevel::Warn => Self::WARN,
+            LogLevel::Info => Self::INFO,
+            LogLevel::Debug => Self::DEBUG,
+            LogLevel::Trace => Self::TRACE,
+        }
+    }
+}
+
+/// Where to write the editor's log events, and how verbosely.
+#[derive(Debug, Clone)]
+pub struct LogFile {
+    pub directory: PathBuf,
+    pub level: LogLevel,
+}
+
+/// Prints the editor's log events at info level and above to stdout and, with `log_file`,
+/// also writes them to daily rotating files so they can be attached to bug reports.
+/// Events from `app_target`, the crate of the app the editor is embedded in, are included.
+/// File events are written on a background thread until the returned guard is dropped,
+/// so it should be held for as long as the app runs.
+/// Fails if the log directory can't be created or a global subscriber is already set.
+pub fn init_logging(
+    log_file: Option<LogFile>,
+    app_target: &'static str,
+) -> Result<Option<LogGuard>, String> {
+    // only Cap's own events, not those of every dependency
+    let targets = |level: LogLevel| {
+        Targets::new().with_targets(
+            LOG_TARGETS
+                .into_iter()
+                .chain([app_target])
+                .map(|target| (target, tracing::Level::from(level))),
+        )
+    };
+
+    let (file_layer, guard) = match log_file {
+        Some(LogFile { directory, level }) => {
+            let appender = RollingFileAppender::builder()
+                .rotation(Rotation::DAILY)
+                .filename_prefix(LOG_FILE_PREFIX)
+                .filename_suffix("log")
+                .max_log_files(MAX_LOG_FILES)
+                .build(&directory)
+                .map_err(|e| {
+                    format!("Failed to create log file in {}: {e}", directory.display())
+                })?;
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+
+            let layer = fmt::layer()
+                .with_writer(writer)
+                .with_ansi(false)
+                .with_filter(targets(level));
+
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(fmt::layer().with_filter(targets(LogLevel::Info)))
+        .with(file_layer)
+        .try_init()
+        .map_err(|e| format!("Failed to set up logging: {e}"))?;
+
+    Ok(guard)
+}
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+
+/// Composition guides drawn over preview frames only. Snapshots and exports never include them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverlayConfig {
+    pub safe_zones: Vec<SafeZone>,
+    pub center_cross: bool,
+}
+
+/// A rectangle centred in the frame, sized as a percentage of the frame's width and height.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SafeZone {
+    pub width: f32,
+    pub height: f32,
+    pub color: [u8; 4],
+}
+
+impl SafeZone {
+    pub const ACTION_SAFE: Self = Self {
+        width: 93.0,
+        height: 93.0,
+        color: [255, 255, 255, 160],
+    };
+
+    pub const TITLE_SAFE: Self = Self {
+        width: 90.0,
+        height: 90.0,
+        color: [255, 220, 0, 160],
+    };
+}
+
+const LINE_WIDTH: u32 = 2;
+const CROSS_SIZE: u32 = 24;
+const CROSS_COLOR: [u8; 4] = [255, 255, 255, 200];
+
+impl OverlayConfig {
+    pub fn is_empty(&self) -> bool {
+        self.safe_zones.is_empty() && !self.center_cross
+    }
+
+    /// Draws the overlay onto a tightly packed RGBA frame.
+    pub fn draw(&self, frame: &mut [u8], width: u32, height: u32) {
+        let mut canvas = Canvas {
+            frame,
+            width,
+            height,
+        };
+
+        for zone in &self.safe_zones {
+            let zone_width = (width as f32 * zone.width.clamp(0.0, 100.0) / 100.0) as u32;
+            let zone_height = (height as f32 * zone.height.clamp(0.0, 100.0) / 100.0) as u32;
+            let x = (width - zone_width) / 2;
+            let y = (height - zone_height) / 2;
+
+            canvas.fill(x, y, zone_width, LINE_WIDTH, zone.color);
+            canvas.fill(
+                x,
+                (y + zone_height).saturating_sub(LINE_WIDTH),
+                zone_width,
+                LINE_WIDTH,
+                zone.color,
+            );
+            canvas.fill(x, y, LINE_WIDTH, zone_height, zone.color);
+            canvas.fill(
+                (x + zone_width).saturating_sub(LINE_WIDTH),
+                y,
+                LINE_WIDTH,
+                zone_height,
+                zone.color,
+            );
+        }
+
+        if self.center_cross {
+            let (cx, cy) = (width / 2, height / 2);
+
+            canvas.fill(
+                cx.saturating_sub(CROSS_SIZE / 2),
+                cy.saturating_sub(LINE_WIDTH / 2),
+                CROSS_SIZE,
+                LINE_WIDTH,
+                CROSS_COLOR,
+            );
+            canvas.fill(
+                cx.saturating_sub(LINE_WIDTH / 2),
+                cy.saturating_sub(CROSS_SIZE / 2),
+                LINE_WIDTH,
+                CROSS_SIZE,
+                CROSS_COLOR,
+            );
+        }
+    }
+}
+
+struct Canvas<'a> {
+    frame: &'a mut [u8],
+    width: u32,
+    height: u32,
+}
+
+impl Canvas<'_> {
+    /// Alpha-blends `color` over the given rectangle, clipped to the frame.
+    fn fill(&mut self, x: u32, y: u32, width: u32, height: u32, color: [u8; 4]) {
+        let alpha = color[3] as u32;
+        let x_end = (x + width).min(self.width);
+        let y_end = (y + height).min(self.height);
+
+        for row in y..y_end {
+            for col in x..x_end {
+                let i = ((row * self.width + col) * 4) as usize;
+                let Some(pixel) = self.frame.get_mut(i..i + 3) else {
+                    return;
+                };
+
+                for (dst, src) in pixel.iter_mut().zip(color) {
+                    *dst = ((src as u32 * alpha + *dst as u32 * (255 - alpha)) / 255) as u8;
+                }
+            }
+        }
+    }
+}
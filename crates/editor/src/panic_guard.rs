@@ -0,0 +1,20 @@
+use std::{any::Any, future::Future, panic::AssertUnwindSafe};
+
+use futures::FutureExt;
+
+/// Runs `future`, returning its panic message instead of unwinding if it panics,
+/// so a long-running task can report the failure and carry on.
+pub(crate) async fn catch_panic<T>(future: impl Future<Output = T>) -> Result<T, String> {
+    AssertUnwindSafe(future)
+        .catch_unwind()
+        .await
+        .map_err(|payload| panic_message(&*payload))
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
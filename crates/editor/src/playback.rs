@@ -1,112 +1,260 @@
 use std::{sync::Arc, sync::Mutex as StdMutex, time::Duration};
 
-use cap_project::ProjectConfiguration;
-use cap_rendering::{ProjectUniforms, RecordingDecoders, RenderVideoConstants};
+use cap_project::{ProjectConfiguration, TimelinePosition};
+use cap_rendering::{
+    CardFrames, PendingFrames, ProjectUniforms, RecordingDecoders, RenderVideoConstants, SeekMode,
+};
 use cpal::{
     traits::{DeviceTrait, HostTrait, StreamTrait},
     SampleFormat, SizedSample,
 };
+use serde::Serialize;
+use specta::Type;
 use tokio::{sync::watch, time::Instant};
 
-use crate::{audio::AudioData, editor, project_recordings::ProjectRecordings};
+use crate::{
+    audio::{
+        fade_gain, track_gain, AudioData, ProcessedAudioCache, ResampleQuality, MICROPHONE_TRACK,
+    },
+    editor,
+    editor_instance::{get_recording_frame, with_visualizer_audio},
+    project_recordings::ProjectRecordings,
+};
 
 pub struct Playback {
     pub audio: Arc<StdMutex<Option<AudioData>>>,
+    pub processed_audio: Arc<ProcessedAudioCache>,
     pub renderer: Arc<editor::RendererHandle>,
     pub render_constants: Arc<RenderVideoConstants>,
     pub decoders: RecordingDecoders,
     pub start_frame_number: u32,
     pub project: watch::Receiver<ProjectConfiguration>,
     pub recordings: ProjectRecordings,
+    pub audio_enabled: watch::Receiver<bool>,
+    pub resample_quality: ResampleQuality,
 }
 
 const FPS: u32 = 30;
 
+/// Why playback didn't begin, shown to the user by the `start_playback` command.
+#[derive(Debug, thiserror::Error)]
+pub enum PlaybackStartError {
+    #[error("Editor is busy, another playback request is in progress")]
+    Busy,
+    #[error("Failed to decode frame {0}")]
+    Decoder(u32),
+    #[error("No audio output device available")]
+    NoAudioDevice,
+    #[error("Failed to open audio output: {0}")]
+    AudioOutput(String),
+}
+
 #[derive(Clone, Copy)]
 pub enum PlaybackEvent {
     Start,
     Frame(u32),
+    /// Level of the audio played since the previous one, after volume and mixing.
+    AudioLevel(AudioLevel),
     Stop,
 }
 
+/// Peak and RMS amplitude of a stretch of audio, from 0 to 1 for unclipped audio.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Type)]
+pub struct AudioLevel {
+    pub peak: f32,
+    pub rms: f32,
+}
+
 #[derive(Clone)]
 pub struct PlaybackHandle {
     stop_tx: watch::Sender<bool>,
     event_rx: watch::Receiver<PlaybackEvent>,
+    // kept apart from `event_rx` so levels can't overwrite frame events before they're seen
+    level_rx: watch::Receiver<AudioLevel>,
+    // set by `stop`, but not when playback reaches the end by itself
+    cancel_tx: watch::Sender<bool>,
+    // hold one side of playback in place while the other carries on, see `hold_audio`
+    audio_held: watch::Sender<bool>,
+    video_held: watch::Sender<bool>,
 }
 
 impl Playback {
-    pub async fn start(self) -> PlaybackHandle {
+    /// Starts playback from `start_frame_number`. Fails without playing anything if the first
+    /// frames can't be decoded or the audio output can't be opened.
+    pub async fn start(self) -> Result<PlaybackHandle, PlaybackStartError> {
         let (stop_tx, mut stop_rx) = watch::channel(false);
         stop_rx.borrow_and_update();
 
+        // make sure the decoders can actually produce frames before committing to playback
+        let first_frames = self.decoders.get_frames(self.start_frame_number).await;
+        require_frames(first_frames, self.start_frame_number)?;
+
+        let duration = self
+            .project
+            .borrow()
+            .timeline()
+            .map(|t| t.duration())
+            .unwrap_or(f64::MAX);
+        let recording_duration = self.recordings.duration();
+        let total_duration = self.project.borrow().total_duration(recording_duration);
+        let cards = CardFrames::load(
+            &self.project.borrow(),
+            self.render_constants.options.screen_size,
+        );
+
+        let (level_tx, mut level_rx) = watch::channel(AudioLevel::default());
+        level_rx.borrow_and_update();
+
+        let (audio_held, audio_held_rx) = watch::channel(false);
+        let (video_held, mut video_held_rx) = watch::channel(false);
+
+        let audio = self.audio.lock().unwrap().clone();
+        if let Some(audio_data) = audio {
+            AudioPlayback {
+                audio: audio_data,
+                processed_audio: self.processed_audio.clone(),
+                stop_rx: stop_rx.clone(),
+                start_frame_number: self.start_frame_number,
+                duration,
+                project: self.project.clone(),
+                enabled: self.audio_enabled.clone(),
+                held: audio_held_rx,
+                level_tx,
+                resample_quality: self.resample_quality,
+            }
+            .spawn()
+            .await?;
+        }
+
         let (event_tx, mut event_rx) = watch::channel(PlaybackEvent::Start);
         event_rx.borrow_and_update();
 
         let handle = PlaybackHandle {
             stop_tx: stop_tx.clone(),
             event_rx,
+            level_rx,
+            cancel_tx: watch::channel(false).0,
+            audio_held,
+            video_held,
         };
 
         tokio::spawn(async move {
-            let start = Instant::now();
+            let mut start = Instant::now();
 
             let mut frame_number = self.start_frame_number + 1;
+            // the next frame, requested before rendering this one so it decodes meanwhile
+            let mut pending = None::<PendingFrames>;
+
+            'playback: loop {
+                if *video_held_rx.borrow_and_update() {
+                    let held_at = Instant::now();
+
+                    while *video_held_rx.borrow_and_update() {
+                        tokio::select! {
+                            _ = stop_rx.changed() => break 'playback,
+                            changed = video_held_rx.changed() => if changed.is_err() {
+                                break;
+                            },
+                        }
+                    }
 
-            let duration = self
-                .project
-                .borrow()
-                .timeline()
-                .map(|t| t.duration())
-                .unwrap_or(f64::MAX);
-
-            // Lock the mutex and check if audio data is available
-            if let Some(audio_data) = self.audio.lock().unwrap().as_ref() {
-                AudioPlayback {
-                    audio: audio_data.clone(),
-                    stop_rx: stop_rx.clone(),
-                    start_frame_number: self.start_frame_number,
-                    duration,
-                    project: self.project.clone(),
+                    // carry on from the held frame rather than skipping ahead to catch up
+                    start += held_at.elapsed();
+                }
+
+                // when rendering falls more than a frame behind, drop the frames whose time
+                // has passed rather than showing each of them late
+                let now = Instant::now();
+                if now >= frame_due(start, self.start_frame_number, frame_number + 1) {
+                    let elapsed_frames = (now - start).as_secs_f64() * FPS as f64;
+                    frame_number = self.start_frame_number + elapsed_frames as u32;
                 }
-                .spawn();
-            };
 
-            loop {
-                if frame_number as f64 > FPS as f64 * duration {
+                if frame_number as f64 > FPS as f64 * total_duration {
                     break;
                 };
 
                 let project = self.project.borrow().clone();
 
-                let time = if let Some(timeline) = project.timeline() {
-                    match timeline.get_recording_time(frame_number as f64 / FPS as f64) {
-                        Some(time) => time,
+                let Some(position) =
+                    project.timeline_position(frame_number as f64 / FPS as f64, recording_duration)
+                else {
+                    break;
+                };
+
+                let (card, recording_frame) = match position {
+                    TimelinePosition::Recording(time) => {
+                        let time = match project.timeline() {
+                            Some(timeline) => match timeline.get_recording_time(time) {
+                                Some(time) => time,
+                                None => break,
+                            },
+                            None => time,
+                        };
+
+                        (None, (time * FPS as f64) as u32)
+                    }
+                    card => match cards.get(card) {
+                        Some(frame) => (Some(frame), 0),
                         None => break,
+                    },
+                };
+
+                // the requested frame is discarded if frames were since dropped or the project
+                // edited, though the decoders may still have it cached
+                let requested = card.is_none().then(|| {
+                    pending
+                        .take()
+                        .filter(|pending| pending.frame_number() == recording_frame)
+                        .unwrap_or_else(|| {
+                            self.decoders
+                                .request_frames(recording_frame, SeekMode::Exact)
+                        })
+                });
+
+                let frames = async {
+                    match card {
+                        Some(card) => Some((card, vec![None; self.decoders.cameras().len()])),
+                        None => requested?.wait().await,
                     }
-                } else {
-                    frame_number as f64 / FPS as f64
                 };
 
                 tokio::select! {
                     _ = stop_rx.changed() => {
                        break;
                     },
-                    Some((screen_frame, camera_frame)) = self.decoders.get_frames((time * FPS as f64) as u32) => {
+                    Some((screen_frame, camera_frames)) = frames => {
                         // println!("decoded frame in {:?}", debug.elapsed());
-                        let uniforms = ProjectUniforms::new(&self.render_constants, &project);
+                        pending =
+                            get_recording_frame(&project, frame_number + 1, recording_duration)
+                                .map(|frame| self.decoders.request_frames(frame, SeekMode::Exact));
+
+                        let uniforms = ProjectUniforms::new(&self.render_constants, &project)
+                            .for_preview()
+                            .at_time(frame_number as f64 / FPS as f64, FPS);
+                        let uniforms = with_visualizer_audio(
+                            uniforms,
+                            &project,
+                            &self.audio,
+                            frame_number,
+                            recording_duration,
+                        );
 
                         self
                             .renderer
                             .render_frame(
                                 screen_frame,
-                                camera_frame,
+                                camera_frames,
                                 project.background.source.clone(),
                                 uniforms.clone()
                             )
-                            .await;
+                            .await
+                            // the renderer logs panics, and playback carries on with the next frame
+                            .ok();
 
-                        tokio::time::sleep_until(start + (frame_number - self.start_frame_number) * Duration::from_secs_f32(1.0 / FPS as f32)).await;
+                        tokio::time::sleep_until(
+                            frame_due(start, self.start_frame_number, frame_number)
+                        ).await;
 
                         event_tx.send(PlaybackEvent::Frame(frame_number)).ok();
 
@@ -118,47 +266,91 @@ impl Playback {
                 }
             }
 
-            println!("playback done");
+            tracing::info!("playback done");
             stop_tx.send(true).ok();
 
             event_tx.send(PlaybackEvent::Stop).ok();
         });
 
-        handle
+        Ok(handle)
     }
 }
 
 impl PlaybackHandle {
+    /// Whether the playback is still going, until it's stopped or reaches the end.
+    pub fn is_playing(&self) -> bool {
+        !*self.stop_tx.borrow()
+    }
+
     pub fn stop(&self) {
         self.stop_tx.send(true).ok();
+        self.cancel_tx.send_replace(true);
+    }
+
+    /// Resolves once `stop` has been called on any clone of the handle, so tasks
+    /// listening to the playback's events can exit without waiting for it to wind down.
+    pub fn cancelled(&self) -> impl std::future::Future<Output = ()> + Send + 'static {
+        let mut cancel_rx = self.cancel_tx.subscribe();
+        async move {
+            cancel_rx.wait_for(|cancelled| *cancelled).await.ok();
+        }
+    }
+
+    /// Silences audio and stops it advancing while video carries on, or lets it continue
+    /// from where it was held. Releasing it leaves audio behind video by the time it was
+    /// held for, which is meant for diagnosing and working around sync issues.
+    pub fn hold_audio(&self, held: bool) {
+        self.audio_held.send_replace(held);
     }
 
-    pub async fn receive_event(&mut self) -> watch::Ref<'_, PlaybackEvent> {
-        self.event_rx.changed().await.ok();
-        self.event_rx.borrow_and_update()
+    /// Freezes video on its current frame while audio carries on, or lets it continue from
+    /// that frame. No `PlaybackEvent::Frame` events are sent while it's held.
+    pub fn hold_video(&self, held: bool) {
+        self.video_held.send_replace(held);
+    }
+
+    pub async fn receive_event(&mut self) -> PlaybackEvent {
+        tokio::select! {
+            biased;
+            _ = self.event_rx.changed() => *self.event_rx.borrow_and_update(),
+            // without audio the sender is gone straight away, which disables this branch
+            Ok(()) = self.level_rx.changed() => {
+                PlaybackEvent::AudioLevel(*self.level_rx.borrow_and_update())
+            }
+        }
     }
 }
 
 struct AudioPlayback {
     audio: AudioData,
+    processed_audio: Arc<ProcessedAudioCache>,
     stop_rx: watch::Receiver<bool>,
     start_frame_number: u32,
     duration: f64,
     project: watch::Receiver<ProjectConfiguration>,
+    enabled: watch::Receiver<bool>,
+    held: watch::Receiver<bool>,
+    level_tx: watch::Sender<AudioLevel>,
+    resample_quality: ResampleQuality,
 }
 
 impl AudioPlayback {
-    fn spawn(mut self) {
+    /// Starts audio output on a dedicated thread, returning once the stream is playing.
+    async fn spawn(mut self) -> Result<(), PlaybackStartError> {
         let handle = tokio::runtime::Handle::current();
+        let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
 
         std::thread::spawn(move || {
-            let audio = self.audio;
-
-            let host = cpal::default_host();
-            let device = host.default_output_device().unwrap();
-            let supported_config = device
-                .default_output_config()
-                .expect("Failed to get default output format");
+            let config = self.project.borrow().audio.clone();
+            let audio = self.processed_audio.get(&self.audio, &config);
+
+            let (device, supported_config) = match output_device() {
+                Ok(device) => device,
+                Err(e) => {
+                    ready_tx.send(Err(e)).ok();
+                    return;
+                }
+            };
             let mut config = supported_config.config();
             config.channels = 1;
 
@@ -173,9 +365,33 @@ impl AudioPlayback {
 
             let resample_ratio = audio.sample_rate as f64 / config.sample_rate.0 as f64;
 
-            let next_sample = move || {
+            // a level is reported for every frame's worth of output samples
+            let mut meter = LevelMeter::new((config.sample_rate.0 / FPS) as usize);
+            let level_tx = self.level_tx;
+            let resample_quality = self.resample_quality;
+            let mut next_sample = move || {
+                // held audio doesn't advance, so it resumes from where it was held
+                if *self.held.borrow() {
+                    return Some(0.0);
+                }
+
                 time += time_inc;
-                let time = self.project.borrow().timeline()?.get_recording_time(time)?;
+                // disabled audio still advances so it resumes in sync when re-enabled
+                let output_gain = if *self.enabled.borrow() { 1.0 } else { 0.0 };
+                let project = self.project.borrow();
+                let time = match project.timeline_position(time, duration)? {
+                    TimelinePosition::Recording(time) => time,
+                    // cards are silent
+                    _ => return Some(0.0),
+                };
+                let gain =
+                    fade_gain(&project, time, duration) * track_gain(&project, MICROPHONE_TRACK);
+                let timeline = project.timeline()?;
+                // held frames are silent
+                if timeline.is_held(time) {
+                    return Some(0.0);
+                }
+                let time = timeline.get_recording_time(time)?;
 
                 let index = time / duration * data.len() as f64;
 
@@ -191,59 +407,315 @@ impl AudioPlayback {
                     return None;
                 }
 
-                let frac = index.fract();
-                let current = data[index_int];
-                let next = data[(index_int + 1) % data.len()];
-                Some(current * (1.0 - frac) + next * frac)
+                let sample = resample_quality.sample_at(&data, index, resample_ratio);
+                Some(sample * gain * output_gain)
             };
-
-            let shared_data = (&device, &config, next_sample);
-            let stream = match supported_config.sample_format() {
-                SampleFormat::I8 => create_stream::<i8>(shared_data),
-                SampleFormat::I16 => create_stream::<i16>(shared_data),
-                SampleFormat::I32 => create_stream::<i32>(shared_data),
-                SampleFormat::I64 => create_stream::<i64>(shared_data),
-                SampleFormat::U8 => create_stream::<u8>(shared_data),
-                SampleFormat::U16 => create_stream::<u16>(shared_data),
-                SampleFormat::U32 => create_stream::<u32>(shared_data),
-                SampleFormat::U64 => create_stream::<u64>(shared_data),
-                SampleFormat::F32 => create_stream::<f32>(shared_data),
-                SampleFormat::F64 => create_stream::<f64>(shared_data),
-                _ => unimplemented!(),
+            let next_sample = move || {
+                let sample = next_sample();
+                if let Some(level) = meter.push(sample.unwrap_or(0.0)) {
+                    level_tx.send_replace(level);
+                }
+                sample
             };
 
-            fn create_stream<T: SizedSample + cpal::FromSample<f64> + 'static>(
-                (device, config, mut next_sample): (
-                    &cpal::Device,
-                    &cpal::StreamConfig,
-                    impl FnMut() -> Option<f64> + Send + 'static,
-                ),
-            ) -> cpal::Stream {
-                device
-                    .build_output_stream(
-                        config,
-                        move |buffer: &mut [T], _info| {
-                            for sample in buffer.iter_mut() {
-                                let Some(s) = next_sample() else {
-                                    *sample = T::EQUILIBRIUM;
-                                    continue;
-                                };
-                                let value = cpal::Sample::from_sample::<f64>(s);
-                                *sample = value;
-                            }
-                        },
-                        |_| {},
-                        None,
-                    )
-                    .unwrap()
-            }
+            let stream = play_output_stream(&device, &supported_config, &config, next_sample);
 
-            stream.play().unwrap();
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    ready_tx.send(Err(PlaybackStartError::AudioOutput(e))).ok();
+                    return;
+                }
+            };
+
+            ready_tx.send(Ok(())).ok();
 
             handle.block_on(self.stop_rx.changed()).ok();
 
             stream.pause().ok();
             drop(stream);
         });
+
+        ready_rx.await.map_err(|_| {
+            PlaybackStartError::AudioOutput("audio thread exited unexpectedly".to_string())
+        })?
+    }
+}
+
+/// Accumulates output samples into an `AudioLevel` per window of samples.
+struct LevelMeter {
+    window: usize,
+    count: usize,
+    peak: f64,
+    sum_squares: f64,
+}
+
+impl LevelMeter {
+    fn new(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            count: 0,
+            peak: 0.0,
+            sum_squares: 0.0,
+        }
+    }
+
+    /// Adds a sample, returning the window's level once it's full.
+    fn push(&mut self, sample: f64) -> Option<AudioLevel> {
+        self.peak = self.peak.max(sample.abs());
+        self.sum_squares += sample * sample;
+        self.count += 1;
+
+        if self.count < self.window {
+            return None;
+        }
+
+        let level = AudioLevel {
+            peak: self.peak as f32,
+            rms: (self.sum_squares / self.count as f64).sqrt() as f32,
+        };
+        *self = Self::new(self.window);
+
+        Some(level)
+    }
+}
+
+/// Plays a short burst of mono `samples` at `sample_rate`, resampled to the output device's
+/// rate with `resample_quality`, returning once it has started.
+/// The burst stops early if `cancel` changes.
+pub(crate) async fn play_burst(
+    samples: Vec<f64>,
+    sample_rate: u32,
+    resample_quality: ResampleQuality,
+    mut cancel: watch::Receiver<bool>,
+) -> Result<(), PlaybackStartError> {
+    let handle = tokio::runtime::Handle::current();
+    let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+
+    std::thread::spawn(move || {
+        let (device, supported_config) = match output_device() {
+            Ok(device) => device,
+            Err(e) => {
+                ready_tx.send(Err(e)).ok();
+                return;
+            }
+        };
+        let mut config = supported_config.config();
+        config.channels = 1;
+
+        let burst_duration = Duration::from_secs_f64(samples.len() as f64 / sample_rate as f64);
+        let resample_ratio = sample_rate as f64 / config.sample_rate.0 as f64;
+        let mut index = 0.0;
+
+        let next_sample = move || {
+            let index_int = index as usize;
+            if index_int + 1 >= samples.len() {
+                return None;
+            }
+
+            let sample = resample_quality.sample_at(&samples, index, resample_ratio);
+            index += resample_ratio;
+
+            Some(sample)
+        };
+
+        let stream = match play_output_stream(&device, &supported_config, &config, next_sample) {
+            Ok(stream) => stream,
+            Err(e) => {
+                ready_tx.send(Err(PlaybackStartError::AudioOutput(e))).ok();
+                return;
+            }
+        };
+
+        ready_tx.send(Ok(())).ok();
+
+        handle.block_on(async {
+            tokio::select! {
+                _ = cancel.changed() => {}
+                _ = tokio::time::sleep(burst_duration) => {}
+            }
+        });
+
+        stream.pause().ok();
+        drop(stream);
+    });
+
+    ready_rx.await.map_err(|_| {
+        PlaybackStartError::AudioOutput("audio thread exited unexpectedly".to_string())
+    })?
+}
+
+/// When `frame_number` is due in playback that started from `start_frame_number` at `start`.
+/// Measured from the start instead of adding up frame durations, so rounding errors and late
+/// wake-ups don't accumulate into drift over long playback.
+fn frame_due(start: Instant, start_frame_number: u32, frame_number: u32) -> Instant {
+    start + Duration::from_secs_f64((frame_number - start_frame_number) as f64 / FPS as f64)
+}
+
+/// Fails with `PlaybackStartError::Decoder` when the decoders couldn't produce the frames
+/// at `frame_number`.
+fn require_frames<T>(frames: Option<T>, frame_number: u32) -> Result<T, PlaybackStartError> {
+    frames.ok_or(PlaybackStartError::Decoder(frame_number))
+}
+
+fn output_device() -> Result<(cpal::Device, cpal::SupportedStreamConfig), PlaybackStartError> {
+    let host = cpal::default_host();
+    with_output_config(host.default_output_device(), |device| {
+        device.default_output_config()
+    })
+}
+
+/// Pairs the output `device` with its `config`, telling a missing device apart from one
+/// whose config can't be read.
+fn with_output_config<D, C, E: ToString>(
+    device: Option<D>,
+    config: impl FnOnce(&D) -> Result<C, E>,
+) -> Result<(D, C), PlaybackStartError> {
+    let device = device.ok_or(PlaybackStartError::NoAudioDevice)?;
+    let config = config(&device).map_err(|e| PlaybackStartError::AudioOutput(e.to_string()))?;
+
+    Ok((device, config))
+}
+
+/// Builds an output stream in the device's sample format that pulls from `next_sample`,
+/// outputting silence once it returns `None`, and starts playing it.
+fn play_output_stream(
+    device: &cpal::Device,
+    supported_config: &cpal::SupportedStreamConfig,
+    config: &cpal::StreamConfig,
+    next_sample: impl FnMut() -> Option<f64> + Send + 'static,
+) -> Result<cpal::Stream, String> {
+    let shared_data = (device, config, next_sample);
+    let stream = match supported_config.sample_format() {
+        SampleFormat::I8 => create_stream::<i8>(shared_data),
+        SampleFormat::I16 => create_stream::<i16>(shared_data),
+        SampleFormat::I32 => create_stream::<i32>(shared_data),
+        SampleFormat::I64 => create_stream::<i64>(shared_data),
+        SampleFormat::U8 => create_stream::<u8>(shared_data),
+        SampleFormat::U16 => create_stream::<u16>(shared_data),
+        SampleFormat::U32 => create_stream::<u32>(shared_data),
+        SampleFormat::U64 => create_stream::<u64>(shared_data),
+        SampleFormat::F32 => create_stream::<f32>(shared_data),
+        SampleFormat::F64 => create_stream::<f64>(shared_data),
+        format => Err(format!("unsupported sample format {format}")),
+    }?;
+
+    stream.play().map_err(|e| e.to_string())?;
+
+    Ok(stream)
+}
+
+fn create_stream<T: SizedSample + cpal::FromSample<f64> + 'static>(
+    (device, config, mut next_sample): (
+        &cpal::Device,
+        &cpal::StreamConfig,
+        impl FnMut() -> Option<f64> + Send + 'static,
+    ),
+) -> Result<cpal::Stream, String> {
+    device
+        .build_output_stream(
+            config,
+            move |buffer: &mut [T], _info| {
+                for sample in buffer.iter_mut() {
+                    let Some(s) = next_sample() else {
+                        *sample = T::EQUILIBRIUM;
+                        continue;
+                    };
+                    let value = cpal::Sample::from_sample::<f64>(s);
+                    *sample = value;
+                }
+            },
+            |_| {},
+            None,
+        )
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_frames_fail_with_decoder_error() {
+        let result = require_frames(None::<()>, 42);
+
+        assert!(matches!(result, Err(PlaybackStartError::Decoder(42))));
+        assert_eq!(result.unwrap_err().to_string(), "Failed to decode frame 42");
+    }
+
+    #[test]
+    fn decoded_frames_are_passed_through() {
+        assert!(matches!(require_frames(Some("frames"), 0), Ok("frames")));
+    }
+
+    #[test]
+    fn missing_output_device_fails_with_no_audio_device() {
+        let result = with_output_config(None::<()>, |_| Ok::<_, String>(()));
+
+        assert!(matches!(result, Err(PlaybackStartError::NoAudioDevice)));
+    }
+
+    #[test]
+    fn unreadable_output_config_fails_with_audio_output_error() {
+        let result = with_output_config(Some(()), |_| Err::<(), _>("device busy"));
+
+        match result {
+            Err(PlaybackStartError::AudioOutput(message)) => assert_eq!(message, "device busy"),
+            _ => panic!("expected an audio output error"),
+        }
+    }
+
+    #[test]
+    fn output_device_is_paired_with_its_config() {
+        let result = with_output_config(Some(2), |device| Ok::<_, String>(device * 24_000));
+
+        assert!(matches!(result, Ok((2, 48_000))));
+    }
+
+    #[test]
+    fn level_meter_reports_once_per_window() {
+        let mut meter = LevelMeter::new(4);
+
+        assert_eq!(meter.push(0.5), None);
+        assert_eq!(meter.push(-0.5), None);
+        assert_eq!(meter.push(0.5), None);
+        assert_eq!(
+            meter.push(-0.5),
+            Some(AudioLevel {
+                peak: 0.5,
+                rms: 0.5
+            })
+        );
+    }
+
+    #[test]
+    fn level_meter_measures_peak_and_rms() {
+        let mut meter = LevelMeter::new(4);
+        let level = [0.0, -1.0, 0.0, 1.0]
+            .into_iter()
+            .find_map(|sample| meter.push(sample))
+            .unwrap();
+
+        assert_eq!(level.peak, 1.0);
+        assert!((level.rms - 0.5f32.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn level_meter_starts_each_window_afresh() {
+        let mut meter = LevelMeter::new(2);
+        meter.push(1.0);
+        meter.push(1.0);
+
+        meter.push(0.1);
+        let level = meter.push(0.0).unwrap();
+
+        assert!((level.peak - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn level_meter_windows_hold_at_least_one_sample() {
+        let mut meter = LevelMeter::new(0);
+
+        assert!(meter.push(0.25).is_some());
     }
 }
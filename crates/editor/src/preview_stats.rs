@@ -0,0 +1,66 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::editor_instance::FPS;
+
+/// Socket sends slower than this can't keep up with playback.
+pub(crate) const SEND_LAG_THRESHOLD: Duration = Duration::from_nanos(1_000_000_000 / FPS as u64);
+
+/// Counts of what happened to preview frames since an `EditorInstance` was created.
+/// Many dropped frames point at slow rendering, many failed, lagged or timed out sends at
+/// the connection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PreviewStats {
+    /// Frames rendered and handed to the frames socket.
+    pub frames_rendered: u64,
+    /// Frames skipped because the renderer was still busy with an earlier one.
+    pub frames_dropped: u64,
+    /// Frames the frames socket failed to send, usually because the client went away.
+    pub sends_failed: u64,
+    /// Frames the frames socket took longer than a frame interval to send.
+    pub sends_lagged: u64,
+    /// Frames dropped because the client stopped reading and the send timed out.
+    pub sends_timed_out: u64,
+}
+
+/// Shared counters behind `PreviewStats`, updated by the renderer and the frames socket.
+#[derive(Default)]
+pub(crate) struct PreviewCounters {
+    frames_rendered: AtomicU64,
+    frames_dropped: AtomicU64,
+    sends_failed: AtomicU64,
+    sends_lagged: AtomicU64,
+    sends_timed_out: AtomicU64,
+}
+
+impl PreviewCounters {
+    pub fn frame_rendered(&self) {
+        self.frames_rendered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn frame_dropped(&self) {
+        self.frames_dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn send_failed(&self) {
+        self.sends_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn send_lagged(&self) {
+        self.sends_lagged.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn send_timed_out(&self) {
+        self.sends_timed_out.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> PreviewStats {
+        PreviewStats {
+            frames_rendered: self.frames_rendered.load(Ordering::Relaxed),
+            frames_dropped: self.frames_dropped.load(Ordering::Relaxed),
+            sends_failed: self.sends_failed.load(Ordering::Relaxed),
+            sends_lagged: self.sends_lagged.load(Ordering::Relaxed),
+            sends_timed_out: self.sends_timed_out.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
 use cap_project::RecordingMeta;
+use cap_rendering::decoder::Rotation;
 use serde::Serialize;
 use specta::Type;
 
@@ -14,23 +15,36 @@ pub struct Video {
 
 impl Video {
     pub fn new(path: &PathBuf) -> Self {
-        let input = ffmpeg::format::input(path).unwrap();
-        let stream = input.streams().best(ffmpeg::media::Type::Video).unwrap();
+        Self::try_new(path).unwrap()
+    }
+
+    /// Like `new`, but fails instead of panicking for files that aren't a decodable video,
+    /// such as ones picked by the user.
+    pub fn try_new(path: &PathBuf) -> Result<Self, String> {
+        let input = ffmpeg::format::input(path)
+            .map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+        let stream = input
+            .streams()
+            .best(ffmpeg::media::Type::Video)
+            .ok_or_else(|| format!("{} has no video stream", path.display()))?;
 
         let video_decoder = ffmpeg::codec::Context::from_parameters(stream.parameters())
-            .unwrap()
-            .decoder()
-            .video()
-            .unwrap();
+            .and_then(|context| context.decoder().video())
+            .map_err(|e| format!("Failed to decode {}: {e}", path.display()))?;
 
-        let frame_rate = video_decoder.frame_rate().unwrap();
+        let frame_rate = video_decoder
+            .frame_rate()
+            .ok_or_else(|| format!("{} has no frame rate", path.display()))?;
+        // decoded frames are rotated upright, so report the size they're shown at
+        let (width, height) = Rotation::from_stream(&stream)
+            .apply_to_size((video_decoder.width(), video_decoder.height()));
 
-        Video {
-            width: video_decoder.width(),
-            height: video_decoder.height(),
-            duration: (input.duration() / 1_000_000) as f64,
+        Ok(Video {
+            width,
+            height,
+            duration: input.duration().max(0) as f64 / 1_000_000.0,
             fps: frame_rate.numerator() as f32 / frame_rate.denominator() as f32,
-        }
+        })
     }
 }
 
@@ -53,27 +67,40 @@ impl Audio {
             .unwrap();
 
         Audio {
-            duration: (input.duration() / 1_000_000) as f64,
+            duration: input.duration().max(0) as f64 / 1_000_000.0,
             sample_rate: video_decoder.rate(),
             channels: video_decoder.channels(),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Type)]
+#[derive(Debug, Clone, Serialize, Type)]
 pub struct ProjectRecordings {
     pub display: Video,
     pub camera: Option<Video>,
+    pub extra_cameras: Vec<Video>,
     pub audio: Option<Audio>,
 }
 
 impl ProjectRecordings {
     pub fn new(meta: &RecordingMeta) -> Self {
-        let display = Video::new(&meta.project_path.join(&meta.display.path));
+        let mut display = Video::new(&meta.project_path.join(&meta.display.path));
+        // extra segments continue the first one, so only their length matters
+        display.duration += meta
+            .display
+            .extra_segments
+            .iter()
+            .map(|path| Video::new(&meta.project_path.join(path)).duration)
+            .sum::<f64>();
         let camera = meta
             .camera
             .as_ref()
             .map(|camera| Video::new(&meta.project_path.join(&camera.path)));
+        let extra_cameras = meta
+            .extra_cameras
+            .iter()
+            .map(|camera| Video::new(&meta.project_path.join(&camera.path)))
+            .collect();
         let audio = meta
             .audio
             .as_ref()
@@ -82,18 +109,24 @@ impl ProjectRecordings {
         ProjectRecordings {
             display,
             camera,
+            extra_cameras,
             audio,
         }
     }
 
+    /// The primary camera followed by any extra cameras, matching `RecordingMeta::cameras`.
+    pub fn cameras(&self) -> impl Iterator<Item = &Video> {
+        self.camera.iter().chain(self.extra_cameras.iter())
+    }
+
     pub fn duration(&self) -> f64 {
         let mut duration_ns = [
             Some(self.display.duration),
-            self.camera.as_ref().map(|s| s.duration),
             self.audio.as_ref().map(|s| s.duration),
         ]
         .into_iter()
         .flatten()
+        .chain(self.cameras().map(|s| s.duration))
         .collect::<Vec<_>>();
         duration_ns.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
         duration_ns[0]
@@ -0,0 +1,102 @@
+use cap_project::{Reframe, ReframeKeyframe};
+
+use crate::editor_instance::{get_recording_frame, EditorInstance, FPS};
+
+// timeline frames between the frames compared for activity
+const REFRAME_SAMPLE_INTERVAL: u32 = 10;
+// how far the window moves towards new activity at each sample, smaller is steadier
+const REFRAME_SMOOTHING: f32 = 0.3;
+// smallest brightness change, between 0 and 1, that counts as activity
+const ACTIVITY_THRESHOLD: f32 = 0.1;
+// fraction of sampled pixels that must change for the window to move
+const MIN_ACTIVITY: f32 = 0.001;
+// rows and columns between sampled pixels
+const PIXEL_STEP: usize = 4;
+
+impl EditorInstance {
+    /// Generates reframe keyframes that follow where the screen changes the most,
+    /// such as the cursor or text being typed, to set as the project's `reframe`.
+    pub async fn generate_reframe(&self) -> Result<Reframe, String> {
+        let project = self.get_project();
+        let recording_duration = self.recordings.duration();
+        let (width, height) = self.render_constants.options.screen_size;
+
+        let total_frames = (project.total_duration(recording_duration) * FPS as f64) as u32;
+
+        let mut previous = None::<Vec<f32>>;
+        let mut center = 0.5;
+        let mut keyframes = vec![];
+
+        for frame_number in (0..total_frames).step_by(REFRAME_SAMPLE_INTERVAL as usize) {
+            // cards aren't part of the recording, so there's nothing to follow
+            let Some(recording_frame) =
+                get_recording_frame(&project, frame_number, recording_duration)
+            else {
+                continue;
+            };
+
+            let (screen_frame, _) = self
+                .decoders
+                .get_frames(self.clamp_frame(recording_frame))
+                .await
+                .ok_or(format!("Failed to decode frame {recording_frame}"))?;
+
+            let luma = sample_luma(&screen_frame, (width, height));
+
+            if let Some(target) = previous
+                .as_ref()
+                .and_then(|previous| activity_center(previous, &luma, width))
+            {
+                center += (target - center) * REFRAME_SMOOTHING;
+            }
+            previous = Some(luma);
+
+            keyframes.push(ReframeKeyframe {
+                time: frame_number as f64 / FPS as f64,
+                x: center,
+            });
+        }
+
+        Ok(Reframe { keyframes })
+    }
+}
+
+/// Brightness between 0 and 1 of every few pixels of a tightly packed RGBA frame, row by row.
+fn sample_luma(frame: &[u8], (width, height): (u32, u32)) -> Vec<f32> {
+    let (width, height) = (width as usize, height as usize);
+    let mut luma = Vec::with_capacity((width / PIXEL_STEP + 1) * (height / PIXEL_STEP + 1));
+
+    for y in (0..height).step_by(PIXEL_STEP) {
+        for x in (0..width).step_by(PIXEL_STEP) {
+            let i = (y * width + x) * 4;
+            luma.push(frame.get(i..i + 3).map_or(0.0, |pixel| {
+                (0.2126 * pixel[0] as f32 + 0.7152 * pixel[1] as f32 + 0.0722 * pixel[2] as f32)
+                    / 255.0
+            }));
+        }
+    }
+
+    luma
+}
+
+/// Horizontal centre of the pixels that changed between two `sample_luma` results of a
+/// frame `width` pixels wide, as a fraction of the width. `None` if too little changed.
+fn activity_center(previous: &[f32], current: &[f32], width: u32) -> Option<f32> {
+    if previous.len() != current.len() || current.is_empty() {
+        return None;
+    }
+
+    let columns = (width as usize).div_ceil(PIXEL_STEP);
+
+    let (sum, count) = previous
+        .iter()
+        .zip(current)
+        .enumerate()
+        .filter(|(_, (a, b))| (*a - *b).abs() > ACTIVITY_THRESHOLD)
+        .fold((0.0, 0usize), |(sum, count), (i, _)| {
+            (sum + (i % columns) as f32 + 0.5, count + 1)
+        });
+
+    (count as f32 >= current.len() as f32 * MIN_ACTIVITY)
+        .then(|| sum / count as f32 / columns as f32)
+}
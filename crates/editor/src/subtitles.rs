@@ -0,0 +1,160 @@
+use std::{fmt::Write, path::PathBuf};
+
+use cap_project::Caption;
+
+use crate::editor_instance::{EditorInstance, FPS};
+
+#[derive(Debug, Clone, Copy)]
+pub enum SubtitleFormat {
+    Srt,
+    WebVtt,
+}
+
+impl EditorInstance {
+    /// Writes the project's captions to `out` as a standalone subtitle file.
+    pub async fn export_subtitles(
+        &self,
+        out: PathBuf,
+        format: SubtitleFormat,
+    ) -> Result<(), String> {
+        let contents = format_subtitles(&self.get_project().captions, format);
+
+        if let Some(parent) = out.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Failed to create output directory: {e}"))?;
+        }
+
+        tokio::fs::write(&out, contents)
+            .await
+            .map_err(|e| format!("Failed to write subtitles to {}: {e}", out.display()))
+    }
+}
+
+/// Captions are sorted by start time, and a caption that overlaps the next one is cut
+/// short when the next one starts, since many players only show one cue at a time.
+pub fn format_subtitles(captions: &[Caption], format: SubtitleFormat) -> String {
+    let mut captions = captions
+        .iter()
+        .filter(|caption| {
+            caption.end_frame > caption.start_frame && !caption.text.trim().is_empty()
+        })
+        .collect::<Vec<_>>();
+    captions.sort_by_key(|caption| caption.start_frame);
+
+    let mut out = String::new();
+
+    if let SubtitleFormat::WebVtt = format {
+        out.push_str("WEBVTT\n\n");
+    }
+
+    let mut cue_number = 0;
+    for (i, caption) in captions.iter().enumerate() {
+        let end_frame = match captions.get(i + 1) {
+            Some(next) => caption.end_frame.min(next.start_frame),
+            None => caption.end_frame,
+        };
+
+        // captions starting on the same frame as the next one would be empty
+        if end_frame <= caption.start_frame {
+            continue;
+        }
+
+        cue_number += 1;
+        if let SubtitleFormat::Srt = format {
+            writeln!(out, "{cue_number}").ok();
+        }
+
+        writeln!(
+            out,
+            "{} --> {}",
+            format_timestamp(caption.start_frame, format),
+            format_timestamp(end_frame, format)
+        )
+        .ok();
+        writeln!(out, "{}\n", caption.text.trim()).ok();
+    }
+
+    out
+}
+
+fn format_timestamp(frame: u32, format: SubtitleFormat) -> String {
+    let total_ms = frame as u64 * 1000 / FPS as u64;
+
+    let ms = total_ms % 1000;
+    let secs = total_ms / 1000 % 60;
+    let mins = total_ms / 60_000 % 60;
+    let hours = total_ms / 3_600_000;
+
+    let separator = match format {
+        SubtitleFormat::Srt => ',',
+        SubtitleFormat::WebVtt => '.',
+    };
+
+    format!("{hours:02}:{mins:02}:{secs:02}{separator}{ms:03}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caption(start_frame: u32, end_frame: u32, text: &str) -> Caption {
+        Caption {
+            start_frame,
+            end_frame,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn formats_srt_cues() {
+        let captions = [
+            caption(0, 45, "Hello"),
+            caption(60, 3 * 3600 * FPS + 15, " world "),
+        ];
+
+        assert_eq!(
+            format_subtitles(&captions, SubtitleFormat::Srt),
+            "1\n00:00:00,000 --> 00:00:01,500\nHello\n\n\
+             2\n00:00:02,000 --> 03:00:00,500\nworld\n\n"
+        );
+    }
+
+    #[test]
+    fn formats_webvtt_cues() {
+        let captions = [caption(30, 61, "Hello")];
+
+        assert_eq!(
+            format_subtitles(&captions, SubtitleFormat::WebVtt),
+            "WEBVTT\n\n00:00:01.000 --> 00:00:02.033\nHello\n\n"
+        );
+    }
+
+    #[test]
+    fn sorts_cues_and_cuts_overlaps_short() {
+        let captions = [caption(60, 90, "second"), caption(0, 75, "first")];
+
+        assert_eq!(
+            format_subtitles(&captions, SubtitleFormat::Srt),
+            "1\n00:00:00,000 --> 00:00:02,000\nfirst\n\n\
+             2\n00:00:02,000 --> 00:00:03,000\nsecond\n\n"
+        );
+    }
+
+    #[test]
+    fn skips_empty_cues_without_gaps_in_numbering() {
+        let captions = [
+            caption(0, 30, "first"),
+            caption(30, 30, "zero length"),
+            caption(30, 60, "   "),
+            caption(60, 90, "same start"),
+            caption(60, 120, "second"),
+        ];
+
+        assert_eq!(
+            format_subtitles(&captions, SubtitleFormat::Srt),
+            "1\n00:00:00,000 --> 00:00:01,000\nfirst\n\n\
+             2\n00:00:02,000 --> 00:00:04,000\nsecond\n\n"
+        );
+    }
+}
@@ -0,0 +1,60 @@
+/// Where a flash + beep sync test pattern was found on the timeline.
+#[derive(Debug, Clone, Copy)]
+pub struct SyncMeasurement {
+    /// Timeline frame where the flash first appears.
+    pub flash_frame: u32,
+    /// Timeline time, in seconds, of the flash frame.
+    pub flash_time: f64,
+    /// Timeline time, in seconds, where the beep starts.
+    pub beep_time: f64,
+    /// How far the audio lags the video, in milliseconds. Negative when it leads.
+    pub drift_ms: f64,
+}
+
+impl SyncMeasurement {
+    pub(crate) fn new(flash_frame: u32, flash_time: f64, beep_time: f64) -> Self {
+        Self {
+            flash_frame,
+            flash_time,
+            beep_time,
+            drift_ms: (beep_time - flash_time) * 1000.0,
+        }
+    }
+}
+
+/// Average brightness of a tightly packed RGBA frame between 0 and 1,
+/// sampling every few pixels since only large changes matter.
+pub(crate) fn mean_luma(frame: &[u8]) -> f64 {
+    const PIXEL_STEP: usize = 16;
+
+    let (sum, count) =
+        frame
+            .chunks_exact(4)
+            .step_by(PIXEL_STEP)
+            .fold((0.0, 0usize), |(sum, count), pixel| {
+                let luma =
+                    0.2126 * pixel[0] as f64 + 0.7152 * pixel[1] as f64 + 0.0722 * pixel[2] as f64;
+                (sum + luma / 255.0, count + 1)
+            });
+
+    if count == 0 {
+        0.0
+    } else {
+        sum / count as f64
+    }
+}
+
+/// Index of the first value past the midpoint between the smallest and largest values,
+/// which is where a step like a flash or beep begins. `None` if the values never change
+/// by more than `min_step`.
+pub(crate) fn find_onset(values: &[f64], min_step: f64) -> Option<usize> {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    if max - min < min_step {
+        return None;
+    }
+
+    let midpoint = (min + max) / 2.0;
+    values.iter().position(|&value| value > midpoint)
+}
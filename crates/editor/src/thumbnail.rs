@@ -0,0 +1,88 @@
+use std::io::Cursor;
+
+use cap_rendering::{produce_frame, Background, CardFrames, ProjectUniforms, SeekMode};
+use image::{ImageFormat, RgbaImage};
+
+use crate::editor_instance::{with_visualizer_audio, EditorInstance, FPS};
+
+impl EditorInstance {
+    /// Renders the composite `position_fraction` of the way through the recording's part of
+    /// the timeline, with trims and cuts applied and the cards skipped, and returns it as a
+    /// PNG scaled down to fit within `max_size`. Uses the current project configuration,
+    /// so it makes a more representative project thumbnail than the first recorded frame.
+    pub async fn representative_thumbnail(
+        &self,
+        max_size: (u32, u32),
+        position_fraction: f64,
+    ) -> Result<Vec<u8>, String> {
+        let project = self.get_project();
+        let frame_number = thumbnail_frame(
+            project.intro_duration(),
+            project
+                .timeline()
+                .map(|t| t.duration())
+                .unwrap_or(self.recordings.duration()),
+            position_fraction,
+        );
+
+        let Some((screen_frame, camera_frames)) = self
+            .get_timeline_frames(
+                &project,
+                frame_number,
+                &CardFrames::default(),
+                SeekMode::Exact,
+            )
+            .await
+        else {
+            return Err(format!(
+                "Failed to decode frame {frame_number} for thumbnail"
+            ));
+        };
+
+        let uniforms = with_visualizer_audio(
+            ProjectUniforms::new(&self.render_constants, &project)
+                .at_time(frame_number as f64 / FPS as f64, FPS),
+            &project,
+            &self.audio,
+            frame_number,
+            self.recordings.duration(),
+        );
+        let (width, height) = uniforms.output_size;
+        let scale = (max_size.0 as f32 / width as f32)
+            .min(max_size.1 as f32 / height as f32)
+            .min(1.0);
+        let uniforms = uniforms.scaled(scale);
+
+        let frame = produce_frame(
+            &self.render_constants,
+            &screen_frame,
+            &camera_frames,
+            Background::from(project.background.source.clone()),
+            &uniforms,
+        )
+        .await?;
+
+        let (width, height) = uniforms.output_size;
+        let image = RgbaImage::from_raw(width, height, frame)
+            .ok_or("Rendered thumbnail doesn't match its size")?;
+
+        let mut png = Cursor::new(vec![]);
+        image
+            .write_to(&mut png, ImageFormat::Png)
+            .map_err(|e| format!("Failed to encode thumbnail: {e}"))?;
+
+        Ok(png.into_inner())
+    }
+}
+
+/// Timeline frame `position_fraction` of the way through the `recording_duration` seconds
+/// that follow the intro, kept within them so the end doesn't land on the outro.
+fn thumbnail_frame(intro_duration: f64, recording_duration: f64, position_fraction: f64) -> u32 {
+    let first_frame = (intro_duration * FPS as f64).ceil() as u32;
+    let last_frame = (((intro_duration + recording_duration) * FPS as f64).ceil() as u32)
+        .saturating_sub(1)
+        .max(first_frame);
+
+    let time = intro_duration + position_fraction.clamp(0.0, 1.0) * recording_duration;
+    ((time * FPS as f64) as u32).clamp(first_frame, last_frame)
+}
@@ -1,9 +1,15 @@
+use cap_project::{
+    BitDepth, ColorSpace, EncoderPreset, ExportConfig, HardwareAcceleration, HardwareEncoder,
+    RateControl, VideoCodec,
+};
+use serde::Serialize;
+use specta::Type;
 use std::{
     ffi::OsString,
     io::{Read, Write},
     ops::Deref,
     path::{Path, PathBuf},
-    process::{Child, ChildStdin, Command, Stdio},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
 };
 use tauri::utils::platform;
 
@@ -38,10 +44,27 @@ impl FFmpegProcess {
         println!("Sent stop command to FFmpeg");
     }
 
+    /// Takes FFmpeg's stdout, which is only captured if the command was set up with
+    /// `Stdio::piped()` before starting.
+    pub fn take_stdout(&mut self) -> Option<ChildStdout> {
+        self.cmd.stdout.take()
+    }
+
     pub fn wait(&mut self) -> std::io::Result<std::process::ExitStatus> {
         self.cmd.wait()
     }
 
+    /// Closes stdin so FFmpeg sees the end of its input, then waits for it to exit.
+    pub fn finish(self) -> std::io::Result<std::process::ExitStatus> {
+        let Self {
+            ffmpeg_stdin,
+            mut cmd,
+        } = self;
+
+        drop(ffmpeg_stdin);
+        cmd.wait()
+    }
+
     pub fn kill(&mut self) {
         let _ = self.cmd.kill();
     }
@@ -125,6 +148,28 @@ impl FFmpegProcess {
     }
 }
 
+/// Reads `reader` to its end, such as FFmpeg's stdout, handing `send` chunks of at most
+/// `chunk_size` bytes in order until it returns false.
+pub fn read_chunks(
+    mut reader: impl Read,
+    chunk_size: usize,
+    mut send: impl FnMut(Vec<u8>) -> bool,
+) -> std::io::Result<()> {
+    let mut buffer = vec![0; chunk_size];
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(n) => {
+                if !send(buffer[..n].to_vec()) {
+                    return Ok(());
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
 pub struct FFmpegInput<T> {
     inner: T,
     pub index: u8,
@@ -219,6 +264,168 @@ impl ApplyFFmpegArgs for FFmpegRawAudioInput {
     }
 }
 
+impl ApplyFFmpegArgs for ExportConfig {
+    fn apply_ffmpeg_args(&self, command: &mut Command) {
+        ExportEncoder {
+            config: self.clone(),
+            hardware: None,
+        }
+        .apply_ffmpeg_args(command);
+    }
+}
+
+/// The encoder an export runs with, as chosen by `select_export_encoder`.
+#[derive(Debug, Clone)]
+pub struct ExportEncoder {
+    pub config: ExportConfig,
+    /// `None` when encoding on the CPU.
+    pub hardware: Option<HardwareEncoder>,
+}
+
+impl ExportEncoder {
+    /// Name of the FFmpeg encoder, eg. `libx264` or `h264_nvenc`.
+    pub fn name(&self) -> &'static str {
+        self.hardware
+            .and_then(|hardware| hardware.encoder(self.config.codec))
+            .unwrap_or(self.config.codec.encoder())
+    }
+}
+
+impl ApplyFFmpegArgs for ExportEncoder {
+    fn apply_ffmpeg_args(&self, command: &mut Command) {
+        let config = &self.config;
+        command.args(["-codec:v", self.name()]);
+
+        match (self.hardware, config.codec) {
+            (None, VideoCodec::H264 | VideoCodec::H265) => {
+                let preset = match config.preset {
+                    EncoderPreset::Fast => "ultrafast",
+                    EncoderPreset::Balanced => "medium",
+                    EncoderPreset::Quality => "slow",
+                };
+
+                command.args(["-preset", preset, "-tune", "zerolatency"]);
+            }
+            (None, VideoCodec::Vp9) => {
+                let (deadline, cpu_used) = match config.preset {
+                    EncoderPreset::Fast => ("realtime", "8"),
+                    EncoderPreset::Balanced => ("good", "2"),
+                    EncoderPreset::Quality => ("good", "0"),
+                };
+
+                command
+                    .args(["-deadline", deadline, "-cpu-used", cpu_used])
+                    .args(["-row-mt", "1"]);
+
+                // libvpx can't encode the alpha plane with alternate reference frames
+                if config.alpha {
+                    command.args(["-auto-alt-ref", "0"]);
+                }
+            }
+            (None, VideoCodec::Av1) => {
+                let preset = match config.preset {
+                    EncoderPreset::Fast => "12",
+                    EncoderPreset::Balanced => "8",
+                    EncoderPreset::Quality => "4",
+                };
+
+                command.args(["-preset", preset]);
+            }
+            // VideoToolbox has no presets, only a hint to favour speed
+            (Some(HardwareEncoder::VideoToolbox), _) => {
+                if config.preset == EncoderPreset::Fast {
+                    command.args(["-realtime", "1"]);
+                }
+            }
+            (Some(HardwareEncoder::Nvenc), _) => {
+                let preset = match config.preset {
+                    EncoderPreset::Fast => "p1",
+                    EncoderPreset::Balanced => "p4",
+                    EncoderPreset::Quality => "p7",
+                };
+
+                command.args(["-preset", preset]);
+            }
+            (Some(HardwareEncoder::Qsv), _) => {
+                let preset = match config.preset {
+                    EncoderPreset::Fast => "veryfast",
+                    EncoderPreset::Balanced => "medium",
+                    EncoderPreset::Quality => "veryslow",
+                };
+
+                command.args(["-preset", preset]);
+            }
+        }
+
+        if config.codec == VideoCodec::H265 {
+            // lets Apple players recognise HEVC in mp4
+            command.args(["-tag:v", "hvc1"]);
+        }
+
+        match (self.hardware, config.rate_control) {
+            (_, RateControl::Bitrate { kbps }) => {
+                command.args(["-b:v", &format!("{kbps}k")]);
+            }
+            (None, RateControl::Crf { value }) => {
+                command.args(["-crf", &value.to_string()]);
+
+                // VP9 only uses constant quality mode without a bitrate limit
+                if config.codec == VideoCodec::Vp9 {
+                    command.args(["-b:v", "0"]);
+                }
+            }
+            // hardware encoders have their own constant quality scales that the CRF is mapped to
+            (Some(HardwareEncoder::VideoToolbox), RateControl::Crf { value }) => {
+                let quality =
+                    100 - value.min(config.codec.max_crf()) * 100 / config.codec.max_crf();
+                command.args(["-q:v", &quality.max(1).to_string()]);
+            }
+            (Some(HardwareEncoder::Nvenc), RateControl::Crf { value }) => {
+                command
+                    .args(["-rc", "vbr", "-cq", &value.to_string()])
+                    .args(["-b:v", "0"]);
+            }
+            (Some(HardwareEncoder::Qsv), RateControl::Crf { value }) => {
+                command.args(["-global_quality", &value.to_string()]);
+            }
+        }
+
+        // hardware encoders take semi-planar frames
+        let pix_fmt = match (self.hardware, config.bit_depth) {
+            (None, _) if config.alpha => "yuva420p",
+            (None, bit_depth) => bit_depth.pix_fmt(),
+            (Some(_), BitDepth::Eight) => "nv12",
+            (Some(_), BitDepth::Ten) => "p010le",
+        };
+
+        // frames are rendered as sRGB, so they're converted from it to the requested space
+        let size = match config.max_size {
+            Some((width, height)) => format!(
+                "w='min({width},iw)':h='min({height},ih)':\
+                 force_original_aspect_ratio=decrease:force_divisible_by=2:"
+            ),
+            None => String::new(),
+        };
+        let mut filter =
+            format!("scale={size}out_color_matrix=bt709:out_range=tv,format={pix_fmt}");
+        match config.color_space {
+            ColorSpace::Srgb => {}
+            ColorSpace::Rec709 => filter.push_str(",colorspace=all=bt709:iall=bt709:itrc=srgb"),
+            ColorSpace::Rec2020 => filter.push_str(",colorspace=all=bt2020:iall=bt709:itrc=srgb"),
+        }
+
+        let (primaries, transfer, matrix) = config.color_space.ffmpeg_tags();
+        command
+            .args(["-vf", &filter])
+            .args(["-pix_fmt", pix_fmt])
+            .args(["-color_primaries", primaries])
+            .args(["-color_trc", transfer])
+            .args(["-colorspace", matrix])
+            .args(["-color_range", "tv"])
+            .args(&config.extra_ffmpeg_args);
+    }
+}
+
 pub struct FFmpeg {
     pub command: Command,
     source_index: u8,
@@ -284,6 +491,175 @@ impl FFmpeg {
     pub fn start(self) -> FFmpegProcess {
         FFmpegProcess::spawn(self.command)
     }
+
+    /// Checks that the bundled FFmpeg was built with the given encoder.
+    pub fn has_encoder(encoder: &str) -> Result<bool, String> {
+        let output = Self::new()
+            .command
+            .args(["-hide_banner", "-encoders"])
+            .output()
+            .map_err(|e| format!("Failed to run ffmpeg: {e}"))?;
+
+        // lines look like ` V....D libx264   libx264 H.264 / AVC / MPEG-4 AVC`
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .any(|line| line.split_whitespace().nth(1) == Some(encoder)))
+    }
+
+    /// Checks the encoder actually works on this system by encoding a single frame.
+    /// Hardware encoders are often built in but fail without the hardware or drivers.
+    pub fn can_encode(encoder: &str) -> bool {
+        Self::new()
+            .command
+            .args(["-hide_banner", "-loglevel", "error"])
+            .args(["-f", "lavfi", "-i", "color=size=256x256:rate=1"])
+            .args(["-frames:v", "1", "-codec:v", encoder, "-f", "null", "-"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+}
+
+/// Checks an export configuration can be encoded on this system.
+pub fn validate_export_config(config: &ExportConfig) -> Result<(), String> {
+    config.validate()?;
+
+    let encoder = config.codec.encoder();
+    if !FFmpeg::has_encoder(encoder)? {
+        return Err(format!(
+            "The {encoder} encoder needed for {:?} export isn't available",
+            config.codec
+        ));
+    }
+
+    let (container, audio_encoder) = config.container();
+    if !FFmpeg::has_encoder(audio_encoder)? {
+        return Err(format!(
+            "The {audio_encoder} encoder needed for {container} audio isn't available"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks an export can be encoded with `encoder` on this system.
+/// Hardware encoders are only chosen once they've been seen to work, so they aren't checked again.
+pub fn validate_export_encoder(encoder: &ExportEncoder) -> Result<(), String> {
+    match encoder.hardware {
+        Some(_) => encoder.config.validate(),
+        None => validate_export_config(&encoder.config),
+    }
+}
+
+/// Picks the encoder for an export, using the first of the platform's hardware encoders
+/// that works if the config allows hardware acceleration, and the CPU otherwise.
+/// Runs FFmpeg, so avoid calling this on an async runtime thread.
+pub fn select_export_encoder(config: &ExportConfig) -> ExportEncoder {
+    select_export_encoder_with(config, HardwareEncoder::for_platform(), FFmpeg::can_encode)
+}
+
+/// Like `select_export_encoder`, trying `candidates` in order and asking `can_encode`
+/// whether an FFmpeg encoder works instead of running FFmpeg.
+pub fn select_export_encoder_with(
+    config: &ExportConfig,
+    candidates: &[HardwareEncoder],
+    mut can_encode: impl FnMut(&str) -> bool,
+) -> ExportEncoder {
+    let hardware = match config.hardware_acceleration {
+        // hardware encoders drop the alpha plane
+        _ if config.alpha => None,
+        HardwareAcceleration::Off => None,
+        HardwareAcceleration::Auto => candidates.iter().copied().find(|hardware| {
+            hardware
+                .encoder(config.codec)
+                .is_some_and(|encoder| can_encode(encoder))
+        }),
+    };
+
+    ExportEncoder {
+        config: config.clone(),
+        hardware,
+    }
+}
+
+/// Which of the bundled command line tools were found and what they can be used for,
+/// so features that need a missing one can be disabled up front.
+#[derive(Debug, Clone, Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyStatus {
+    /// Version of FFmpeg, or `None` if it couldn't be run.
+    pub ffmpeg_version: Option<String>,
+    pub ffprobe_version: Option<String>,
+    /// Audio is decoded by running FFmpeg.
+    pub audio_decode: bool,
+    /// Export needs FFmpeg and at least one of the codecs in `codecs`.
+    pub export: bool,
+    /// Codecs whose encoder FFmpeg was built with.
+    pub codecs: Vec<VideoCodec>,
+}
+
+/// Checks which of the bundled command line tools are available.
+/// Runs them, so avoid calling this on an async runtime thread.
+pub fn dependency_status() -> DependencyStatus {
+    dependency_status_with(command_version, |encoder| {
+        FFmpeg::has_encoder(encoder).unwrap_or(false)
+    })
+}
+
+/// Like `dependency_status`, asking `version` for each tool's version and `has_encoder`
+/// whether FFmpeg has an encoder instead of running them.
+pub fn dependency_status_with(
+    mut version: impl FnMut(&str) -> Option<String>,
+    mut has_encoder: impl FnMut(&str) -> bool,
+) -> DependencyStatus {
+    let ffmpeg_version = version("ffmpeg");
+    let codecs = match ffmpeg_version {
+        Some(_) => [
+            VideoCodec::H264,
+            VideoCodec::H265,
+            VideoCodec::Vp9,
+            VideoCodec::Av1,
+        ]
+        .into_iter()
+        .filter(|codec| has_encoder(codec.encoder()))
+        .collect(),
+        None => vec![],
+    };
+
+    DependencyStatus {
+        audio_decode: ffmpeg_version.is_some(),
+        export: ffmpeg_version.is_some() && !codecs.is_empty(),
+        ffprobe_version: version("ffprobe"),
+        ffmpeg_version,
+        codecs,
+    }
+}
+
+/// Runs `<command> -version` next to the executable and returns the version it reports.
+fn command_version(command: &str) -> Option<String> {
+    let output = Command::new(relative_command_path(command).ok()?)
+        .arg("-version")
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    reported_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Version in the output of `<command> -version`.
+fn reported_version(output: &str) -> Option<String> {
+    // the first line looks like `ffmpeg version 7.0.1 Copyright (c) 2000-2024 ...`
+    output
+        .lines()
+        .next()?
+        .split_whitespace()
+        .skip_while(|word| *word != "version")
+        .nth(1)
+        .map(str::to_string)
 }
 
 fn relative_command_path(command: impl AsRef<Path>) -> Result<PathBuf, tauri_plugin_shell::Error> {
@@ -295,3 +671,323 @@ fn relative_command_path(command: impl AsRef<Path>) -> Result<PathBuf, tauri_plu
         None => Err(tauri_plugin_shell::Error::CurrentExeHasNoParent),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versions(ffmpeg: Option<&str>, ffprobe: Option<&str>) -> impl FnMut(&str) -> Option<String> {
+        let (ffmpeg, ffprobe) = (ffmpeg.map(str::to_string), ffprobe.map(str::to_string));
+        move |command| match command {
+            "ffmpeg" => ffmpeg.clone(),
+            "ffprobe" => ffprobe.clone(),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn dependency_status_with_ffmpeg_present() {
+        let status = dependency_status_with(versions(Some("7.0.1"), Some("7.0.1")), |encoder| {
+            matches!(encoder, "libx264" | "libvpx-vp9")
+        });
+
+        assert_eq!(status.ffmpeg_version.as_deref(), Some("7.0.1"));
+        assert_eq!(status.ffprobe_version.as_deref(), Some("7.0.1"));
+        assert!(status.audio_decode);
+        assert!(status.export);
+        assert_eq!(status.codecs, vec![VideoCodec::H264, VideoCodec::Vp9]);
+    }
+
+    #[test]
+    fn dependency_status_with_ffmpeg_absent() {
+        let mut encoders_checked = 0;
+        let status = dependency_status_with(versions(None, Some("7.0.1")), |_| {
+            encoders_checked += 1;
+            true
+        });
+
+        assert_eq!(status.ffmpeg_version, None);
+        assert!(!status.audio_decode);
+        assert!(!status.export);
+        assert!(status.codecs.is_empty());
+        assert_eq!(encoders_checked, 0);
+    }
+
+    #[test]
+    fn export_is_unavailable_without_any_encoders() {
+        let status = dependency_status_with(versions(Some("7.0.1"), None), |_| false);
+
+        assert!(status.audio_decode);
+        assert!(!status.export);
+        assert_eq!(status.ffprobe_version, None);
+    }
+
+    #[test]
+    fn reads_the_version_from_the_first_line() {
+        let output = "ffmpeg version 7.0.1 Copyright (c) 2000-2024 the FFmpeg developers\n\
+                      built with Apple clang version 15.0.0\n";
+
+        assert_eq!(reported_version(output).as_deref(), Some("7.0.1"));
+        assert_eq!(reported_version(""), None);
+        assert_eq!(reported_version("unexpected output"), None);
+    }
+
+    fn args(config: &impl ApplyFFmpegArgs) -> Vec<String> {
+        let mut command = Command::new("ffmpeg");
+        config.apply_ffmpeg_args(&mut command);
+        command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn export_args_end_with_the_extra_ffmpeg_args() {
+        let config = ExportConfig {
+            extra_ffmpeg_args: vec!["-movflags".into(), "+faststart".into()],
+            ..Default::default()
+        };
+
+        let args = args(&config);
+
+        assert_eq!(args[..2], ["-codec:v", "libx264"]);
+        assert_eq!(args[args.len() - 2..], ["-movflags", "+faststart"]);
+    }
+
+    #[test]
+    fn export_args_follow_the_config() {
+        let config = ExportConfig {
+            codec: VideoCodec::Vp9,
+            rate_control: RateControl::Crf { value: 31 },
+            ..Default::default()
+        };
+
+        let args = args(&config).join(" ");
+
+        assert!(args.starts_with("-codec:v libvpx-vp9 -deadline realtime"));
+        assert!(args.contains("-crf 31 -b:v 0"));
+        assert!(args.contains("-pix_fmt yuv420p"));
+    }
+
+    #[test]
+    fn crf_mode_passes_the_crf_without_a_bitrate() {
+        let args = args(&ExportConfig {
+            rate_control: RateControl::Crf { value: 28 },
+            ..Default::default()
+        });
+
+        assert!(has_arg(&args, "-codec:v", "libx264"));
+        assert!(has_arg(&args, "-crf", "28"));
+        assert!(!args.iter().any(|arg| arg == "-b:v"));
+    }
+
+    #[test]
+    fn bitrate_mode_passes_the_bitrate_without_a_crf() {
+        for codec in [VideoCodec::H264, VideoCodec::Vp9] {
+            let args = args(&ExportConfig {
+                codec,
+                rate_control: RateControl::Bitrate { kbps: 8000 },
+                ..Default::default()
+            });
+
+            assert!(has_arg(&args, "-b:v", "8000k"), "{codec:?}");
+            assert!(!has_arg(&args, "-b:v", "0"), "{codec:?}");
+            assert!(!args.iter().any(|arg| arg == "-crf"), "{codec:?}");
+        }
+    }
+
+    #[test]
+    fn bitrate_mode_on_hardware_skips_its_quality_scale() {
+        let args = args(&ExportEncoder {
+            config: ExportConfig {
+                rate_control: RateControl::Bitrate { kbps: 6000 },
+                ..Default::default()
+            },
+            hardware: Some(HardwareEncoder::Nvenc),
+        });
+
+        assert!(has_arg(&args, "-b:v", "6000k"));
+        assert!(!args.iter().any(|arg| arg == "-cq"));
+    }
+
+    #[test]
+    fn read_chunks_hands_over_the_reader_in_order() {
+        let data = (0..10).collect::<Vec<u8>>();
+        let mut chunks = Vec::new();
+
+        read_chunks(&data[..], 4, |chunk| {
+            chunks.push(chunk);
+            true
+        })
+        .unwrap();
+
+        assert_eq!(chunks, vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9]]);
+    }
+
+    #[test]
+    fn read_chunks_stops_once_send_does() {
+        let data = [0; 10];
+        let mut sent = 0;
+
+        read_chunks(&data[..], 4, |_| {
+            sent += 1;
+            false
+        })
+        .unwrap();
+
+        assert_eq!(sent, 1);
+    }
+
+    /// Types of the top level boxes of an MP4, checking each is complete.
+    fn mp4_boxes(mut data: &[u8]) -> Vec<String> {
+        let mut boxes = Vec::new();
+        while !data.is_empty() {
+            assert!(data.len() >= 8, "truncated box header");
+            let size = u32::from_be_bytes(data[..4].try_into().unwrap()) as usize;
+            assert!((8..=data.len()).contains(&size), "truncated box");
+
+            boxes.push(String::from_utf8_lossy(&data[4..8]).into_owned());
+            data = &data[size..];
+        }
+        boxes
+    }
+
+    #[test]
+    #[ignore = "needs ffmpeg on the PATH"]
+    fn streamed_export_is_a_complete_fragmented_mp4() {
+        let config = ExportConfig::default();
+        let mut command = Command::new("ffmpeg");
+        command
+            .args(["-f", "lavfi", "-i", "testsrc=duration=2:size=64x64:rate=30"])
+            .args(["-f", config.container().0]);
+        config.apply_ffmpeg_args(&mut command);
+        command
+            .args(config.stream_muxer_args().unwrap())
+            .arg("pipe:1")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+
+        let mut child = command.spawn().unwrap();
+        let mut output = Vec::new();
+        read_chunks(child.stdout.take().unwrap(), 4096, |chunk| {
+            output.extend(chunk);
+            true
+        })
+        .unwrap();
+        assert!(child.wait().unwrap().success());
+
+        let boxes = mp4_boxes(&output);
+        assert_eq!(boxes[..2], ["ftyp", "moov"]);
+        assert!(boxes.iter().any(|b| b == "moof") && boxes.iter().any(|b| b == "mdat"));
+    }
+
+    fn auto_config(codec: VideoCodec) -> ExportConfig {
+        ExportConfig {
+            codec,
+            hardware_acceleration: HardwareAcceleration::Auto,
+            ..Default::default()
+        }
+    }
+
+    /// Whether `args` has `flag` followed by `value`.
+    fn has_arg(args: &[String], flag: &str, value: &str) -> bool {
+        args.windows(2)
+            .any(|pair| pair[0] == flag && pair[1] == value)
+    }
+
+    #[test]
+    fn nvenc_is_chosen_when_it_works() {
+        let candidates = [HardwareEncoder::Nvenc, HardwareEncoder::Qsv];
+        let encoder =
+            select_export_encoder_with(&auto_config(VideoCodec::H264), &candidates, |_| true);
+
+        assert_eq!(encoder.hardware, Some(HardwareEncoder::Nvenc));
+
+        let args = args(&encoder);
+        assert!(has_arg(&args, "-codec:v", "h264_nvenc"));
+        assert!(has_arg(&args, "-preset", "p1"));
+        assert!(has_arg(&args, "-cq", "23"));
+        assert!(args.iter().any(|arg| arg.contains("format=nv12")));
+    }
+
+    #[test]
+    fn later_candidates_are_tried_when_earlier_ones_fail() {
+        let candidates = [HardwareEncoder::Nvenc, HardwareEncoder::Qsv];
+        let mut tried = Vec::new();
+        let encoder =
+            select_export_encoder_with(&auto_config(VideoCodec::H265), &candidates, |encoder| {
+                tried.push(encoder.to_string());
+                encoder == "hevc_qsv"
+            });
+
+        assert_eq!(tried, ["hevc_nvenc", "hevc_qsv"]);
+        assert_eq!(encoder.hardware, Some(HardwareEncoder::Qsv));
+
+        let args = args(&encoder);
+        assert!(has_arg(&args, "-codec:v", "hevc_qsv"));
+        assert!(has_arg(&args, "-global_quality", "23"));
+    }
+
+    #[test]
+    fn videotoolbox_maps_the_crf_to_its_quality_scale() {
+        let encoder = select_export_encoder_with(
+            &auto_config(VideoCodec::H264),
+            &[HardwareEncoder::VideoToolbox],
+            |_| true,
+        );
+
+        let args = args(&encoder);
+        assert!(has_arg(&args, "-codec:v", "h264_videotoolbox"));
+        assert!(has_arg(&args, "-q:v", "55"));
+        assert!(has_arg(&args, "-realtime", "1"));
+    }
+
+    #[test]
+    fn falls_back_to_the_cpu_when_no_hardware_encoder_works() {
+        let candidates = [HardwareEncoder::Nvenc, HardwareEncoder::Qsv];
+        let encoder =
+            select_export_encoder_with(&auto_config(VideoCodec::H264), &candidates, |_| false);
+
+        assert_eq!(encoder.hardware, None);
+
+        let args = args(&encoder);
+        assert!(has_arg(&args, "-codec:v", "libx264"));
+        assert!(has_arg(&args, "-crf", "23"));
+    }
+
+    #[test]
+    fn hardware_without_an_encoder_for_the_codec_is_skipped() {
+        let mut tried = 0;
+        let encoder = select_export_encoder_with(
+            &auto_config(VideoCodec::Vp9),
+            &[HardwareEncoder::VideoToolbox],
+            |_| {
+                tried += 1;
+                true
+            },
+        );
+
+        assert_eq!(encoder.hardware, None);
+        assert_eq!(tried, 0);
+    }
+
+    #[test]
+    fn hardware_isnt_used_when_off_or_for_alpha() {
+        let candidates = [HardwareEncoder::Nvenc];
+        let off = ExportConfig::default();
+        let alpha = ExportConfig {
+            alpha: true,
+            ..auto_config(VideoCodec::Vp9)
+        };
+
+        assert_eq!(
+            select_export_encoder_with(&off, &candidates, |_| true).hardware,
+            None
+        );
+        assert_eq!(
+            select_export_encoder_with(&alpha, &candidates, |_| true).hardware,
+            None
+        );
+    }
+}
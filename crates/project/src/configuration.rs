@@ -1,6 +1,10 @@
+use std::path::{Path, PathBuf};
+
 use serde::{Deserialize, Serialize};
 use specta::Type;
 
+use crate::{ExportPreset, RecordingMeta};
+
 #[derive(Type, Serialize, Deserialize, Clone, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub enum AspectRatio {
@@ -32,6 +36,9 @@ pub enum BackgroundSource {
         #[serde(default = "default_gradient_angle")]
         angle: u16,
     },
+    /// Leaves the background see-through, so the recording can be composited over other
+    /// footage. Only kept in exports with `ExportConfig::alpha` set.
+    Transparent,
 }
 
 fn default_gradient_angle() -> u16 {
@@ -73,6 +80,9 @@ pub struct BackgroundConfiguration {
     pub blur: u32,
     pub padding: f32,
     pub rounding: f32,
+    // drop shadow behind the screen, visible within the padding
+    #[serde(default)]
+    pub shadow: f32,
     pub inset: u32,
     pub crop: Option<Crop>,
 }
@@ -110,6 +120,13 @@ pub struct CameraConfiguration {
     pub rounding: f32,
     pub shadow: u32,
     pub size: f32,
+    #[serde(default = "CameraConfiguration::default_opacity")]
+    pub opacity: f32,
+    /// Replaces `rounding` when set.
+    #[serde(default)]
+    pub shape: Option<CameraShape>,
+    #[serde(default)]
+    pub border: Option<CameraBorder>,
 }
 
 impl Default for CameraConfiguration {
@@ -121,10 +138,33 @@ impl Default for CameraConfiguration {
             rounding: Self::default_rounding(),
             shadow: 0,
             size: Self::default_size(),
+            opacity: Self::default_opacity(),
+            shape: None,
+            border: None,
         }
     }
 }
 
+#[derive(Type, Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum CameraShape {
+    Rectangle,
+    /// `radius` is a percentage of half the camera's size, like `CameraConfiguration::rounding`.
+    RoundedRect {
+        radius: f32,
+    },
+    Circle,
+}
+
+/// Ring drawn just inside the camera's edge, following its shape.
+#[derive(Type, Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CameraBorder {
+    /// Width as a percentage of the camera's size.
+    pub width: f32,
+    pub color: Color,
+}
+
 impl CameraConfiguration {
     fn default_size() -> f32 {
         30.0
@@ -133,6 +173,10 @@ impl CameraConfiguration {
     fn default_rounding() -> f32 {
         100.0
     }
+
+    fn default_opacity() -> f32 {
+        100.0
+    }
 }
 
 #[derive(Type, Serialize, Deserialize, Clone, Debug, Default)]
@@ -140,6 +184,68 @@ impl CameraConfiguration {
 pub struct AudioConfiguration {
     mute: bool,
     improve: bool,
+    #[serde(default)]
+    pub fade_in_ms: u32,
+    #[serde(default)]
+    pub fade_out_ms: u32,
+    /// Cuts silent stretches out of playback and export when set.
+    #[serde(default)]
+    pub skip_silence: Option<SilenceDetection>,
+    /// Ids of recorded audio tracks left out of playback and export. Only the microphone,
+    /// `"microphone"`, is recorded as a track.
+    #[serde(default)]
+    pub muted_tracks: Vec<String>,
+    /// Silences background noise between speech when set.
+    #[serde(default)]
+    pub noise_gate: Option<NoiseGate>,
+    /// Adjusts the overall level to a target loudness when set.
+    #[serde(default)]
+    pub normalize_loudness: Option<LoudnessNormalization>,
+    /// Lowers external audio mixed over the recording while the microphone is speaking.
+    #[serde(default)]
+    pub ducking: Option<Ducking>,
+}
+
+impl AudioConfiguration {
+    pub fn is_track_muted(&self, track_id: &str) -> bool {
+        self.muted_tracks.iter().any(|id| id == track_id)
+    }
+}
+
+#[derive(Type, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NoiseGate {
+    /// Audio quieter than this, in dBFS, is silenced.
+    pub threshold_db: f32,
+}
+
+#[derive(Type, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Ducking {
+    /// Microphone audio louder than this, in dBFS, counts as speech.
+    pub threshold_db: f32,
+    /// How far the external audio is lowered during speech, in dB.
+    pub amount_db: f32,
+    /// Roughly how long the external audio takes to lower once speech starts.
+    pub attack_ms: u32,
+    /// Roughly how long the external audio takes to come back up once speech stops.
+    pub release_ms: u32,
+}
+
+#[derive(Type, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LoudnessNormalization {
+    /// Integrated loudness to reach, in LUFS. -16 is typical for spoken content.
+    pub target_lufs: f32,
+}
+
+#[derive(Type, Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SilenceDetection {
+    /// Audio quieter than this, in dBFS, counts as silence.
+    pub threshold_db: f32,
+    /// Quiet stretches shorter than this aren't skipped.
+    pub min_duration_ms: u32,
 }
 
 #[derive(Type, Serialize, Deserialize, Clone, Debug, Default)]
@@ -156,6 +262,10 @@ pub struct CursorConfiguration {
     hide_when_idle: bool,
     size: u32,
     r#type: CursorType,
+    /// How much the cursor path between recorded positions is curved rather than straight,
+    /// from 0 to 1.
+    #[serde(default)]
+    pub smoothing: f32,
 }
 
 #[derive(Type, Serialize, Deserialize, Clone, Debug, Default)]
@@ -186,17 +296,67 @@ impl TimelineSegment {
     }
 }
 
+/// Plays a range of the recording `factor` times faster, on top of the timescale of the
+/// segments it overlaps. A `factor` below 1 slows it down.
+#[derive(Type, Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SpeedSegment {
+    /// Start and end in recording time.
+    pub range: (f64, f64),
+    pub factor: f64,
+}
+
+/// Pauses on a frame of the recording for a while as the timeline carries on, then plays on
+/// from it. The recording's audio is silent while it's held.
+#[derive(Type, Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Hold {
+    /// Frame of the recording to hold, at `TIMELINE_FPS`.
+    pub frame: u32,
+    /// Frames of timeline to hold it for, extending the timeline by as much.
+    pub duration_frames: u32,
+}
+
+impl Hold {
+    /// Recording time of the held frame.
+    fn time(&self) -> f64 {
+        self.frame as f64 / TIMELINE_FPS as f64
+    }
+
+    fn duration(&self) -> f64 {
+        self.duration_frames as f64 / TIMELINE_FPS as f64
+    }
+}
+
 #[derive(Type, Serialize, Deserialize, Clone, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct TimelineConfiguration {
     pub segments: Vec<TimelineSegment>,
+    /// Sorted by start and not overlapping.
+    #[serde(default)]
+    pub speed_segments: Vec<SpeedSegment>,
+    #[serde(default)]
+    pub holds: Vec<Hold>,
 }
 
 impl TimelineConfiguration {
     pub fn get_recording_time(&self, tick_time: f64) -> Option<f64> {
+        let pieces = self.speed_pieces();
+
+        let mut tick_time = tick_time;
+        for (_, start, hold) in self.hold_positions(&pieces) {
+            if tick_time < start {
+                break;
+            }
+            if tick_time < start + hold.duration() {
+                return Some(hold.time());
+            }
+            tick_time -= hold.duration();
+        }
+
         let mut accum_duration = 0.0;
 
-        for segment in &self.segments {
+        for (_, segment) in pieces {
             if tick_time < accum_duration + segment.duration() {
                 return segment.interpolate_time(tick_time - accum_duration);
             }
@@ -207,28 +367,898 @@ impl TimelineConfiguration {
         None
     }
 
+    /// Whether `tick_time` is within a hold, where the recording's frame is held and its
+    /// audio is silent.
+    pub fn is_held(&self, tick_time: f64) -> bool {
+        let mut tick_time = tick_time;
+        for (_, start, hold) in self.hold_positions(&self.speed_pieces()) {
+            if tick_time < start {
+                break;
+            }
+            if tick_time < start + hold.duration() {
+                return true;
+            }
+            tick_time -= hold.duration();
+        }
+
+        false
+    }
+
+    /// Start and end, in timeline time, of the segment containing `tick_time`.
+    /// Holds lengthen the segment they're in.
+    pub fn get_segment_bounds(&self, tick_time: f64) -> Option<(f64, f64)> {
+        let pieces = self.speed_pieces();
+
+        let mut durations = vec![0.0; self.segments.len()];
+        for (index, piece) in &pieces {
+            durations[*index] += piece.duration();
+        }
+        for (index, _, hold) in self.hold_positions(&pieces) {
+            durations[index] += hold.duration();
+        }
+
+        let mut accum_duration = 0.0;
+
+        for duration in durations {
+            let end = accum_duration + duration;
+
+            if tick_time < end {
+                return Some((accum_duration, end));
+            }
+
+            accum_duration = end;
+        }
+
+        None
+    }
+
     pub fn duration(&self) -> f64 {
-        self.segments.iter().map(|s| s.duration()).sum()
+        let pieces = self.speed_pieces();
+        let holds = self.hold_positions(&pieces);
+
+        pieces.iter().map(|(_, s)| s.duration()).sum::<f64>()
+            + holds
+                .iter()
+                .map(|(_, _, hold)| hold.duration())
+                .sum::<f64>()
+    }
+
+    /// Holds whose frame is on the timeline, sorted by where they start, each with the index
+    /// of the segment it's in and where it starts in timeline time without the holds before it.
+    fn hold_positions<'a>(
+        &'a self,
+        pieces: &[(usize, TimelineSegment)],
+    ) -> Vec<(usize, f64, &'a Hold)> {
+        let mut positions = self
+            .holds
+            .iter()
+            .filter(|hold| hold.duration_frames > 0)
+            .filter_map(|hold| {
+                let time = hold.time();
+                let mut accum_duration = 0.0;
+
+                for (index, piece) in pieces {
+                    if (piece.start..piece.end).contains(&time) {
+                        let start = accum_duration + (time - piece.start) / piece.timescale;
+                        return Some((*index, start, hold));
+                    }
+
+                    accum_duration += piece.duration();
+                }
+
+                None
+            })
+            .collect::<Vec<_>>();
+
+        positions.sort_by(|a, b| a.1.total_cmp(&b.1));
+        positions
+    }
+
+    /// The segments split wherever a speed segment starts or ends, with the speed applied
+    /// to their timescale, each paired with the index of the segment it came from.
+    fn speed_pieces(&self) -> Vec<(usize, TimelineSegment)> {
+        let mut pieces = vec![];
+
+        for (index, segment) in self.segments.iter().enumerate() {
+            let mut start = segment.start;
+
+            for speed in &self.speed_segments {
+                let (range_start, range_end) = speed.range;
+                if range_end <= start
+                    || range_start >= segment.end
+                    || !(speed.factor.is_finite() && speed.factor > 0.0)
+                {
+                    continue;
+                }
+
+                if range_start > start {
+                    pieces.push((
+                        index,
+                        TimelineSegment {
+                            start,
+                            end: range_start,
+                            ..segment.clone()
+                        },
+                    ));
+                    start = range_start;
+                }
+
+                let end = range_end.min(segment.end);
+                pieces.push((
+                    index,
+                    TimelineSegment {
+                        timescale: segment.timescale * speed.factor,
+                        start,
+                        end,
+                    },
+                ));
+                start = end;
+            }
+
+            if start < segment.end {
+                pieces.push((
+                    index,
+                    TimelineSegment {
+                        start,
+                        ..segment.clone()
+                    },
+                ));
+            }
+        }
+
+        pieces
+    }
+
+    /// Cuts the given ranges of recording time out of the timeline, splitting segments around them.
+    /// `ranges` must be sorted by start time and not overlap.
+    pub fn without_recording_ranges(&self, ranges: &[(f64, f64)]) -> Self {
+        let mut segments = vec![];
+
+        for segment in &self.segments {
+            let mut start = segment.start;
+
+            for &(range_start, range_end) in ranges {
+                if range_end <= start || range_start >= segment.end {
+                    continue;
+                }
+
+                if range_start > start {
+                    segments.push(TimelineSegment {
+                        start,
+                        end: range_start,
+                        ..segment.clone()
+                    });
+                }
+
+                start = range_end;
+            }
+
+            if start < segment.end {
+                segments.push(TimelineSegment {
+                    start,
+                    ..segment.clone()
+                });
+            }
+        }
+
+        Self {
+            segments,
+            speed_segments: self.speed_segments.clone(),
+            holds: self.holds.clone(),
+        }
     }
 }
 
+#[derive(Type, Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum ColorGrade {
+    /// Slider adjustments in the range -100 to 100, where 0 leaves the frame untouched.
+    Adjustments {
+        brightness: f32,
+        contrast: f32,
+        saturation: f32,
+    },
+    /// A 3D LUT loaded from a `.cube` file.
+    Lut { path: String },
+}
+
 #[derive(Type, Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct ProjectConfiguration {
     pub aspect_ratio: Option<AspectRatio>,
     pub background: BackgroundConfiguration,
     pub camera: CameraConfiguration,
+    // configuration for each of the recording's extra cameras, by index
+    #[serde(default)]
+    pub extra_cameras: Vec<CameraConfiguration>,
     pub audio: AudioConfiguration,
     pub cursor: CursorConfiguration,
     pub hotkeys: HotkeysConfiguration,
     #[serde(default)]
     pub timeline: Option<TimelineConfiguration>,
+    #[serde(default)]
+    pub color_grade: Option<ColorGrade>,
+    #[serde(default)]
+    pub captions: Vec<Caption>,
+    #[serde(default)]
+    pub time_badge: Option<TimeBadge>,
+    // cards shown before and after the recording
+    #[serde(default)]
+    pub intro: Option<Card>,
+    #[serde(default)]
+    pub outro: Option<Card>,
+    #[serde(default)]
+    pub reframe: Option<Reframe>,
+    #[serde(default)]
+    pub markers: Vec<Marker>,
+    #[serde(default)]
+    pub split_screen: Option<SplitScreen>,
+    #[serde(default)]
+    pub spotlight: Option<Spotlight>,
+    #[serde(default)]
+    pub motion_blur: Option<MotionBlur>,
+    #[serde(default)]
+    pub audio_visualizer: Option<AudioVisualizer>,
+    /// Export settings saved with the project, see `ExportPreset::find`.
+    #[serde(default)]
+    pub export_presets: Vec<ExportPreset>,
+}
+
+/// Lays the screen out beside a second recording's screen, when the editor has one loaded.
+#[derive(Type, Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SplitScreen {
+    /// Where the two sides meet, as a fraction of the output width from the left.
+    pub divider: f32,
+}
+
+/// Blurs the screen along the direction it moves while the reframe crop pans quickly,
+/// so fast pans look smooth instead of unnaturally sharp.
+#[derive(Type, Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MotionBlur {
+    /// Fraction of the movement between frames that's blurred, from 0 to 1, like how long
+    /// a camera's shutter stays open for each frame.
+    pub strength: f32,
+}
+
+/// Dims everything outside a circle to draw attention to what's inside it.
+#[derive(Type, Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Spotlight {
+    /// Centre of the circle as fractions of the output width and height.
+    pub center: XY<f32>,
+    /// Radius of the circle as a fraction of the output's smaller side.
+    pub radius: f32,
+    /// How much everything outside the circle is dimmed, from 0 to 100.
+    pub darkness: f32,
+    /// Sorted by time. When there are any they move the circle instead of `center` and
+    /// `radius`, and it holds still before the first and after the last.
+    #[serde(default)]
+    pub keyframes: Vec<SpotlightKeyframe>,
+}
+
+#[derive(Type, Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SpotlightKeyframe {
+    /// Timeline time in seconds.
+    pub time: f64,
+    pub center: XY<f32>,
+    pub radius: f32,
+}
+
+impl Spotlight {
+    /// Centre and radius of the circle at `time`, interpolated between keyframes.
+    pub fn at(&self, time: f64) -> (XY<f32>, f32) {
+        let next = self.keyframes.iter().position(|k| k.time > time);
+
+        let (a, b, t) = match next {
+            None => match self.keyframes.last() {
+                Some(last) => (last, last, 0.0),
+                None => return (self.center.clone(), self.radius),
+            },
+            Some(0) => (&self.keyframes[0], &self.keyframes[0], 0.0),
+            Some(i) => {
+                let (a, b) = (&self.keyframes[i - 1], &self.keyframes[i]);
+                (a, b, ((time - a.time) / (b.time - a.time)) as f32)
+            }
+        };
+
+        (
+            XY {
+                x: a.center.x + (b.center.x - a.center.x) * t,
+                y: a.center.y + (b.center.y - a.center.y) * t,
+            },
+            a.radius + (b.radius - a.radius) * t,
+        )
+    }
+}
+
+/// A point on the timeline the playhead can jump between.
+#[derive(Type, Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Marker {
+    /// Timeline time in seconds.
+    pub time: f64,
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+/// Pans a full height 9:16 crop window across the screen to follow the action,
+/// for turning landscape recordings into vertical clips. Replaces the background crop.
+#[derive(Type, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Reframe {
+    /// Sorted by time. The window holds still before the first and after the last.
+    pub keyframes: Vec<ReframeKeyframe>,
+}
+
+#[derive(Type, Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ReframeKeyframe {
+    /// Timeline time in seconds.
+    pub time: f64,
+    /// Horizontal centre of the crop window as a fraction of the screen width.
+    pub x: f32,
+}
+
+impl Reframe {
+    pub const ASPECT_RATIO: f32 = 9.0 / 16.0;
+
+    /// The crop window centred on the screen. It's panned with `center_at`.
+    pub fn crop(&self, (width, height): (u32, u32)) -> Crop {
+        let crop_width = ((height as f32 * Self::ASPECT_RATIO) as u32).min(width);
+
+        Crop {
+            position: XY {
+                x: (width - crop_width) / 2,
+                y: 0,
+            },
+            size: XY {
+                x: crop_width,
+                y: height,
+            },
+        }
+    }
+
+    /// Centre of the crop window at `time`, interpolated between keyframes.
+    pub fn center_at(&self, time: f64) -> f32 {
+        let next = self.keyframes.iter().position(|k| k.time > time);
+
+        match next {
+            None => self.keyframes.last().map_or(0.5, |k| k.x),
+            Some(0) => self.keyframes[0].x,
+            Some(i) => {
+                let (a, b) = (self.keyframes[i - 1], self.keyframes[i]);
+                let t = ((time - a.time) / (b.time - a.time)) as f32;
+                a.x + (b.x - a.x) * t
+            }
+        }
+    }
+}
+
+/// Frame rate of the editor's timeline, which `Card` and `Hold` frames are counted in.
+pub const TIMELINE_FPS: u32 = 30;
+
+/// A still image shown in place of the recording for `duration_frames` timeline frames.
+#[derive(Type, Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Card {
+    pub image: String,
+    pub duration_frames: u32,
+}
+
+impl Card {
+    /// Duration of the card in seconds.
+    pub fn duration(&self) -> f64 {
+        self.duration_frames as f64 / TIMELINE_FPS as f64
+    }
+}
+
+/// What's shown at a point on the timeline once the intro and outro cards are included.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimelinePosition {
+    Intro,
+    /// Time on the recording's part of the timeline, before it's mapped through
+    /// the timeline's segments to recording time.
+    Recording(f64),
+    Outro,
+}
+
+/// A timecode drawn in a corner of the video showing the time elapsed on the timeline.
+#[derive(Type, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeBadge {
+    pub position: CameraPosition,
+    #[serde(default)]
+    pub format: TimeBadgeFormat,
+    #[serde(default)]
+    pub style: TimeBadgeStyle,
+    // only draw the badge in exports, not in the editor preview
+    #[serde(default)]
+    pub export_only: bool,
+}
+
+#[derive(Type, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TimeBadgeFormat {
+    /// `01:23`
+    #[default]
+    Elapsed,
+    /// `01:23:15`, where the last part is the frame within the second.
+    ElapsedWithFrames,
+    /// `01:23 / 04:56`
+    ElapsedOfTotal,
+}
+
+#[derive(Type, Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeBadgeStyle {
+    /// Height of the badge as a percentage of the output height.
+    pub size: f32,
+    pub color: Color,
+    pub background_opacity: f32,
+}
+
+impl Default for TimeBadgeStyle {
+    fn default() -> Self {
+        Self {
+            size: 5.0,
+            color: [255, 255, 255],
+            background_opacity: 50.0,
+        }
+    }
+}
+
+/// Levels of the audio heard at each frame, drawn over the video.
+#[derive(Type, Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioVisualizer {
+    #[serde(default)]
+    pub style: AudioVisualizerStyle,
+    pub position: CameraPosition,
+    /// Width of the visualizer as a percentage of the output width.
+    pub width: f32,
+    /// Height of the visualizer as a percentage of the output height.
+    pub height: f32,
+    pub color: Color,
+    /// Number of bars, or of columns the waveform is drawn with.
+    pub bars: u32,
+    // only draw the visualizer in exports, not in the editor preview
+    #[serde(default)]
+    pub export_only: bool,
+}
+
+#[derive(Type, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum AudioVisualizerStyle {
+    /// Loudness across frequencies, low to high.
+    #[default]
+    Bars,
+    /// Loudness over the last few seconds, scrolling left as the audio plays.
+    Waveform,
+}
+
+/// Text shown from `start_frame` up to, but not including, `end_frame` in timeline frames.
+#[derive(Type, Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Caption {
+    pub start_frame: u32,
+    pub end_frame: u32,
+    pub text: String,
 }
 
 impl ProjectConfiguration {
+    /// Defaults for a project that hasn't been edited yet. The timeline plays the
+    /// recording's segments back to back, and the camera is hidden if none was recorded.
+    pub fn default_for(meta: &RecordingMeta) -> Self {
+        let segments = meta
+            .segments
+            .iter()
+            .map(|segment| TimelineSegment {
+                timescale: 1.0,
+                start: segment.start,
+                end: segment.end,
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            camera: CameraConfiguration {
+                hide: meta.camera.is_none(),
+                ..Default::default()
+            },
+            extra_cameras: vec![CameraConfiguration::default(); meta.extra_cameras.len()],
+            timeline: (!segments.is_empty()).then_some(TimelineConfiguration {
+                segments,
+                speed_segments: vec![],
+                holds: vec![],
+            }),
+            ..Default::default()
+        }
+    }
+
     pub fn timeline(&self) -> Option<&TimelineConfiguration> {
         self.timeline.as_ref()
     }
+
+    /// Configuration for the camera at `index`, where 0 is the primary camera.
+    /// Extra cameras without a configuration of their own use the defaults.
+    pub fn camera_config(&self, index: usize) -> CameraConfiguration {
+        match index {
+            0 => self.camera.clone(),
+            i => self.extra_cameras.get(i - 1).cloned().unwrap_or_default(),
+        }
+    }
+
+    pub fn intro_duration(&self) -> f64 {
+        self.intro.as_ref().map(Card::duration).unwrap_or(0.0)
+    }
+
+    pub fn outro_duration(&self) -> f64 {
+        self.outro.as_ref().map(Card::duration).unwrap_or(0.0)
+    }
+
+    /// Duration of the timeline in seconds, including the intro and outro cards.
+    /// `recording_duration` is used for projects without a timeline.
+    pub fn total_duration(&self, recording_duration: f64) -> f64 {
+        self.intro_duration()
+            + self
+                .timeline()
+                .map(|t| t.duration())
+                .unwrap_or(recording_duration)
+            + self.outro_duration()
+    }
+
+    /// What's shown at `time` on the timeline, or `None` past its end.
+    /// `recording_duration` is used for projects without a timeline.
+    pub fn timeline_position(
+        &self,
+        time: f64,
+        recording_duration: f64,
+    ) -> Option<TimelinePosition> {
+        let time = time - self.intro_duration();
+        if time < 0.0 {
+            return Some(TimelinePosition::Intro);
+        }
+
+        let recording_end = self
+            .timeline()
+            .map(|t| t.duration())
+            .unwrap_or(recording_duration);
+        if time < recording_end {
+            return Some(TimelinePosition::Recording(time));
+        }
+
+        (time - recording_end < self.outro_duration()).then_some(TimelinePosition::Outro)
+    }
+
+    /// Recording time whose audio is heard at `time` on the timeline, or `None` where
+    /// it's silent, on the cards, in holds and past the end.
+    pub fn audio_time(&self, time: f64, recording_duration: f64) -> Option<f64> {
+        let TimelinePosition::Recording(time) = self.timeline_position(time, recording_duration)?
+        else {
+            return None;
+        };
+
+        match self.timeline() {
+            Some(timeline) if timeline.is_held(time) => None,
+            Some(timeline) => timeline.get_recording_time(time),
+            None => Some(time),
+        }
+    }
+}
+
+/// A problem found by `ProjectConfiguration::validate`. `field` is the path to the offending
+/// value, eg. `timeline.segments[1]`.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ConfigError {
+    #[error("{field} is {value}, outside {min} to {max}")]
+    OutOfRange {
+        field: String,
+        value: f64,
+        min: f64,
+        max: f64,
+    },
+    #[error("{field} is {value}, but must be positive")]
+    NotPositive { field: String, value: f64 },
+    #[error("{field} ends at {end}, before it starts at {start}")]
+    Unordered { field: String, start: f64, end: f64 },
+    #[error("{field} must be sorted by time")]
+    Unsorted { field: String },
+    #[error("{field} overlaps {other}")]
+    Overlapping { field: String, other: String },
+    #[error("{field} is at {time}s of the recording, which isn't on the timeline")]
+    OffTimeline { field: String, time: f64 },
+    #[error("{field} refers to {path}, which doesn't exist")]
+    MissingFile { field: String, path: PathBuf },
+}
+
+#[derive(Default)]
+struct Validator {
+    errors: Vec<ConfigError>,
+}
+
+impl Validator {
+    fn range(&mut self, field: impl ToString, value: impl Into<f64>, min: f64, max: f64) {
+        let value = value.into();
+        if !(min..=max).contains(&value) {
+            self.errors.push(ConfigError::OutOfRange {
+                field: field.to_string(),
+                value,
+                min,
+                max,
+            });
+        }
+    }
+
+    fn ordered(&mut self, field: impl ToString, start: impl Into<f64>, end: impl Into<f64>) {
+        let (start, end) = (start.into(), end.into());
+        if start.partial_cmp(&end) != Some(std::cmp::Ordering::Less) {
+            self.errors.push(ConfigError::Unordered {
+                field: field.to_string(),
+                start,
+                end,
+            });
+        }
+    }
+
+    fn positive(&mut self, field: impl ToString, value: f64) {
+        if !(value.is_finite() && value > 0.0) {
+            self.errors.push(ConfigError::NotPositive {
+                field: field.to_string(),
+                value,
+            });
+        }
+    }
+
+    fn sorted(&mut self, field: impl ToString, times: impl IntoIterator<Item = f64>) {
+        let times = times.into_iter().collect::<Vec<_>>();
+        if times.windows(2).any(|pair| pair[0] > pair[1]) {
+            self.errors.push(ConfigError::Unsorted {
+                field: field.to_string(),
+            });
+        }
+    }
+
+    fn disjoint(&mut self, field: &str, ranges: impl IntoIterator<Item = (f64, f64)>) {
+        let mut ranges = ranges.into_iter().enumerate().collect::<Vec<_>>();
+        ranges.sort_by(|a, b| a.1 .0.total_cmp(&b.1 .0));
+
+        for pair in ranges.windows(2) {
+            let ((prev, (_, prev_end)), (next, (next_start, _))) = (pair[0], pair[1]);
+            if next_start < prev_end {
+                self.errors.push(ConfigError::Overlapping {
+                    field: format!("{field}[{next}]"),
+                    other: format!("{field}[{prev}]"),
+                });
+            }
+        }
+    }
+
+    fn file(&mut self, field: impl ToString, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        if !path.exists() {
+            self.errors.push(ConfigError::MissingFile {
+                field: field.to_string(),
+                path: path.to_path_buf(),
+            });
+        }
+    }
+
+    fn camera(&mut self, field: &str, camera: &CameraConfiguration) {
+        self.range(format!("{field}.rounding"), camera.rounding, 0.0, 100.0);
+        self.range(format!("{field}.size"), camera.size, 0.0, 100.0);
+        self.range(format!("{field}.opacity"), camera.opacity, 0.0, 100.0);
+
+        if let Some(CameraShape::RoundedRect { radius }) = camera.shape {
+            self.range(format!("{field}.shape.radius"), radius, 0.0, 100.0);
+        }
+
+        if let Some(border) = &camera.border {
+            self.range(format!("{field}.border.width"), border.width, 0.0, 100.0);
+        }
+    }
+}
+
+impl ProjectConfiguration {
+    /// Checks that values are within their ranges, ranges start before they end and speed
+    /// segments don't overlap, keyframes are sorted, holds and fades fit on the timeline and
+    /// referenced files exist. Returns every problem found rather than the first.
+    pub fn validate(&self) -> Result<(), Vec<ConfigError>> {
+        let mut v = Validator::default();
+
+        let background = &self.background;
+        if let BackgroundSource::Image { path: Some(path) } = &background.source {
+            v.file("background.source.path", path);
+        }
+        v.range("background.padding", background.padding, 0.0, 100.0);
+        v.range("background.rounding", background.rounding, 0.0, 100.0);
+        v.range("background.shadow", background.shadow, 0.0, 100.0);
+
+        v.camera("camera", &self.camera);
+        for (i, camera) in self.extra_cameras.iter().enumerate() {
+            v.camera(&format!("extraCameras[{i}]"), camera);
+        }
+
+        v.range("cursor.smoothing", self.cursor.smoothing, 0.0, 1.0);
+
+        if let Some(timeline) = &self.timeline {
+            for (i, segment) in timeline.segments.iter().enumerate() {
+                v.ordered(
+                    format!("timeline.segments[{i}]"),
+                    segment.start,
+                    segment.end,
+                );
+                v.positive(
+                    format!("timeline.segments[{i}].timescale"),
+                    segment.timescale,
+                );
+            }
+
+            for (i, speed) in timeline.speed_segments.iter().enumerate() {
+                v.ordered(
+                    format!("timeline.speedSegments[{i}]"),
+                    speed.range.0,
+                    speed.range.1,
+                );
+                v.positive(format!("timeline.speedSegments[{i}].factor"), speed.factor);
+            }
+            v.sorted(
+                "timeline.speedSegments",
+                timeline.speed_segments.iter().map(|s| s.range.0),
+            );
+            v.disjoint(
+                "timeline.speedSegments",
+                timeline.speed_segments.iter().map(|s| s.range),
+            );
+
+            for (i, hold) in timeline.holds.iter().enumerate() {
+                let field = format!("timeline.holds[{i}]");
+                v.positive(
+                    format!("{field}.durationFrames"),
+                    hold.duration_frames as f64,
+                );
+                if !timeline
+                    .segments
+                    .iter()
+                    .any(|segment| (segment.start..segment.end).contains(&hold.time()))
+                {
+                    v.errors.push(ConfigError::OffTimeline {
+                        field,
+                        time: hold.time(),
+                    });
+                }
+            }
+
+            let duration_ms = timeline.duration() * 1000.0;
+            v.range("audio.fadeInMs", self.audio.fade_in_ms, 0.0, duration_ms);
+            v.range("audio.fadeOutMs", self.audio.fade_out_ms, 0.0, duration_ms);
+        }
+
+        match &self.color_grade {
+            Some(ColorGrade::Adjustments {
+                brightness,
+                contrast,
+                saturation,
+            }) => {
+                v.range("colorGrade.brightness", *brightness, -100.0, 100.0);
+                v.range("colorGrade.contrast", *contrast, -100.0, 100.0);
+                v.range("colorGrade.saturation", *saturation, -100.0, 100.0);
+            }
+            Some(ColorGrade::Lut { path }) => v.file("colorGrade.path", path),
+            None => {}
+        }
+
+        for (i, caption) in self.captions.iter().enumerate() {
+            v.ordered(
+                format!("captions[{i}]"),
+                caption.start_frame,
+                caption.end_frame,
+            );
+        }
+
+        if let Some(time_badge) = &self.time_badge {
+            let style = &time_badge.style;
+            v.range("timeBadge.style.size", style.size, 0.0, 100.0);
+            v.range(
+                "timeBadge.style.backgroundOpacity",
+                style.background_opacity,
+                0.0,
+                100.0,
+            );
+        }
+
+        if let Some(intro) = &self.intro {
+            v.file("intro.image", &intro.image);
+        }
+        if let Some(outro) = &self.outro {
+            v.file("outro.image", &outro.image);
+        }
+
+        if let Some(reframe) = &self.reframe {
+            for (i, keyframe) in reframe.keyframes.iter().enumerate() {
+                v.range(format!("reframe.keyframes[{i}].x"), keyframe.x, 0.0, 1.0);
+            }
+            v.sorted(
+                "reframe.keyframes",
+                reframe.keyframes.iter().map(|k| k.time),
+            );
+        }
+
+        for (i, marker) in self.markers.iter().enumerate() {
+            v.range(
+                format!("markers[{i}].time"),
+                marker.time,
+                0.0,
+                f64::INFINITY,
+            );
+        }
+
+        if let Some(split_screen) = &self.split_screen {
+            v.range("splitScreen.divider", split_screen.divider, 0.0, 1.0);
+        }
+
+        if let Some(spotlight) = &self.spotlight {
+            v.range("spotlight.center.x", spotlight.center.x, 0.0, 1.0);
+            v.range("spotlight.center.y", spotlight.center.y, 0.0, 1.0);
+            v.range("spotlight.radius", spotlight.radius, 0.0, 1.0);
+            v.range("spotlight.darkness", spotlight.darkness, 0.0, 100.0);
+            for (i, keyframe) in spotlight.keyframes.iter().enumerate() {
+                let field = format!("spotlight.keyframes[{i}]");
+                v.range(format!("{field}.center.x"), keyframe.center.x, 0.0, 1.0);
+                v.range(format!("{field}.center.y"), keyframe.center.y, 0.0, 1.0);
+                v.range(format!("{field}.radius"), keyframe.radius, 0.0, 1.0);
+            }
+            v.sorted(
+                "spotlight.keyframes",
+                spotlight.keyframes.iter().map(|k| k.time),
+            );
+        }
+
+        if let Some(motion_blur) = &self.motion_blur {
+            v.range("motionBlur.strength", motion_blur.strength, 0.0, 1.0);
+        }
+
+        if let Some(visualizer) = &self.audio_visualizer {
+            v.range("audioVisualizer.width", visualizer.width, 0.0, 100.0);
+            v.range("audioVisualizer.height", visualizer.height, 0.0, 100.0);
+            v.range("audioVisualizer.bars", visualizer.bars, 1.0, 256.0);
+        }
+
+        if let Some(noise_gate) = &self.audio.noise_gate {
+            v.range(
+                "audio.noiseGate.thresholdDb",
+                noise_gate.threshold_db,
+                -100.0,
+                0.0,
+            );
+        }
+
+        if let Some(normalize) = &self.audio.normalize_loudness {
+            v.range(
+                "audio.normalizeLoudness.targetLufs",
+                normalize.target_lufs,
+                -70.0,
+                0.0,
+            );
+        }
+
+        if let Some(ducking) = &self.audio.ducking {
+            v.range(
+                "audio.ducking.thresholdDb",
+                ducking.threshold_db,
+                -100.0,
+                0.0,
+            );
+            v.range("audio.ducking.amountDb", ducking.amount_db, 0.0, 60.0);
+        }
+
+        if v.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(v.errors)
+        }
+    }
 }
 
 impl Default for ProjectConfiguration {
@@ -240,10 +1270,497 @@ impl Default for ProjectConfiguration {
                 ..Default::default()
             },
             camera: CameraConfiguration::default(),
+            extra_cameras: Vec::new(),
             audio: AudioConfiguration::default(),
             cursor: CursorConfiguration::default(),
             hotkeys: HotkeysConfiguration::default(),
             timeline: None,
+            color_grade: None,
+            captions: Vec::new(),
+            time_badge: None,
+            intro: None,
+            outro: None,
+            reframe: None,
+            markers: Vec::new(),
+            split_screen: None,
+            spotlight: None,
+            motion_blur: None,
+            audio_visualizer: None,
+            export_presets: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(json: serde_json::Value) -> RecordingMeta {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn default_for_plays_the_recording_segments_back_to_back() {
+        let meta = meta(serde_json::json!({
+            "pretty_name": "Recording",
+            "display": { "path": "display.mp4" },
+            "segments": [{ "start": 0.0, "end": 2.0 }, { "start": 5.0, "end": 6.5 }],
+        }));
+
+        let timeline = ProjectConfiguration::default_for(&meta).timeline.unwrap();
+
+        assert_eq!(timeline.segments.len(), 2);
+        assert_eq!(
+            (timeline.segments[1].start, timeline.segments[1].end),
+            (5.0, 6.5)
+        );
+        assert!(timeline.segments.iter().all(|s| s.timescale == 1.0));
+        assert!(timeline.speed_segments.is_empty() && timeline.holds.is_empty());
+    }
+
+    #[test]
+    fn default_for_has_no_timeline_without_segments() {
+        let meta = meta(serde_json::json!({
+            "pretty_name": "Recording",
+            "display": { "path": "display.mp4" },
+        }));
+
+        assert!(ProjectConfiguration::default_for(&meta).timeline.is_none());
+    }
+
+    #[test]
+    fn default_for_hides_the_camera_only_without_one() {
+        let without_camera = meta(serde_json::json!({
+            "pretty_name": "Recording",
+            "display": { "path": "display.mp4" },
+        }));
+        let with_cameras = meta(serde_json::json!({
+            "pretty_name": "Recording",
+            "display": { "path": "display.mp4" },
+            "camera": { "path": "camera.mp4" },
+            "extra_cameras": [{ "path": "camera-1.mp4" }, { "path": "camera-2.mp4" }],
+        }));
+
+        assert!(
+            ProjectConfiguration::default_for(&without_camera)
+                .camera
+                .hide
+        );
+
+        let project = ProjectConfiguration::default_for(&with_cameras);
+        assert!(!project.camera.hide);
+        assert_eq!(project.extra_cameras.len(), 2);
+    }
+
+    #[test]
+    fn validate_accepts_the_default_configuration() {
+        assert_eq!(ProjectConfiguration::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_reports_every_problem() {
+        let mut config = ProjectConfiguration::default();
+        config.background.padding = 150.0;
+        config.timeline = Some(TimelineConfiguration {
+            segments: vec![TimelineSegment {
+                timescale: 0.0,
+                start: 4.0,
+                end: 2.0,
+            }],
+            speed_segments: vec![
+                SpeedSegment {
+                    range: (5.0, 6.0),
+                    factor: 2.0,
+                },
+                SpeedSegment {
+                    range: (1.0, 2.0),
+                    factor: 2.0,
+                },
+            ],
+            holds: Vec::new(),
+        });
+
+        let errors = config.validate().unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec![
+                ConfigError::OutOfRange {
+                    field: "background.padding".into(),
+                    value: 150.0,
+                    min: 0.0,
+                    max: 100.0,
+                },
+                ConfigError::Unordered {
+                    field: "timeline.segments[0]".into(),
+                    start: 4.0,
+                    end: 2.0,
+                },
+                ConfigError::NotPositive {
+                    field: "timeline.segments[0].timescale".into(),
+                    value: 0.0,
+                },
+                ConfigError::Unsorted {
+                    field: "timeline.speedSegments".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_reports_missing_files() {
+        let mut config = ProjectConfiguration::default();
+        config.color_grade = Some(ColorGrade::Lut {
+            path: "/nonexistent/grade.cube".into(),
+        });
+
+        assert_eq!(
+            config.validate(),
+            Err(vec![ConfigError::MissingFile {
+                field: "colorGrade.path".into(),
+                path: "/nonexistent/grade.cube".into(),
+            }])
+        );
+    }
+
+    #[test]
+    fn validate_reports_an_out_of_order_trim_and_a_missing_background_image() {
+        let mut config = ProjectConfiguration::default();
+        config.background.source = BackgroundSource::Image {
+            path: Some("/nonexistent/background.png".into()),
+        };
+        config.timeline = Some(TimelineConfiguration {
+            segments: vec![
+                TimelineSegment {
+                    timescale: 1.0,
+                    start: 0.0,
+                    end: 2.0,
+                },
+                TimelineSegment {
+                    timescale: 1.0,
+                    start: 6.0,
+                    end: 3.0,
+                },
+            ],
+            speed_segments: Vec::new(),
+            holds: Vec::new(),
+        });
+
+        assert_eq!(
+            config.validate(),
+            Err(vec![
+                ConfigError::MissingFile {
+                    field: "background.source.path".into(),
+                    path: "/nonexistent/background.png".into(),
+                },
+                ConfigError::Unordered {
+                    field: "timeline.segments[1]".into(),
+                    start: 6.0,
+                    end: 3.0,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn validate_reports_overlapping_speed_segments() {
+        let mut config = ProjectConfiguration::default();
+        config.timeline = Some(TimelineConfiguration {
+            segments: vec![TimelineSegment {
+                timescale: 1.0,
+                start: 0.0,
+                end: 10.0,
+            }],
+            speed_segments: vec![
+                SpeedSegment {
+                    range: (1.0, 4.0),
+                    factor: 2.0,
+                },
+                SpeedSegment {
+                    range: (3.0, 5.0),
+                    factor: 2.0,
+                },
+                SpeedSegment {
+                    range: (5.0, 6.0),
+                    factor: 2.0,
+                },
+            ],
+            holds: Vec::new(),
+        });
+
+        assert_eq!(
+            config.validate(),
+            Err(vec![ConfigError::Overlapping {
+                field: "timeline.speedSegments[1]".into(),
+                other: "timeline.speedSegments[0]".into(),
+            }])
+        );
+    }
+
+    #[test]
+    fn validate_reports_holds_and_fades_that_dont_fit_the_timeline() {
+        let mut config = ProjectConfiguration::default();
+        config.audio.fade_in_ms = 500;
+        config.audio.fade_out_ms = 3000;
+        config.timeline = Some(TimelineConfiguration {
+            segments: vec![TimelineSegment {
+                timescale: 1.0,
+                start: 0.0,
+                end: 2.0,
+            }],
+            speed_segments: Vec::new(),
+            holds: vec![
+                Hold {
+                    frame: 30,
+                    duration_frames: 0,
+                },
+                Hold {
+                    frame: 90,
+                    duration_frames: 15,
+                },
+            ],
+        });
+
+        // the second hold is ignored as it's off the timeline, so it's 2s long
+        assert_eq!(
+            config.validate(),
+            Err(vec![
+                ConfigError::NotPositive {
+                    field: "timeline.holds[0].durationFrames".into(),
+                    value: 0.0,
+                },
+                ConfigError::OffTimeline {
+                    field: "timeline.holds[1]".into(),
+                    time: 3.0,
+                },
+                ConfigError::OutOfRange {
+                    field: "audio.fadeOutMs".into(),
+                    value: 3000.0,
+                    min: 0.0,
+                    max: 2000.0,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn validate_reports_noise_gate_and_normalization_out_of_range() {
+        let mut config = ProjectConfiguration::default();
+        config.audio.noise_gate = Some(NoiseGate { threshold_db: 6.0 });
+        config.audio.normalize_loudness = Some(LoudnessNormalization { target_lufs: -90.0 });
+
+        assert_eq!(
+            config.validate(),
+            Err(vec![
+                ConfigError::OutOfRange {
+                    field: "audio.noiseGate.thresholdDb".into(),
+                    value: 6.0,
+                    min: -100.0,
+                    max: 0.0,
+                },
+                ConfigError::OutOfRange {
+                    field: "audio.normalizeLoudness.targetLufs".into(),
+                    value: -90.0,
+                    min: -70.0,
+                    max: 0.0,
+                },
+            ])
+        );
+    }
+
+    fn held_timeline(holds: Vec<Hold>) -> TimelineConfiguration {
+        TimelineConfiguration {
+            segments: vec![TimelineSegment {
+                timescale: 1.0,
+                start: 0.0,
+                end: 4.0,
+            }],
+            speed_segments: Vec::new(),
+            holds,
+        }
+    }
+
+    #[test]
+    fn hold_extends_the_timeline_by_its_frames() {
+        let timeline = held_timeline(vec![Hold {
+            frame: 60,
+            duration_frames: 30,
+        }]);
+
+        assert_eq!(timeline.duration(), 5.0);
+        assert_eq!(held_timeline(Vec::new()).duration(), 4.0);
+    }
+
+    #[test]
+    fn held_frames_all_show_the_held_frame() {
+        let timeline = held_timeline(vec![Hold {
+            frame: 60,
+            duration_frames: 30,
+        }]);
+        let recording_time = |frame: u32| {
+            timeline
+                .get_recording_time(frame as f64 / TIMELINE_FPS as f64)
+                .unwrap()
+        };
+
+        for frame in 60..90 {
+            assert!(timeline.is_held(frame as f64 / TIMELINE_FPS as f64));
+            assert_eq!(recording_time(frame), 2.0);
+        }
+
+        // playback carries on from the held frame once the hold ends
+        assert!(!timeline.is_held(59.0 / TIMELINE_FPS as f64));
+        assert!(!timeline.is_held(90.0 / TIMELINE_FPS as f64));
+        assert_eq!(recording_time(90), 2.0);
+        assert_eq!(recording_time(120), 3.0);
+    }
+
+    #[test]
+    fn holds_off_the_timeline_or_without_frames_are_ignored() {
+        let timeline = held_timeline(vec![
+            Hold {
+                frame: 150,
+                duration_frames: 30,
+            },
+            Hold {
+                frame: 30,
+                duration_frames: 0,
+            },
+        ]);
+
+        assert_eq!(timeline.duration(), 4.0);
+        assert!(!timeline.is_held(1.0));
+    }
+
+    #[test]
+    fn hold_durations_are_counted_in_timeline_frames() {
+        let hold = Hold {
+            frame: 45,
+            duration_frames: 15,
+        };
+
+        assert_eq!(hold.time(), 1.5);
+        assert_eq!(hold.duration(), 0.5);
+    }
+
+    fn with_cards(intro_frames: Option<u32>, outro_frames: Option<u32>) -> ProjectConfiguration {
+        let card = |duration_frames| Card {
+            image: "card.png".to_string(),
+            duration_frames,
+        };
+
+        ProjectConfiguration {
+            intro: intro_frames.map(card),
+            outro: outro_frames.map(card),
+            ..Default::default()
         }
     }
+
+    #[test]
+    fn intro_extends_the_timeline_by_its_frames() {
+        let project = with_cards(Some(15), None);
+
+        assert_eq!(project.total_duration(4.0), 4.5);
+        assert_eq!(with_cards(None, None).total_duration(4.0), 4.0);
+    }
+
+    #[test]
+    fn cards_extend_the_timeline_rather_than_the_recording() {
+        let mut project = with_cards(Some(15), Some(30));
+        project.timeline = Some(TimelineConfiguration {
+            segments: vec![TimelineSegment {
+                timescale: 1.0,
+                start: 1.0,
+                end: 3.0,
+            }],
+            speed_segments: Vec::new(),
+            holds: Vec::new(),
+        });
+
+        // the timeline's own duration is used over the recording's
+        assert_eq!(project.total_duration(10.0), 3.5);
+    }
+
+    #[test]
+    fn first_frames_show_the_intro() {
+        let project = with_cards(Some(15), None);
+        let position = |frame: u32| project.timeline_position(frame as f64 / 30.0, 4.0);
+
+        for frame in 0..15 {
+            assert_eq!(position(frame), Some(TimelinePosition::Intro));
+        }
+        assert_eq!(position(15), Some(TimelinePosition::Recording(0.0)));
+        assert_eq!(position(45), Some(TimelinePosition::Recording(1.0)));
+    }
+
+    #[test]
+    fn last_frames_show_the_outro_until_the_end() {
+        let project = with_cards(None, Some(30));
+
+        assert_eq!(
+            project.timeline_position(3.5, 4.0),
+            Some(TimelinePosition::Recording(3.5))
+        );
+        assert_eq!(
+            project.timeline_position(4.0, 4.0),
+            Some(TimelinePosition::Outro)
+        );
+        assert_eq!(
+            project.timeline_position(4.9, 4.0),
+            Some(TimelinePosition::Outro)
+        );
+        assert_eq!(project.timeline_position(5.0, 4.0), None);
+    }
+
+    /// Four seconds of recording with the middle two played at `factor` times speed.
+    fn sped_up(factor: f64) -> TimelineConfiguration {
+        TimelineConfiguration {
+            segments: vec![TimelineSegment {
+                timescale: 1.0,
+                start: 0.0,
+                end: 4.0,
+            }],
+            speed_segments: vec![SpeedSegment {
+                range: (1.0, 3.0),
+                factor,
+            }],
+            holds: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn speed_segments_split_the_segments_they_overlap() {
+        let pieces = sped_up(2.0)
+            .speed_pieces()
+            .into_iter()
+            .map(|(index, piece)| (index, piece.start, piece.end, piece.timescale))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            pieces,
+            vec![(0, 0.0, 1.0, 1.0), (0, 1.0, 3.0, 2.0), (0, 3.0, 4.0, 1.0)]
+        );
+    }
+
+    #[test]
+    fn double_speed_segment_shortens_the_timeline() {
+        assert_eq!(sped_up(2.0).duration(), 3.0);
+        assert_eq!(sped_up(0.5).duration(), 6.0);
+    }
+
+    #[test]
+    fn recording_time_advances_faster_in_a_sped_up_segment() {
+        let timeline = sped_up(2.0);
+
+        assert_eq!(timeline.get_recording_time(0.5), Some(0.5));
+        assert_eq!(timeline.get_recording_time(1.5), Some(2.0));
+        assert_eq!(timeline.get_recording_time(2.5), Some(3.5));
+        assert_eq!(timeline.get_recording_time(3.5), None);
+    }
+
+    #[test]
+    fn invalid_speed_factors_are_ignored() {
+        assert_eq!(sped_up(0.0).duration(), 4.0);
+        assert_eq!(sped_up(f64::NAN).duration(), 4.0);
+    }
 }
@@ -0,0 +1,499 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use crate::{AspectRatio, ProjectConfiguration};
+
+#[derive(Type, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum VideoCodec {
+    #[default]
+    H264,
+    H265,
+    Vp9,
+    Av1,
+}
+
+impl VideoCodec {
+    /// Name of the FFmpeg encoder used for this codec.
+    pub fn encoder(&self) -> &'static str {
+        match self {
+            Self::H264 => "libx264",
+            Self::H265 => "libx265",
+            Self::Vp9 => "libvpx-vp9",
+            Self::Av1 => "libsvtav1",
+        }
+    }
+
+    pub fn max_crf(&self) -> u32 {
+        match self {
+            Self::H264 | Self::H265 => 51,
+            Self::Vp9 | Self::Av1 => 63,
+        }
+    }
+}
+
+/// Whether exports may encode on a GPU's video encoder instead of the CPU.
+#[derive(Type, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum HardwareAcceleration {
+    /// Always encodes on the CPU.
+    #[default]
+    Off,
+    /// Uses a hardware encoder for the codec if one works on this system, and the CPU otherwise.
+    /// Much faster on long recordings, though files are a little larger at the same quality.
+    Auto,
+}
+
+/// Platform video encoders that run on dedicated hardware.
+#[derive(Type, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum HardwareEncoder {
+    /// Apple's encoder on macOS.
+    VideoToolbox,
+    /// NVIDIA GPUs.
+    Nvenc,
+    /// Intel Quick Sync Video on Intel GPUs.
+    Qsv,
+}
+
+impl HardwareEncoder {
+    /// Hardware encoders that might be available on this platform, in order of preference.
+    pub fn for_platform() -> &'static [Self] {
+        if cfg!(target_os = "macos") {
+            &[Self::VideoToolbox]
+        } else {
+            &[Self::Nvenc, Self::Qsv]
+        }
+    }
+
+    /// Name of this hardware's FFmpeg encoder for `codec`, if it can encode it.
+    pub fn encoder(&self, codec: VideoCodec) -> Option<&'static str> {
+        match (self, codec) {
+            (Self::VideoToolbox, VideoCodec::H264) => Some("h264_videotoolbox"),
+            (Self::VideoToolbox, VideoCodec::H265) => Some("hevc_videotoolbox"),
+            (Self::Nvenc, VideoCodec::H264) => Some("h264_nvenc"),
+            (Self::Nvenc, VideoCodec::H265) => Some("hevc_nvenc"),
+            (Self::Nvenc, VideoCodec::Av1) => Some("av1_nvenc"),
+            (Self::Qsv, VideoCodec::H264) => Some("h264_qsv"),
+            (Self::Qsv, VideoCodec::H265) => Some("hevc_qsv"),
+            (Self::Qsv, VideoCodec::Vp9) => Some("vp9_qsv"),
+            (Self::Qsv, VideoCodec::Av1) => Some("av1_qsv"),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Type, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum RateControl {
+    /// Constant quality, where lower values are higher quality.
+    Crf { value: u32 },
+    /// Target average bitrate in kilobits per second.
+    Bitrate { kbps: u32 },
+}
+
+impl Default for RateControl {
+    fn default() -> Self {
+        Self::Crf { value: 23 }
+    }
+}
+
+/// Trades encoding speed for compression efficiency. Mapped to each encoder's own presets.
+#[derive(Type, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum EncoderPreset {
+    #[default]
+    Fast,
+    Balanced,
+    Quality,
+}
+
+/// What to do when the audio and video of an export don't last equally long.
+#[derive(Type, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum StreamLengthPolicy {
+    /// Pads short audio with silence and short video by holding its last frame,
+    /// so nothing recorded is lost.
+    #[default]
+    PadToLongest,
+    /// Ends the export as soon as either stream runs out.
+    TruncateToShortest,
+}
+
+/// Colour space exported video is encoded and tagged in.
+/// Frames are rendered in sRGB and converted while encoding.
+#[derive(Type, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ColorSpace {
+    /// BT.709 primaries with the sRGB transfer function, as frames are rendered.
+    #[default]
+    Srgb,
+    /// BT.709 primaries and transfer function, as broadcast and most video tools expect.
+    Rec709,
+    /// BT.2020 primaries for wide gamut workflows. Best exported with `BitDepth::Ten`.
+    Rec2020,
+}
+
+impl ColorSpace {
+    /// FFmpeg names of the colour primaries, transfer characteristics and matrix
+    /// the output is tagged with.
+    pub fn ffmpeg_tags(&self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            Self::Srgb => ("bt709", "iec61966-2-1", "bt709"),
+            Self::Rec709 => ("bt709", "bt709", "bt709"),
+            Self::Rec2020 => ("bt2020", "bt2020-10", "bt2020nc"),
+        }
+    }
+}
+
+#[derive(Type, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum BitDepth {
+    #[default]
+    Eight,
+    /// Reduces banding in gradients, but isn't supported by every player.
+    Ten,
+}
+
+impl BitDepth {
+    /// FFmpeg pixel format of the encoded video.
+    pub fn pix_fmt(&self) -> &'static str {
+        match self {
+            Self::Eight => "yuv420p",
+            Self::Ten => "yuv420p10le",
+        }
+    }
+}
+
+// arguments that exports set themselves: inputs, output formats and codecs.
+// matched before any stream specifier, so `-c:v:0` counts as `-c`
+const MANAGED_FFMPEG_ARGS: &[&str] =
+    &["-i", "-f", "-y", "-n", "-c", "-codec", "-vcodec", "-acodec"];
+
+#[derive(Type, Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportConfig {
+    #[serde(default)]
+    pub codec: VideoCodec,
+    #[serde(default)]
+    pub rate_control: RateControl,
+    #[serde(default)]
+    pub preset: EncoderPreset,
+    #[serde(default)]
+    pub length_policy: StreamLengthPolicy,
+    #[serde(default)]
+    pub color_space: ColorSpace,
+    #[serde(default)]
+    pub bit_depth: BitDepth,
+    #[serde(default)]
+    pub hardware_acceleration: HardwareAcceleration,
+    /// Keeps the transparency of a `BackgroundSource::Transparent` background. Of the
+    /// codecs only VP9 on the CPU can encode it, so these exports are written as WebM.
+    #[serde(default)]
+    pub alpha: bool,
+    /// Largest width and height of the exported video. Larger renders are scaled down to
+    /// fit, keeping their aspect ratio, and smaller ones are left as they are.
+    #[serde(default)]
+    pub max_size: Option<(u32, u32)>,
+    /// An escape hatch for FFmpeg options that aren't otherwise exposed, appended to the
+    /// output's arguments after those generated from the settings above. Only arguments
+    /// that would change the export's inputs, output format or codecs are rejected, so
+    /// anything else is passed through as is and can still make the export fail.
+    #[serde(default)]
+    pub extra_ffmpeg_args: Vec<String>,
+}
+
+impl ExportConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        match self.rate_control {
+            RateControl::Crf { value } if value > self.codec.max_crf() => Err(format!(
+                "CRF {value} is out of range for {:?}, the maximum is {}",
+                self.codec,
+                self.codec.max_crf()
+            )),
+            RateControl::Bitrate { kbps: 0 } => Err("Bitrate must be greater than 0".to_string()),
+            _ => Ok(()),
+        }?;
+
+        if let Some((0, _) | (_, 0)) = self.max_size {
+            return Err("Maximum export size must be greater than 0".to_string());
+        }
+
+        if self.alpha && (self.codec != VideoCodec::Vp9 || self.bit_depth != BitDepth::Eight) {
+            return Err("Exports with alpha must use 8 bit VP9".to_string());
+        }
+
+        self.validate_extra_ffmpeg_args()
+    }
+
+    /// FFmpeg muxer the export is written with and the audio encoder it's muxed with.
+    pub fn container(&self) -> (&'static str, &'static str) {
+        if self.alpha {
+            ("webm", "libopus")
+        } else {
+            ("mp4", "aac")
+        }
+    }
+
+    /// FFmpeg muxer options that let the export be written to a pipe, which can't be seeked
+    /// back in to finish the file. `None` if the container can only be written to a file.
+    pub fn stream_muxer_args(&self) -> Option<&'static [&'static str]> {
+        match self.container().0 {
+            // a regular MP4 is finalized by seeking back to its header, so streams are
+            // fragmented to be playable without ever going back
+            "mp4" => Some(&["-movflags", "frag_keyframe+empty_moov+default_base_moof"]),
+            // WebM just leaves out the seek index it would go back to write
+            "webm" => Some(&[]),
+            _ => None,
+        }
+    }
+
+    fn validate_extra_ffmpeg_args(&self) -> Result<(), String> {
+        let mut previous_was_option = false;
+
+        for arg in &self.extra_ffmpeg_args {
+            let is_option = arg.starts_with('-');
+
+            if is_option {
+                let name = arg.split(':').next().unwrap_or(arg);
+                if MANAGED_FFMPEG_ARGS.contains(&name) {
+                    return Err(format!(
+                        "Extra FFmpeg argument {arg} conflicts with one the export sets itself"
+                    ));
+                }
+            } else if !previous_was_option {
+                // FFmpeg takes a value that doesn't follow an option as another output
+                return Err(format!(
+                    "Extra FFmpeg argument {arg} isn't an option or an option's value"
+                ));
+            }
+
+            previous_was_option = is_option;
+        }
+
+        Ok(())
+    }
+}
+
+/// Export settings saved under a name, so the same kind of export can be repeated without
+/// configuring it each time. The file format follows from `config`, see `ExportConfig::container`.
+#[derive(Type, Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportPreset {
+    pub name: String,
+    /// Frame rate of the export. The recording's own frame rate when `None`.
+    #[serde(default)]
+    pub fps: Option<u32>,
+    /// Overrides the project's aspect ratio for the export when set.
+    #[serde(default)]
+    pub aspect_ratio: Option<AspectRatio>,
+    #[serde(default)]
+    pub config: ExportConfig,
+}
+
+impl ExportPreset {
+    /// Presets available in every project, after those saved in the project itself.
+    pub fn built_in() -> Vec<Self> {
+        vec![
+            Self {
+                name: "Web 1080p".to_string(),
+                fps: Some(30),
+                aspect_ratio: None,
+                config: ExportConfig {
+                    rate_control: RateControl::Bitrate { kbps: 8_000 },
+                    preset: EncoderPreset::Balanced,
+                    max_size: Some((1920, 1080)),
+                    ..Default::default()
+                },
+            },
+            Self {
+                name: "Social 9:16".to_string(),
+                fps: Some(30),
+                aspect_ratio: Some(AspectRatio::Vertical),
+                config: ExportConfig {
+                    rate_control: RateControl::Bitrate { kbps: 6_000 },
+                    preset: EncoderPreset::Balanced,
+                    max_size: Some((1080, 1920)),
+                    ..Default::default()
+                },
+            },
+            // lossless apart from the chroma subsampling every export is encoded with
+            Self {
+                name: "Lossless".to_string(),
+                fps: None,
+                aspect_ratio: None,
+                config: ExportConfig {
+                    rate_control: RateControl::Crf { value: 0 },
+                    preset: EncoderPreset::Quality,
+                    ..Default::default()
+                },
+            },
+        ]
+    }
+
+    /// The preset called `name`, looking in `project`'s saved presets before the built in ones.
+    pub fn find(project: &ProjectConfiguration, name: &str) -> Option<Self> {
+        project
+            .export_presets
+            .iter()
+            .cloned()
+            .chain(Self::built_in())
+            .find(|preset| preset.name == name)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.fps == Some(0) {
+            return Err(format!("Export preset {} has a frame rate of 0", self.name));
+        }
+
+        self.config
+            .validate()
+            .map_err(|e| format!("Export preset {}: {e}", self.name))
+    }
+
+    /// `project` with the preset's aspect ratio applied, ready to be exported.
+    pub fn apply(&self, mut project: ProjectConfiguration) -> ProjectConfiguration {
+        if let Some(aspect_ratio) = &self.aspect_ratio {
+            project.aspect_ratio = Some(aspect_ratio.clone());
+        }
+
+        project
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_extra_args(args: &[&str]) -> ExportConfig {
+        ExportConfig {
+            extra_ffmpeg_args: args.iter().map(|arg| arg.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn validate_accepts_extra_options_and_their_values() {
+        let config =
+            with_extra_args(&["-movflags", "+faststart", "-an", "-metadata", "title=Demo"]);
+
+        assert_eq!(config.validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_rejects_extra_args_the_export_sets_itself() {
+        for arg in ["-i", "-f", "-y", "-c:v", "-codec:a:0", "-vcodec"] {
+            let config = with_extra_args(&[arg, "value"]);
+
+            assert!(
+                config.validate().unwrap_err().contains("conflicts"),
+                "{arg} was accepted"
+            );
+        }
+    }
+
+    #[test]
+    fn validate_rejects_extra_outputs() {
+        let config = with_extra_args(&["other.mp4"]);
+
+        assert!(config.validate().unwrap_err().contains("isn't an option"));
+    }
+
+    #[test]
+    fn validate_checks_the_rate_control_against_the_codec() {
+        let config = |codec, rate_control| ExportConfig {
+            codec,
+            rate_control,
+            ..Default::default()
+        };
+
+        assert!(config(VideoCodec::H264, RateControl::Crf { value: 51 })
+            .validate()
+            .is_ok());
+        assert!(config(VideoCodec::H264, RateControl::Crf { value: 52 })
+            .validate()
+            .is_err());
+        assert!(config(VideoCodec::Vp9, RateControl::Crf { value: 63 })
+            .validate()
+            .is_ok());
+        assert!(config(VideoCodec::H264, RateControl::Bitrate { kbps: 0 })
+            .validate()
+            .is_err());
+        assert!(config(VideoCodec::H264, RateControl::Bitrate { kbps: 1 })
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn exports_default_to_h264() {
+        assert_eq!(ExportConfig::default().codec, VideoCodec::H264);
+    }
+
+    #[test]
+    fn stream_muxer_args_fragment_mp4() {
+        let mp4 = ExportConfig::default();
+        let webm = ExportConfig {
+            codec: VideoCodec::Vp9,
+            alpha: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            mp4.stream_muxer_args(),
+            Some(&["-movflags", "frag_keyframe+empty_moov+default_base_moof"][..])
+        );
+        assert_eq!(webm.stream_muxer_args(), Some(&[][..]));
+    }
+
+    #[test]
+    fn built_in_presets_are_valid() {
+        for preset in ExportPreset::built_in() {
+            assert_eq!(preset.validate(), Ok(()), "{}", preset.name);
+        }
+    }
+
+    #[test]
+    fn applying_a_built_in_preset_uses_its_definition() {
+        let project = ProjectConfiguration::default();
+        let preset = ExportPreset::find(&project, "Social 9:16").unwrap();
+
+        let config = &preset.config;
+        assert_eq!(preset.fps, Some(30));
+        assert_eq!(config.codec, VideoCodec::H264);
+        assert_eq!(config.rate_control, RateControl::Bitrate { kbps: 6_000 });
+        assert_eq!(config.preset, EncoderPreset::Balanced);
+        assert_eq!(config.max_size, Some((1080, 1920)));
+        assert!(!config.alpha && config.extra_ffmpeg_args.is_empty());
+        assert_eq!(config.container(), ("mp4", "aac"));
+
+        let project = preset.apply(project);
+        assert!(matches!(project.aspect_ratio, Some(AspectRatio::Vertical)));
+    }
+
+    #[test]
+    fn project_presets_shadow_built_in_ones() {
+        let mut project = ProjectConfiguration::default();
+        project.export_presets.push(ExportPreset {
+            name: "Lossless".to_string(),
+            fps: Some(60),
+            aspect_ratio: None,
+            config: ExportConfig::default(),
+        });
+
+        let preset = ExportPreset::find(&project, "Lossless").unwrap();
+
+        assert_eq!(preset.fps, Some(60));
+        assert!(ExportPreset::find(&project, "Missing").is_none());
+    }
+
+    #[test]
+    fn presets_without_an_aspect_ratio_keep_the_projects() {
+        let mut project = ProjectConfiguration::default();
+        project.aspect_ratio = Some(AspectRatio::Square);
+        let preset = ExportPreset::find(&project, "Web 1080p").unwrap();
+
+        let project = preset.apply(project);
+
+        assert!(matches!(project.aspect_ratio, Some(AspectRatio::Square)));
+    }
+}
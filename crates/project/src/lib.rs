@@ -1,14 +1,27 @@
 mod configuration;
+mod export;
+mod migrations;
 
 use std::path::PathBuf;
 
 pub use configuration::*;
+pub use export::*;
+pub use migrations::RECORDING_META_VERSION;
 use serde::{Deserialize, Serialize};
 use specta::Type;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Type)]
 pub struct Display {
     pub path: PathBuf,
+    // long recordings may continue in further files, played back in order after `path`
+    #[serde(default)]
+    pub extra_segments: Vec<PathBuf>,
+}
+
+impl Display {
+    pub fn paths(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.path).chain(self.extra_segments.iter())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Type)]
@@ -38,12 +51,18 @@ pub struct RecordingMeta {
     // this field is just for convenience, it shouldn't be persisted
     #[serde(skip_serializing, default)]
     pub project_path: PathBuf,
+    // older metas are migrated on load, see `migrations`
+    #[serde(default)]
+    pub version: u32,
     pub pretty_name: String,
     #[serde(default)]
     pub sharing: Option<SharingMeta>,
     pub display: Display,
     #[serde(default)]
     pub camera: Option<CameraMeta>,
+    // cameras recorded alongside the primary one, composited after it in order
+    #[serde(default)]
+    pub extra_cameras: Vec<CameraMeta>,
     #[serde(default)]
     pub audio: Option<AudioMeta>,
     #[serde(default)]
@@ -58,22 +77,31 @@ impl RecordingMeta {
             Err(_) => {
                 return Ok(Self {
                     project_path: project_path.clone(),
+                    version: RECORDING_META_VERSION,
                     pretty_name: String::new(),
                     sharing: None,
                     display: Display {
                         path: PathBuf::new(),
+                        extra_segments: Vec::new(),
                     },
                     camera: None,
+                    extra_cameras: Vec::new(),
                     audio: None,
                     segments: Vec::new(),
                 });
             }
         };
-        let mut meta: Self = serde_json::from_str(&meta).map_err(|e| e.to_string())?;
+        let mut meta: serde_json::Value = serde_json::from_str(&meta).map_err(|e| e.to_string())?;
+        migrations::migrate_recording_meta(&mut meta)?;
+        let mut meta: Self = serde_json::from_value(meta).map_err(|e| e.to_string())?;
         meta.project_path = project_path.clone();
         Ok(meta)
     }
 
+    pub fn cameras(&self) -> impl Iterator<Item = &CameraMeta> {
+        self.camera.iter().chain(self.extra_cameras.iter())
+    }
+
     pub fn save_for_project(&self) {
         let meta_path = &self.project_path.join("recording-meta.json");
         let meta = serde_json::to_string_pretty(&self).unwrap();
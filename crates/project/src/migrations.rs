@@ -0,0 +1,161 @@
+use serde_json::{json, Map, Value};
+
+/// Version written to `recording-meta.json` by this build.
+/// Metas without a `version` field predate versioning and are treated as version 0.
+pub const RECORDING_META_VERSION: u32 = 1;
+
+type Migration = fn(&mut Map<String, Value>);
+
+// MIGRATIONS[n] upgrades a version n meta to version n + 1
+const MIGRATIONS: [Migration; RECORDING_META_VERSION as usize] = [upgrade_unversioned];
+
+/// Upgrades a parsed `recording-meta.json` to the current structure in place.
+pub(crate) fn migrate_recording_meta(meta: &mut Value) -> Result<(), String> {
+    let meta = meta
+        .as_object_mut()
+        .ok_or("recording meta is not an object")?;
+
+    let version = match meta.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or("recording meta has an invalid version")?,
+    };
+
+    if version > RECORDING_META_VERSION {
+        return Err(format!(
+            "recording meta version {version} is newer than supported version {RECORDING_META_VERSION}"
+        ));
+    }
+
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        migration(meta);
+        tracing::info!(
+            "Migrated recording meta from version {from} to {}",
+            from + 1
+        );
+    }
+
+    meta.insert("version".to_string(), json!(RECORDING_META_VERSION));
+
+    Ok(())
+}
+
+fn upgrade_unversioned(meta: &mut Map<String, Value>) {
+    expand_file_paths(meta);
+    fill_multi_file_defaults(meta);
+}
+
+// the earliest metas stored each file as a bare path string rather than an object
+fn expand_file_paths(meta: &mut Map<String, Value>) {
+    for field in ["display", "camera", "audio"] {
+        if let Some(path) = meta.get_mut(field).filter(|value| value.is_string()) {
+            *path = json!({ "path": path.take() });
+        }
+    }
+}
+
+// version 0 metas were written before recordings could span multiple files
+// or cameras, or be split into segments
+fn fill_multi_file_defaults(meta: &mut Map<String, Value>) {
+    if let Some(display) = meta.get_mut("display").and_then(Value::as_object_mut) {
+        display.entry("extra_segments").or_insert_with(|| json!([]));
+    }
+
+    meta.entry("extra_cameras").or_insert_with(|| json!([]));
+    meta.entry("segments").or_insert_with(|| json!([]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RecordingMeta;
+    use std::path::PathBuf;
+
+    #[test]
+    fn migrates_unversioned_metas_to_the_current_version() {
+        let mut meta = json!({
+            "pretty_name": "Recording",
+            "display": { "path": "content/display.mp4" },
+        });
+
+        migrate_recording_meta(&mut meta).unwrap();
+
+        assert_eq!(
+            meta,
+            json!({
+                "pretty_name": "Recording",
+                "display": { "path": "content/display.mp4", "extra_segments": [] },
+                "extra_cameras": [],
+                "segments": [],
+                "version": RECORDING_META_VERSION,
+            })
+        );
+    }
+
+    #[test]
+    fn loads_metas_with_bare_file_paths() {
+        let mut meta = json!({
+            "pretty_name": "Cap 2024-03-01",
+            "display": "content/display.mp4",
+            "camera": "content/camera.mp4",
+            "audio": null,
+        });
+        assert!(serde_json::from_value::<RecordingMeta>(meta.clone()).is_err());
+
+        migrate_recording_meta(&mut meta).unwrap();
+        let meta: RecordingMeta = serde_json::from_value(meta).unwrap();
+
+        assert_eq!(meta.version, RECORDING_META_VERSION);
+        assert_eq!(meta.display.path, PathBuf::from("content/display.mp4"));
+        assert!(meta.display.extra_segments.is_empty());
+        assert_eq!(
+            meta.camera.map(|camera| camera.path),
+            Some(PathBuf::from("content/camera.mp4"))
+        );
+        assert!(meta.audio.is_none());
+        assert!(meta.extra_cameras.is_empty());
+    }
+
+    #[test]
+    fn keeps_fields_older_metas_already_have() {
+        let mut meta = json!({
+            "display": { "path": "display.mp4", "extra_segments": ["display-1.mp4"] },
+            "segments": [{ "start": 0.0, "end": 1.0 }],
+        });
+
+        migrate_recording_meta(&mut meta).unwrap();
+
+        assert_eq!(meta["display"]["extra_segments"], json!(["display-1.mp4"]));
+        assert_eq!(meta["segments"], json!([{ "start": 0.0, "end": 1.0 }]));
+    }
+
+    #[test]
+    fn leaves_current_metas_unchanged() {
+        let original = json!({
+            "display": { "path": "display.mp4" },
+            "version": RECORDING_META_VERSION,
+        });
+        let mut meta = original.clone();
+
+        migrate_recording_meta(&mut meta).unwrap();
+
+        assert_eq!(meta, original);
+    }
+
+    #[test]
+    fn rejects_newer_metas() {
+        let mut meta = json!({ "version": RECORDING_META_VERSION + 1 });
+
+        let error = migrate_recording_meta(&mut meta).unwrap_err();
+        assert!(error.contains("newer than supported"), "{error}");
+    }
+
+    #[test]
+    fn rejects_invalid_versions_and_non_objects() {
+        assert!(migrate_recording_meta(&mut json!({ "version": "1" })).is_err());
+        assert!(migrate_recording_meta(&mut json!({ "version": -1 })).is_err());
+        assert!(migrate_recording_meta(&mut json!([])).is_err());
+    }
+}
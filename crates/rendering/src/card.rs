@@ -0,0 +1,55 @@
+use std::sync::Arc;
+
+use cap_project::{Card, ProjectConfiguration, TimelinePosition};
+use image::imageops::FilterType;
+
+use crate::decoder::DecodedFrame;
+
+/// A project's intro and outro card images, decoded at the screen recording's size
+/// so they can be composited in place of screen frames.
+#[derive(Clone, Default)]
+pub struct CardFrames {
+    intro: Option<DecodedFrame>,
+    outro: Option<DecodedFrame>,
+}
+
+impl CardFrames {
+    /// Cards whose image can't be loaded are shown as black frames.
+    pub fn load(project: &ProjectConfiguration, screen_size: (u32, u32)) -> Self {
+        Self {
+            intro: project
+                .intro
+                .as_ref()
+                .map(|card| load_card(card, screen_size)),
+            outro: project
+                .outro
+                .as_ref()
+                .map(|card| load_card(card, screen_size)),
+        }
+    }
+
+    /// The frame shown at `position`, or `None` if the recording is shown there.
+    pub fn get(&self, position: TimelinePosition) -> Option<DecodedFrame> {
+        match position {
+            TimelinePosition::Intro => self.intro.clone(),
+            TimelinePosition::Recording(_) => None,
+            TimelinePosition::Outro => self.outro.clone(),
+        }
+    }
+}
+
+fn load_card(card: &Card, (width, height): (u32, u32)) -> DecodedFrame {
+    match image::open(&card.image) {
+        // cropped rather than stretched when the aspect ratios differ
+        Ok(image) => Arc::new(
+            image
+                .resize_to_fill(width, height, FilterType::Lanczos3)
+                .into_rgba8()
+                .into_raw(),
+        ),
+        Err(e) => {
+            tracing::warn!("Failed to load card image {}: {e}", card.image);
+            Arc::new([0, 0, 0, 255].repeat((width * height) as usize))
+        }
+    }
+}
@@ -0,0 +1,137 @@
+/// A cursor position recorded `time` seconds into the recording, in frame pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CursorSample {
+    pub time: f64,
+    pub x: f64,
+    pub y: f64,
+}
+
+/// Cursor position at `time` between recorded samples, which must be sorted by time.
+/// `smoothing` blends from straight lines between samples at 0 to a Catmull-Rom curve
+/// through them at 1, so cursors recorded less often than the video still move every frame.
+/// The nearest sample is held before the first and after the last one.
+pub fn interpolate_cursor(
+    samples: &[CursorSample],
+    time: f64,
+    smoothing: f32,
+) -> Option<(f64, f64)> {
+    let next = samples.partition_point(|sample| sample.time <= time);
+
+    let (p1, p2) = match next {
+        0 => return samples.first().map(|s| (s.x, s.y)),
+        n if n == samples.len() => return samples.last().map(|s| (s.x, s.y)),
+        n => (samples[n - 1], samples[n]),
+    };
+
+    let span = p2.time - p1.time;
+    let t = if span > 0.0 {
+        (time - p1.time) / span
+    } else {
+        0.0
+    };
+
+    let linear = |a: f64, b: f64| a + (b - a) * t;
+    let position = (linear(p1.x, p2.x), linear(p1.y, p2.y));
+
+    let smoothing = smoothing.clamp(0.0, 1.0) as f64;
+    if smoothing == 0.0 {
+        return Some(position);
+    }
+
+    // the samples either side of the segment shape the curve, or its ends at the edges
+    let p0 = if next >= 2 { samples[next - 2] } else { p1 };
+    let p3 = samples.get(next + 1).copied().unwrap_or(p2);
+
+    let catmull_rom = |p0: f64, p1: f64, p2: f64, p3: f64| {
+        0.5 * (2.0 * p1
+            + (p2 - p0) * t
+            + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t * t
+            + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t * t * t)
+    };
+    let curve = (
+        catmull_rom(p0.x, p1.x, p2.x, p3.x),
+        catmull_rom(p0.y, p1.y, p2.y, p3.y),
+    );
+
+    Some((
+        position.0 + (curve.0 - position.0) * smoothing,
+        position.1 + (curve.1 - position.1) * smoothing,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(points: &[(f64, f64, f64)]) -> Vec<CursorSample> {
+        points
+            .iter()
+            .map(|&(time, x, y)| CursorSample { time, x, y })
+            .collect()
+    }
+
+    #[test]
+    fn frames_between_sparse_samples_are_interpolated_linearly() {
+        let samples = samples(&[(0.0, 0.0, 0.0), (1.0, 300.0, 150.0)]);
+
+        // samples once a second, drawn at 30fps
+        for frame in 0..=30 {
+            let (x, y) = interpolate_cursor(&samples, frame as f64 / 30.0, 0.0).unwrap();
+            assert!(
+                (x - frame as f64 * 10.0).abs() < 1e-9,
+                "frame {frame} at {x}"
+            );
+            assert!(
+                (y - frame as f64 * 5.0).abs() < 1e-9,
+                "frame {frame} at {y}"
+            );
+        }
+    }
+
+    #[test]
+    fn smoothed_cursor_passes_through_the_samples() {
+        let samples = samples(&[(0.0, 0.0, 0.0), (1.0, 100.0, 40.0), (2.0, 100.0, 0.0)]);
+
+        assert_eq!(interpolate_cursor(&samples, 1.0, 1.0), Some((100.0, 40.0)));
+        assert_eq!(interpolate_cursor(&samples, 2.0, 1.0), Some((100.0, 0.0)));
+    }
+
+    #[test]
+    fn smoothing_curves_the_path_around_corners() {
+        let samples = samples(&[
+            (0.0, 0.0, 0.0),
+            (1.0, 100.0, 0.0),
+            (2.0, 100.0, 0.0),
+            (3.0, 0.0, 0.0),
+        ]);
+
+        let linear = interpolate_cursor(&samples, 1.5, 0.0).unwrap();
+        let curved = interpolate_cursor(&samples, 1.5, 1.0).unwrap();
+        let halfway = interpolate_cursor(&samples, 1.5, 0.5).unwrap();
+
+        assert_eq!(linear.0, 100.0);
+        assert_eq!(curved.0, 112.5);
+        assert_eq!(halfway.0, 106.25);
+    }
+
+    #[test]
+    fn smoothing_keeps_evenly_spaced_straight_lines_straight() {
+        let samples = samples(&[
+            (0.0, 0.0, 0.0),
+            (1.0, 100.0, 0.0),
+            (2.0, 200.0, 0.0),
+            (3.0, 300.0, 0.0),
+        ]);
+
+        assert_eq!(interpolate_cursor(&samples, 1.5, 1.0), Some((150.0, 0.0)));
+    }
+
+    #[test]
+    fn cursor_holds_the_nearest_sample_outside_the_recording() {
+        let samples = samples(&[(1.0, 10.0, 20.0), (2.0, 30.0, 40.0)]);
+
+        assert_eq!(interpolate_cursor(&samples, 0.0, 1.0), Some((10.0, 20.0)));
+        assert_eq!(interpolate_cursor(&samples, 5.0, 1.0), Some((30.0, 40.0)));
+        assert_eq!(interpolate_cursor(&[], 1.0, 1.0), None);
+    }
+}
@@ -1,9 +1,12 @@
 use std::{
     cell::Cell,
     collections::BTreeMap,
-    path::PathBuf,
+    path::{Path, PathBuf},
     ptr::{null, null_mut},
-    sync::{mpsc, Arc},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, RwLock,
+    },
 };
 
 use ffmpeg::{
@@ -13,15 +16,118 @@ use ffmpeg::{
     rescale, Codec, Packet, Rational, Rescale, Stream,
 };
 use ffmpeg_sys_next::{
-    av_buffer_ref, av_buffer_unref, av_hwdevice_ctx_create, av_hwframe_transfer_data,
-    avcodec_find_decoder, avcodec_get_hw_config, AVBufferRef, AVCodecContext, AVHWDeviceType,
-    AVPixelFormat, AV_CODEC_HW_CONFIG_METHOD_HW_DEVICE_CTX,
+    av_buffer_ref, av_buffer_unref, av_display_rotation_get, av_hwdevice_ctx_create,
+    av_hwframe_transfer_data, av_packet_side_data_get, avcodec_find_decoder, avcodec_get_hw_config,
+    AVBufferRef, AVCodecContext, AVHWDeviceType, AVPacketSideDataType, AVPixelFormat,
+    AV_CODEC_HW_CONFIG_METHOD_HW_DEVICE_CTX,
 };
+use serde::{Deserialize, Serialize};
 
 pub type DecodedFrame = Arc<Vec<u8>>;
 
 enum VideoDecoderMessage {
-    GetFrame(u32, tokio::sync::oneshot::Sender<Option<Arc<Vec<u8>>>>),
+    GetFrame(
+        u32,
+        SeekMode,
+        tokio::sync::oneshot::Sender<Option<Arc<Vec<u8>>>>,
+    ),
+    SetCacheSize(usize),
+    // replies with the bytes freed
+    ClearCache(tokio::sync::oneshot::Sender<usize>),
+    SetFrameIndex(FrameIndex),
+}
+
+/// How precisely a frame request is served.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SeekMode {
+    /// Returns the requested frame, decoding forward from the preceding keyframe if needed.
+    #[default]
+    Exact,
+    /// Returns the nearest keyframe at or before the requested frame, which avoids
+    /// decoding intermediate frames. Good enough while scrubbing.
+    Keyframe,
+}
+
+/// Clockwise rotation a video's display matrix asks for before it's shown, as recorded
+/// by phones and some cameras. Decoded frames already have it applied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rotation {
+    #[default]
+    None,
+    Clockwise90,
+    Clockwise180,
+    Clockwise270,
+}
+
+impl Rotation {
+    pub fn from_stream(stream: &Stream) -> Self {
+        let degrees = unsafe {
+            let parameters = stream.parameters();
+            let parameters = &*parameters.as_ptr();
+            let side_data = av_packet_side_data_get(
+                parameters.coded_side_data,
+                parameters.nb_coded_side_data,
+                AVPacketSideDataType::AV_PKT_DATA_DISPLAYMATRIX,
+            );
+
+            if side_data.is_null() || (*side_data).size < 9 * 4 {
+                return Self::None;
+            }
+
+            // the matrix describes a counterclockwise rotation
+            -av_display_rotation_get((*side_data).data as *const i32)
+        };
+
+        if !degrees.is_finite() {
+            return Self::None;
+        }
+
+        match ((degrees / 90.0).round() as i32).rem_euclid(4) {
+            1 => Self::Clockwise90,
+            2 => Self::Clockwise180,
+            3 => Self::Clockwise270,
+            _ => Self::None,
+        }
+    }
+
+    /// Size of a `width` x `height` frame once rotated.
+    pub fn apply_to_size(&self, (width, height): (u32, u32)) -> (u32, u32) {
+        match self {
+            Self::Clockwise90 | Self::Clockwise270 => (height, width),
+            Self::None | Self::Clockwise180 => (width, height),
+        }
+    }
+
+    /// Rotates a tightly packed RGBA frame.
+    fn apply(&self, frame: Vec<u8>, width: usize, height: usize) -> Vec<u8> {
+        if *self == Self::None {
+            return frame;
+        }
+
+        let mut rotated = vec![0; frame.len()];
+
+        for (i, pixel) in frame.chunks_exact(4).enumerate() {
+            let (x, y) = (i % width, i / width);
+            let target = match self {
+                Self::Clockwise90 => x * height + (height - 1 - y),
+                Self::Clockwise180 => (height - 1 - y) * width + (width - 1 - x),
+                Self::Clockwise270 => (width - 1 - x) * height + y,
+                Self::None => i,
+            };
+            rotated[target * 4..target * 4 + 4].copy_from_slice(pixel);
+        }
+
+        rotated
+    }
+}
+
+/// Returns the last keyframe at or before `frame_number`, or `frame_number` itself
+/// if there is none.
+fn preceding_keyframe(keyframes: &[u32], frame_number: u32) -> u32 {
+    match keyframes.partition_point(|&k| k <= frame_number) {
+        0 => frame_number,
+        i => keyframes[i - 1],
+    }
 }
 
 fn ts_to_frame(ts: i64, time_base: Rational, frame_rate: Rational) -> u32 {
@@ -30,50 +136,376 @@ fn ts_to_frame(ts: i64, time_base: Rational, frame_rate: Rational) -> u32 {
         / (time_base.denominator() as i64 * frame_rate.denominator() as i64)) as u32
 }
 
-const FRAME_CACHE_SIZE: usize = 50;
+/// Where a video's frames and keyframes are, found by reading through all of its packets
+/// instead of trusting the container's own index, which can be missing or wrong in
+/// recordings that weren't finalized.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrameIndex {
+    pub frame_count: u32,
+    /// In ascending order of frame number.
+    pub keyframes: Vec<IndexedKeyframe>,
+}
 
-pub struct AsyncVideoDecoder;
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct IndexedKeyframe {
+    pub frame: u32,
+    /// Presentation timestamp in the stream's time base.
+    pub pts: i64,
+}
 
-impl AsyncVideoDecoder {
-    pub fn spawn(path: PathBuf) -> AsyncVideoDecoderHandle {
-        let (tx, rx) = mpsc::channel();
+impl FrameIndex {
+    /// Reads every packet of the video stream in `path` without decoding it, calling
+    /// `on_progress` with the fraction of the file read so far.
+    pub fn scan(path: &Path, mut on_progress: impl FnMut(f64)) -> Result<Self, DecoderError> {
+        if !path.exists() {
+            return Err(DecoderError::FileMissing(path.to_path_buf()));
+        }
 
-        std::thread::spawn(move || {
-            let mut input = ffmpeg::format::input(&path).unwrap();
+        let mut input = ffmpeg::format::input(&path).map_err(|e| DecoderError::corrupt(path, e))?;
 
-            let input_stream = input
+        let (stream_index, time_base, frame_rate) = {
+            let stream = input
                 .streams()
                 .best(ffmpeg::media::Type::Video)
-                .ok_or("Could not find a video stream")
-                .unwrap();
-
-            let decoder_codec =
-                ff_find_decoder(&input, &input_stream, input_stream.parameters().id()).unwrap();
+                .ok_or(DecoderError::corrupt(path, "no video stream found"))?;
+            (stream.index(), stream.time_base(), stream.rate())
+        };
 
-            let mut context = codec::context::Context::new_with_codec(decoder_codec);
-            context.set_parameters(input_stream.parameters()).unwrap();
+        let file_size = std::fs::metadata(path).map_or(0, |m| m.len()).max(1) as f64;
+        let mut reported = 0.0;
+        let mut frame_count = 0;
+        let mut keyframes = vec![];
 
-            let hw_device: Option<HwDevice> = {
-                #[cfg(target_os = "macos")]
-                {
-                    context
-                        .try_use_hw_device(
-                            AVHWDeviceType::AV_HWDEVICE_TYPE_VIDEOTOOLBOX,
-                            Pixel::NV12,
-                        )
-                        .ok()
-                }
+        for (stream, packet) in input.packets() {
+            if stream.index() != stream_index {
+                continue;
+            }
 
-                #[cfg(not(target_os = "macos"))]
-                None
+            let Some(pts) = packet.pts() else {
+                continue;
             };
 
-            let input_stream_index = input_stream.index();
-            let time_base = input_stream.time_base();
-            let frame_rate = input_stream.rate();
+            frame_count += 1;
+
+            if packet.is_key() {
+                keyframes.push(IndexedKeyframe {
+                    frame: ts_to_frame(pts - stream.start_time(), time_base, frame_rate),
+                    pts,
+                });
+            }
+
+            let progress = (packet.position().max(0) as f64 / file_size).min(1.0);
+            if progress - reported >= 0.01 {
+                reported = progress;
+                on_progress(progress);
+            }
+        }
+
+        if frame_count == 0 {
+            return Err(DecoderError::corrupt(path, "no video packets found"));
+        }
+
+        keyframes.sort_unstable_by_key(|k| k.frame);
+        keyframes.dedup_by_key(|k| k.frame);
+        on_progress(1.0);
+
+        Ok(Self {
+            frame_count,
+            keyframes,
+        })
+    }
+
+    /// The last keyframe at or before `frame_number`.
+    fn preceding_keyframe(&self, frame_number: u32) -> Option<IndexedKeyframe> {
+        match self.keyframes.partition_point(|k| k.frame <= frame_number) {
+            0 => None,
+            i => Some(self.keyframes[i - 1]),
+        }
+    }
+}
+
+/// Tuning for a video decoder.
+///
+/// A larger `cache_size` lets playback and scrubbing near the playhead be served without
+/// decoding, at the cost of memory: every cached frame is a full RGBA image, so 50 frames
+/// of 1080p video is around 400MB. Smaller caches save memory but decode more often.
+/// `threads` is passed to FFmpeg's frame threading. More threads decode faster on
+/// many-core machines, but each buffers its own frame so they add latency and memory.
+#[derive(Debug, Clone, Copy)]
+pub struct DecoderConfig {
+    /// Maximum number of decoded frames kept around the most recently requested frame.
+    pub cache_size: usize,
+    /// Number of FFmpeg decode threads, or 0 to let FFmpeg choose based on the CPU.
+    pub threads: usize,
+}
+
+impl Default for DecoderConfig {
+    fn default() -> Self {
+        Self {
+            cache_size: 50,
+            threads: 0,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DecoderError {
+    #[error("Video file {0} not found")]
+    FileMissing(PathBuf),
+    #[error("Video file {path} uses an unsupported codec: {reason}")]
+    UnsupportedCodec { path: PathBuf, reason: String },
+    #[error("Video file {path} is corrupt: {reason}")]
+    Corrupt { path: PathBuf, reason: String },
+}
+
+impl DecoderError {
+    fn unsupported(path: &Path, reason: impl ToString) -> Self {
+        Self::UnsupportedCodec {
+            path: path.to_path_buf(),
+            reason: reason.to_string(),
+        }
+    }
+
+    fn corrupt(path: &Path, reason: impl ToString) -> Self {
+        Self::Corrupt {
+            path: path.to_path_buf(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+struct OpenedVideo {
+    input: format::context::Input,
+    decoder: ffmpeg::decoder::Video,
+    hw_device: Option<HwDevice>,
+    stream_index: usize,
+    time_base: Rational,
+    frame_rate: Rational,
+    rotation: Rotation,
+    // frame numbers of the stream's keyframes, in ascending order
+    keyframes: Vec<u32>,
+}
+
+impl OpenedVideo {
+    fn open(path: &Path, config: DecoderConfig) -> Result<Self, DecoderError> {
+        if !path.exists() {
+            return Err(DecoderError::FileMissing(path.to_path_buf()));
+        }
+
+        let mut input = ffmpeg::format::input(&path).map_err(|e| DecoderError::corrupt(path, e))?;
+
+        let input_stream = input
+            .streams()
+            .best(ffmpeg::media::Type::Video)
+            .ok_or(DecoderError::corrupt(path, "no video stream found"))?;
+
+        let decoder_codec = ff_find_decoder(&input, &input_stream, input_stream.parameters().id())
+            .ok_or_else(|| {
+                DecoderError::unsupported(
+                    path,
+                    format!("no decoder for {:?}", input_stream.parameters().id()),
+                )
+            })?;
+
+        let mut context = codec::context::Context::new_with_codec(decoder_codec);
+        context
+            .set_parameters(input_stream.parameters())
+            .map_err(|e| DecoderError::corrupt(path, e))?;
+        context.set_threading(ffmpeg::threading::Config {
+            kind: ffmpeg::threading::Type::Frame,
+            count: config.threads,
+            ..Default::default()
+        });
+
+        let hw_device: Option<HwDevice> = {
+            #[cfg(target_os = "macos")]
+            {
+                context
+                    .try_use_hw_device(AVHWDeviceType::AV_HWDEVICE_TYPE_VIDEOTOOLBOX, Pixel::NV12)
+                    .ok()
+            }
+
+            #[cfg(not(target_os = "macos"))]
+            None
+        };
+
+        let stream_index = input_stream.index();
+        let time_base = input_stream.time_base();
+        let frame_rate = input_stream.rate();
+        let rotation = Rotation::from_stream(&input_stream);
+
+        // Create a decoder for the video stream
+        let decoder = context
+            .decoder()
+            .video()
+            .map_err(|e| DecoderError::unsupported(path, e))?;
+
+        let mut this = Self {
+            input,
+            decoder,
+            hw_device,
+            stream_index,
+            time_base,
+            frame_rate,
+            rotation,
+            keyframes: vec![],
+        };
+
+        this.probe_first_frame(path)?;
+        this.keyframes = this.scan_keyframes(path)?;
+
+        Ok(this)
+    }
+
+    /// Decodes until the first frame is produced so that files which open but
+    /// contain no usable video fail here instead of on every frame request.
+    fn probe_first_frame(&mut self, path: &Path) -> Result<(), DecoderError> {
+        let mut frame = frame::Video::empty();
+        let mut decoded = false;
+
+        for (stream, packet) in self.input.packets() {
+            if stream.index() != self.stream_index {
+                continue;
+            }
+
+            if self.decoder.send_packet(&packet).is_err() {
+                continue;
+            }
+
+            if self.decoder.receive_frame(&mut frame).is_ok() {
+                decoded = true;
+                break;
+            }
+        }
+
+        if !decoded {
+            self.decoder.send_eof().ok();
+            decoded = self.decoder.receive_frame(&mut frame).is_ok();
+        }
+
+        if !decoded {
+            return Err(DecoderError::corrupt(path, "no decodable video frames"));
+        }
+
+        if frame.pts().is_none() {
+            return Err(DecoderError::corrupt(
+                path,
+                "decoded frame has no timestamp",
+            ));
+        }
 
-            // Create a decoder for the video stream
-            let mut decoder = context.decoder().video().unwrap();
+        self.decoder.flush();
+        self.input
+            .seek(0, ..0)
+            .map_err(|e| DecoderError::corrupt(path, e))?;
+
+        Ok(())
+    }
+
+    /// Reads through the stream's packets without decoding them to find where its keyframes are.
+    fn scan_keyframes(&mut self, path: &Path) -> Result<Vec<u32>, DecoderError> {
+        let mut keyframes = vec![];
+
+        for (stream, packet) in self.input.packets() {
+            if stream.index() != self.stream_index || !packet.is_key() {
+                continue;
+            }
+
+            if let Some(pts) = packet.pts() {
+                keyframes.push(ts_to_frame(
+                    pts - stream.start_time(),
+                    self.time_base,
+                    self.frame_rate,
+                ));
+            }
+        }
+
+        keyframes.sort_unstable();
+        keyframes.dedup();
+
+        self.input
+            .seek(0, ..0)
+            .map_err(|e| DecoderError::corrupt(path, e))?;
+
+        Ok(keyframes)
+    }
+
+    fn frame_count(&self) -> u32 {
+        let duration_secs = self.input.duration() as f64 / 1_000_000.0;
+        (duration_secs * f64::from(self.frame_rate)).round() as u32
+    }
+}
+
+pub struct AsyncVideoDecoder;
+
+impl AsyncVideoDecoder {
+    /// Opens the video and decodes its first frame before handing back a handle,
+    /// so missing, unsupported and corrupt files are reported up front.
+    pub async fn spawn(path: PathBuf) -> Result<AsyncVideoDecoderHandle, DecoderError> {
+        Self::spawn_segments(vec![path], DecoderConfig::default()).await
+    }
+
+    /// Opens a recording stored as multiple files, presenting them as one continuous video.
+    /// Frame numbers past the end of a segment are served from the following one.
+    pub async fn spawn_segments(
+        paths: Vec<PathBuf>,
+        config: DecoderConfig,
+    ) -> Result<AsyncVideoDecoderHandle, DecoderError> {
+        let mut segments = Vec::with_capacity(paths.len());
+        let mut keyframes = vec![];
+        let mut start_frame = 0;
+
+        for path in paths {
+            let (segment, frame_count, segment_keyframes) =
+                Self::spawn_segment(path, start_frame, config).await?;
+            segments.push(segment);
+            keyframes.extend(segment_keyframes.into_iter().map(|k| k + start_frame));
+            start_frame += frame_count;
+        }
+
+        Ok(AsyncVideoDecoderHandle {
+            segments: Arc::new(segments),
+            keyframes: Arc::new(RwLock::new(keyframes)),
+            total_frames: start_frame,
+        })
+    }
+
+    /// Returns the segment along with its frame count and keyframes.
+    async fn spawn_segment(
+        path: PathBuf,
+        start_frame: u32,
+        config: DecoderConfig,
+    ) -> Result<(DecoderSegment, u32, Vec<u32>), DecoderError> {
+        let (tx, rx) = mpsc::channel();
+        let (ready_tx, ready_rx) = tokio::sync::oneshot::channel();
+        let cache_bytes = Arc::new(AtomicUsize::new(0));
+
+        let thread_path = path.clone();
+        let thread_cache_bytes = cache_bytes.clone();
+
+        std::thread::spawn(move || {
+            let OpenedVideo {
+                mut input,
+                mut decoder,
+                hw_device,
+                stream_index: input_stream_index,
+                time_base,
+                frame_rate,
+                rotation,
+                mut keyframes,
+            } = match OpenedVideo::open(&thread_path, config) {
+                Ok(opened) => {
+                    ready_tx
+                        .send(Ok((opened.frame_count(), opened.keyframes.clone())))
+                        .ok();
+                    opened
+                }
+                Err(e) => {
+                    ready_tx.send(Err(e)).ok();
+                    return;
+                }
+            };
 
             use ffmpeg::format::Pixel;
             use ffmpeg::software::scaling::{context::Context, flag::Flags};
@@ -96,7 +528,8 @@ impl AsyncVideoDecoder {
 
             let mut temp_frame = ffmpeg::frame::Video::empty();
 
-            let render_more_margin = (FRAME_CACHE_SIZE / 4) as u32;
+            let mut cache_size = config.cache_size.max(1);
+            let render_more_margin = (cache_size / 4) as u32;
 
             let mut cache = BTreeMap::<u32, Arc<Vec<u8>>>::new();
             // active frame is a frame that triggered decode.
@@ -105,6 +538,8 @@ impl AsyncVideoDecoder {
 
             let mut last_decoded_frame = None::<u32>;
 
+            let mut frame_index = None::<FrameIndex>;
+
             struct PacketStuff<'a> {
                 packets: PacketIter<'a>,
                 skipped_packet: Option<(Stream<'a>, Packet)>,
@@ -120,9 +555,14 @@ impl AsyncVideoDecoder {
 
             while let Ok(r) = peekable_requests.recv() {
                 match r {
-                    VideoDecoderMessage::GetFrame(frame_number, sender) => {
+                    VideoDecoderMessage::GetFrame(frame_number, seek_mode, sender) => {
                         // println!("retrieving frame {frame_number}");
 
+                        let frame_number = match seek_mode {
+                            SeekMode::Exact => frame_number,
+                            SeekMode::Keyframe => preceding_keyframe(&keyframes, frame_number),
+                        };
+
                         let mut sender = if let Some(cached) = cache.get(&frame_number) {
                             // println!("sending frame {frame_number} from cache");
                             sender.send(Some(cached.clone())).ok();
@@ -131,22 +571,52 @@ impl AsyncVideoDecoder {
                             Some(sender)
                         };
 
-                        let cache_min = frame_number.saturating_sub(FRAME_CACHE_SIZE as u32 / 2);
-                        let cache_max = frame_number + FRAME_CACHE_SIZE as u32 / 2;
+                        // stepping backwards would otherwise decode from the same keyframe
+                        // on every step, so cache the requested frame's GOP up to it instead
+                        // of frames either side. further backward steps within the GOP are
+                        // then served from the cache, as long as the GOP fits in it.
+                        let backwards = last_active_frame.is_some_and(|f| frame_number < f);
+                        let (cache_min, cache_max) = if backwards {
+                            let gop_start = if keyframes.is_empty() {
+                                0
+                            } else {
+                                preceding_keyframe(&keyframes, frame_number)
+                            };
+                            let oldest = frame_number.saturating_sub(cache_size as u32 - 1);
+                            (gop_start.max(oldest), frame_number)
+                        } else {
+                            (
+                                frame_number.saturating_sub(cache_size as u32 / 2),
+                                frame_number + cache_size as u32 / 2,
+                            )
+                        };
 
                         if frame_number <= 0
                             || last_decoded_frame
                                 .map(|f| {
                                     frame_number < f ||
                                     // seek forward for big jumps. this threshold is arbitrary but should be derived from i-frames in future
-                                    frame_number - f > FRAME_CACHE_SIZE as u32
+                                    frame_number - f > cache_size as u32
                                 })
                                 .unwrap_or(true)
                         {
-                            let timestamp_us =
-                                ((frame_number as f32 / frame_rate.numerator() as f32)
-                                    * 1_000_000.0) as i64;
-                            let position = timestamp_us.rescale((1, 1_000_000), rescale::TIME_BASE);
+                            // an indexed keyframe's own timestamp is landed on exactly, even
+                            // when the container's index is missing or wrong
+                            let position = match frame_index
+                                .as_ref()
+                                .and_then(|index| index.preceding_keyframe(frame_number))
+                            {
+                                Some(keyframe) => {
+                                    keyframe.pts.rescale(time_base, rescale::TIME_BASE)
+                                }
+                                None => {
+                                    let timestamp_us = ((frame_number as f32
+                                        / frame_rate.numerator() as f32)
+                                        * 1_000_000.0)
+                                        as i64;
+                                    timestamp_us.rescale((1, 1_000_000), rescale::TIME_BASE)
+                                }
+                            };
 
                             println!("seeking to {position} for frame {frame_number}");
 
@@ -227,7 +697,8 @@ impl AsyncVideoDecoder {
                                         frame_buffer.extend_from_slice(&line_data[0..width * 4]);
                                     }
 
-                                    let frame = Arc::new(frame_buffer);
+                                    let frame =
+                                        Arc::new(rotation.apply(frame_buffer, width, height));
 
                                     if current_frame == frame_number {
                                         if let Some(sender) = sender.take() {
@@ -236,7 +707,7 @@ impl AsyncVideoDecoder {
                                     }
 
                                     if !too_small_for_cache_bounds {
-                                        if cache.len() >= FRAME_CACHE_SIZE {
+                                        if cache.len() >= cache_size {
                                             if let Some(last_active_frame) = &last_active_frame {
                                                 let frame = if frame_number > *last_active_frame {
                                                     *cache.keys().next().unwrap()
@@ -275,26 +746,186 @@ impl AsyncVideoDecoder {
                             println!("failed to send frame {frame_number}");
                         }
                     }
+                    VideoDecoderMessage::SetCacheSize(size) => {
+                        cache_size = size.max(1);
+
+                        // evict the frames furthest from the playhead until the cache fits
+                        let center = last_active_frame.unwrap_or(0);
+                        while cache.len() > cache_size {
+                            let first = *cache.keys().next().unwrap();
+                            let last = *cache.keys().next_back().unwrap();
+                            let furthest = if center.abs_diff(first) > center.abs_diff(last) {
+                                first
+                            } else {
+                                last
+                            };
+                            cache.remove(&furthest);
+                        }
+                    }
+                    VideoDecoderMessage::ClearCache(sender) => {
+                        let freed = cache.values().map(|frame| frame.len()).sum();
+                        // frames decoded ahead of the playhead are cached too, so this
+                        // drops them as well. the decoder keeps its position.
+                        cache.clear();
+                        sender.send(freed).ok();
+                    }
+                    VideoDecoderMessage::SetFrameIndex(index) => {
+                        keyframes = index.keyframes.iter().map(|k| k.frame).collect();
+                        frame_index = Some(index);
+                    }
                 }
+
+                thread_cache_bytes.store(
+                    cache.values().map(|frame| frame.len()).sum(),
+                    Ordering::Relaxed,
+                );
             }
         });
 
-        AsyncVideoDecoderHandle { sender: tx }
+        let (frame_count, keyframes) = ready_rx.await.map_err(|_| {
+            DecoderError::corrupt(&path, "decoder thread exited during initialization")
+        })??;
+
+        let segment = DecoderSegment {
+            start_frame,
+            sender: tx,
+            cache_bytes,
+        };
+
+        Ok((segment, frame_count, keyframes))
     }
 }
 
+struct DecoderSegment {
+    // frame number of the segment's first frame in the combined video
+    start_frame: u32,
+    sender: mpsc::Sender<VideoDecoderMessage>,
+    // bytes of decoded frames currently held in the segment's cache
+    cache_bytes: Arc<AtomicUsize>,
+}
+
 #[derive(Clone)]
 pub struct AsyncVideoDecoderHandle {
-    sender: mpsc::Sender<VideoDecoderMessage>,
+    segments: Arc<Vec<DecoderSegment>>,
+    // replaced when a frame index is set, so shared between clones
+    keyframes: Arc<RwLock<Vec<u32>>>,
+    total_frames: u32,
 }
 
 impl AsyncVideoDecoderHandle {
-    pub async fn get_frame(&self, frame_number: u32) -> Option<Arc<Vec<u8>>> {
+    /// Number of frames across all segments, estimated from the container durations.
+    pub fn total_frames(&self) -> u32 {
+        self.total_frames
+    }
+
+    /// Frame numbers of the video's keyframes across all segments, in ascending order.
+    /// Requests using `SeekMode::Keyframe` snap to these.
+    pub fn keyframes(&self) -> Vec<u32> {
+        self.keyframes.read().unwrap().clone()
+    }
+
+    /// Number of files the video is stored as.
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// Seeks using `indexes`, one per segment in order, instead of the containers' own
+    /// indexes, and takes their keyframes as the video's keyframes.
+    pub fn set_frame_index(&self, indexes: Vec<FrameIndex>) {
+        let mut keyframes = vec![];
+
+        for (segment, index) in self.segments.iter().zip(indexes) {
+            keyframes.extend(
+                index
+                    .keyframes
+                    .iter()
+                    .map(|k| k.frame + segment.start_frame),
+            );
+            segment
+                .sender
+                .send(VideoDecoderMessage::SetFrameIndex(index))
+                .ok();
+        }
+
+        *self.keyframes.write().unwrap() = keyframes;
+    }
+
+    /// Bytes of decoded frames currently cached across all segments.
+    pub fn cache_bytes(&self) -> usize {
+        self.segments
+            .iter()
+            .map(|segment| segment.cache_bytes.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Limits how many decoded frames the decoder keeps, evicting frames furthest from
+    /// the playhead if it currently holds more. The limit is shared between segments.
+    pub fn set_cache_size(&self, cache_size: usize) {
+        let per_segment = (cache_size / self.segments.len().max(1)).max(1);
+
+        for segment in self.segments.iter() {
+            segment
+                .sender
+                .send(VideoDecoderMessage::SetCacheSize(per_segment))
+                .ok();
+        }
+    }
+
+    /// Drops every cached frame, returning the bytes freed. The decoders stay open, so
+    /// later requests decode again from the current position.
+    pub async fn clear_cache(&self) -> usize {
+        let receivers = self
+            .segments
+            .iter()
+            .filter_map(|segment| {
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                segment
+                    .sender
+                    .send(VideoDecoderMessage::ClearCache(tx))
+                    .ok()?;
+                Some(rx)
+            })
+            .collect::<Vec<_>>();
+
+        let mut freed = 0;
+        for rx in receivers {
+            freed += rx.await.unwrap_or(0);
+        }
+        freed
+    }
+
+    pub async fn get_frame(&self, frame_number: u32, seek_mode: SeekMode) -> Option<Arc<Vec<u8>>> {
+        self.request_frame(frame_number, seek_mode)?
+            .await
+            .ok()
+            .flatten()
+    }
+
+    /// Asks for a frame without waiting for it to be decoded. The decoder sends it to the
+    /// returned receiver once it's ready, so the caller can get on with rendering the frame
+    /// before it meanwhile. Requests are served in the order they're made, and dropping the
+    /// receiver doesn't cancel the decode, whose frames are still cached.
+    pub fn request_frame(
+        &self,
+        frame_number: u32,
+        seek_mode: SeekMode,
+    ) -> Option<tokio::sync::oneshot::Receiver<Option<DecodedFrame>>> {
+        let segment = self
+            .segments
+            .iter()
+            .rev()
+            .find(|segment| segment.start_frame <= frame_number)?;
+
         let (tx, rx) = tokio::sync::oneshot::channel();
-        self.sender
-            .send(VideoDecoderMessage::GetFrame(frame_number, tx))
-            .unwrap();
-        rx.await.ok().flatten()
+        segment
+            .sender
+            .send(VideoDecoderMessage::GetFrame(
+                frame_number - segment.start_frame,
+                seek_mode,
+                tx,
+            ))
+            .ok()?;
+        Some(rx)
     }
 }
 
@@ -474,3 +1105,59 @@ impl CodecContextExt for codec::context::Context {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `contents` to a file named `name` in a directory for this test run.
+    fn video_file(name: &str, contents: &[u8]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cap-decoder-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn open_error(path: &Path) -> DecoderError {
+        match OpenedVideo::open(path, DecoderConfig::default()) {
+            Ok(_) => panic!("{} opened", path.display()),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn missing_files_are_reported_as_missing() {
+        let path = std::env::temp_dir().join("cap-decoder-test-missing.mp4");
+
+        let error = open_error(&path);
+
+        assert!(matches!(&error, DecoderError::FileMissing(p) if *p == path));
+        assert!(error.to_string().contains("not found"), "{error}");
+    }
+
+    #[test]
+    fn truncated_files_are_reported_as_corrupt() {
+        // an mp4 cut off right after its header, before any of its boxes describing the video
+        let mut contents = b"\0\0\0\x20ftypisom\0\0\x02\0isomiso2avc1mp41".to_vec();
+        contents.extend_from_slice(b"\0\0\x10\0moov\0\0\0\x6cmvhd");
+        let path = video_file("truncated.mp4", &contents);
+
+        let error = open_error(&path);
+
+        assert!(
+            matches!(&error, DecoderError::Corrupt { path: p, .. } if *p == path),
+            "{error}"
+        );
+        assert!(error.to_string().contains("is corrupt"), "{error}");
+    }
+
+    #[test]
+    fn files_that_arent_video_are_reported_as_corrupt() {
+        let path = video_file("notes.mp4", b"not a video at all");
+
+        let error = open_error(&path);
+
+        assert!(matches!(error, DecoderError::Corrupt { .. }), "{error}");
+    }
+}
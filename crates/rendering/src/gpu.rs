@@ -0,0 +1,81 @@
+use serde::Serialize;
+use specta::Type;
+
+/// Features the renderer requests when creating its device.
+pub(crate) const REQUIRED_FEATURES: wgpu::Features = wgpu::Features::empty();
+
+/// Limits the renderer requests when creating its device.
+pub(crate) fn required_limits() -> wgpu::Limits {
+    wgpu::Limits::default()
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct GpuReport {
+    pub adapters: Vec<AdapterReport>,
+}
+
+impl GpuReport {
+    /// Whether at least one adapter can run the renderer.
+    pub fn is_supported(&self) -> bool {
+        self.adapters.iter().any(|adapter| adapter.supported)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct AdapterReport {
+    pub name: String,
+    pub backend: String,
+    pub device_type: String,
+    pub driver: String,
+    /// Largest width or height of a 2D texture, which bounds the output size.
+    pub max_texture_size: u32,
+    /// Required features the adapter doesn't provide.
+    pub missing_features: Vec<String>,
+    /// Required limits the adapter doesn't reach.
+    pub unmet_limits: Vec<String>,
+    pub supported: bool,
+}
+
+/// Lists every adapter wgpu can find along with whether it meets the renderer's
+/// feature and limit requirements.
+pub fn gpu_report() -> GpuReport {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let required_limits = required_limits();
+
+    let adapters = instance
+        .enumerate_adapters(wgpu::Backends::all())
+        .into_iter()
+        .map(|adapter| {
+            let info = adapter.get_info();
+            let limits = adapter.limits();
+
+            let missing_features = REQUIRED_FEATURES
+                .difference(adapter.features())
+                .iter_names()
+                .map(|(name, _)| name.to_string())
+                .collect::<Vec<_>>();
+
+            let mut unmet_limits = Vec::new();
+            required_limits.check_limits_with_fail_fn(&limits, false, |name, required, allowed| {
+                unmet_limits.push(format!("{name} (requires {required}, has {allowed})"));
+            });
+
+            AdapterReport {
+                name: info.name,
+                backend: info.backend.to_str().to_string(),
+                device_type: format!("{:?}", info.device_type),
+                driver: format!("{} {}", info.driver, info.driver_info)
+                    .trim()
+                    .to_string(),
+                max_texture_size: limits.max_texture_dimension_2d,
+                supported: missing_features.is_empty() && unmet_limits.is_empty(),
+                missing_features,
+                unmet_limits,
+            }
+        })
+        .collect();
+
+    GpuReport { adapters }
+}
@@ -1,27 +1,74 @@
 use anyhow::Result;
 use bytemuck::{Pod, Zeroable};
 use decoder::AsyncVideoDecoderHandle;
-use futures::future::OptionFuture;
+use futures::future::join_all;
 use futures_intrusive::channel::shared::oneshot_channel;
 use serde::{Deserialize, Serialize};
 use specta::Type;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use wgpu::util::DeviceExt;
 use wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
 
 use cap_project::{
-    AspectRatio, BackgroundSource, CameraXPosition, CameraYPosition, Crop, ProjectConfiguration, XY,
+    AspectRatio, AudioVisualizer, BackgroundSource, CameraShape, CameraXPosition, CameraYPosition,
+    ColorGrade, Crop, MotionBlur, ProjectConfiguration, Reframe, Spotlight, TimeBadge,
+    TimelinePosition, XY,
 };
 
 use std::time::Instant;
 
+mod card;
+mod cursor;
 pub mod decoder;
-pub use decoder::DecodedFrame;
+mod gpu;
+pub mod lut;
+mod profiler;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+mod time_badge;
+mod visualizer;
+pub use card::CardFrames;
+pub use cursor::{interpolate_cursor, CursorSample};
+pub use decoder::{DecodedFrame, DecoderError, FrameIndex, SeekMode};
+pub use gpu::{gpu_report, AdapterReport, GpuReport};
+pub use lut::Lut;
+use profiler::GpuProfiler;
+pub use profiler::PassTiming;
+pub use time_badge::format_timecode;
+pub use visualizer::VisualizerAudio;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Type)]
 pub struct RenderOptions {
-    pub camera_size: Option<(u32, u32)>,
+    pub camera_sizes: Vec<(u32, u32)>,
     pub screen_size: (u32, u32),
+    /// Times each render pass on the GPU, see `RenderVideoConstants::gpu_profile`.
+    /// Ignored if the adapter doesn't support timestamp queries.
+    #[serde(default)]
+    pub profile_gpu: bool,
+    /// Size of the screen recording shown beside the main one in split screen.
+    #[serde(default)]
+    pub secondary_screen_size: Option<(u32, u32)>,
+    /// How smoothly the edges of the screen and cameras are drawn.
+    #[serde(default)]
+    pub antialiasing: Antialiasing,
+}
+
+/// Edge antialiasing for previews and for exports, which can afford to be slower.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub struct Antialiasing {
+    pub preview: EdgeAntialiasing,
+    pub export: EdgeAntialiasing,
+}
+
+impl Default for Antialiasing {
+    fn default() -> Self {
+        Self {
+            preview: EdgeAntialiasing::Medium,
+            export: EdgeAntialiasing::High,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Type)]
@@ -66,6 +113,7 @@ impl From<BackgroundSource> for Background {
                 ],
                 angle: angle as f32,
             },
+            BackgroundSource::Transparent => Background::Color([0.0; 4]),
             _ => unimplemented!(),
         }
     }
@@ -74,112 +122,290 @@ impl From<BackgroundSource> for Background {
 #[derive(Clone)]
 pub struct RecordingDecoders {
     screen: AsyncVideoDecoderHandle,
-    camera: Option<AsyncVideoDecoderHandle>,
+    // shared between clones so a replaced camera is used everywhere. None once removed.
+    cameras: Arc<RwLock<Vec<Option<AsyncVideoDecoderHandle>>>>,
+    secondary_screen: Option<AsyncVideoDecoderHandle>,
 }
 
 impl RecordingDecoders {
-    pub fn new(screen: AsyncVideoDecoderHandle, camera: Option<AsyncVideoDecoderHandle>) -> Self {
-        RecordingDecoders { screen, camera }
+    pub fn new(screen: AsyncVideoDecoderHandle, cameras: Vec<AsyncVideoDecoderHandle>) -> Self {
+        RecordingDecoders {
+            screen,
+            cameras: Arc::new(RwLock::new(cameras.into_iter().map(Some).collect())),
+            secondary_screen: None,
+        }
+    }
+
+    /// Adds another recording's screen to show beside this one in split screen.
+    /// It's decoded at the same frame numbers as this recording.
+    pub fn with_secondary_screen(mut self, screen: AsyncVideoDecoderHandle) -> Self {
+        self.secondary_screen = Some(screen);
+        self
+    }
+
+    pub fn secondary_screen(&self) -> Option<&AsyncVideoDecoderHandle> {
+        self.secondary_screen.as_ref()
+    }
+
+    pub fn screen(&self) -> &AsyncVideoDecoderHandle {
+        &self.screen
+    }
+
+    /// The camera decoders, with `None` for cameras that have been removed.
+    pub fn cameras(&self) -> Vec<Option<AsyncVideoDecoderHandle>> {
+        self.cameras.read().unwrap().clone()
+    }
+
+    /// Swaps the decoder of the camera at `index`, or removes it with `None`, for this and
+    /// every clone of these decoders. Removed cameras decode no frames, so aren't drawn.
+    pub fn replace_camera(&self, index: usize, decoder: Option<AsyncVideoDecoderHandle>) {
+        if let Some(camera) = self.cameras.write().unwrap().get_mut(index) {
+            *camera = decoder;
+        }
+    }
+
+    /// Number of frames in the screen recording.
+    pub fn total_frames(&self) -> u32 {
+        self.screen.total_frames()
+    }
+
+    /// Bytes of decoded frames currently cached by all decoders.
+    pub fn cache_bytes(&self) -> usize {
+        self.overlay_decoders()
+            .iter()
+            .flatten()
+            .map(|d| d.cache_bytes())
+            .sum::<usize>()
+            + self.screen.cache_bytes()
+    }
+
+    /// Limits every decoder to caching `cache_size` frames.
+    pub fn set_cache_size(&self, cache_size: usize) {
+        self.screen.set_cache_size(cache_size);
+        for decoder in self.overlay_decoders().iter().flatten() {
+            decoder.set_cache_size(cache_size);
+        }
+    }
+
+    /// Empties every decoder's frame cache, returning the bytes freed.
+    pub async fn clear_caches(&self) -> usize {
+        let overlay_decoders = self.overlay_decoders();
+        let (screen, others) = tokio::join!(
+            self.screen.clear_cache(),
+            join_all(overlay_decoders.iter().flatten().map(|d| d.clear_cache()))
+        );
+
+        screen + others.into_iter().sum::<usize>()
+    }
+
+    /// The camera decoders followed by the secondary screen's, in the order their frames
+    /// are returned by `get_frames`.
+    fn overlay_decoders(&self) -> Vec<Option<AsyncVideoDecoderHandle>> {
+        let mut decoders = self.cameras();
+        if let Some(secondary_screen) = &self.secondary_screen {
+            decoders.push(Some(secondary_screen.clone()));
+        }
+        decoders
+    }
+
+    /// Keyframe positions of the screen recording.
+    pub fn keyframes(&self) -> Vec<u32> {
+        self.screen.keyframes()
+    }
+
+    /// Decodes only the screen frame, for looking at the recording without the cameras.
+    pub async fn get_screen_frame(
+        &self,
+        frame_number: u32,
+        seek_mode: SeekMode,
+    ) -> Option<DecodedFrame> {
+        self.screen.get_frame(frame_number, seek_mode).await
     }
+
+    /// Decodes the screen frame and one frame per camera concurrently.
+    /// Camera frames are returned in the same order as the camera decoders,
+    /// followed by the secondary screen's frame when there is one.
     pub async fn get_frames(
         &self,
         frame_number: u32,
-    ) -> Option<(DecodedFrame, Option<DecodedFrame>)> {
-        let (screen_frame, camera_frame) = tokio::join!(
-            self.screen.get_frame(frame_number),
-            OptionFuture::from(self.camera.as_ref().map(|d| d.get_frame(frame_number)))
-        );
+    ) -> Option<(DecodedFrame, Vec<Option<DecodedFrame>>)> {
+        self.get_frames_with_seek(frame_number, SeekMode::Exact)
+            .await
+    }
+
+    /// Like `get_frames`, but with `SeekMode::Keyframe` each decoder returns its nearest
+    /// preceding keyframe instead of the exact frame.
+    pub async fn get_frames_with_seek(
+        &self,
+        frame_number: u32,
+        seek_mode: SeekMode,
+    ) -> Option<(DecodedFrame, Vec<Option<DecodedFrame>>)> {
+        self.request_frames(frame_number, seek_mode).wait().await
+    }
 
-        screen_frame.map(|f| (f, camera_frame.flatten()))
+    /// Asks every decoder for its frame at `frame_number` without waiting for them. Requesting
+    /// the next frame before rendering the current one lets the two overlap, as the decoders
+    /// work through requests in the order they're made.
+    pub fn request_frames(&self, frame_number: u32, seek_mode: SeekMode) -> PendingFrames {
+        PendingFrames {
+            frame_number,
+            screen: self.screen.request_frame(frame_number, seek_mode),
+            overlays: self
+                .overlay_decoders()
+                .iter()
+                .map(|d| d.as_ref()?.request_frame(frame_number, seek_mode))
+                .collect(),
+        }
     }
 
     pub async fn stop(&self) {
         // Implement the stop logic for the decoders
         // This might involve stopping any running decoding tasks
         // and cleaning up resources
-        if let Some(camera) = &self.camera {
-            camera.stop().await;
+        for decoder in self.overlay_decoders().iter().flatten() {
+            decoder.stop().await;
         }
         self.screen.stop().await;
         println!("Decoders stopped");
     }
 }
 
+type FrameReceiver = tokio::sync::oneshot::Receiver<Option<DecodedFrame>>;
+
+/// Frames requested with `RecordingDecoders::request_frames`, which the decoders send
+/// once they're decoded.
+pub struct PendingFrames {
+    frame_number: u32,
+    screen: Option<FrameReceiver>,
+    // in the order of `RecordingDecoders::overlay_decoders`
+    overlays: Vec<Option<FrameReceiver>>,
+}
+
+impl PendingFrames {
+    pub fn frame_number(&self) -> u32 {
+        self.frame_number
+    }
+
+    /// Waits for the frames, returned as by `RecordingDecoders::get_frames`.
+    pub async fn wait(self) -> Option<(DecodedFrame, Vec<Option<DecodedFrame>>)> {
+        let receive = |rx: Option<FrameReceiver>| async move { rx?.await.ok().flatten() };
+        let (screen_frame, camera_frames) = tokio::join!(
+            receive(self.screen),
+            join_all(self.overlays.into_iter().map(receive))
+        );
+
+        screen_frame.map(|f| (f, camera_frames))
+    }
+}
+
+/// Renders the project at `output_fps`, dropping or duplicating source frames
+/// when it differs from the recording's `source_fps`. Starts at output frame `start_frame`.
+/// The project's audio visualizer follows `audio`, and isn't drawn without it.
+#[allow(clippy::too_many_arguments)]
 pub async fn render_video_to_channel(
     options: RenderOptions,
     project: ProjectConfiguration,
     sender: tokio::sync::mpsc::UnboundedSender<Vec<u8>>,
     decoders: RecordingDecoders,
+    source_fps: f64,
+    output_fps: u32,
+    start_frame: u32,
+    audio: Option<VisualizerAudio>,
 ) -> Result<(), String> {
     let constants = RenderVideoConstants::new(options).await?;
 
-    println!("Setting up FFmpeg input for screen recording...");
+    tracing::info!(
+        "Rendering {source_fps} fps source at {output_fps} fps (conversion ratio {:.3})",
+        output_fps as f64 / source_fps
+    );
+
+    tracing::debug!("Setting up FFmpeg input for screen recording");
 
     ffmpeg::init().unwrap();
 
     let start_time = Instant::now();
 
-    let duration = project.timeline().map(|t| t.duration()).unwrap_or(f64::MAX);
+    let recording_duration = decoders.total_frames() as f64 / source_fps;
+    let duration = project.total_duration(recording_duration);
+    let cards = CardFrames::load(&project, constants.options.screen_size);
 
     let render_handle: tokio::task::JoinHandle<Result<u32, String>> = tokio::spawn(async move {
-        let mut frame_number = 0;
+        let mut frame_number = start_frame;
 
         let uniforms = ProjectUniforms::new(&constants, &project);
         let background = Background::from(project.background.source.clone());
 
         loop {
-            if frame_number as f64 > 30_f64 * duration {
+            if frame_number as f64 > output_fps as f64 * duration {
                 break;
             };
 
-            let time = if let Some(timeline) = project.timeline() {
-                match timeline.get_recording_time(frame_number as f64 / 30_f64) {
-                    Some(time) => time,
-                    None => break,
-                }
-            } else {
-                frame_number as f64 / 30_f64
-            };
-
-            let Some((screen_frame, camera_frame)) =
-                decoders.get_frames((time * 30.0) as u32).await
+            let Some(position) = project
+                .timeline_position(frame_number as f64 / output_fps as f64, recording_duration)
             else {
                 break;
             };
 
+            let (screen_frame, camera_frames) = match position {
+                TimelinePosition::Recording(time) => {
+                    let time = if let Some(timeline) = project.timeline() {
+                        match timeline.get_recording_time(time) {
+                            Some(time) => time,
+                            None => break,
+                        }
+                    } else {
+                        time
+                    };
+
+                    let Some(frames) = decoders.get_frames((time * source_fps) as u32).await else {
+                        break;
+                    };
+
+                    frames
+                }
+                card => match cards.get(card) {
+                    Some(frame) => (frame, vec![None; decoders.cameras.len()]),
+                    None => break,
+                },
+            };
+
+            let time = frame_number as f64 / output_fps as f64;
+            let mut frame_uniforms = uniforms.clone().at_time(time, output_fps);
+            if let Some(audio) = &audio {
+                frame_uniforms =
+                    frame_uniforms.with_audio(audio, project.audio_time(time, recording_duration));
+            }
+
             let frame = match produce_frame(
                 &constants,
                 &screen_frame,
-                &camera_frame,
+                &camera_frames,
                 background,
-                &uniforms,
+                &frame_uniforms,
             )
             .await
             {
                 Ok(frame) => frame,
-                Err(e) => {
-                    eprintln!("{e}");
-                    break;
-                }
+                // the frames so far are incomplete, so the export fails instead of ending early
+                Err(e) => return Err(format!("Failed to render frame {frame_number}: {e}")),
             };
 
             if sender.send(frame).is_err() {
-                eprintln!("Failed to send processed frame to channel");
+                tracing::warn!("Failed to send processed frame to channel");
                 break;
             }
 
             frame_number += 1;
             if frame_number % 60 == 0 {
                 let elapsed = start_time.elapsed();
-                println!(
-                    "Rendered {} frames in {:?} seconds",
-                    frame_number,
+                tracing::debug!(
+                    "Rendered {frame_number} frames in {:.2} seconds",
                     elapsed.as_secs_f32()
                 );
             }
         }
 
-        println!("Render loop exited");
+        tracing::debug!("Render loop exited");
 
         Ok(frame_number)
     });
@@ -187,9 +413,9 @@ pub async fn render_video_to_channel(
     let total_frames = render_handle.await.map_err(|e| e.to_string())??;
 
     let total_time = start_time.elapsed();
-    println!(
-        "Render complete. Processed {} frames in {:?} seconds",
-        total_frames,
+    tracing::info!(
+        "Render complete. Processed {} frames in {:.2} seconds",
+        total_frames - start_frame,
         total_time.as_secs_f32()
     );
 
@@ -201,9 +427,43 @@ pub struct RenderVideoConstants {
     pub _adapter: wgpu::Adapter,
     pub queue: wgpu::Queue,
     pub device: wgpu::Device,
+    /// The options the constants were created with. Camera sizes may have changed since,
+    /// see `current_options`.
     pub options: RenderOptions,
+    // sizes of the cameras as currently recorded, updated by `set_camera_size`
+    camera_sizes: RwLock<Vec<(u32, u32)>>,
     composite_video_frame_pipeline: CompositeVideoFramePipeline,
     gradient_or_color_pipeline: GradientOrColorPipeline,
+    color_grade_pipeline: ColorGradePipeline,
+    spotlight_pipeline: SpotlightPipeline,
+    luts: Mutex<HashMap<String, Result<Arc<Lut>, String>>>,
+    base_layer: Mutex<Option<BaseLayer>>,
+    profiler: Option<GpuProfiler>,
+    // set by wgpu once the device is lost, after which nothing renders with these constants
+    device_lost: Arc<AtomicBool>,
+}
+
+/// The background and screen as composited for the last rendered frame,
+/// reused when the next frame would draw them identically.
+struct BaseLayer {
+    key: BaseLayerKey,
+    texture: Arc<wgpu::Texture>,
+}
+
+struct BaseLayerKey {
+    screen_frame: DecodedFrame,
+    uniforms: Vec<u8>,
+    output_size: (u32, u32),
+}
+
+impl PartialEq for BaseLayerKey {
+    fn eq(&self, other: &Self) -> bool {
+        // holding the frame keeps its allocation alive, so comparing by pointer is
+        // reliable and avoids comparing the pixels
+        Arc::ptr_eq(&self.screen_frame, &other.screen_frame)
+            && self.uniforms == other.uniforms
+            && self.output_size == other.output_size
+    }
 }
 
 impl RenderVideoConstants {
@@ -214,36 +474,144 @@ impl RenderVideoConstants {
             .request_adapter(&wgpu::RequestAdapterOptions::default())
             .await
             .unwrap();
+
+        let profile_gpu =
+            options.profile_gpu && adapter.features().contains(wgpu::Features::TIMESTAMP_QUERY);
+        if options.profile_gpu && !profile_gpu {
+            tracing::warn!("GPU profiling disabled, the adapter doesn't support timestamp queries");
+        }
+
+        let mut required_features = gpu::REQUIRED_FEATURES;
+        if profile_gpu {
+            required_features |= wgpu::Features::TIMESTAMP_QUERY;
+        }
+
         let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor::default(), None)
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    required_features,
+                    required_limits: gpu::required_limits(),
+                    ..Default::default()
+                },
+                None,
+            )
             .await
             .map_err(|e| e.to_string())?;
 
+        let device_lost = Arc::new(AtomicBool::new(false));
+        device.set_device_lost_callback({
+            let device_lost = device_lost.clone();
+            move |reason, message| {
+                tracing::error!("GPU device lost ({reason:?}): {message}");
+                device_lost.store(true, Ordering::Relaxed);
+            }
+        });
+
         Ok(Self {
             composite_video_frame_pipeline: CompositeVideoFramePipeline::new(&device),
             gradient_or_color_pipeline: GradientOrColorPipeline::new(&device),
+            color_grade_pipeline: ColorGradePipeline::new(&device),
+            spotlight_pipeline: SpotlightPipeline::new(&device),
+            luts: Mutex::new(HashMap::new()),
+            base_layer: Mutex::new(None),
+            profiler: profile_gpu.then(|| GpuProfiler::new(&device, &queue)),
+            device_lost,
             _instance: instance,
             _adapter: adapter,
             queue,
             device,
+            camera_sizes: RwLock::new(options.camera_sizes.clone()),
             options,
         })
     }
+
+    /// Whether the GPU device has been lost, eg. to a driver reset. Rendering with these
+    /// constants fails from then on, so new ones need to be created.
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Relaxed)
+    }
+
+    /// Sets the size of the camera at `index`'s frames, for when its recording is replaced.
+    /// Frames rendered afterwards use it to lay out and upload the camera.
+    pub fn set_camera_size(&self, index: usize, size: (u32, u32)) {
+        if let Some(camera_size) = self.camera_sizes.write().unwrap().get_mut(index) {
+            *camera_size = size;
+        }
+    }
+
+    /// `options` with the current camera sizes.
+    pub fn current_options(&self) -> RenderOptions {
+        RenderOptions {
+            camera_sizes: self.camera_sizes.read().unwrap().clone(),
+            ..self.options.clone()
+        }
+    }
+
+    /// Loads and validates a `.cube` LUT, caching the result so each file is only parsed once.
+    pub fn load_lut(&self, path: &str) -> Result<Arc<Lut>, String> {
+        let mut luts = self.luts.lock().unwrap();
+
+        luts.entry(path.to_string())
+            .or_insert_with(|| {
+                Lut::load(path)
+                    .map(Arc::new)
+                    .inspect_err(|e| tracing::warn!("{e}"))
+            })
+            .clone()
+    }
+
+    /// Average GPU time of each render pass over every frame rendered so far, or `None`
+    /// if `RenderOptions::profile_gpu` wasn't set or isn't supported. Profiling waits for
+    /// each frame to finish on the GPU, so it slows rendering down.
+    pub fn gpu_profile(&self) -> Option<Vec<PassTiming>> {
+        self.profiler.as_ref().map(GpuProfiler::timings)
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct ProjectUniforms {
     pub output_size: (u32, u32),
     display: CompositeVideoFrameUniforms,
-    camera: Option<CompositeVideoFrameUniforms>,
+    // one entry per camera, None when that camera is hidden
+    cameras: Vec<Option<CompositeVideoFrameUniforms>>,
+    color_grade: Option<ColorGradeUniforms>,
+    lut: Option<Arc<Lut>>,
+    time_badge: Option<TimeBadge>,
+    timeline_duration: Option<f64>,
+    // text drawn in the time badge, set for each frame with `at_time`
+    time_badge_text: Option<String>,
+    reframe: Option<Reframe>,
+    // the secondary screen in split screen, which then shares the output with `display`
+    secondary_screen: Option<CompositeVideoFrameUniforms>,
+    spotlight: Option<Spotlight>,
+    // the spotlight's circle, placed for each frame with `at_time`
+    spotlight_uniforms: Option<SpotlightUniforms>,
+    motion_blur: Option<MotionBlur>,
+    audio_visualizer: Option<AudioVisualizer>,
+    // heights of the visualizer's bars, set for each frame with `with_audio`
+    visualizer_levels: Option<Vec<f32>>,
+    // used by `for_preview` in place of the export antialiasing set by `new`
+    preview_antialiasing: EdgeAntialiasing,
 }
 
 const CAMERA_PADDING: f32 = 50.0;
 
 const SCREEN_MAX_PADDING: f32 = 0.4;
+// smallest fraction of the output width either side of a split screen can have
+const SPLIT_SCREEN_MIN_SIDE: f32 = 0.1;
+// maximum shadow size as a fraction of the shadowed frame's smallest side
+const SHADOW_MAX_SIZE: f32 = 0.1;
+// width of the spotlight's soft edge as a fraction of its radius
+const SPOTLIGHT_FEATHER: f32 = 0.25;
+// the screen must move at least this many output pixels between frames to be motion blurred
+const MOTION_BLUR_MIN_PX: f32 = 2.0;
 
 impl ProjectUniforms {
     fn get_crop(options: &RenderOptions, project: &ProjectConfiguration) -> Crop {
+        if let Some(reframe) = &project.reframe {
+            return reframe.crop(options.screen_size);
+        }
+
         project.background.crop.clone().unwrap_or(Crop {
             position: XY { x: 0, y: 0 },
             size: XY {
@@ -360,31 +728,78 @@ impl ProjectUniforms {
                 target_bounds,
                 target_size,
                 rounding_px: project.background.rounding / 100.0 * 0.5 * min_target_axis,
+                // the shadow can't extend beyond the padding or it would be clipped
+                shadow_px: (project.background.shadow.clamp(0.0, 100.0) / 100.0
+                    * SHADOW_MAX_SIZE
+                    * min_target_axis)
+                    .min(padding),
                 ..Default::default()
             }
         };
 
-        let camera = options
-            .camera_size
-            .filter(|_| !project.camera.hide)
-            .map(|camera_size| {
+        // in split screen each screen is fitted to its own side of the divider
+        let (display, secondary_screen) =
+            match (&project.split_screen, options.secondary_screen_size) {
+                (Some(split), Some((width, height))) => {
+                    let padding = Self::get_padding(options, project);
+                    let output_size = [output_size.0 as f32, output_size.1 as f32];
+                    let divider = output_size[0]
+                        * split
+                            .divider
+                            .clamp(SPLIT_SCREEN_MIN_SIDE, 1.0 - SPLIT_SCREEN_MIN_SIDE);
+
+                    let secondary_screen = CompositeVideoFrameUniforms {
+                        frame_size: [width as f32, height as f32],
+                        crop_bounds: [0.0, 0.0, width as f32, height as f32],
+                        ..display
+                    };
+
+                    (
+                        Self::fit_in_region(
+                            display,
+                            [0.0, 0.0, divider, output_size[1]],
+                            padding,
+                            project,
+                        ),
+                        Some(Self::fit_in_region(
+                            secondary_screen,
+                            [divider, 0.0, output_size[0], output_size[1]],
+                            padding,
+                            project,
+                        )),
+                    )
+                }
+                _ => (display, None),
+            };
+
+        let camera_sizes = constants.camera_sizes.read().unwrap().clone();
+        let cameras = camera_sizes
+            .iter()
+            .enumerate()
+            .map(|(i, camera_size)| {
+                let camera = project.camera_config(i);
+
+                if camera.hide {
+                    return None;
+                }
+
                 let output_size = [output_size.0 as f32, output_size.1 as f32];
 
                 let frame_size = [camera_size.0 as f32, camera_size.1 as f32];
                 let min_axis = output_size[0].min(output_size[1]);
 
                 let size = [
-                    min_axis * project.camera.size / 100.0 + CAMERA_PADDING,
-                    min_axis * project.camera.size / 100.0 + CAMERA_PADDING,
+                    min_axis * camera.size / 100.0 + CAMERA_PADDING,
+                    min_axis * camera.size / 100.0 + CAMERA_PADDING,
                 ];
 
                 let position = {
-                    let x = match &project.camera.position.x {
+                    let x = match &camera.position.x {
                         CameraXPosition::Left => CAMERA_PADDING,
                         CameraXPosition::Center => output_size[0] / 2.0 - (size[0]) / 2.0,
                         CameraXPosition::Right => output_size[0] - CAMERA_PADDING - size[0],
                     };
-                    let y = match &project.camera.position.y {
+                    let y = match &camera.position.y {
                         CameraYPosition::Top => CAMERA_PADDING,
                         CameraYPosition::Bottom => output_size[1] - size[1] - CAMERA_PADDING,
                     };
@@ -399,7 +814,15 @@ impl ProjectUniforms {
                     position[1] + size[1],
                 ];
 
-                CompositeVideoFrameUniforms {
+                // the camera is square, so full rounding makes it a circle
+                let rounding = match camera.shape {
+                    None => camera.rounding,
+                    Some(CameraShape::Rectangle) => 0.0,
+                    Some(CameraShape::RoundedRect { radius }) => radius,
+                    Some(CameraShape::Circle) => 100.0,
+                };
+
+                Some(CompositeVideoFrameUniforms {
                     output_size,
                     frame_size,
                     crop_bounds: [
@@ -413,164 +836,836 @@ impl ProjectUniforms {
                         target_bounds[2] - target_bounds[0],
                         target_bounds[3] - target_bounds[1],
                     ],
-                    rounding_px: project.camera.rounding / 100.0 * 0.5 * size[0],
-                    mirror_x: if project.camera.mirror { 1.0 } else { 0.0 },
+                    rounding_px: rounding.clamp(0.0, 100.0) / 100.0 * 0.5 * size[0],
+                    mirror_x: if camera.mirror { 1.0 } else { 0.0 },
+                    opacity: camera.opacity.clamp(0.0, 100.0) / 100.0,
+                    shadow_px: (camera.shadow.min(100) as f32 / 100.0 * SHADOW_MAX_SIZE * size[0])
+                        .min(CAMERA_PADDING),
+                    border_px: camera.border.as_ref().map_or(0.0, |border| {
+                        (border.width.clamp(0.0, 100.0) / 100.0 * size[0]).min(0.5 * size[0])
+                    }),
+                    border_color: camera.border.as_ref().map_or([0.0; 4], |border| {
+                        [
+                            srgb_to_linear(border.color[0]),
+                            srgb_to_linear(border.color[1]),
+                            srgb_to_linear(border.color[2]),
+                            1.0,
+                        ]
+                    }),
                     ..Default::default()
-                }
-            });
+                })
+            })
+            .collect();
+
+        let (color_grade, lut) = match &project.color_grade {
+            None => (None, None),
+            Some(ColorGrade::Adjustments {
+                brightness,
+                contrast,
+                saturation,
+            }) => (
+                Some(ColorGradeUniforms {
+                    brightness: brightness.clamp(-100.0, 100.0) / 100.0 * 0.5,
+                    contrast: 1.0 + contrast.clamp(-100.0, 100.0) / 100.0,
+                    saturation: 1.0 + saturation.clamp(-100.0, 100.0) / 100.0,
+                    lut_size: 0.0,
+                }),
+                None,
+            ),
+            Some(ColorGrade::Lut { path }) => match constants.load_lut(path) {
+                Ok(lut) => (
+                    Some(ColorGradeUniforms {
+                        lut_size: lut.size as f32,
+                        ..Default::default()
+                    }),
+                    Some(lut),
+                ),
+                Err(_) => (None, None),
+            },
+        };
 
         Self {
             output_size,
             display,
-            camera,
+            cameras,
+            color_grade,
+            lut,
+            time_badge: project.time_badge.clone(),
+            timeline_duration: project.timeline().map(|t| t.duration()),
+            time_badge_text: None,
+            reframe: project.reframe.clone(),
+            secondary_screen,
+            spotlight: project.spotlight.clone(),
+            spotlight_uniforms: project
+                .spotlight
+                .as_ref()
+                .map(|spotlight| SpotlightUniforms::new(spotlight, output_size, 0.0)),
+            motion_blur: project.motion_blur,
+            audio_visualizer: project.audio_visualizer.clone(),
+            visualizer_levels: None,
+            preview_antialiasing: options.antialiasing.preview,
         }
+        .with_scale_filter(ScaleFilter::Lanczos)
+        .with_edge_antialiasing(options.antialiasing.export)
     }
-}
 
-pub async fn produce_frame(
-    RenderVideoConstants {
-        device,
-        options,
-        composite_video_frame_pipeline,
-        gradient_or_color_pipeline,
-        queue,
-        ..
-    }: &RenderVideoConstants,
-    screen_frame: &Vec<u8>,
-    camera_frame: &Option<DecodedFrame>,
-    background: Background,
-    uniforms: &ProjectUniforms,
-) -> Result<Vec<u8>, String> {
-    let mut encoder = device.create_command_encoder(
-        &(wgpu::CommandEncoderDescriptor {
-            label: Some("Render Encoder"),
-        }),
-    );
+    /// Scales a frame's crop to fit `region` of the output, less `padding` on each side,
+    /// and centers it there.
+    fn fit_in_region(
+        mut frame: CompositeVideoFrameUniforms,
+        region: [f32; 4],
+        padding: f32,
+        project: &ProjectConfiguration,
+    ) -> CompositeVideoFrameUniforms {
+        let cropped_size = [
+            frame.crop_bounds[2] - frame.crop_bounds[0],
+            frame.crop_bounds[3] - frame.crop_bounds[1],
+        ];
+        let region_size = [region[2] - region[0], region[3] - region[1]];
+        let available_size = [
+            (region_size[0] - 2.0 * padding).max(1.0),
+            (region_size[1] - 2.0 * padding).max(1.0),
+        ];
+
+        let scale = f32::min(
+            available_size[0] / cropped_size[0],
+            available_size[1] / cropped_size[1],
+        );
+        let target_size = [cropped_size[0] * scale, cropped_size[1] * scale];
+        let target_start = [
+            region[0] + (region_size[0] - target_size[0]) / 2.0,
+            region[1] + (region_size[1] - target_size[1]) / 2.0,
+        ];
+        let min_target_axis = target_size[0].min(target_size[1]);
+
+        frame.target_bounds = [
+            target_start[0],
+            target_start[1],
+            target_start[0] + target_size[0],
+            target_start[1] + target_size[1],
+        ];
+        frame.target_size = target_size;
+        frame.rounding_px = project.background.rounding / 100.0 * 0.5 * min_target_axis;
+        frame.shadow_px = (project.background.shadow.clamp(0.0, 100.0) / 100.0
+            * SHADOW_MAX_SIZE
+            * min_target_axis)
+            .min(padding);
+        frame
+    }
 
-    let output_texture_desc = wgpu::TextureDescriptor {
-        size: wgpu::Extent3d {
-            width: uniforms.output_size.0,
-            height: uniforms.output_size.1,
-            depth_or_array_layers: 1,
-        },
-        mip_level_count: 1,
-        sample_count: 1,
-        dimension: wgpu::TextureDimension::D2,
-        format: wgpu::TextureFormat::Rgba8UnormSrgb,
-        usage: wgpu::TextureUsages::TEXTURE_BINDING
-            | wgpu::TextureUsages::RENDER_ATTACHMENT
-            | wgpu::TextureUsages::COPY_SRC,
-        label: Some("Intermediate Texture"),
-        view_formats: &[],
-    };
+    /// Sets the timeline time, in seconds, shown by the project's time badge and
+    /// used to pan the reframe crop and move the spotlight. Without this the badge isn't drawn.
+    /// The pan's speed over the previous frame sets how much the screen is motion blurred.
+    pub fn at_time(mut self, time: f64, fps: u32) -> Self {
+        self.time_badge_text = self
+            .time_badge
+            .as_ref()
+            .map(|badge| format_timecode(badge.format, time, self.timeline_duration, fps));
+
+        if let Some(reframe) = &self.reframe {
+            let crop_bounds = &mut self.display.crop_bounds;
+            let screen_width = self.display.frame_size[0];
+            let crop_width = crop_bounds[2] - crop_bounds[0];
+            let crop_left = |time: f64| {
+                (reframe.center_at(time) * screen_width - crop_width / 2.0)
+                    .clamp(0.0, screen_width - crop_width)
+            };
 
-    let textures = (
-        device.create_texture(&output_texture_desc),
-        device.create_texture(&output_texture_desc),
-    );
+            let left = crop_left(time);
+            crop_bounds[0] = left;
+            crop_bounds[2] = left + crop_width;
 
-    let textures = (&textures.0, &textures.1);
+            if let Some(motion_blur) = self.motion_blur {
+                // the crop moving right moves the screen left, so the blur samples from
+                // where the screen was, to the right
+                let moved = (left - crop_left(time - 1.0 / fps as f64)) / crop_width;
+                let moved_px = moved.abs() * self.display.target_size[0];
 
-    let texture_views = (
-        textures
-            .0
-            .create_view(&wgpu::TextureViewDescriptor::default()),
-        textures
-            .1
-            .create_view(&wgpu::TextureViewDescriptor::default()),
-    );
+                self.display.velocity_uv = if moved_px >= MOTION_BLUR_MIN_PX {
+                    [moved * motion_blur.strength.clamp(0.0, 1.0), 0.0]
+                } else {
+                    [0.0; 2]
+                };
+            }
+        }
 
-    let texture_views = (&texture_views.0, &texture_views.1);
+        if let Some(spotlight) = &self.spotlight {
+            self.spotlight_uniforms =
+                Some(SpotlightUniforms::new(spotlight, self.output_size, time));
+        }
 
-    let mut output_is_left = true;
+        self
+    }
 
-    {
-        do_render_pass(
-            &mut encoder,
-            get_either(texture_views, output_is_left),
-            &gradient_or_color_pipeline.render_pipeline,
-            gradient_or_color_pipeline.bind_group(
-                device,
-                &GradientOrColorUniforms::from(background).to_buffer(device),
-            ),
-        );
+    /// The same frame without the color grade and spotlight, for comparing against.
+    pub fn without_effects(mut self) -> Self {
+        self.color_grade = None;
+        self.lut = None;
+        self.spotlight = None;
+        self.spotlight_uniforms = None;
+        self
+    }
 
-        output_is_left = !output_is_left;
+    /// Hides every layer except `layer`. The background still comes from the `Background`
+    /// passed to `produce_frame`, so pass a plain black one to hide it too.
+    pub fn only_layer(mut self, layer: Layer) -> Self {
+        if layer != Layer::Screen {
+            self.display.opacity = 0.0;
+            if let Some(secondary_screen) = self.secondary_screen.as_mut() {
+                secondary_screen.opacity = 0.0;
+            }
+        }
+
+        if layer != Layer::Camera {
+            self.cameras.iter_mut().for_each(|camera| *camera = None);
+        }
+
+        self
     }
 
-    {
-        let frame_size = options.screen_size;
+    /// Sets the filter used to scale the screen and cameras. Exports default to
+    /// `ScaleFilter::Lanczos` and previews to `ScaleFilter::Bilinear`.
+    pub fn with_scale_filter(mut self, filter: ScaleFilter) -> Self {
+        self.display.scale_filter = filter.shader_value();
+        for camera in self
+            .cameras
+            .iter_mut()
+            .flatten()
+            .chain(self.secondary_screen.as_mut())
+        {
+            camera.scale_filter = filter.shader_value();
+        }
+        self
+    }
 
-        let texture = device.create_texture(
-            &(wgpu::TextureDescriptor {
-                size: wgpu::Extent3d {
-                    width: options.screen_size.0,
-                    height: options.screen_size.1,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                usage: wgpu::TextureUsages::TEXTURE_BINDING
-                    | wgpu::TextureUsages::RENDER_ATTACHMENT
-                    | wgpu::TextureUsages::COPY_DST,
-                label: Some("Screen Frame texture"),
-                view_formats: &[],
-            }),
+    /// Sets how smoothly the edges of the screen and cameras' rounded masks are drawn.
+    /// Exports and previews default to `RenderOptions::antialiasing`.
+    pub fn with_edge_antialiasing(mut self, quality: EdgeAntialiasing) -> Self {
+        self.display.edge_samples = quality.shader_value();
+        for camera in self
+            .cameras
+            .iter_mut()
+            .flatten()
+            .chain(self.secondary_screen.as_mut())
+        {
+            camera.edge_samples = quality.shader_value();
+        }
+        self
+    }
+
+    /// Renders at `scale` times the output size with the same layout, to use less GPU memory.
+    pub fn scaled(mut self, scale: f32) -> Self {
+        let scaled_size = |size: u32| ((size as f32 * scale).round() as u32).max(1);
+        let output_size = (
+            scaled_size(self.output_size.0),
+            scaled_size(self.output_size.1),
         );
+        // the scale actually applied along each axis once sizes are rounded
+        let sx = output_size.0 as f32 / self.output_size.0 as f32;
+        let sy = output_size.1 as f32 / self.output_size.1 as f32;
+
+        for frame in std::iter::once(&mut self.display)
+            .chain(self.cameras.iter_mut().flatten())
+            .chain(self.secondary_screen.as_mut())
+        {
+            let [left, top, right, bottom] = frame.target_bounds;
+            frame.target_bounds = [left * sx, top * sy, right * sx, bottom * sy];
+            frame.target_size = [frame.target_size[0] * sx, frame.target_size[1] * sy];
+            frame.output_size = [output_size.0 as f32, output_size.1 as f32];
+            frame.rounding_px *= scale;
+            frame.shadow_px *= scale;
+            frame.border_px *= scale;
+        }
 
-        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        if let Some(spotlight) = self.spotlight_uniforms.as_mut() {
+            spotlight.center = [spotlight.center[0] * sx, spotlight.center[1] * sy];
+            spotlight.radius *= scale;
+            spotlight.feather = (spotlight.feather * scale).max(1.0);
+        }
 
-        queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            screen_frame,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(options.screen_size.0 * 4),
-                rows_per_image: None,
-            },
-            wgpu::Extent3d {
-                width: frame_size.0,
-                height: frame_size.1,
-                depth_or_array_layers: 1,
-            },
-        );
+        self.output_size = output_size;
+        self
+    }
 
-        do_render_pass(
-            &mut encoder,
-            get_either(texture_views, output_is_left),
-            &composite_video_frame_pipeline.render_pipeline,
-            composite_video_frame_pipeline.bind_group(
-                device,
-                &uniforms.display.to_buffer(device),
-                &texture_view,
-                get_either(texture_views, !output_is_left),
-            ),
-        );
+    /// Sets the audio drawn by the project's audio visualizer to `audio` at `time` in the
+    /// recording, or silence for `None`. Without this the visualizer isn't drawn.
+    pub fn with_audio(mut self, audio: &VisualizerAudio, time: Option<f64>) -> Self {
+        self.visualizer_levels = self
+            .audio_visualizer
+            .as_ref()
+            .map(|visualizer| visualizer::levels(visualizer, audio, time));
+        self
+    }
 
-        output_is_left = !output_is_left;
+    /// Drops layers that only appear in exports and uses cheaper scaling and antialiasing,
+    /// for rendering editor previews.
+    pub fn for_preview(mut self) -> Self {
+        if self
+            .time_badge
+            .as_ref()
+            .is_some_and(|badge| badge.export_only)
+        {
+            self.time_badge = None;
+            self.time_badge_text = None;
+        }
+        if self
+            .audio_visualizer
+            .as_ref()
+            .is_some_and(|visualizer| visualizer.export_only)
+        {
+            self.audio_visualizer = None;
+            self.visualizer_levels = None;
+        }
+        let antialiasing = self.preview_antialiasing;
+        self.with_scale_filter(ScaleFilter::Bilinear)
+            .with_edge_antialiasing(antialiasing)
     }
+}
 
-    if let (Some(camera_size), Some(camera_frame), Some(uniforms)) =
-        (options.camera_size, camera_frame, &uniforms.camera)
-    {
-        let texture = device.create_texture(
-            &(wgpu::TextureDescriptor {
-                size: wgpu::Extent3d {
-                    width: camera_size.0,
-                    height: camera_size.1,
-                    depth_or_array_layers: 1,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+/// How the screen and camera frames are sampled when scaled to fit the output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScaleFilter {
+    /// Picks the closest pixel. Fastest, but aliases badly when downscaling.
+    Nearest,
+    /// Blends the four closest pixels. Cheap and good enough for previews.
+    #[default]
+    Bilinear,
+    /// Windowed sinc over a neighbourhood scaled to the downscale factor, which keeps
+    /// fine detail like text sharp without aliasing. Slower, so used for exports.
+    Lanczos,
+}
+
+impl ScaleFilter {
+    fn shader_value(self) -> f32 {
+        match self {
+            Self::Nearest => 0.0,
+            Self::Bilinear => 1.0,
+            Self::Lanczos => 2.0,
+        }
+    }
+}
+
+/// How many samples per pixel decide how much of a pixel on the edge of a rounded mask is
+/// covered. More samples give smoother corners at high resolutions, at the cost of speed.
+///
+/// Every layer is drawn as a single triangle covering the output and masked in its shader,
+/// so this takes the place of multisampling, which would only smooth the triangle's edges.
+/// Any level works on any GPU.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Type)]
+pub enum EdgeAntialiasing {
+    /// One sample, so pixels are either in or out and curves look jagged.
+    Low,
+    /// 2x2 samples. Good enough for previews.
+    #[default]
+    Medium,
+    /// 4x4 samples, for exports.
+    High,
+    /// 8x8 samples, for exports scaled well below their recorded size.
+    Ultra,
+}
+
+impl EdgeAntialiasing {
+    // samples along each axis of the pixel
+    fn shader_value(self) -> f32 {
+        match self {
+            Self::Low => 1.0,
+            Self::Medium => 2.0,
+            Self::High => 4.0,
+            Self::Ultra => 8.0,
+        }
+    }
+}
+
+/// One layer of the composite, for looking at on its own with `ProjectUniforms::only_layer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    Background,
+    /// The screen, and the secondary screen in split screen.
+    Screen,
+    /// Every camera.
+    Camera,
+}
+
+/// Why `produce_frame` failed.
+#[derive(Debug, thiserror::Error)]
+pub enum FrameError {
+    /// The GPU couldn't allocate the frame's textures or buffers. Rendering smaller
+    /// frames or with fewer layers may succeed.
+    #[error("GPU ran out of memory rendering the frame")]
+    OutOfMemory,
+    /// The GPU device was lost, so nothing renders until new `RenderVideoConstants`
+    /// are created.
+    #[error("GPU device was lost")]
+    DeviceLost,
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl From<FrameError> for String {
+    fn from(error: FrameError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Format of rendered frames, and of textures passed to `produce_frame_to_texture`.
+pub const OUTPUT_TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+pub async fn produce_frame(
+    constants: &RenderVideoConstants,
+    screen_frame: &DecodedFrame,
+    camera_frames: &[Option<DecodedFrame>],
+    background: Background,
+    uniforms: &ProjectUniforms,
+) -> Result<Vec<u8>, FrameError> {
+    let RenderVideoConstants { device, queue, .. } = constants;
+
+    if constants.is_device_lost() {
+        return Err(FrameError::DeviceLost);
+    }
+
+    // wgpu would otherwise panic on allocation failures
+    device.push_error_scope(wgpu::ErrorFilter::OutOfMemory);
+
+    let output_texture =
+        render_frame_texture(constants, screen_frame, camera_frames, background, uniforms);
+
+    let output_texture_size = wgpu::Extent3d {
+        width: uniforms.output_size.0,
+        height: uniforms.output_size.1,
+        depth_or_array_layers: 1,
+    };
+
+    // Calculate the aligned bytes per row
+    let align = COPY_BYTES_PER_ROW_ALIGNMENT;
+    let unpadded_bytes_per_row = uniforms.output_size.0 * 4;
+    let padding = (align - (unpadded_bytes_per_row % align)) % align;
+    let padded_bytes_per_row = unpadded_bytes_per_row + padding;
+
+    // Ensure the padded_bytes_per_row is a multiple of 4 (32 bits)
+    let padded_bytes_per_row = (padded_bytes_per_row + 3) & !3;
+
+    let output_buffer_size = (padded_bytes_per_row * uniforms.output_size.1) as u64;
+
+    let output_buffer = device.create_buffer(
+        &(wgpu::BufferDescriptor {
+            size: output_buffer_size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            label: Some("Output Buffer"),
+            mapped_at_creation: false,
+        }),
+    );
+
+    {
+        let mut encoder = device.create_command_encoder(
+            &(wgpu::CommandEncoderDescriptor {
+                label: Some("Copy Encoder"),
+            }),
+        );
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &output_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &output_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(uniforms.output_size.1),
+                },
+            },
+            output_texture_size,
+        );
+
+        queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    if let Some(error) = device.pop_error_scope().await {
+        return Err(match error {
+            wgpu::Error::OutOfMemory { .. } => FrameError::OutOfMemory,
+            error => FrameError::Failed(error.to_string()),
+        });
+    }
+
+    let buffer_slice = output_buffer.slice(..);
+    let (tx, rx) = oneshot_channel();
+    buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
+        tx.send(result).ok();
+    });
+    device.poll(wgpu::Maintain::Wait);
+
+    let Some(frame_result) = rx.receive().await else {
+        return Err(FrameError::Failed(
+            "2: Channel closed unexpectedly".to_string(),
+        ));
+    };
+
+    if let Err(e) = frame_result {
+        if constants.is_device_lost() {
+            return Err(FrameError::DeviceLost);
+        }
+        return Err(FrameError::Failed(format!("Failed to map buffer: {:?}", e)));
+    }
+
+    let data = buffer_slice.get_mapped_range();
+    let padded_data: Vec<u8> = data.to_vec(); // Ensure the type is Vec<u8>
+    let mut image_data =
+        Vec::with_capacity((uniforms.output_size.0 * uniforms.output_size.1 * 4) as usize);
+    for chunk in padded_data.chunks(padded_bytes_per_row as usize) {
+        image_data.extend_from_slice(&chunk[..unpadded_bytes_per_row as usize]);
+    }
+
+    // Unmap the buffer
+    drop(data);
+    output_buffer.unmap();
+
+    if let (Some(badge), Some(text)) = (&uniforms.time_badge, &uniforms.time_badge_text) {
+        time_badge::draw(&mut image_data, uniforms.output_size, badge, text);
+    }
+    if let (Some(visualizer), Some(levels)) =
+        (&uniforms.audio_visualizer, &uniforms.visualizer_levels)
+    {
+        visualizer::draw(&mut image_data, uniforms.output_size, visualizer, levels);
+    }
+
+    Ok(image_data)
+}
+
+/// Renders a frame directly into `target` on the GPU, skipping the readback to memory
+/// that `produce_frame` does. The time badge and audio visualizer are drawn on the CPU
+/// so aren't included.
+/// `target` must be created from `constants.device`, match `uniforms.output_size`,
+/// use `OUTPUT_TEXTURE_FORMAT` and allow `COPY_DST`.
+pub fn produce_frame_to_texture(
+    constants: &RenderVideoConstants,
+    screen_frame: &DecodedFrame,
+    camera_frames: &[Option<DecodedFrame>],
+    background: Background,
+    uniforms: &ProjectUniforms,
+    target: &wgpu::Texture,
+) -> Result<(), String> {
+    let output_texture_size = wgpu::Extent3d {
+        width: uniforms.output_size.0,
+        height: uniforms.output_size.1,
+        depth_or_array_layers: 1,
+    };
+
+    if target.size() != output_texture_size {
+        return Err(format!(
+            "Target texture is {}x{}, expected {}x{}",
+            target.width(),
+            target.height(),
+            uniforms.output_size.0,
+            uniforms.output_size.1
+        ));
+    }
+
+    if target.format() != OUTPUT_TEXTURE_FORMAT {
+        return Err(format!(
+            "Target texture format is {:?}, expected {:?}",
+            target.format(),
+            OUTPUT_TEXTURE_FORMAT
+        ));
+    }
+
+    if !target.usage().contains(wgpu::TextureUsages::COPY_DST) {
+        return Err("Target texture must allow COPY_DST".to_string());
+    }
+
+    let output_texture =
+        render_frame_texture(constants, screen_frame, camera_frames, background, uniforms);
+
+    let mut encoder = constants.device.create_command_encoder(
+        &(wgpu::CommandEncoderDescriptor {
+            label: Some("Target Copy Encoder"),
+        }),
+    );
+
+    encoder.copy_texture_to_texture(
+        output_texture.as_image_copy(),
+        target.as_image_copy(),
+        output_texture_size,
+    );
+
+    constants.queue.submit(std::iter::once(encoder.finish()));
+
+    Ok(())
+}
+
+/// Renders a frame into a texture that can be used in another pass or copied elsewhere.
+/// The texture uses `OUTPUT_TEXTURE_FORMAT` and is `uniforms.output_size` in size.
+fn render_frame_texture(
+    RenderVideoConstants {
+        device,
+        options,
+        composite_video_frame_pipeline,
+        gradient_or_color_pipeline,
+        color_grade_pipeline,
+        spotlight_pipeline,
+        queue,
+        base_layer,
+        profiler,
+        ..
+    }: &RenderVideoConstants,
+    screen_frame: &DecodedFrame,
+    camera_frames: &[Option<DecodedFrame>],
+    background: Background,
+    uniforms: &ProjectUniforms,
+) -> wgpu::Texture {
+    let mut encoder = device.create_command_encoder(
+        &(wgpu::CommandEncoderDescriptor {
+            label: Some("Render Encoder"),
+        }),
+    );
+
+    let mut timestamps = profiler.as_ref().map(GpuProfiler::begin_frame);
+
+    let output_texture_desc = wgpu::TextureDescriptor {
+        size: wgpu::Extent3d {
+            width: uniforms.output_size.0,
+            height: uniforms.output_size.1,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: OUTPUT_TEXTURE_FORMAT,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::COPY_SRC
+            | wgpu::TextureUsages::COPY_DST,
+        label: Some("Intermediate Texture"),
+        view_formats: &[],
+    };
+
+    let owned_textures = (
+        device.create_texture(&output_texture_desc),
+        device.create_texture(&output_texture_desc),
+    );
+
+    let textures = (&owned_textures.0, &owned_textures.1);
+
+    let texture_views = (
+        textures
+            .0
+            .create_view(&wgpu::TextureViewDescriptor::default()),
+        textures
+            .1
+            .create_view(&wgpu::TextureViewDescriptor::default()),
+    );
+
+    let texture_views = (&texture_views.0, &texture_views.1);
+
+    let mut output_is_left = true;
+
+    let background_uniforms = GradientOrColorUniforms::from(background);
+    let base_layer_key = BaseLayerKey {
+        screen_frame: screen_frame.clone(),
+        uniforms: [
+            bytemuck::bytes_of(&background_uniforms),
+            bytemuck::bytes_of(&uniforms.display),
+        ]
+        .concat(),
+        output_size: uniforms.output_size,
+    };
+
+    let cached_base_layer = base_layer
+        .lock()
+        .unwrap()
+        .as_ref()
+        .filter(|cached| cached.key == base_layer_key)
+        .map(|cached| cached.texture.clone());
+
+    // the background and screen only need redrawing when they've changed,
+    // so camera-only changes on a static screen start from the previous result
+    if let Some(cached) = cached_base_layer {
+        encoder.copy_texture_to_texture(
+            cached.as_image_copy(),
+            get_either(textures, !output_is_left).as_image_copy(),
+            output_texture_desc.size,
+        );
+    } else {
+        {
+            do_render_pass(
+                &mut encoder,
+                get_either(texture_views, output_is_left),
+                &gradient_or_color_pipeline.render_pipeline,
+                gradient_or_color_pipeline
+                    .bind_group(device, &background_uniforms.to_buffer(device)),
+                timestamps.as_mut().and_then(|t| t.pass("background")),
+            );
+
+            output_is_left = !output_is_left;
+        }
+
+        {
+            let frame_size = options.screen_size;
+
+            let texture = device.create_texture(
+                &(wgpu::TextureDescriptor {
+                    size: wgpu::Extent3d {
+                        width: options.screen_size.0,
+                        height: options.screen_size.1,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING
+                        | wgpu::TextureUsages::RENDER_ATTACHMENT
+                        | wgpu::TextureUsages::COPY_DST,
+                    label: Some("Screen Frame texture"),
+                    view_formats: &[],
+                }),
+            );
+
+            let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                screen_frame,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(options.screen_size.0 * 4),
+                    rows_per_image: None,
+                },
+                wgpu::Extent3d {
+                    width: frame_size.0,
+                    height: frame_size.1,
+                    depth_or_array_layers: 1,
+                },
+            );
+
+            do_render_pass(
+                &mut encoder,
+                get_either(texture_views, output_is_left),
+                &composite_video_frame_pipeline.render_pipeline,
+                composite_video_frame_pipeline.bind_group(
+                    device,
+                    &uniforms.display.to_buffer(device),
+                    &texture_view,
+                    get_either(texture_views, !output_is_left),
+                ),
+                timestamps.as_mut().and_then(|t| t.pass("screen")),
+            );
+
+            output_is_left = !output_is_left;
+        }
+
+        let texture = Arc::new(device.create_texture(
+            &(wgpu::TextureDescriptor {
+                usage: wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::COPY_DST,
+                label: Some("Base Layer texture"),
+                ..output_texture_desc
+            }),
+        ));
+
+        encoder.copy_texture_to_texture(
+            get_either(textures, !output_is_left).as_image_copy(),
+            texture.as_image_copy(),
+            output_texture_desc.size,
+        );
+
+        *base_layer.lock().unwrap() = Some(BaseLayer {
+            key: base_layer_key,
+            texture,
+        });
+    }
+
+    // the secondary screen's frame comes after the camera frames
+    if let (Some(frame_size), Some(Some(frame)), Some(uniforms)) = (
+        options.secondary_screen_size,
+        camera_frames.get(options.camera_sizes.len()),
+        &uniforms.secondary_screen,
+    ) {
+        let texture = device.create_texture(
+            &(wgpu::TextureDescriptor {
+                size: wgpu::Extent3d {
+                    width: frame_size.0,
+                    height: frame_size.1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::COPY_DST,
+                label: Some("Secondary Screen Frame texture"),
+                view_formats: &[],
+            }),
+        );
+
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            frame,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(frame_size.0 * 4),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: frame_size.0,
+                height: frame_size.1,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        do_render_pass(
+            &mut encoder,
+            get_either(texture_views, output_is_left),
+            &composite_video_frame_pipeline.render_pipeline,
+            composite_video_frame_pipeline.bind_group(
+                device,
+                &uniforms.to_buffer(device),
+                &texture_view,
+                get_either(texture_views, !output_is_left),
+            ),
+            timestamps.as_mut().and_then(|t| t.pass("secondary_screen")),
+        );
+
+        output_is_left = !output_is_left;
+    }
+
+    for (camera_frame, uniforms) in camera_frames.iter().zip(&uniforms.cameras) {
+        let (Some(camera_frame), Some(uniforms)) = (camera_frame, uniforms) else {
+            continue;
+        };
+
+        let camera_size = (uniforms.frame_size[0] as u32, uniforms.frame_size[1] as u32);
+        // a frame decoded just before the camera was replaced can have the old size
+        if camera_frame.len() != camera_size.0 as usize * camera_size.1 as usize * 4 {
+            continue;
+        }
+
+        let texture = device.create_texture(
+            &(wgpu::TextureDescriptor {
+                size: wgpu::Extent3d {
+                    width: camera_size.0,
+                    height: camera_size.1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
                 usage: wgpu::TextureUsages::TEXTURE_BINDING
                     | wgpu::TextureUsages::RENDER_ATTACHMENT
                     | wgpu::TextureUsages::COPY_DST,
@@ -611,121 +1706,352 @@ pub async fn produce_frame(
                 &texture_view,
                 get_either(texture_views, !output_is_left),
             ),
+            timestamps.as_mut().and_then(|t| t.pass("camera")),
+        );
+
+        output_is_left = !output_is_left;
+    }
+
+    if let Some(color_grade) = uniforms.color_grade {
+        let lut_texture = color_grade_pipeline.lut_texture(device, queue, uniforms.lut.as_deref());
+        let lut_view = lut_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        do_render_pass(
+            &mut encoder,
+            get_either(texture_views, output_is_left),
+            &color_grade_pipeline.render_pipeline,
+            color_grade_pipeline.bind_group(
+                device,
+                &color_grade.to_buffer(device),
+                get_either(texture_views, !output_is_left),
+                &lut_view,
+            ),
+            timestamps.as_mut().and_then(|t| t.pass("color_grade")),
         );
 
         output_is_left = !output_is_left;
     }
 
+    if let Some(spotlight) = uniforms.spotlight_uniforms {
+        do_render_pass(
+            &mut encoder,
+            get_either(texture_views, output_is_left),
+            &spotlight_pipeline.render_pipeline,
+            spotlight_pipeline.bind_group(
+                device,
+                &spotlight.to_buffer(device),
+                get_either(texture_views, !output_is_left),
+            ),
+            timestamps.as_mut().and_then(|t| t.pass("spotlight")),
+        );
+
+        output_is_left = !output_is_left;
+    }
+
+    if let Some(timestamps) = &timestamps {
+        timestamps.resolve(&mut encoder);
+    }
+
     queue.submit(std::iter::once(encoder.finish()));
 
-    let output_texture_size = wgpu::Extent3d {
-        width: uniforms.output_size.0,
-        height: uniforms.output_size.1,
-        depth_or_array_layers: 1,
-    };
+    if let Some(timestamps) = timestamps {
+        timestamps.finish(device);
+    }
 
-    // Calculate the aligned bytes per row
-    let align = COPY_BYTES_PER_ROW_ALIGNMENT;
-    let unpadded_bytes_per_row = uniforms.output_size.0 * 4;
-    let padding = (align - (unpadded_bytes_per_row % align)) % align;
-    let padded_bytes_per_row = unpadded_bytes_per_row + padding;
+    get_either(owned_textures, !output_is_left)
+}
 
-    // Ensure the padded_bytes_per_row is a multiple of 4 (32 bits)
-    let padded_bytes_per_row = (padded_bytes_per_row + 3) & !3;
+struct CompositeVideoFramePipeline {
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub render_pipeline: wgpu::RenderPipeline,
+}
 
-    let output_buffer_size = (padded_bytes_per_row * uniforms.output_size.1) as u64;
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+#[repr(C)]
+struct CompositeVideoFrameUniforms {
+    pub crop_bounds: [f32; 4],
+    pub target_bounds: [f32; 4],
+    pub output_size: [f32; 2],
+    pub frame_size: [f32; 2],
+    pub velocity_uv: [f32; 2],
+    pub target_size: [f32; 2],
+    pub rounding_px: f32,
+    pub mirror_x: f32,
+    pub opacity: f32,
+    pub shadow_px: f32,
+    pub scale_filter: f32,
+    pub border_px: f32,
+    pub edge_samples: f32,
+    _padding: f32,
+    pub border_color: [f32; 4],
+}
 
-    let output_buffer = device.create_buffer(
-        &(wgpu::BufferDescriptor {
-            size: output_buffer_size,
-            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
-            label: Some("Output Buffer"),
-            mapped_at_creation: false,
-        }),
-    );
+impl Default for CompositeVideoFrameUniforms {
+    fn default() -> Self {
+        Self {
+            crop_bounds: [0.0; 4],
+            target_bounds: [0.0; 4],
+            output_size: [0.0; 2],
+            frame_size: [0.0; 2],
+            velocity_uv: [0.0; 2],
+            target_size: [0.0; 2],
+            rounding_px: 0.0,
+            mirror_x: 0.0,
+            opacity: 1.0,
+            shadow_px: 0.0,
+            scale_filter: ScaleFilter::default().shader_value(),
+            border_px: 0.0,
+            edge_samples: EdgeAntialiasing::default().shader_value(),
+            _padding: 0.0,
+            border_color: [0.0; 4],
+        }
+    }
+}
 
-    {
-        let mut encoder = device.create_command_encoder(
-            &(wgpu::CommandEncoderDescriptor {
-                label: Some("Copy Encoder"),
+impl CompositeVideoFrameUniforms {
+    fn to_buffer(self, device: &wgpu::Device) -> wgpu::Buffer {
+        device.create_buffer_init(
+            &(wgpu::util::BufferInitDescriptor {
+                label: Some("CompositeVideoFrameUniforms Buffer"),
+                contents: bytemuck::cast_slice(&[self]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }),
+        )
+    }
+}
+
+impl CompositeVideoFramePipeline {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let bind_group_layout = Self::bind_group_layout(device);
+        let render_pipeline =
+            create_shader_render_pipeline(device, &bind_group_layout, Self::shader());
+
+        Self {
+            bind_group_layout,
+            render_pipeline,
+        }
+    }
+
+    fn shader() -> &'static str {
+        include_str!("shaders/composite-video-frame.wgsl")
+    }
+
+    fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("composite-video-frame.wgsl Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        })
+    }
+
+    pub fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        uniforms: &wgpu::Buffer,
+        frame: &wgpu::TextureView,
+        intermediate: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        let sampler = device.create_sampler(
+            &(wgpu::SamplerDescriptor {
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Nearest,
+                ..Default::default()
             }),
         );
 
-        encoder.copy_texture_to_buffer(
-            wgpu::ImageCopyTexture {
-                texture: get_either(textures, !output_is_left),
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
+        let bind_group = device.create_bind_group(
+            &(wgpu::BindGroupDescriptor {
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniforms.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(frame),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(intermediate),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                ],
+                label: Some("bind_group"),
+            }),
+        );
+
+        bind_group
+    }
+}
+
+struct GradientOrColorPipeline {
+    pub bind_group_layout: wgpu::BindGroupLayout,
+    pub render_pipeline: wgpu::RenderPipeline,
+}
+
+#[derive(Debug, Clone, Copy, Pod, Zeroable, Default)]
+#[repr(C)]
+struct GradientOrColorUniforms {
+    pub start: [f32; 4],
+    pub end: [f32; 4],
+    pub angle: f32,
+    _padding: [f32; 3],
+}
+
+impl GradientOrColorUniforms {
+    fn to_buffer(self, device: &wgpu::Device) -> wgpu::Buffer {
+        device.create_buffer_init(
+            &(wgpu::util::BufferInitDescriptor {
+                label: Some("GradientOrColorUniforms Buffer"),
+                contents: bytemuck::cast_slice(&[self]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }),
+        )
+    }
+}
+
+impl From<Background> for GradientOrColorUniforms {
+    fn from(value: Background) -> Self {
+        match value {
+            Background::Color(color) => Self {
+                start: color,
+                end: color,
+                angle: 0.0,
+                _padding: [0.0; 3],
             },
-            wgpu::ImageCopyBuffer {
-                buffer: &output_buffer,
-                layout: wgpu::ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: Some(padded_bytes_per_row),
-                    rows_per_image: Some(uniforms.output_size.1),
-                },
+            Background::Gradient { start, end, angle } => Self {
+                start,
+                end,
+                angle,
+                _padding: [0.0; 3],
             },
-            output_texture_size,
-        );
-
-        queue.submit(std::iter::once(encoder.finish()));
+        }
     }
+}
 
-    let buffer_slice = output_buffer.slice(..);
-    let (tx, rx) = oneshot_channel();
-    buffer_slice.map_async(wgpu::MapMode::Read, move |result| {
-        tx.send(result).ok();
-    });
-    device.poll(wgpu::Maintain::Wait);
+impl GradientOrColorPipeline {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let bind_group_layout = Self::bind_group_layout(device);
+        let render_pipeline =
+            create_shader_render_pipeline(device, &bind_group_layout, Self::shader());
 
-    let Some(frame_result) = rx.receive().await else {
-        return Err("2: Channel closed unexpectedly".to_string());
-    };
+        Self {
+            bind_group_layout,
+            render_pipeline,
+        }
+    }
 
-    if let Err(e) = frame_result {
-        return Err(format!("Failed to map buffer: {:?}", e));
+    fn shader() -> &'static str {
+        include_str!("shaders/gradient-or-color.wgsl")
     }
 
-    let data = buffer_slice.get_mapped_range();
-    let padded_data: Vec<u8> = data.to_vec(); // Ensure the type is Vec<u8>
-    let mut image_data =
-        Vec::with_capacity((uniforms.output_size.0 * uniforms.output_size.1 * 4) as usize);
-    for chunk in padded_data.chunks(padded_bytes_per_row as usize) {
-        image_data.extend_from_slice(&chunk[..unpadded_bytes_per_row as usize]);
+    fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("composite-video-frame.wgsl Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        })
     }
 
-    // Unmap the buffer
-    drop(data);
-    output_buffer.unmap();
+    pub fn bind_group(&self, device: &wgpu::Device, uniforms: &wgpu::Buffer) -> wgpu::BindGroup {
+        let bind_group = device.create_bind_group(
+            &(wgpu::BindGroupDescriptor {
+                layout: &self.bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniforms.as_entire_binding(),
+                }],
+                label: Some("bind_group"),
+            }),
+        );
 
-    Ok(image_data)
+        bind_group
+    }
 }
 
-struct CompositeVideoFramePipeline {
+struct ColorGradePipeline {
     pub bind_group_layout: wgpu::BindGroupLayout,
     pub render_pipeline: wgpu::RenderPipeline,
 }
 
-#[derive(Debug, Clone, Copy, Pod, Zeroable, Default)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
 #[repr(C)]
-struct CompositeVideoFrameUniforms {
-    pub crop_bounds: [f32; 4],
-    pub target_bounds: [f32; 4],
-    pub output_size: [f32; 2],
-    pub frame_size: [f32; 2],
-    pub velocity_uv: [f32; 2],
-    pub target_size: [f32; 2],
-    pub rounding_px: f32,
-    pub mirror_x: f32,
-    _padding: [f32; 3],
+struct ColorGradeUniforms {
+    pub brightness: f32,
+    pub contrast: f32,
+    pub saturation: f32,
+    // 0 when no LUT is bound
+    pub lut_size: f32,
 }
 
-impl CompositeVideoFrameUniforms {
+impl Default for ColorGradeUniforms {
+    fn default() -> Self {
+        Self {
+            brightness: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+            lut_size: 0.0,
+        }
+    }
+}
+
+impl ColorGradeUniforms {
     fn to_buffer(self, device: &wgpu::Device) -> wgpu::Buffer {
         device.create_buffer_init(
             &(wgpu::util::BufferInitDescriptor {
-                label: Some("CompositeVideoFrameUniforms Buffer"),
+                label: Some("ColorGradeUniforms Buffer"),
                 contents: bytemuck::cast_slice(&[self]),
                 usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             }),
@@ -733,7 +2059,7 @@ impl CompositeVideoFrameUniforms {
     }
 }
 
-impl CompositeVideoFramePipeline {
+impl ColorGradePipeline {
     pub fn new(device: &wgpu::Device) -> Self {
         let bind_group_layout = Self::bind_group_layout(device);
         let render_pipeline =
@@ -746,12 +2072,12 @@ impl CompositeVideoFramePipeline {
     }
 
     fn shader() -> &'static str {
-        include_str!("shaders/composite-video-frame.wgsl")
+        include_str!("shaders/color-grade.wgsl")
     }
 
     fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("composite-video-frame.wgsl Bind Group Layout"),
+            label: Some("color-grade.wgsl Bind Group Layout"),
             entries: &[
                 wgpu::BindGroupLayoutEntry {
                     binding: 0,
@@ -778,7 +2104,7 @@ impl CompositeVideoFramePipeline {
                     visibility: wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Texture {
                         sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        view_dimension: wgpu::TextureViewDimension::D2,
+                        view_dimension: wgpu::TextureViewDimension::D3,
                         multisampled: false,
                     },
                     count: None,
@@ -793,12 +2119,60 @@ impl CompositeVideoFramePipeline {
         })
     }
 
+    /// Uploads the LUT as a 3D texture, or a 1x1x1 placeholder when only adjustments are used.
+    pub fn lut_texture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        lut: Option<&Lut>,
+    ) -> wgpu::Texture {
+        let size = lut.map(|l| l.size).unwrap_or(1);
+        let data = lut.map(|l| l.to_rgba8()).unwrap_or(vec![0, 0, 0, 255]);
+
+        let extent = wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: size,
+        };
+
+        let texture = device.create_texture(
+            &(wgpu::TextureDescriptor {
+                size: extent,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D3,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                label: Some("LUT texture"),
+                view_formats: &[],
+            }),
+        );
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(size * 4),
+                rows_per_image: Some(size),
+            },
+            extent,
+        );
+
+        texture
+    }
+
     pub fn bind_group(
         &self,
         device: &wgpu::Device,
         uniforms: &wgpu::Buffer,
         frame: &wgpu::TextureView,
-        intermediate: &wgpu::TextureView,
+        lut: &wgpu::TextureView,
     ) -> wgpu::BindGroup {
         let sampler = device.create_sampler(
             &(wgpu::SamplerDescriptor {
@@ -826,7 +2200,7 @@ impl CompositeVideoFramePipeline {
                     },
                     wgpu::BindGroupEntry {
                         binding: 2,
-                        resource: wgpu::BindingResource::TextureView(intermediate),
+                        resource: wgpu::BindingResource::TextureView(lut),
                     },
                     wgpu::BindGroupEntry {
                         binding: 3,
@@ -841,25 +2215,44 @@ impl CompositeVideoFramePipeline {
     }
 }
 
-struct GradientOrColorPipeline {
+struct SpotlightPipeline {
     pub bind_group_layout: wgpu::BindGroupLayout,
     pub render_pipeline: wgpu::RenderPipeline,
 }
 
-#[derive(Debug, Clone, Copy, Pod, Zeroable, Default)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
 #[repr(C)]
-struct GradientOrColorUniforms {
-    pub start: [f32; 4],
-    pub end: [f32; 4],
-    pub angle: f32,
+struct SpotlightUniforms {
+    pub center: [f32; 2],
+    pub radius: f32,
+    pub feather: f32,
+    pub darkness: f32,
     _padding: [f32; 3],
 }
 
-impl GradientOrColorUniforms {
+impl SpotlightUniforms {
+    /// Places `spotlight`'s circle, in pixels, on an output of `output_size` at `time`.
+    fn new(spotlight: &Spotlight, output_size: (u32, u32), time: f64) -> Self {
+        let (center, radius) = spotlight.at(time);
+        let radius = radius.max(0.0) * output_size.0.min(output_size.1) as f32;
+
+        Self {
+            center: [
+                center.x * output_size.0 as f32,
+                center.y * output_size.1 as f32,
+            ],
+            radius,
+            // keeps the edge soft even for a zero radius
+            feather: (radius * SPOTLIGHT_FEATHER).max(1.0),
+            darkness: spotlight.darkness.clamp(0.0, 100.0) / 100.0,
+            _padding: [0.0; 3],
+        }
+    }
+
     fn to_buffer(self, device: &wgpu::Device) -> wgpu::Buffer {
         device.create_buffer_init(
             &(wgpu::util::BufferInitDescriptor {
-                label: Some("GradientOrColorUniforms Buffer"),
+                label: Some("SpotlightUniforms Buffer"),
                 contents: bytemuck::cast_slice(&[self]),
                 usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             }),
@@ -867,26 +2260,7 @@ impl GradientOrColorUniforms {
     }
 }
 
-impl From<Background> for GradientOrColorUniforms {
-    fn from(value: Background) -> Self {
-        match value {
-            Background::Color(color) => Self {
-                start: color,
-                end: color,
-                angle: 0.0,
-                _padding: [0.0; 3],
-            },
-            Background::Gradient { start, end, angle } => Self {
-                start,
-                end,
-                angle,
-                _padding: [0.0; 3],
-            },
-        }
-    }
-}
-
-impl GradientOrColorPipeline {
+impl SpotlightPipeline {
     pub fn new(device: &wgpu::Device) -> Self {
         let bind_group_layout = Self::bind_group_layout(device);
         let render_pipeline =
@@ -899,38 +2273,81 @@ impl GradientOrColorPipeline {
     }
 
     fn shader() -> &'static str {
-        include_str!("shaders/gradient-or-color.wgsl")
+        include_str!("shaders/spotlight.wgsl")
     }
 
     fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("composite-video-frame.wgsl Bind Group Layout"),
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
+            label: Some("spotlight.wgsl Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
-                count: None,
-            }],
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
         })
     }
 
-    pub fn bind_group(&self, device: &wgpu::Device, uniforms: &wgpu::Buffer) -> wgpu::BindGroup {
-        let bind_group = device.create_bind_group(
+    pub fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        uniforms: &wgpu::Buffer,
+        frame: &wgpu::TextureView,
+    ) -> wgpu::BindGroup {
+        let sampler = device.create_sampler(
+            &(wgpu::SamplerDescriptor {
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                address_mode_w: wgpu::AddressMode::ClampToEdge,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Nearest,
+                ..Default::default()
+            }),
+        );
+
+        device.create_bind_group(
             &(wgpu::BindGroupDescriptor {
                 layout: &self.bind_group_layout,
-                entries: &[wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: uniforms.as_entire_binding(),
-                }],
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniforms.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(frame),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                ],
                 label: Some("bind_group"),
             }),
-        );
-
-        bind_group
+        )
     }
 }
 
@@ -939,6 +2356,7 @@ fn do_render_pass(
     output_view: &wgpu::TextureView,
     render_pipeline: &wgpu::RenderPipeline,
     bind_group: wgpu::BindGroup,
+    timestamp_writes: Option<wgpu::RenderPassTimestampWrites>,
 ) {
     let mut render_pass = encoder.begin_render_pass(
         &(wgpu::RenderPassDescriptor {
@@ -952,7 +2370,7 @@ fn do_render_pass(
                 },
             })],
             depth_stencil_attachment: None,
-            timestamp_writes: None,
+            timestamp_writes,
             occlusion_query_set: None,
         }),
     );
@@ -0,0 +1,250 @@
+use std::path::Path;
+
+const MAX_LUT_SIZE: u32 = 256;
+
+/// A 3D colour lookup table parsed from an Adobe `.cube` file.
+#[derive(Debug, Clone)]
+pub struct Lut {
+    pub size: u32,
+    /// RGB entries with red changing fastest, then green, then blue.
+    pub data: Vec<[f32; 3]>,
+}
+
+impl Lut {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read LUT file {}: {e}", path.display()))?;
+
+        Self::parse_cube(&contents).map_err(|e| format!("Invalid LUT file {}: {e}", path.display()))
+    }
+
+    pub fn parse_cube(contents: &str) -> Result<Self, String> {
+        let mut size = None::<u32>;
+        let mut domain_min = [0.0f32; 3];
+        let mut domain_max = [1.0f32; 3];
+        let mut data = Vec::new();
+
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            let line_number = i + 1;
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            let Some(keyword) = parts.next() else {
+                continue;
+            };
+
+            match keyword {
+                "TITLE" => {}
+                "LUT_1D_SIZE" => return Err("1D LUTs are not supported".to_string()),
+                "LUT_3D_SIZE" => {
+                    let value = parts
+                        .next()
+                        .and_then(|v| v.parse::<u32>().ok())
+                        .ok_or(format!("line {line_number}: invalid LUT_3D_SIZE"))?;
+
+                    if !(2..=MAX_LUT_SIZE).contains(&value) {
+                        return Err(format!(
+                            "line {line_number}: LUT_3D_SIZE must be between 2 and {MAX_LUT_SIZE}"
+                        ));
+                    }
+
+                    size = Some(value);
+                }
+                "DOMAIN_MIN" => domain_min = parse_triplet(parts, line_number)?,
+                "DOMAIN_MAX" => domain_max = parse_triplet(parts, line_number)?,
+                _ => {
+                    let entry = parse_triplet(std::iter::once(keyword).chain(parts), line_number)?;
+                    data.push(entry);
+                }
+            }
+        }
+
+        let size = size.ok_or("missing LUT_3D_SIZE")?;
+        let expected = (size * size * size) as usize;
+
+        if data.len() != expected {
+            return Err(format!(
+                "expected {expected} entries for size {size}, found {}",
+                data.len()
+            ));
+        }
+
+        if domain_min
+            .iter()
+            .zip(domain_max)
+            .any(|(min, max)| max <= *min)
+        {
+            return Err("DOMAIN_MAX must be greater than DOMAIN_MIN".to_string());
+        }
+
+        for entry in &mut data {
+            for ((value, min), max) in entry.iter_mut().zip(domain_min).zip(domain_max) {
+                *value = ((*value - min) / (max - min)).clamp(0.0, 1.0);
+            }
+        }
+
+        Ok(Self { size, data })
+    }
+
+    /// Packs the table as RGBA8 texels suitable for uploading to a 3D texture.
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        self.data
+            .iter()
+            .flat_map(|[r, g, b]| {
+                [
+                    (r * 255.0).round() as u8,
+                    (g * 255.0).round() as u8,
+                    (b * 255.0).round() as u8,
+                    255,
+                ]
+            })
+            .collect()
+    }
+}
+
+fn parse_triplet<'a>(
+    mut parts: impl Iterator<Item = &'a str>,
+    line_number: usize,
+) -> Result<[f32; 3], String> {
+    let mut values = [0.0f32; 3];
+
+    for value in &mut values {
+        *value = parts
+            .next()
+            .and_then(|v| v.parse::<f32>().ok())
+            .filter(|v| v.is_finite())
+            .ok_or(format!("line {line_number}: expected three numeric values"))?;
+    }
+
+    if parts.next().is_some() {
+        return Err(format!("line {line_number}: expected three numeric values"));
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDENTITY_2: &str = "\
+# identity
+TITLE \"Identity\"
+LUT_3D_SIZE 2
+
+0 0 0
+1 0 0
+0 1 0
+1 1 0
+0 0 1
+1 0 1
+0 1 1
+1 1 1
+";
+
+    #[test]
+    fn parses_entries_with_red_changing_fastest() {
+        let lut = Lut::parse_cube(IDENTITY_2).unwrap();
+
+        assert_eq!(lut.size, 2);
+        assert_eq!(lut.data.len(), 8);
+        assert_eq!(lut.data[1], [1.0, 0.0, 0.0]);
+        assert_eq!(lut.data[2], [0.0, 1.0, 0.0]);
+        assert_eq!(lut.data[4], [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn rescales_entries_from_their_domain() {
+        let contents = IDENTITY_2
+            .replace(
+                "LUT_3D_SIZE 2",
+                "LUT_3D_SIZE 2\nDOMAIN_MIN 0 0 0\nDOMAIN_MAX 2 2 2",
+            )
+            .replace("1 1 1", "2 1 4");
+        let lut = Lut::parse_cube(&contents).unwrap();
+
+        assert_eq!(lut.data[1], [0.5, 0.0, 0.0]);
+        // values outside the domain are clamped
+        assert_eq!(lut.data[7], [1.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn rejects_wrong_entry_count() {
+        let contents = IDENTITY_2.replace("1 1 1\n", "");
+
+        assert_eq!(
+            Lut::parse_cube(&contents).unwrap_err(),
+            "expected 8 entries for size 2, found 7"
+        );
+    }
+
+    #[test]
+    fn rejects_missing_size() {
+        let contents = IDENTITY_2.replace("LUT_3D_SIZE 2", "");
+
+        assert_eq!(
+            Lut::parse_cube(&contents).unwrap_err(),
+            "missing LUT_3D_SIZE"
+        );
+    }
+
+    #[test]
+    fn rejects_out_of_range_size() {
+        let error = Lut::parse_cube("LUT_3D_SIZE 1").unwrap_err();
+
+        assert!(
+            error.contains("LUT_3D_SIZE must be between 2 and 256"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn rejects_1d_luts() {
+        assert_eq!(
+            Lut::parse_cube("LUT_1D_SIZE 16").unwrap_err(),
+            "1D LUTs are not supported"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_entries_with_their_line() {
+        let contents = IDENTITY_2.replace("1 0 0\n", "1 0\n");
+
+        assert_eq!(
+            Lut::parse_cube(&contents).unwrap_err(),
+            "line 6: expected three numeric values"
+        );
+    }
+
+    #[test]
+    fn rejects_non_finite_entries() {
+        let contents = IDENTITY_2.replace("1 0 0\n", "1 NaN 0\n");
+
+        assert!(Lut::parse_cube(&contents).is_err());
+    }
+
+    #[test]
+    fn rejects_empty_domain() {
+        let contents = IDENTITY_2.replace("LUT_3D_SIZE 2", "LUT_3D_SIZE 2\nDOMAIN_MAX 0 1 1");
+
+        assert_eq!(
+            Lut::parse_cube(&contents).unwrap_err(),
+            "DOMAIN_MAX must be greater than DOMAIN_MIN"
+        );
+    }
+
+    #[test]
+    fn packs_rgba8_texels() {
+        let lut = Lut::parse_cube(IDENTITY_2).unwrap();
+        let texels = lut.to_rgba8();
+
+        assert_eq!(texels.len(), 8 * 4);
+        assert_eq!(&texels[4..8], &[255, 0, 0, 255]);
+        assert_eq!(&texels[28..32], &[255, 255, 255, 255]);
+    }
+}
@@ -0,0 +1,177 @@
+use std::sync::{Mutex, MutexGuard};
+
+use serde::Serialize;
+use specta::Type;
+
+// render passes timed per frame, any after this aren't timed
+const MAX_PROFILED_PASSES: u32 = 16;
+
+/// GPU time spent in one kind of render pass, averaged over the frames it ran in.
+#[derive(Debug, Clone, Serialize, Type)]
+#[serde(rename_all = "camelCase")]
+pub struct PassTiming {
+    pub pass: &'static str,
+    pub frames: u32,
+    pub average_ms: f64,
+}
+
+/// Times render passes with GPU timestamp queries, totalled by pass name.
+pub(crate) struct GpuProfiler {
+    queries: Mutex<Queries>,
+    totals: Mutex<Vec<(&'static str, u32, f64)>>,
+    // nanoseconds per timestamp tick
+    period: f32,
+}
+
+struct Queries {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    read_buffer: wgpu::Buffer,
+}
+
+/// The passes recorded for a single frame, holding the profiler's queries until finished.
+pub(crate) struct FrameTimestamps<'a> {
+    profiler: &'a GpuProfiler,
+    queries: MutexGuard<'a, Queries>,
+    passes: Vec<&'static str>,
+}
+
+impl GpuProfiler {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let size = (MAX_PROFILED_PASSES * 2) as u64 * wgpu::QUERY_SIZE as u64;
+
+        Self {
+            queries: Mutex::new(Queries {
+                query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                    label: Some("Render Pass Timestamps"),
+                    ty: wgpu::QueryType::Timestamp,
+                    count: MAX_PROFILED_PASSES * 2,
+                }),
+                resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Timestamp Resolve Buffer"),
+                    size,
+                    usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                    mapped_at_creation: false,
+                }),
+                read_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Timestamp Read Buffer"),
+                    size,
+                    usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                    mapped_at_creation: false,
+                }),
+            }),
+            totals: Mutex::new(Vec::new()),
+            period: queue.get_timestamp_period(),
+        }
+    }
+
+    pub fn begin_frame(&self) -> FrameTimestamps<'_> {
+        FrameTimestamps {
+            profiler: self,
+            queries: self.queries.lock().unwrap(),
+            passes: Vec::new(),
+        }
+    }
+
+    pub fn timings(&self) -> Vec<PassTiming> {
+        self.totals
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|&(pass, frames, total_ms)| PassTiming {
+                pass,
+                frames,
+                average_ms: total_ms / frames as f64,
+            })
+            .collect()
+    }
+}
+
+impl FrameTimestamps<'_> {
+    /// Timestamp writes for a pass named `pass`, or `None` once the frame's queries are used up.
+    pub fn pass(&mut self, pass: &'static str) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        let index = self.passes.len() as u32;
+        if index >= MAX_PROFILED_PASSES {
+            return None;
+        }
+
+        self.passes.push(pass);
+
+        Some(wgpu::RenderPassTimestampWrites {
+            query_set: &self.queries.query_set,
+            beginning_of_pass_write_index: Some(index * 2),
+            end_of_pass_write_index: Some(index * 2 + 1),
+        })
+    }
+
+    /// Copies the frame's timestamps somewhere they can be read, after its last pass.
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.passes.is_empty() {
+            return;
+        }
+
+        let count = self.passes.len() as u32 * 2;
+        encoder.resolve_query_set(
+            &self.queries.query_set,
+            0..count,
+            &self.queries.resolve_buffer,
+            0,
+        );
+        encoder.copy_buffer_to_buffer(
+            &self.queries.resolve_buffer,
+            0,
+            &self.queries.read_buffer,
+            0,
+            count as u64 * wgpu::QUERY_SIZE as u64,
+        );
+    }
+
+    /// Waits for the frame's commands to finish and adds its pass times to the totals.
+    /// Must be called after the encoder passed to `resolve` has been submitted.
+    pub fn finish(self, device: &wgpu::Device) {
+        if self.passes.is_empty() {
+            return;
+        }
+
+        let size = self.passes.len() as u64 * 2 * wgpu::QUERY_SIZE as u64;
+        let slice = self.queries.read_buffer.slice(..size);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            tx.send(result).ok();
+        });
+        device.poll(wgpu::Maintain::Wait);
+
+        if !matches!(rx.recv(), Ok(Ok(()))) {
+            tracing::warn!("Failed to read render pass timestamps");
+            return;
+        }
+
+        let timestamps = slice
+            .get_mapped_range()
+            .chunks_exact(8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        self.queries.read_buffer.unmap();
+
+        // passes with the same name, such as each camera, add up within a frame
+        let mut frame: Vec<(&'static str, f64)> = Vec::new();
+        for (pass, ticks) in self.passes.iter().zip(timestamps.chunks_exact(2)) {
+            let ms = ticks[1].saturating_sub(ticks[0]) as f64 * self.profiler.period as f64 / 1e6;
+            match frame.iter_mut().find(|(name, _)| *name == *pass) {
+                Some((_, total)) => *total += ms,
+                None => frame.push((pass, ms)),
+            }
+        }
+
+        let mut totals = self.profiler.totals.lock().unwrap();
+        for (pass, ms) in frame {
+            match totals.iter_mut().find(|(name, ..)| *name == pass) {
+                Some((_, frames, total_ms)) => {
+                    *frames += 1;
+                    *total_ms += ms;
+                }
+                None => totals.push((pass, 1, ms)),
+            }
+        }
+    }
+}
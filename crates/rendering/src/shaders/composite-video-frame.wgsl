@@ -6,9 +6,26 @@ struct Uniforms {
     velocity_uv: vec2<f32>,
     target_size: vec2<f32>,
     rounding_px: f32,
-    mirror_x: f32
+    mirror_x: f32,
+    opacity: f32,
+    shadow_px: f32,
+    scale_filter: f32,
+    border_px: f32,
+    edge_samples: f32,
+    border_color: vec4<f32>,
 };
 
+const SHADOW_OPACITY: f32 = 0.5;
+
+// values of scale_filter, matching ScaleFilter
+const SCALE_FILTER_NEAREST: f32 = 0.0;
+const SCALE_FILTER_LANCZOS: f32 = 2.0;
+
+const PI: f32 = 3.14159265;
+const LANCZOS_RADIUS: f32 = 2.0;
+// downscales beyond this are filtered as if they were this large to bound the number of taps
+const LANCZOS_MAX_SCALE: f32 = 4.0;
+
 @group(0) @binding(0) var<uniform> u: Uniforms;
 @group(0) @binding(1) var frame_tex: texture_2d<f32>;
 @group(0) @binding(2) var prev_tex: texture_2d<f32>;
@@ -17,8 +34,7 @@ struct Uniforms {
 @fragment
 fn fs_main(@builtin(position) frag_coord: vec4<f32>) -> @location(0) vec4<f32> {
     let base_color = textureSample(frame_tex, sampler0, frag_coord.xy / u.output_size);
-    let color = main_image(base_color, frag_coord.xy);
-    return vec4(color.rgb, 1.0);
+    return main_image(base_color, frag_coord.xy);
 }
 
 fn main_image(frag_color: vec4<f32>, frag_coord: vec2<f32>) -> vec4<f32> {
@@ -26,7 +42,7 @@ fn main_image(frag_color: vec4<f32>, frag_coord: vec2<f32>) -> vec4<f32> {
 		let target_uv = (frag_coord - u.target_bounds.xy) / u.target_size;
 		let crop_bounds_uv = vec4<f32>(u.crop_bounds.xy / u.frame_size, u.crop_bounds.zw / u.frame_size);
 
-		let blur_scale = 5.0;
+		// velocity_uv is the distance blurred over, in target uv
 		var blur_samples = 50;
 
 		if u.velocity_uv.x == 0.0 && u.velocity_uv.y == 0.0 {
@@ -37,17 +53,60 @@ fn main_image(frag_color: vec4<f32>, frag_coord: vec2<f32>) -> vec4<f32> {
 
 		for (var i = 0; i < blur_samples; i++) {
 		    let t = f32(i) / f32(blur_samples - 1);
-		    let offset = u.velocity_uv * t * blur_scale;
-		   	let sample_uv = target_uv - offset;
+		    let offset = u.velocity_uv * t;
+		   	let sample_uv = target_uv + offset;
 		   	var sample_color = sample_texture(sample_uv, crop_bounds_uv);
 				sample_color = apply_rounded_corners(sample_color, sample_uv);
 
 				color += sample_color;
 		}
 
-		let ret_color = color / f32(blur_samples);
+		let content_color = color / f32(blur_samples);
+		let border = border_alpha(frag_coord - u.target_bounds.xy);
+		let ret_color = vec4(
+				mix(content_color.rgb, u.border_color.rgb, border),
+				max(content_color.a, border)
+		);
+		let alpha = ret_color.a * u.opacity;
 
-		return mix(textureSample(prev_tex, sampler0, uv), ret_color.rgba, ret_color.a);
+		let shadow = shadow_alpha(frag_coord) * u.opacity;
+		let background = over(vec4(0.0, 0.0, 0.0, 1.0), shadow, textureSample(prev_tex, sampler0, uv));
+
+		return over(ret_color, alpha, background);
+}
+
+// `top` drawn with `alpha` coverage over `bottom`, keeping the result's transparency
+// when `bottom` is see-through. The same as mixing them when `bottom` is opaque.
+fn over(top: vec4<f32>, alpha: f32, bottom: vec4<f32>) -> vec4<f32> {
+		let out_alpha = alpha + bottom.a * (1.0 - alpha);
+		let rgb = top.rgb * alpha + bottom.rgb * bottom.a * (1.0 - alpha);
+
+		return vec4(rgb / max(out_alpha, 1e-6), out_alpha);
+}
+
+// soft shadow following the rounded target rect, fading out over shadow_px
+fn shadow_alpha(frag_coord: vec2<f32>) -> f32 {
+		if u.shadow_px <= 0.0 {
+				return 0.0;
+		}
+
+		let half_size = u.target_size / 2.0;
+		let center = u.target_bounds.xy + half_size + vec2(0.0, u.shadow_px * 0.25);
+		let q = abs(frag_coord - center) - (half_size - u.rounding_px);
+		let distance = length(max(q, vec2(0.0))) + min(max(q.x, q.y), 0.0) - u.rounding_px;
+
+		return SHADOW_OPACITY * (1.0 - smoothstep(0.0, u.shadow_px, distance));
+}
+
+// ring of border_px just inside the rounded target rect, antialiased on both edges
+fn border_alpha(target_coord: vec2<f32>) -> f32 {
+		if u.border_px <= 0.0 {
+				return 0.0;
+		}
+
+		let distance = rounded_rect_distance(target_coord);
+
+		return clamp(0.5 - distance, 0.0, 1.0) * clamp(distance + u.border_px + 0.5, 0.0, 1.0);
 }
 
 fn sample_texture(uv: vec2<f32>, crop_bounds_uv: vec4<f32>) -> vec4<f32> {
@@ -58,27 +117,114 @@ fn sample_texture(uv: vec2<f32>, crop_bounds_uv: vec4<f32>) -> vec4<f32> {
 						cropped_uv.x = (1.0 - uv.x + crop_bounds_uv.x) * (crop_bounds_uv.z - crop_bounds_uv.x);
 				}
 
-				return textureSample(frame_tex, sampler0, cropped_uv);
+				return sample_frame(cropped_uv);
 		}
 
 		return vec4(0.0);
 }
 
+fn sample_frame(uv: vec2<f32>) -> vec4<f32> {
+		if u.scale_filter == SCALE_FILTER_NEAREST {
+				let dims = vec2<i32>(textureDimensions(frame_tex));
+				let texel = clamp(vec2<i32>(floor(uv * vec2<f32>(dims))), vec2(0), dims - 1);
+				return textureLoad(frame_tex, texel, 0);
+		}
+
+		// motion blur already averages many samples, so the extra taps aren't worth it
+		if u.scale_filter == SCALE_FILTER_LANCZOS && u.velocity_uv.x == 0.0 && u.velocity_uv.y == 0.0 {
+				return sample_lanczos(uv);
+		}
+
+		return textureSample(frame_tex, sampler0, uv);
+}
+
+fn lanczos(x: f32) -> f32 {
+		if abs(x) < 0.00001 {
+				return 1.0;
+		}
+
+		if abs(x) >= LANCZOS_RADIUS {
+				return 0.0;
+		}
+
+		let px = PI * x;
+		return LANCZOS_RADIUS * sin(px) * sin(px / LANCZOS_RADIUS) / (px * px);
+}
+
+fn sample_lanczos(uv: vec2<f32>) -> vec4<f32> {
+		let dims = vec2<f32>(textureDimensions(frame_tex));
+		let max_texel = vec2<i32>(dims) - 1;
+
+		// frame texels per output pixel, widening the kernel when downscaling
+		let scale = clamp(
+				(u.crop_bounds.zw - u.crop_bounds.xy) / u.target_size,
+				vec2(1.0),
+				vec2(LANCZOS_MAX_SCALE)
+		);
+		let center = uv * dims - 0.5;
+		let support = LANCZOS_RADIUS * scale;
+		let start = vec2<i32>(ceil(center - support));
+		let end = vec2<i32>(floor(center + support));
+
+		var color = vec4(0.0);
+		var weight_sum = 0.0;
+
+		for (var y = start.y; y <= end.y; y++) {
+				let weight_y = lanczos((f32(y) - center.y) / scale.y);
+
+				for (var x = start.x; x <= end.x; x++) {
+						let weight = lanczos((f32(x) - center.x) / scale.x) * weight_y;
+						let texel = clamp(vec2(x, y), vec2(0), max_texel);
+
+						color += textureLoad(frame_tex, texel, 0) * weight;
+						weight_sum += weight;
+				}
+		}
+
+		if weight_sum <= 0.0 {
+				return textureSample(frame_tex, sampler0, uv);
+		}
+
+		// the kernel's negative lobes can overshoot around sharp edges
+		return clamp(color / weight_sum, vec4(0.0), vec4(1.0));
+}
+
 fn apply_rounded_corners(current_color: vec4<f32>, target_uv: vec2<f32>) -> vec4<f32> {
-		let target_coord = abs(target_uv * u.target_size - u.target_size / 2.0);
-		let rounding_point = u.target_size / 2.0 - u.rounding_px;
-		let target_rounding_coord = target_coord - rounding_point;
+		return current_color * rounded_coverage(target_uv * u.target_size);
+}
 
-		let distance = abs(length(target_rounding_coord)) - u.rounding_px;
+// signed distance from the edge of the rounded target rect, negative inside
+fn rounded_rect_distance(target_coord: vec2<f32>) -> f32 {
+		let half_size = u.target_size / 2.0;
+		let q = abs(target_coord - half_size) - (half_size - u.rounding_px);
+		return length(max(q, vec2(0.0))) + min(max(q.x, q.y), 0.0) - u.rounding_px;
+}
 
-		let distance_blur = 1.0;
+// fraction of the pixel centred on target_coord inside the rounded target rect,
+// from a grid of edge_samples x edge_samples points spread over the pixel
+fn rounded_coverage(target_coord: vec2<f32>) -> f32 {
+		let distance = rounded_rect_distance(target_coord);
+		// only pixels on the edge need sampling
+		if distance <= -1.0 {
+				return 1.0;
+		}
+		if distance >= 1.0 {
+				return 0.0;
+		}
 
-		if target_rounding_coord.x >= 0.0 && target_rounding_coord.y >= 0.0 && distance >= -distance_blur/2 {
-			// distance_blur adds some antialiasing
-			return mix(current_color, vec4<f32>(0.0), min(distance / distance_blur + 0.5, 1.0));
+		let samples = max(i32(u.edge_samples), 1);
+		var covered = 0.0;
+
+		for (var y = 0; y < samples; y++) {
+				for (var x = 0; x < samples; x++) {
+						let offset = (vec2(f32(x), f32(y)) + 0.5) / f32(samples) - 0.5;
+						if rounded_rect_distance(target_coord + offset) < 0.0 {
+								covered += 1.0;
+						}
+				}
 		}
 
-		return current_color;
+		return covered / f32(samples * samples);
 }
 
 struct VertexOutput {
@@ -0,0 +1,37 @@
+struct Uniforms {
+    center: vec2<f32>,
+    radius: f32,
+    feather: f32,
+    darkness: f32,
+};
+
+@group(0) @binding(0) var<uniform> u: Uniforms;
+@group(0) @binding(1) var frame_tex: texture_2d<f32>;
+@group(0) @binding(2) var sampler0: sampler;
+
+@fragment
+fn fs_main(@builtin(position) frag_coord: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = frag_coord.xy / vec2<f32>(textureDimensions(frame_tex));
+    let base_color = textureSample(frame_tex, sampler0, uv);
+
+    // fades from untouched at the radius to fully dimmed feather pixels beyond it
+    let outside = smoothstep(u.radius, u.radius + u.feather, distance(frag_coord.xy, u.center));
+
+    return vec4(base_color.rgb * (1.0 - u.darkness * outside), base_color.a);
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let x = f32(i32(in_vertex_index & 1u) * 4 - 1);
+    let y = f32(i32(in_vertex_index & 2u) * 2 - 1);
+    out.tex_coords = vec2<f32>(x * 0.5 + 0.5, 1.0 - (y * 0.5 + 0.5));
+    out.clip_position = vec4<f32>(x, y, 0.0, 1.0);
+
+    return out;
+}
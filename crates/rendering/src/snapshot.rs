@@ -0,0 +1,146 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use image::{ImageBuffer, Rgba, RgbaImage};
+
+/// Set to regenerate golden images from the current output instead of comparing against them.
+pub const UPDATE_GOLDENS_ENV: &str = "CAP_UPDATE_GOLDENS";
+
+/// How a rendered frame differs from its golden image.
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotDiff {
+    pub golden_path: PathBuf,
+    /// Pixels where any channel differs by more than the tolerance.
+    pub mismatched_pixels: usize,
+    pub total_pixels: usize,
+    /// Largest difference seen in any channel of any pixel.
+    pub max_channel_diff: u8,
+    pub tolerance: u8,
+    /// The image marking the differing pixels, written when there are any.
+    pub diff_path: Option<PathBuf>,
+    /// Whether the golden was overwritten with the frame instead of compared,
+    /// see `UPDATE_GOLDENS_ENV`.
+    pub updated: bool,
+}
+
+impl SnapshotDiff {
+    pub fn is_match(&self) -> bool {
+        self.mismatched_pixels == 0
+    }
+}
+
+impl fmt::Display for SnapshotDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let golden = self.golden_path.display();
+
+        if self.updated {
+            return write!(f, "Updated golden {golden}");
+        }
+
+        write!(
+            f,
+            "{} of {} pixels differ from {golden} by more than {} (max {})",
+            self.mismatched_pixels, self.total_pixels, self.tolerance, self.max_channel_diff
+        )?;
+        if let Some(diff_path) = &self.diff_path {
+            write!(f, ", see {}", diff_path.display())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Compares a tightly packed RGBA frame with the PNG at `golden_path`, allowing each
+/// channel to differ by up to `tolerance`. On a mismatch, an image marking the differing
+/// pixels in red is written next to the golden as `<name>.diff.png`.
+/// With `CAP_UPDATE_GOLDENS` set the golden is overwritten with the frame instead.
+/// The returned diff describes what happened when displayed, for test failures and logs.
+pub fn compare_with_golden(
+    frame: &[u8],
+    (width, height): (u32, u32),
+    golden_path: &Path,
+    tolerance: u8,
+) -> Result<SnapshotDiff, String> {
+    let frame = RgbaImage::from_raw(width, height, frame.to_vec())
+        .ok_or(format!("Frame doesn't match its size of {width}x{height}"))?;
+
+    if std::env::var_os(UPDATE_GOLDENS_ENV).is_some() {
+        if let Some(parent) = golden_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        frame
+            .save(golden_path)
+            .map_err(|e| format!("Failed to write golden {}: {e}", golden_path.display()))?;
+
+        return Ok(SnapshotDiff {
+            golden_path: golden_path.to_path_buf(),
+            total_pixels: (width * height) as usize,
+            tolerance,
+            updated: true,
+            ..Default::default()
+        });
+    }
+
+    let golden = image::open(golden_path)
+        .map_err(|e| {
+            format!(
+                "Failed to read golden {}, set {UPDATE_GOLDENS_ENV} to create it: {e}",
+                golden_path.display()
+            )
+        })?
+        .into_rgba8();
+
+    if golden.dimensions() != (width, height) {
+        return Err(format!(
+            "Golden {} is {}x{} but the frame is {width}x{height}",
+            golden_path.display(),
+            golden.width(),
+            golden.height()
+        ));
+    }
+
+    let mut diff = SnapshotDiff {
+        golden_path: golden_path.to_path_buf(),
+        total_pixels: (width * height) as usize,
+        tolerance,
+        ..Default::default()
+    };
+    let mut diff_image: RgbaImage = ImageBuffer::new(width, height);
+
+    for ((actual, expected), marked) in frame
+        .pixels()
+        .zip(golden.pixels())
+        .zip(diff_image.pixels_mut())
+    {
+        let channel_diff = actual
+            .0
+            .iter()
+            .zip(expected.0)
+            .map(|(a, e)| a.abs_diff(e))
+            .max()
+            .unwrap_or(0);
+
+        diff.max_channel_diff = diff.max_channel_diff.max(channel_diff);
+
+        *marked = if channel_diff > tolerance {
+            diff.mismatched_pixels += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            // faded copy of the golden so the differences can be placed
+            let [r, g, b, _] = expected.0;
+            Rgba([r / 4, g / 4, b / 4, 255])
+        };
+    }
+
+    if !diff.is_match() {
+        let diff_path = golden_path.with_extension("diff.png");
+        diff_image
+            .save(&diff_path)
+            .map_err(|e| format!("Failed to write diff {}: {e}", diff_path.display()))?;
+        diff.diff_path = Some(diff_path);
+    }
+
+    Ok(diff)
+}
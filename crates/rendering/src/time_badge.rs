@@ -0,0 +1,118 @@
+use cap_project::{CameraXPosition, CameraYPosition, TimeBadge, TimeBadgeFormat};
+
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+// space around the text inside the badge, in glyph pixels
+const BADGE_PADDING: u32 = 2;
+// gap between the badge and the edge of the frame, as a fraction of the smaller side
+const BADGE_MARGIN: f32 = 0.02;
+
+/// Formats `time` on the timeline, in seconds, as shown by a badge using `format`.
+pub fn format_timecode(
+    format: TimeBadgeFormat,
+    time: f64,
+    duration: Option<f64>,
+    fps: u32,
+) -> String {
+    let minutes_seconds = |time: f64| {
+        let seconds = time.max(0.0) as u64;
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    };
+
+    match format {
+        TimeBadgeFormat::Elapsed => minutes_seconds(time),
+        TimeBadgeFormat::ElapsedWithFrames => format!(
+            "{}:{:02}",
+            minutes_seconds(time),
+            (time.max(0.0).fract() * fps as f64) as u32
+        ),
+        TimeBadgeFormat::ElapsedOfTotal => match duration {
+            Some(duration) => format!("{} / {}", minutes_seconds(time), minutes_seconds(duration)),
+            None => minutes_seconds(time),
+        },
+    }
+}
+
+/// Draws `text` in a badge onto a tightly packed RGBA frame.
+/// Characters other than digits, `:` and `/` are left blank.
+pub(crate) fn draw(frame: &mut [u8], (width, height): (u32, u32), badge: &TimeBadge, text: &str) {
+    let badge_height = (height as f32 * badge.style.size / 100.0).max(0.0) as u32;
+    let scale = (badge_height / (GLYPH_HEIGHT + BADGE_PADDING * 2)).max(1);
+
+    let columns = (text.chars().count() as u32 * (GLYPH_WIDTH + 1)).saturating_sub(1);
+    let badge_width = (columns + BADGE_PADDING * 2) * scale;
+    let badge_height = (GLYPH_HEIGHT + BADGE_PADDING * 2) * scale;
+
+    if badge_width > width || badge_height > height {
+        return;
+    }
+
+    let margin = (width.min(height) as f32 * BADGE_MARGIN) as u32;
+    let x = match badge.position.x {
+        CameraXPosition::Left => margin,
+        CameraXPosition::Center => (width - badge_width) / 2,
+        CameraXPosition::Right => width.saturating_sub(badge_width + margin),
+    };
+    let y = match badge.position.y {
+        CameraYPosition::Top => margin,
+        CameraYPosition::Bottom => height.saturating_sub(badge_height + margin),
+    };
+
+    let mut fill = |x: u32, y: u32, w: u32, h: u32, color: [f32; 3], alpha: f32| {
+        for row in y..(y + h).min(height) {
+            for column in x..(x + w).min(width) {
+                let i = ((row * width + column) * 4) as usize;
+                for (channel, value) in frame[i..i + 3].iter_mut().zip(color) {
+                    *channel = (*channel as f32 * (1.0 - alpha) + value * alpha) as u8;
+                }
+            }
+        }
+    };
+
+    let opacity = (badge.style.background_opacity / 100.0).clamp(0.0, 1.0);
+    fill(x, y, badge_width, badge_height, [0.0; 3], opacity);
+
+    let color = badge.style.color.map(|c| c.min(255) as f32);
+    let mut glyph_x = x + BADGE_PADDING * scale;
+    let glyph_y = y + BADGE_PADDING * scale;
+
+    for c in text.chars() {
+        if let Some(rows) = glyph(c) {
+            for (row, bits) in rows.iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                        fill(
+                            glyph_x + column * scale,
+                            glyph_y + row as u32 * scale,
+                            scale,
+                            scale,
+                            color,
+                            1.0,
+                        );
+                    }
+                }
+            }
+        }
+
+        glyph_x += (GLYPH_WIDTH + 1) * scale;
+    }
+}
+
+// 5x7 bitmaps, one row per entry with the leftmost pixel in the highest bit
+fn glyph(c: char) -> Option<[u8; GLYPH_HEIGHT as usize]> {
+    Some(match c {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '/' => [0x01, 0x01, 0x02, 0x04, 0x08, 0x10, 0x10],
+        _ => return None,
+    })
+}
@@ -0,0 +1,152 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use cap_project::{AudioVisualizer, AudioVisualizerStyle, CameraXPosition, CameraYPosition};
+
+// audio analysed for each frame of the bar spectrum, centered on the frame
+const SPECTRUM_WINDOW_SECS: f64 = 0.05;
+// lowest and highest frequencies shown by the bars, spaced evenly in pitch between them
+const SPECTRUM_MIN_HZ: f64 = 50.0;
+const SPECTRUM_MAX_HZ: f64 = 12_000.0;
+// quietest level a bar shows, in dBFS, below which it's empty
+const SPECTRUM_FLOOR_DB: f64 = -60.0;
+// audio shown across the scrolling waveform, ending at the frame
+const WAVEFORM_WINDOW_SECS: f64 = 3.0;
+// fraction of each bar's slot left as a gap before the next
+const BAR_GAP: f32 = 0.3;
+// gap between the visualizer and the edge of the frame, as a fraction of the smaller side
+const VISUALIZER_MARGIN: f32 = 0.02;
+
+/// Mono audio the visualizer follows, indexed by recording time.
+#[derive(Clone, Debug)]
+pub struct VisualizerAudio {
+    pub samples: Arc<Vec<f64>>,
+    pub sample_rate: u32,
+}
+
+/// Heights of the visualizer's bars, from 0 to 1, for the audio at `time` in the recording.
+/// Silence, with `time` as `None`, gives empty bars.
+pub(crate) fn levels(
+    visualizer: &AudioVisualizer,
+    audio: &VisualizerAudio,
+    time: Option<f64>,
+) -> Vec<f32> {
+    let bars = visualizer.bars.max(1) as usize;
+    let Some(time) = time else {
+        return vec![0.0; bars];
+    };
+
+    let rate = audio.sample_rate as f64;
+    let slice = |start: f64, end: f64| {
+        let len = audio.samples.len();
+        let start = ((start.max(0.0) * rate) as usize).min(len);
+        let end = ((end.max(0.0) * rate) as usize).clamp(start, len);
+        &audio.samples[start..end]
+    };
+
+    match visualizer.style {
+        AudioVisualizerStyle::Bars => {
+            let samples = slice(
+                time - SPECTRUM_WINDOW_SECS / 2.0,
+                time + SPECTRUM_WINDOW_SECS / 2.0,
+            );
+            let max_hz = SPECTRUM_MAX_HZ.min(rate / 2.0);
+
+            (0..bars)
+                .map(|bar| {
+                    let position = (bar as f64 + 0.5) / bars as f64;
+                    let frequency = SPECTRUM_MIN_HZ * (max_hz / SPECTRUM_MIN_HZ).powf(position);
+                    let db = 20.0 * magnitude(samples, frequency / rate).max(1e-9).log10();
+                    (1.0 - db / SPECTRUM_FLOOR_DB).clamp(0.0, 1.0) as f32
+                })
+                .collect()
+        }
+        AudioVisualizerStyle::Waveform => {
+            let column_secs = WAVEFORM_WINDOW_SECS / bars as f64;
+            let start = time - WAVEFORM_WINDOW_SECS;
+
+            (0..bars)
+                .map(|column| {
+                    let column_start = start + column as f64 * column_secs;
+                    slice(column_start, column_start + column_secs)
+                        .iter()
+                        .fold(0.0f64, |peak, sample| peak.max(sample.abs()))
+                        .min(1.0) as f32
+                })
+                .collect()
+        }
+    }
+}
+
+/// Amplitude of `samples` at `frequency`, in cycles per sample, with a Hann window
+/// so the window's edges don't spread loud frequencies into the quiet ones.
+fn magnitude(samples: &[f64], frequency: f64) -> f64 {
+    if samples.len() < 2 {
+        return 0.0;
+    }
+
+    // goertzel, which is cheaper than a full FFT for the few frequencies shown
+    let coefficient = 2.0 * (2.0 * PI * frequency).cos();
+    let last = (samples.len() - 1) as f64;
+    let (mut previous, mut before_previous) = (0.0, 0.0);
+    for (i, sample) in samples.iter().enumerate() {
+        let window = 0.5 - 0.5 * (2.0 * PI * i as f64 / last).cos();
+        let current = sample * window + coefficient * previous - before_previous;
+        before_previous = previous;
+        previous = current;
+    }
+
+    let power = previous * previous + before_previous * before_previous
+        - coefficient * previous * before_previous;
+    // the Hann window halves the amplitude of a sine filling it
+    power.max(0.0).sqrt() * 4.0 / samples.len() as f64
+}
+
+/// Draws bars of `levels` heights onto a tightly packed RGBA frame, rising from the
+/// bottom for the spectrum and spreading from the middle for the waveform.
+pub(crate) fn draw(
+    frame: &mut [u8],
+    (width, height): (u32, u32),
+    visualizer: &AudioVisualizer,
+    levels: &[f32],
+) {
+    let area_width = (width as f32 * visualizer.width / 100.0).max(0.0) as u32;
+    let area_height = (height as f32 * visualizer.height / 100.0).max(0.0) as u32;
+
+    if levels.is_empty() || area_width == 0 || area_height == 0 {
+        return;
+    }
+
+    let margin = (width.min(height) as f32 * VISUALIZER_MARGIN) as u32;
+    let x = match visualizer.position.x {
+        CameraXPosition::Left => margin,
+        CameraXPosition::Center => width.saturating_sub(area_width) / 2,
+        CameraXPosition::Right => width.saturating_sub(area_width + margin),
+    };
+    let y = match visualizer.position.y {
+        CameraYPosition::Top => margin,
+        CameraYPosition::Bottom => height.saturating_sub(area_height + margin),
+    };
+
+    let color = visualizer.color.map(|c| c.min(255) as u8);
+    let slot = area_width as f32 / levels.len() as f32;
+    let bar_width = ((slot * (1.0 - BAR_GAP)) as u32).max(1);
+
+    for (i, level) in levels.iter().enumerate() {
+        // always at least a pixel, so silence still shows where the visualizer is
+        let bar_height = ((level.clamp(0.0, 1.0) * area_height as f32) as u32).max(1);
+        let bar_x = x + (i as f32 * slot) as u32;
+        let bar_y = match visualizer.style {
+            AudioVisualizerStyle::Bars => y + area_height - bar_height,
+            AudioVisualizerStyle::Waveform => y + (area_height - bar_height) / 2,
+        };
+
+        for row in bar_y..(bar_y + bar_height).min(height) {
+            for column in bar_x..(bar_x + bar_width).min(width) {
+                let i = ((row * width + column) * 4) as usize;
+                frame[i..i + 3].copy_from_slice(&color);
+                frame[i + 3] = 255;
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Renders synthetic recordings and compares them with the images in `tests/goldens`.
+//! Needs a GPU. After an intended change to the output, regenerate the goldens with
+//! `CAP_UPDATE_GOLDENS=1 cargo test -p cap-rendering --features snapshot --test goldens`
+//! and look over the new images before committing them.
+
+use std::{path::PathBuf, sync::Arc};
+
+use cap_project::{
+    BackgroundSource, CameraPosition, CameraXPosition, CameraYPosition, Crop, ProjectConfiguration,
+    XY,
+};
+use cap_rendering::{
+    produce_frame, snapshot::compare_with_golden, Antialiasing, Background, DecodedFrame,
+    ProjectUniforms, RenderOptions, RenderVideoConstants,
+};
+
+const SCREEN_SIZE: (u32, u32) = (320, 180);
+const CAMERA_SIZE: (u32, u32) = (160, 120);
+// GPUs and drivers round blending and filtering slightly differently
+const TOLERANCE: u8 = 2;
+
+/// Vertical stripes over a diagonal gradient, so scaling and cropping show in the output.
+fn screen_frame() -> DecodedFrame {
+    let (width, height) = SCREEN_SIZE;
+    let mut frame = Vec::with_capacity((width * height * 4) as usize);
+
+    for y in 0..height {
+        for x in 0..width {
+            let stripe = if (x / 40) % 2 == 0 { 255 } else { 64 };
+            frame.extend_from_slice(&[
+                (x * 255 / width) as u8,
+                (y * 255 / height) as u8,
+                stripe,
+                255,
+            ]);
+        }
+    }
+
+    Arc::new(frame)
+}
+
+/// Four solid quadrants, so the camera's placement and orientation show in the output.
+fn camera_frame() -> DecodedFrame {
+    let (width, height) = CAMERA_SIZE;
+    let mut frame = Vec::with_capacity((width * height * 4) as usize);
+
+    for y in 0..height {
+        for x in 0..width {
+            let color = match (x < width / 2, y < height / 2) {
+                (true, true) => [230, 60, 60],
+                (false, true) => [60, 200, 80],
+                (true, false) => [60, 90, 230],
+                (false, false) => [240, 220, 60],
+            };
+            frame.extend_from_slice(&color);
+            frame.push(255);
+        }
+    }
+
+    Arc::new(frame)
+}
+
+/// Renders `project` over the synthetic screen, and the synthetic camera if `with_camera`.
+async fn render(project: &ProjectConfiguration, with_camera: bool) -> (Vec<u8>, (u32, u32)) {
+    let constants = RenderVideoConstants::new(RenderOptions {
+        camera_sizes: if with_camera {
+            vec![CAMERA_SIZE]
+        } else {
+            vec![]
+        },
+        screen_size: SCREEN_SIZE,
+        profile_gpu: false,
+        secondary_screen_size: None,
+        antialiasing: Antialiasing::default(),
+    })
+    .await
+    .unwrap();
+
+    let uniforms = ProjectUniforms::new(&constants, project);
+    let camera_frames = if with_camera {
+        vec![Some(camera_frame())]
+    } else {
+        vec![]
+    };
+
+    let frame = produce_frame(
+        &constants,
+        &screen_frame(),
+        &camera_frames,
+        Background::from(project.background.source.clone()),
+        &uniforms,
+    )
+    .await
+    .unwrap();
+
+    (frame, uniforms.output_size)
+}
+
+fn assert_matches_golden(name: &str, frame: &[u8], size: (u32, u32)) {
+    let golden_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/goldens")
+        .join(format!("{name}.png"));
+
+    let diff = compare_with_golden(frame, size, &golden_path, TOLERANCE).unwrap();
+    assert!(diff.updated || diff.is_match(), "{diff}");
+}
+
+#[tokio::test]
+async fn background_gradient_with_padding_and_rounding() {
+    let mut project = ProjectConfiguration::default();
+    project.background.source = BackgroundSource::Gradient {
+        from: [255, 90, 40],
+        to: [40, 60, 200],
+        angle: 45,
+    };
+    project.background.padding = 10.0;
+    project.background.rounding = 20.0;
+    project.background.shadow = 50.0;
+
+    let (frame, size) = render(&project, false).await;
+    assert_matches_golden("background", &frame, size);
+}
+
+#[tokio::test]
+async fn camera_overlay_in_top_left_corner() {
+    let mut project = ProjectConfiguration::default();
+    project.camera.position = CameraPosition {
+        x: CameraXPosition::Left,
+        y: CameraYPosition::Top,
+    };
+    project.camera.size = 40.0;
+
+    let (frame, size) = render(&project, true).await;
+    assert_matches_golden("camera_overlay", &frame, size);
+}
+
+#[tokio::test]
+async fn zoomed_into_cropped_region() {
+    let mut project = ProjectConfiguration::default();
+    project.background.crop = Some(Crop {
+        position: XY { x: 100, y: 50 },
+        size: XY { x: 160, y: 90 },
+    });
+    project.background.padding = 10.0;
+
+    let (frame, size) = render(&project, false).await;
+    assert_matches_golden("zoom", &frame, size);
+}